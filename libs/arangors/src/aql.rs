@@ -9,9 +9,13 @@
 /// 1. perform AQL query via `database.aql_query`.
 use std::collections::HashMap;
 
-use serde::{Deserialize, Serialize};
+use maybe_async::maybe_async;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
 use serde_json::value::Value;
 use typed_builder::TypedBuilder;
+use url::Url;
+
+use crate::{client::ClientExt, response::deserialize_response, ClientError};
 
 #[derive(Debug, Serialize, TypedBuilder)]
 #[builder(
@@ -464,6 +468,31 @@ pub struct QueryExtra {
     pub warnings: Option<Vec<Value>>,
 }
 
+/// Url of an open cursor, base url should be like `http://server:port/_db/mydb/`
+pub(crate) fn cursor_url(base_url: &Url, cursor_id: &str) -> Url {
+    base_url
+        .join(&format!("_api/cursor/{}", cursor_id))
+        .unwrap()
+}
+
+/// Fetch the next batch of an open cursor.
+///
+/// # Note
+/// this function would make a request to arango server.
+#[maybe_async]
+pub(crate) async fn next_batch<R, C>(
+    session: &C,
+    base_url: &Url,
+    cursor_id: &str,
+) -> Result<Cursor<R>, ClientError>
+where
+    R: DeserializeOwned,
+    C: ClientExt,
+{
+    let resp = session.put(cursor_url(base_url, cursor_id), "").await?;
+    deserialize_response(resp.body())
+}
+
 #[cfg(test)]
 mod test {
     use super::*;
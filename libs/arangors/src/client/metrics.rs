@@ -0,0 +1,90 @@
+//! Prometheus metrics of the requests made by the built-in clients
+//!
+//! They are registered in the default registry of the `prometheus` crate, so
+//! `prometheus::gather()` in the application picks them up.
+use std::time::Instant;
+
+use http::{Request, Response};
+use lazy_static::lazy_static;
+use prometheus::{register_histogram_vec, register_int_counter_vec, HistogramVec, IntCounterVec};
+
+use crate::ClientError;
+
+lazy_static! {
+    static ref REQUEST_DURATION: HistogramVec = register_histogram_vec!(
+        "arangodb_request_duration_seconds",
+        "Latency of requests to ArangoDB by endpoint family",
+        &["method", "endpoint"],
+        vec![0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    )
+    .unwrap();
+    static ref REQUEST_ERRORS: IntCounterVec = register_int_counter_vec!(
+        "arangodb_request_errors_total",
+        "Failed requests to ArangoDB by endpoint family and kind of failure",
+        &["endpoint", "kind"]
+    )
+    .unwrap();
+}
+
+/// Endpoint family of a request path, e.g. `cursor` for
+/// `/_db/name/_api/cursor/123`, so that document keys and cursor ids do not
+/// end up as label values.
+pub fn endpoint_family(path: &str) -> &str {
+    let mut segments = path.trim_start_matches('/').split('/');
+    let mut first = segments.next().unwrap_or_default();
+    if first == "_db" {
+        segments.next();
+        first = segments.next().unwrap_or_default();
+    }
+    match first {
+        "_api" | "_admin" | "_open" => segments.next().filter(|x| !x.is_empty()).unwrap_or(first),
+        "" => "root",
+        _ => first,
+    }
+}
+
+pub(crate) struct RequestMetrics {
+    method: String,
+    endpoint: String,
+    start: Instant,
+}
+
+impl RequestMetrics {
+    pub(crate) fn start(request: &Request<String>) -> Self {
+        RequestMetrics {
+            method: request.method().to_string(),
+            endpoint: endpoint_family(request.uri().path()).to_string(),
+            start: Instant::now(),
+        }
+    }
+
+    pub(crate) fn finish(self, result: &Result<Response<String>, ClientError>) {
+        REQUEST_DURATION
+            .with_label_values(&[&self.method, &self.endpoint])
+            .observe(self.start.elapsed().as_secs_f64());
+        let kind = match result {
+            Ok(response) if response.status().is_server_error() => "server",
+            Ok(response) if response.status().is_client_error() => "client",
+            Ok(_) => return,
+            Err(_) => "transport",
+        };
+        REQUEST_ERRORS
+            .with_label_values(&[&self.endpoint, kind])
+            .inc();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn endpoint_families() {
+        assert_eq!(endpoint_family("/_db/groupware/_api/cursor/1234"), "cursor");
+        assert_eq!(endpoint_family("/_db/groupware/_api/document/users/42"), "document");
+        assert_eq!(endpoint_family("/_api/database/user"), "database");
+        assert_eq!(endpoint_family("/_open/auth"), "auth");
+        assert_eq!(endpoint_family("/_admin/"), "_admin");
+        assert_eq!(endpoint_family("/"), "root");
+    }
+}
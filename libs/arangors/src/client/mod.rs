@@ -6,6 +6,17 @@
 //!
 //! But it's possible to incorporate custom ecosystem. See
 //! `examples/custom_client.rs`.
+//!
+//! With the `tracing` feature, every request made by the built-in clients
+//! gets a span named `arangodb`, a child of the span that is current when
+//! the request is made. It carries the method, path, response status and
+//! duration, plus the AQL text when a cursor is created.
+//!
+//! With the `metrics` feature, their latencies and failures are recorded in
+//! the default prometheus registry, see [`metrics`].
+#[cfg(feature = "tracing")]
+use std::time::Instant;
+
 use http::{HeaderMap, Request, Response};
 use url::Url;
 
@@ -36,6 +47,57 @@ compile_error!(
 pub mod reqwest;
 #[cfg(any(feature = "surf_async"))]
 pub mod surf;
+#[cfg(feature = "metrics")]
+pub mod metrics;
+
+/// Span of a single HTTP call, closed once the response has been read.
+#[cfg(feature = "tracing")]
+pub(crate) struct RequestTrace {
+    span: tracing::Span,
+    start: Instant,
+}
+
+#[cfg(feature = "tracing")]
+impl RequestTrace {
+    pub(crate) fn start(request: &Request<String>) -> Self {
+        let span = tracing::debug_span!(
+            "arangodb",
+            method = %request.method(),
+            path = request.uri().path(),
+            aql = tracing::field::Empty,
+            status = tracing::field::Empty,
+            duration_ms = tracing::field::Empty,
+            error = tracing::field::Empty,
+        );
+        if request.method() == http::Method::POST && request.uri().path().ends_with("/_api/cursor") {
+            let query = serde_json::from_str::<serde_json::Value>(request.body())
+                .ok()
+                .and_then(|body| body.get("query").and_then(|x| x.as_str()).map(String::from));
+            if let Some(query) = query {
+                span.record("aql", query.as_str());
+            }
+        }
+        RequestTrace {
+            span,
+            start: Instant::now(),
+        }
+    }
+
+    /// The span to enter while the request is sent, so that events of the
+    /// HTTP client are attached to it.
+    pub(crate) fn span(&self) -> tracing::Span {
+        self.span.clone()
+    }
+
+    pub(crate) fn finish(self, result: &Result<Response<String>, ClientError>) {
+        self.span
+            .record("duration_ms", self.start.elapsed().as_millis() as u64);
+        match result {
+            Ok(response) => self.span.record("status", response.status().as_u16()),
+            Err(e) => self.span.record("error", tracing::field::display(e)),
+        };
+    }
+}
 
 #[maybe_async::maybe_async]
 pub trait ClientExt: Sync + Clone {
@@ -6,9 +6,15 @@ use ::reqwest::blocking::Client;
 #[cfg(feature = "reqwest_async")]
 use ::reqwest::Client;
 use http::header::HeaderMap;
+#[cfg(all(feature = "tracing", not(feature = "blocking")))]
+use tracing::Instrument;
 
 use super::*;
 use crate::client::ClientExt;
+#[cfg(feature = "metrics")]
+use crate::client::metrics::RequestMetrics;
+#[cfg(feature = "tracing")]
+use crate::client::RequestTrace;
 use crate::transaction::TRANSACTION_HEADER;
 
 #[derive(Debug, Clone)]
@@ -46,6 +52,30 @@ impl ClientExt for ReqwestClient {
     async fn request(
         &self,
         request: http::Request<String>,
+    ) -> Result<http::Response<String>, ClientError> {
+        #[cfg(feature = "tracing")]
+        let trace = RequestTrace::start(&request);
+        #[cfg(feature = "metrics")]
+        let metrics = RequestMetrics::start(&request);
+        #[cfg(all(feature = "tracing", not(feature = "blocking")))]
+        let result = self.send(request).instrument(trace.span()).await;
+        #[cfg(all(feature = "tracing", feature = "blocking"))]
+        let result = trace.span().in_scope(|| self.send(request));
+        #[cfg(not(feature = "tracing"))]
+        let result = self.send(request).await;
+        #[cfg(feature = "tracing")]
+        trace.finish(&result);
+        #[cfg(feature = "metrics")]
+        metrics.finish(&result);
+        result
+    }
+}
+
+#[maybe_async::maybe_async]
+impl ReqwestClient {
+    async fn send(
+        &self,
+        request: http::Request<String>,
     ) -> Result<http::Response<String>, ClientError> {
         let req = request.try_into().unwrap();
 
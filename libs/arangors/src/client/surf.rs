@@ -5,9 +5,15 @@ use http::{
     header::{HeaderMap, HeaderName, HeaderValue, CONTENT_LENGTH, SERVER},
     Method, StatusCode, Version,
 };
+#[cfg(feature = "tracing")]
+use tracing::Instrument;
 
 use super::*;
 use crate::client::ClientExt;
+#[cfg(feature = "metrics")]
+use crate::client::metrics::RequestMetrics;
+#[cfg(feature = "tracing")]
+use crate::client::RequestTrace;
 use crate::transaction::TRANSACTION_HEADER;
 
 #[derive(Debug, Clone)]
@@ -38,6 +44,27 @@ impl ClientExt for SurfClient {
     async fn request(
         &self,
         request: http::Request<String>,
+    ) -> Result<http::Response<String>, ClientError> {
+        #[cfg(feature = "tracing")]
+        let trace = RequestTrace::start(&request);
+        #[cfg(feature = "metrics")]
+        let metrics = RequestMetrics::start(&request);
+        #[cfg(feature = "tracing")]
+        let result = self.send(request).instrument(trace.span()).await;
+        #[cfg(not(feature = "tracing"))]
+        let result = self.send(request).await;
+        #[cfg(feature = "tracing")]
+        trace.finish(&result);
+        #[cfg(feature = "metrics")]
+        metrics.finish(&result);
+        result
+    }
+}
+
+impl SurfClient {
+    async fn send(
+        &self,
+        request: http::Request<String>,
     ) -> Result<http::Response<String>, ClientError> {
         use ::surf::http::headers::HeaderName as SurfHeaderName;
 
@@ -299,9 +299,16 @@ pub struct PropertiesOptions {
     #[serde(skip_serializing_if = "Option::is_none")]
     #[builder(default, setter(strip_option))]
     wait_for_sync: Option<bool>,
-    /* TODO need to implement this with feature gate between versions maybe
-     *  for ArangoDB 3.7
-     * schema: Option<SchemaRules>, */
+
+    /// Optional object that specifies the collection level schema for
+    /// documents, `null` removes the current schema. The attribute keys rule,
+    /// level and message must follow the rules documented in Document Schema
+    /// Validation https://www.arangodb.com/docs/devel/document-schema-validation.html
+    ///
+    /// Only supported since ArangoDB 3.7.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    schema: Option<serde_json::Value>,
 }
 
 impl Default for PropertiesOptions {
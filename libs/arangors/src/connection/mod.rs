@@ -59,14 +59,14 @@ mod auth;
 pub mod options;
 
 pub mod role {
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Normal;
 
-    #[derive(Debug)]
+    #[derive(Debug, Clone)]
     pub struct Admin;
 }
 
-#[derive(Deserialize, Serialize, Debug)]
+#[derive(Deserialize, Serialize, Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Permission {
     #[serde(rename = "none")]
     NoAccess,
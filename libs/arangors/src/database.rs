@@ -9,11 +9,13 @@ use serde::{de::DeserializeOwned, Deserialize};
 use serde_json::value::Value;
 use url::Url;
 
-use crate::graph::{GraphCollection, GraphResponse, GHARIAL_API_PATH};
+use crate::graph::{GraphCollection, GraphHandle, GraphResponse, GHARIAL_API_PATH};
 use crate::index::INDEX_API_PATH;
+#[cfg(not(feature = "blocking"))]
+use crate::stream::CursorStream;
 use crate::{
     analyzer::{AnalyzerDescription, AnalyzerInfo},
-    aql::{AqlQuery, Cursor},
+    aql::{self, AqlQuery, Cursor},
     client::ClientExt,
     collection::{
         options::{CreateOptions, CreateParameters},
@@ -226,12 +228,7 @@ impl<'a, C: ClientExt> Database<C> {
     where
         R: DeserializeOwned,
     {
-        let url = self
-            .base_url
-            .join(&format!("_api/cursor/{}", cursor_id))
-            .unwrap();
-        let resp = self.session.put(url, "").await?;
-        deserialize_response(resp.body())
+        aql::next_batch(self.session.as_ref(), &self.base_url, cursor_id).await
     }
 
     #[maybe_async]
@@ -430,6 +427,16 @@ impl<'a, C: ClientExt> Database<C> {
         Ok(result.graph)
     }
 
+    /// Get a handle to manage the vertices, edges and definitions of a graph
+    /// by name.
+    ///
+    /// # Note
+    /// this function does not make a request, a missing graph is reported by
+    /// the first operation on the handle.
+    pub fn graph_handle(&self, name: &str) -> GraphHandle<C> {
+        GraphHandle::new(name, &self.base_url, self.session())
+    }
+
     /// Retrieve the list of created graphs.
     ///
     /// # Note
@@ -709,6 +716,35 @@ impl<'a, C: ClientExt> Database<C> {
     }
 }
 
+#[cfg(not(feature = "blocking"))]
+impl<C: ClientExt + Send + 'static> Database<C> {
+    /// Execute AQL query and stream its results, fetching one batch at a time
+    /// like `aql_next_batch` only once the previous one is consumed.
+    ///
+    /// Unlike `aql_query`, memory use is bounded by the batch size, which
+    /// makes it suitable for exports of large collections.
+    ///
+    /// When the stream is dropped before its end, the cursor is deleted on
+    /// the server in the background, which needs the `reqwest_async` feature
+    /// and a running tokio runtime. Otherwise, e.g. with `surf_async`, the
+    /// cursor is left to expire after its ttl unless the stream is closed
+    /// with `CursorStream::close`.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    pub async fn aql_stream<R>(&self, aql: AqlQuery<'_>) -> Result<CursorStream<R, C>, ClientError>
+    where
+        R: DeserializeOwned + Send + 'static,
+    {
+        let first = self.aql_query_batch(aql).await?;
+        Ok(CursorStream::new(
+            Arc::clone(&self.session),
+            &self.base_url,
+            first,
+        ))
+    }
+}
+
 #[derive(Debug, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct DatabaseDetails {
@@ -6,8 +6,32 @@
 //!
 //! For detailed information about ArangoDB named graphs, please check out the official
 //! ArangoDB [documentation](https://www.arangodb.com/docs/stable/http/gharial.html).
-use serde::{Deserialize, Serialize};
+//!
+//! Vertices and edges of an existing graph are managed through a
+//! [`GraphHandle`], which goes through the graph module so that ArangoDB
+//! checks edges against the edge definitions and removes dangling edges
+//! together with their vertices.
+//!
+//! [`GraphHandle`]: struct.GraphHandle.html
+use std::sync::Arc;
+
+use http::Request;
+use maybe_async::maybe_async;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::json;
 use typed_builder::TypedBuilder;
+use url::Url;
+
+use crate::{
+    client::ClientExt,
+    document::{
+        options::{InsertOptions, RemoveOptions, UpdateOptions},
+        response::DocumentResponse,
+        Document, Header,
+    },
+    response::deserialize_response,
+    ClientError,
+};
 
 pub(crate) const GHARIAL_API_PATH: &str = "_api/gharial";
 
@@ -98,3 +122,438 @@ pub struct GraphCollection {
 pub struct GraphResponse {
     pub graph: Graph,
 }
+
+/// Vertex or edge collections of a graph as returned by ArangoDB
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct GraphCollectionNames {
+    pub collections: Vec<String>,
+}
+
+/// Vertex or edge as returned by ArangoDB after a HTTP retrieval
+#[derive(Debug, Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct ElementResponse<T> {
+    #[serde(alias = "vertex", alias = "edge")]
+    element: Document<T>,
+}
+
+/// Header of a written vertex or edge, with the documents asked for in the
+/// options
+#[derive(Debug, Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct WriteResponse<T> {
+    #[serde(alias = "vertex", alias = "edge")]
+    element: Header,
+    new: Option<T>,
+    old: Option<T>,
+}
+
+impl<T> From<WriteResponse<T>> for DocumentResponse<T> {
+    fn from(response: WriteResponse<T>) -> Self {
+        DocumentResponse::Response {
+            header: response.element,
+            old: response.old,
+            new: response.new,
+            _old_rev: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(bound = "T: DeserializeOwned")]
+struct RemoveResponse<T> {
+    old: Option<T>,
+}
+
+/// Operations on the vertices, edges and definitions of a named graph.
+///
+/// Obtained with [`Database::graph_handle`], it does not check that the graph
+/// exists until the first request.
+///
+/// [`Database::graph_handle`]: ../database/struct.Database.html#method.graph_handle
+#[derive(Debug, Clone)]
+pub struct GraphHandle<C: ClientExt> {
+    name: String,
+    base_url: Url,
+    session: Arc<C>,
+}
+
+impl<C: ClientExt> GraphHandle<C> {
+    /// Base url should be like `http://server:port/_db/mydb/_api/gharial/{graph-name}/`
+    pub(crate) fn new<T: Into<String>>(name: T, db_url: &Url, session: Arc<C>) -> Self {
+        let name = name.into();
+        let base_url = db_url
+            .join(&format!("{}/{}/", GHARIAL_API_PATH, &name))
+            .unwrap();
+        GraphHandle {
+            name,
+            base_url,
+            session,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    pub fn url(&self) -> &Url {
+        &self.base_url
+    }
+
+    fn element_url(&self, kind: &str, path: &str) -> Url {
+        self.base_url.join(&format!("{}/{}", kind, path)).unwrap()
+    }
+
+    /// Names of the vertex collections of the graph, including orphan
+    /// collections.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn vertex_collections(&self) -> Result<Vec<String>, ClientError> {
+        let url = self.base_url.join("vertex").unwrap();
+        let resp: GraphCollectionNames =
+            deserialize_response(self.session.get(url, "").await?.body())?;
+        Ok(resp.collections)
+    }
+
+    /// Add a vertex collection that is not part of any edge definition, it is
+    /// created if it does not exist yet.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn add_vertex_collection(&self, collection: &str) -> Result<Graph, ClientError> {
+        let url = self.base_url.join("vertex").unwrap();
+        let body = json!({ "collection": collection });
+        let resp: GraphResponse =
+            deserialize_response(self.session.post(url, body.to_string()).await?.body())?;
+        Ok(resp.graph)
+    }
+
+    /// Remove an orphan vertex collection from the graph, collections used in
+    /// edge definitions have to be removed with their definition.
+    ///
+    /// # Arguments
+    /// * `drop_collection` - if set to `true`, drops the collection as well
+    /// when it is not used in other graphs.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn remove_vertex_collection(
+        &self,
+        collection: &str,
+        drop_collection: bool,
+    ) -> Result<Graph, ClientError> {
+        let mut url = self
+            .base_url
+            .join(&format!("vertex/{}", collection))
+            .unwrap();
+        url.set_query(Some(&format!("dropCollection={}", drop_collection)));
+        let resp: GraphResponse = deserialize_response(self.session.delete(url, "").await?.body())?;
+        Ok(resp.graph)
+    }
+
+    /// Names of the edge collections of the graph.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn edge_collections(&self) -> Result<Vec<String>, ClientError> {
+        let url = self.base_url.join("edge").unwrap();
+        let resp: GraphCollectionNames =
+            deserialize_response(self.session.get(url, "").await?.body())?;
+        Ok(resp.collections)
+    }
+
+    /// Add an edge definition, missing collections are created.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn add_edge_definition(
+        &self,
+        definition: EdgeDefinition,
+    ) -> Result<Graph, ClientError> {
+        let url = self.base_url.join("edge").unwrap();
+        let resp: GraphResponse = deserialize_response(
+            self.session
+                .post(url, serde_json::to_string(&definition)?)
+                .await?
+                .body(),
+        )?;
+        Ok(resp.graph)
+    }
+
+    /// Replace the edge definition of the same edge collection. The change
+    /// applies to all graphs that use this edge collection.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn replace_edge_definition(
+        &self,
+        definition: EdgeDefinition,
+    ) -> Result<Graph, ClientError> {
+        let url = self
+            .base_url
+            .join(&format!("edge/{}", definition.collection))
+            .unwrap();
+        let resp: GraphResponse = deserialize_response(
+            self.session
+                .put(url, serde_json::to_string(&definition)?)
+                .await?
+                .body(),
+        )?;
+        Ok(resp.graph)
+    }
+
+    /// Remove the edge definition of an edge collection.
+    ///
+    /// # Arguments
+    /// * `drop_collections` - if set to `true`, drops the edge collection as
+    /// well when it is not used in other graphs.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn remove_edge_definition(
+        &self,
+        collection: &str,
+        drop_collections: bool,
+    ) -> Result<Graph, ClientError> {
+        let mut url = self.base_url.join(&format!("edge/{}", collection)).unwrap();
+        url.set_query(Some(&format!("dropCollections={}", drop_collections)));
+        let resp: GraphResponse = deserialize_response(self.session.delete(url, "").await?.body())?;
+        Ok(resp.graph)
+    }
+
+    /// Create a vertex in a vertex collection of the graph.
+    ///
+    /// Only `wait_for_sync` and `return_new` of the options are supported by
+    /// the graph module.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn create_vertex<T>(
+        &self,
+        collection: &str,
+        doc: T,
+        insert_options: InsertOptions,
+    ) -> Result<DocumentResponse<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let mut url = self.element_url("vertex", collection);
+        let query = serde_qs::to_string(&insert_options).unwrap();
+        url.set_query(Some(query.as_str()));
+        let body = serde_json::to_string(&doc)?;
+        let resp: WriteResponse<T> =
+            deserialize_response(self.session.post(url, body).await?.body())?;
+        Ok(resp.into())
+    }
+
+    /// Read a single vertex with `_key`
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn vertex<T>(&self, collection: &str, _key: &str) -> Result<Document<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let url = self.element_url("vertex", &format!("{}/{}", collection, _key));
+        let resp: ElementResponse<T> =
+            deserialize_response(self.session.get(url, "").await?.body())?;
+        Ok(resp.element)
+    }
+
+    /// Partially update a vertex
+    ///
+    /// Only `keep_null`, `wait_for_sync`, `return_new` and `return_old` of the
+    /// options are supported by the graph module.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn update_vertex<T>(
+        &self,
+        collection: &str,
+        _key: &str,
+        doc: T,
+        update_options: UpdateOptions,
+    ) -> Result<DocumentResponse<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let mut url = self.element_url("vertex", &format!("{}/{}", collection, _key));
+        let query = serde_qs::to_string(&update_options).unwrap();
+        url.set_query(Some(query.as_str()));
+        let body = serde_json::to_string(&doc)?;
+        let resp: WriteResponse<T> =
+            deserialize_response(self.session.patch(url, body).await?.body())?;
+        Ok(resp.into())
+    }
+
+    /// Remove a vertex together with all edges of the graph pointing to or
+    /// from it.
+    ///
+    /// The removed vertex is returned when `return_old` is set. You can
+    /// conditionally remove a vertex based on a target revision id by using
+    /// the if-match HTTP header.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn remove_vertex<T>(
+        &self,
+        collection: &str,
+        _key: &str,
+        remove_options: RemoveOptions,
+        if_match_header: Option<String>,
+    ) -> Result<Option<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let path = format!("{}/{}", collection, _key);
+        self.remove("vertex", &path, remove_options, if_match_header)
+            .await
+    }
+
+    /// Create an edge in an edge collection of the graph.
+    ///
+    /// The document has to carry `_from` and `_to`, which are checked against
+    /// the edge definition of the collection. Only `wait_for_sync` and
+    /// `return_new` of the options are supported by the graph module.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn create_edge<T>(
+        &self,
+        collection: &str,
+        doc: T,
+        insert_options: InsertOptions,
+    ) -> Result<DocumentResponse<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let mut url = self.element_url("edge", collection);
+        let query = serde_qs::to_string(&insert_options).unwrap();
+        url.set_query(Some(query.as_str()));
+        let body = serde_json::to_string(&doc)?;
+        let resp: WriteResponse<T> =
+            deserialize_response(self.session.post(url, body).await?.body())?;
+        Ok(resp.into())
+    }
+
+    /// Read a single edge with `_key`
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn edge<T>(&self, collection: &str, _key: &str) -> Result<Document<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let url = self.element_url("edge", &format!("{}/{}", collection, _key));
+        let resp: ElementResponse<T> =
+            deserialize_response(self.session.get(url, "").await?.body())?;
+        Ok(resp.element)
+    }
+
+    /// Remove an edge
+    ///
+    /// The removed edge is returned when `return_old` is set. You can
+    /// conditionally remove an edge based on a target revision id by using
+    /// the if-match HTTP header.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn remove_edge<T>(
+        &self,
+        collection: &str,
+        _key: &str,
+        remove_options: RemoveOptions,
+        if_match_header: Option<String>,
+    ) -> Result<Option<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let path = format!("{}/{}", collection, _key);
+        self.remove("edge", &path, remove_options, if_match_header)
+            .await
+    }
+
+    #[maybe_async]
+    async fn remove<T>(
+        &self,
+        kind: &str,
+        path: &str,
+        remove_options: RemoveOptions,
+        if_match_header: Option<String>,
+    ) -> Result<Option<T>, ClientError>
+    where
+        T: Serialize + DeserializeOwned,
+    {
+        let mut url = self.element_url(kind, path);
+        let query = serde_qs::to_string(&remove_options).unwrap();
+        url.set_query(Some(query.as_str()));
+        let mut build = Request::delete(url.to_string());
+
+        if let Some(if_match_value) = if_match_header {
+            build = build.header("If-Match", if_match_value);
+        }
+
+        let req = build.body("".to_string()).unwrap();
+
+        let resp: RemoveResponse<T> =
+            deserialize_response(self.session.request(req).await?.body())?;
+        Ok(resp.old)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Debug, Serialize, Deserialize, PartialEq)]
+    struct Membership {
+        _from: String,
+        _to: String,
+        role: String,
+    }
+
+    #[test]
+    fn write_response() {
+        let text =
+            "{\"error\":false,\"code\":202,\"edge\":{\"_id\":\"memberships/1\",\"_key\":\"1\",\
+                    \"_rev\":\"_cB\"},\"new\":{\"_id\":\"memberships/1\",\"_key\":\"1\",\"_rev\":\
+                    \"_cB\",\"_from\":\"users/a\",\"_to\":\"companies/b\",\"role\":\"admin\"}}";
+        let result = deserialize_response::<WriteResponse<Membership>>(text);
+        assert_eq!(result.is_ok(), true, "failed: {:?}", result);
+        let response: DocumentResponse<Membership> = result.unwrap().into();
+        assert_eq!(response.header().unwrap()._key, "1");
+        assert_eq!(response.new_doc().unwrap().role, "admin");
+        assert_eq!(response.old_doc(), None);
+    }
+
+    #[test]
+    fn element_response() {
+        let text = "{\"error\":false,\"code\":200,\"vertex\":{\"_id\":\"memberships/1\",\"_key\":\
+                    \"1\",\"_rev\":\"_cB\",\"_from\":\"users/a\",\"_to\":\"companies/b\",\"role\":\
+                    \"member\"}}";
+        let result = deserialize_response::<ElementResponse<Membership>>(text);
+        assert_eq!(result.is_ok(), true, "failed: {:?}", result);
+        let element = result.unwrap().element;
+        assert_eq!(element.header._id, "memberships/1");
+        assert_eq!(element.document.role, "member");
+
+        let text = "{\"error\":false,\"code\":202,\"removed\":true}";
+        let result = deserialize_response::<RemoveResponse<Membership>>(text);
+        assert_eq!(result.unwrap().old, None);
+    }
+}
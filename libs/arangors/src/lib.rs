@@ -67,7 +67,7 @@
 //!     Provides the API related to:
 //!     - (X) Graph Management
 //!     - (X) Index Management
-//!     - ( ) User Management
+//!     - (X) User Management
 //!
 //!     In this stage, all operations available for database, collection and
 //!     document should be implemented.
@@ -411,5 +411,9 @@ pub mod graph;
 pub mod index;
 mod query;
 mod response;
+#[cfg(not(feature = "blocking"))]
+pub mod stream;
 pub mod transaction;
+pub mod traversal;
+pub mod user;
 pub mod view;
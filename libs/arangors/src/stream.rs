@@ -0,0 +1,324 @@
+//! Streaming of AQL query results.
+//!
+//! [`CursorStream`] yields the results of a query one by one and only asks
+//! the server for the next batch once the current one is used up, so that
+//! large results can be processed in constant memory. It is returned by
+//! `Database::aql_stream` and `Transaction::aql_stream`.
+//!
+//! The server keeps a cursor open until its last batch has been fetched. When
+//! a stream is dropped before that, the cursor is deleted in the background
+//! on the current tokio runtime (with the `reqwest_async` feature). Otherwise
+//! it is left to expire after its ttl, unless the stream is closed with
+//! [`CursorStream::close`].
+//!
+//! Not available with the `blocking` feature.
+use std::{
+    collections::VecDeque,
+    pin::Pin,
+    sync::Arc,
+    task::{Context, Poll},
+};
+
+use futures::{future::BoxFuture, stream::Stream};
+use log::debug;
+use serde::de::DeserializeOwned;
+use url::Url;
+
+use crate::{
+    aql::{cursor_url, next_batch, Cursor},
+    client::ClientExt,
+    ClientError,
+};
+
+/// Results of an AQL query, fetched lazily batch by batch.
+///
+/// Dropping the stream before its end deletes the cursor only with the
+/// `reqwest_async` feature inside a tokio runtime; elsewhere await
+/// [`CursorStream::close`] or let the cursor expire after its ttl.
+pub struct CursorStream<R, C: ClientExt + Send + 'static> {
+    session: Arc<C>,
+    base_url: Url,
+    /// Set while the cursor is open on the server.
+    id: Option<String>,
+    more: bool,
+    prefetch: bool,
+    buffer: VecDeque<R>,
+    pending: Option<BoxFuture<'static, Result<Cursor<R>, ClientError>>>,
+    fetched: Option<Result<Cursor<R>, ClientError>>,
+}
+
+// no field is ever pinned
+impl<R, C: ClientExt + Send + 'static> Unpin for CursorStream<R, C> {}
+
+impl<R, C> CursorStream<R, C>
+where
+    R: DeserializeOwned + Send + 'static,
+    C: ClientExt + Send + 'static,
+{
+    /// Base url should be like `http://server:port/_db/mydb/`
+    pub(crate) fn new(session: Arc<C>, base_url: &Url, first: Cursor<R>) -> Self {
+        let more = first.more;
+        CursorStream {
+            session,
+            base_url: base_url.clone(),
+            id: if more { first.id } else { None },
+            more,
+            prefetch: false,
+            buffer: first.result.into(),
+            pending: None,
+            fetched: None,
+        }
+    }
+
+    /// Request the next batch as soon as the current one is being consumed,
+    /// so that processing and fetching overlap. At most one batch is held in
+    /// addition to the current one.
+    pub fn prefetch(mut self, prefetch: bool) -> Self {
+        self.prefetch = prefetch;
+        self
+    }
+
+    /// Whether the server may still hold results that have not been fetched.
+    pub fn has_more(&self) -> bool {
+        self.more
+    }
+
+    /// Delete the cursor on the server if it is still open.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    pub async fn close(mut self) -> Result<(), ClientError> {
+        self.pending = None;
+        if let Some(id) = self.id.take() {
+            let url = cursor_url(&self.base_url, &id);
+            self.session.delete(url, "").await?;
+        }
+        Ok(())
+    }
+
+    fn start_fetch(&mut self) {
+        if !self.more || self.pending.is_some() || self.fetched.is_some() {
+            return;
+        }
+        let id = match &self.id {
+            Some(id) => id.clone(),
+            None => return,
+        };
+        let session = Arc::clone(&self.session);
+        let base_url = self.base_url.clone();
+        self.pending = Some(Box::pin(async move {
+            next_batch(session.as_ref(), &base_url, &id).await
+        }));
+    }
+
+    /// Drives the request of the next batch, keeping its result once done.
+    fn poll_pending(&mut self, cx: &mut Context<'_>) -> Poll<()> {
+        match self.pending.as_mut() {
+            Some(fut) => match fut.as_mut().poll(cx) {
+                Poll::Ready(result) => {
+                    self.pending = None;
+                    // the server closes the cursor with its last batch, even if that is not
+                    // consumed yet
+                    if matches!(&result, Ok(cursor) if !cursor.more) {
+                        self.id = None;
+                    }
+                    self.fetched = Some(result);
+                    Poll::Ready(())
+                }
+                Poll::Pending => Poll::Pending,
+            },
+            None => Poll::Ready(()),
+        }
+    }
+}
+
+impl<R, C> Stream for CursorStream<R, C>
+where
+    R: DeserializeOwned + Send + 'static,
+    C: ClientExt + Send + 'static,
+{
+    type Item = Result<R, ClientError>;
+
+    fn poll_next(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Option<Self::Item>> {
+        let this = self.get_mut();
+        // a prefetch in flight makes progress whenever the stream is polled
+        let _ = this.poll_pending(cx);
+
+        loop {
+            if let Some(item) = this.buffer.pop_front() {
+                if this.prefetch {
+                    this.start_fetch();
+                    let _ = this.poll_pending(cx);
+                }
+                return Poll::Ready(Some(Ok(item)));
+            }
+
+            match this.fetched.take() {
+                Some(Ok(cursor)) => {
+                    this.more = cursor.more;
+                    this.buffer = cursor.result.into();
+                    continue;
+                }
+                // the cursor stays open and is deleted on drop
+                Some(Err(e)) => {
+                    this.more = false;
+                    return Poll::Ready(Some(Err(e)));
+                }
+                None => {}
+            }
+
+            if !this.more {
+                return Poll::Ready(None);
+            }
+            this.start_fetch();
+            if this.poll_pending(cx).is_pending() {
+                return Poll::Pending;
+            }
+        }
+    }
+}
+
+impl<R, C: ClientExt + Send + 'static> Drop for CursorStream<R, C> {
+    fn drop(&mut self) {
+        if let Some(id) = self.id.take() {
+            let url = cursor_url(&self.base_url, &id);
+            close_in_background(Arc::clone(&self.session), url);
+        }
+    }
+}
+
+#[cfg(feature = "reqwest_async")]
+fn close_in_background<C: ClientExt + Send + 'static>(session: Arc<C>, url: Url) {
+    match tokio_rt::runtime::Handle::try_current() {
+        Ok(handle) => {
+            handle.spawn(async move {
+                if let Err(e) = session.delete(url.clone(), "").await {
+                    debug!("Failed to delete cursor {}: {}", url, e);
+                }
+            });
+        }
+        Err(_) => debug!(
+            "No runtime to delete cursor {}, it expires after its ttl",
+            url
+        ),
+    }
+}
+
+#[cfg(not(feature = "reqwest_async"))]
+fn close_in_background<C: ClientExt + Send + 'static>(_session: Arc<C>, url: Url) {
+    debug!("Cursor {} is left to expire after its ttl", url);
+}
+
+#[cfg(test)]
+mod test {
+    use std::sync::Mutex;
+
+    use futures::{executor::block_on, StreamExt};
+    use http::{HeaderMap, Request, Response};
+
+    use super::*;
+
+    /// Answers every request for the next batch with the given bodies, in order.
+    #[derive(Debug, Clone)]
+    struct Batches {
+        bodies: Arc<Mutex<VecDeque<&'static str>>>,
+        requests: Arc<Mutex<Vec<String>>>,
+    }
+
+    #[maybe_async::maybe_async]
+    impl ClientExt for Batches {
+        fn new<U: Into<Option<HeaderMap>>>(_headers: U) -> Result<Self, ClientError> {
+            Ok(Batches {
+                bodies: Arc::new(Mutex::new(VecDeque::new())),
+                requests: Arc::new(Mutex::new(Vec::new())),
+            })
+        }
+
+        fn clone_with_transaction(&self, _transaction_id: String) -> Result<Self, ClientError> {
+            Ok(self.clone())
+        }
+
+        async fn request(&self, request: Request<String>) -> Result<Response<String>, ClientError> {
+            self.requests.lock().unwrap().push(format!(
+                "{} {}",
+                request.method(),
+                request.uri().path()
+            ));
+            let body = self.bodies.lock().unwrap().pop_front().unwrap_or("{}");
+            Ok(Response::new(body.to_string()))
+        }
+    }
+
+    fn first_batch() -> Cursor<u32> {
+        serde_json::from_str("{\"cached\":false,\"hasMore\":true,\"result\":[1,2],\"id\":\"42\"}")
+            .unwrap()
+    }
+
+    #[test]
+    fn lazy_batches() {
+        let client = Batches::new(None).unwrap();
+        client.bodies.lock().unwrap().extend(vec![
+            "{\"cached\":false,\"hasMore\":true,\"result\":[3],\"id\":\"42\"}",
+            "{\"cached\":false,\"hasMore\":false,\"result\":[4]}",
+        ]);
+        let requests = Arc::clone(&client.requests);
+        let url = Url::parse("http://localhost:8529/_db/test/").unwrap();
+        let mut stream = CursorStream::new(Arc::new(client), &url, first_batch());
+
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 1);
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 2);
+        assert!(requests.lock().unwrap().is_empty());
+
+        let rest: Vec<u32> = block_on(stream.map(|x| x.unwrap()).collect());
+        assert_eq!(rest, vec![3, 4]);
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec![
+                "PUT /_db/test/_api/cursor/42",
+                "PUT /_db/test/_api/cursor/42"
+            ]
+        );
+    }
+
+    #[test]
+    fn prefetch_and_close() {
+        let client = Batches::new(None).unwrap();
+        client
+            .bodies
+            .lock()
+            .unwrap()
+            .push_back("{\"cached\":false,\"hasMore\":true,\"result\":[3],\"id\":\"42\"}");
+        let requests = Arc::clone(&client.requests);
+        let url = Url::parse("http://localhost:8529/_db/test/").unwrap();
+        let mut stream = CursorStream::new(Arc::new(client), &url, first_batch()).prefetch(true);
+
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 1);
+        assert_eq!(requests.lock().unwrap().len(), 1);
+
+        block_on(stream.close()).unwrap();
+        assert_eq!(
+            requests.lock().unwrap().last().unwrap(),
+            "DELETE /_db/test/_api/cursor/42"
+        );
+    }
+
+    #[test]
+    fn prefetched_last_batch() {
+        let client = Batches::new(None).unwrap();
+        client
+            .bodies
+            .lock()
+            .unwrap()
+            .push_back("{\"cached\":false,\"hasMore\":false,\"result\":[3]}");
+        let requests = Arc::clone(&client.requests);
+        let url = Url::parse("http://localhost:8529/_db/test/").unwrap();
+        let mut stream = CursorStream::new(Arc::new(client), &url, first_batch()).prefetch(true);
+
+        assert_eq!(block_on(stream.next()).unwrap().unwrap(), 1);
+        block_on(stream.close()).unwrap();
+        assert_eq!(
+            *requests.lock().unwrap(),
+            vec!["PUT /_db/test/_api/cursor/42"]
+        );
+    }
+}
@@ -7,8 +7,10 @@ use std::sync::Arc;
 use typed_builder::TypedBuilder;
 use url::Url;
 
+#[cfg(not(feature = "blocking"))]
+use crate::stream::CursorStream;
 use crate::{
-    aql::Cursor,
+    aql::{self, Cursor},
     client::ClientExt,
     collection::response::Info,
     response::{deserialize_response, ArangoResult},
@@ -25,6 +27,11 @@ pub struct TransactionCollections {
     read: Option<Vec<String>>,
 
     write: Vec<String>,
+
+    /// Collections locked for the whole transaction, other writers wait until it ends
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    exclusive: Option<Vec<String>>,
 }
 
 #[derive(Debug, Serialize, Deserialize, TypedBuilder)]
@@ -264,13 +271,7 @@ where
     where
         R: DeserializeOwned,
     {
-        let url = self
-            .base_url
-            .join(&format!("_api/cursor/{}", cursor_id))
-            .unwrap();
-        let resp = self.session.put(url, "").await?;
-
-        deserialize_response(resp.body())
+        aql::next_batch(self.session.as_ref(), &self.base_url, cursor_id).await
     }
 
     #[maybe_async]
@@ -350,3 +351,33 @@ where
         self.aql_query(aql).await
     }
 }
+
+#[cfg(not(feature = "blocking"))]
+impl<C: ClientExt + Send + 'static> Transaction<C> {
+    /// Execute AQL query and stream its results, fetching one batch at a time
+    /// like `aql_next_batch` only once the previous one is consumed.
+    ///
+    /// Unlike `aql_query`, memory use is bounded by the batch size, which
+    /// makes it suitable for exports of large collections. Batches are read
+    /// within the transaction.
+    ///
+    /// When the stream is dropped before its end, the cursor is deleted on
+    /// the server in the background, which needs the `reqwest_async` feature
+    /// and a running tokio runtime. Otherwise, e.g. with `surf_async`, the
+    /// cursor is left to expire after its ttl unless the stream is closed
+    /// with `CursorStream::close`.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    pub async fn aql_stream<R>(&self, aql: AqlQuery<'_>) -> Result<CursorStream<R, C>, ClientError>
+    where
+        R: DeserializeOwned + Send + 'static,
+    {
+        let first = self.aql_query_batch(aql).await?;
+        Ok(CursorStream::new(
+            Arc::clone(&self.session),
+            &self.base_url,
+            first,
+        ))
+    }
+}
@@ -0,0 +1,613 @@
+//! Builders for graph traversals and path searches.
+//!
+//! Writing `FOR v, e, p IN 1..3 OUTBOUND @start GRAPH 'x'` by hand invites
+//! mistakes in the order of the clauses and in quoting. [`GraphQuery`]
+//! assembles the query from parts, binds the start and target vertices, the
+//! graph and the edge collections as parameters, and compiles to a
+//! [`CompiledQuery`] that deserializes into typed results.
+//!
+//! ```rust, ignore
+//! use arangors::traversal::{Direction, GraphQuery, TraversalStep, UniqueVertices};
+//!
+//! let query = GraphQuery::traversal(Direction::Inbound, "users/alice")
+//!     .graph("org_chart")
+//!     .depth(1, 3)
+//!     .prune("v.role == @role")
+//!     .bind_var("role", "ceo")
+//!     .filter("e.active == true")
+//!     .unique_vertices(UniqueVertices::Path)
+//!     .build::<User, ReportsTo>()?;
+//! let steps: Vec<TraversalStep<User, ReportsTo>> = query.run(&db).await?;
+//! ```
+//!
+//! Conditions are plain AQL expressions over the variables `v` (vertex), `e`
+//! (edge) and `p` (path) for traversals and shortest paths, and `p` for the
+//! k-paths searches. Values in conditions should be bind parameters, whose
+//! names must not start with `traversal_`, which is used by the builder.
+use std::{collections::HashMap, marker::PhantomData};
+
+use maybe_async::maybe_async;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::{map::Map, value::Value};
+use thiserror::Error;
+
+use crate::{aql::AqlQuery, client::ClientExt, ClientError, Database};
+
+const RESERVED_PREFIX: &str = "traversal_";
+
+/// Why a graph query cannot be compiled.
+#[derive(Error, Debug, Clone, PartialEq, Eq)]
+pub enum GraphQueryError {
+    #[error("Neither a graph nor an edge collection was given")]
+    MissingEdges,
+    #[error("Minimum depth {min} is greater than the maximum depth {max}")]
+    InvalidDepth { min: u32, max: u32 },
+}
+
+/// Direction in which edges are followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Direction {
+    Outbound,
+    Inbound,
+    Any,
+}
+
+impl Direction {
+    fn keyword(self) -> &'static str {
+        match self {
+            Direction::Outbound => "OUTBOUND",
+            Direction::Inbound => "INBOUND",
+            Direction::Any => "ANY",
+        }
+    }
+}
+
+/// Whether a vertex may be visited more than once by a traversal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UniqueVertices {
+    /// No uniqueness check, the default.
+    None,
+    /// A vertex is visited at most once on each path.
+    Path,
+    /// A vertex is visited at most once in the whole traversal, which
+    /// requires breadth-first order.
+    Global,
+}
+
+/// Whether an edge may be followed more than once by a traversal.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum UniqueEdges {
+    /// No uniqueness check.
+    None,
+    /// An edge is followed at most once on each path, the default.
+    Path,
+}
+
+/// Order in which a traversal visits vertices.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub enum Order {
+    /// Depth-first, the default.
+    Dfs,
+    /// Breadth-first.
+    Bfs,
+    /// By increasing weight of the path, see
+    /// [`weight_attribute`](struct.GraphQuery.html#method.weight_attribute).
+    Weighted,
+}
+
+/// Kind of query: `FOR v, e, p IN min..max DIRECTION start`
+#[derive(Debug, Clone, Default)]
+pub struct Traversal {
+    prune: Vec<String>,
+}
+
+/// Kind of query: `FOR v, e IN DIRECTION SHORTEST_PATH start TO target`
+#[derive(Debug, Clone)]
+pub struct ShortestPath {
+    target: Value,
+}
+
+/// Kind of query: `FOR p IN DIRECTION K_SHORTEST_PATHS start TO target`
+#[derive(Debug, Clone)]
+pub struct KShortestPaths {
+    target: Value,
+}
+
+/// Kind of query: `FOR p IN min..max DIRECTION K_PATHS start TO target`
+#[derive(Debug, Clone)]
+pub struct KPaths {
+    target: Value,
+}
+
+/// Kinds of query that accept a depth range.
+pub trait Ranged {}
+impl Ranged for Traversal {}
+impl Ranged for KPaths {}
+
+/// Kinds of query that can weigh edges.
+pub trait Weighted {}
+impl Weighted for Traversal {}
+impl Weighted for ShortestPath {}
+impl Weighted for KShortestPaths {}
+
+/// Where the edges come from.
+#[derive(Debug, Clone)]
+enum Edges {
+    None,
+    Graph(String),
+    Collections(Vec<String>),
+}
+
+/// Builder of a graph query of kind `K`.
+#[derive(Debug, Clone)]
+pub struct GraphQuery<K> {
+    direction: Direction,
+    start: Value,
+    edges: Edges,
+    depth: (u32, u32),
+    filters: Vec<String>,
+    options: Map<String, Value>,
+    limit: Option<u32>,
+    bind_vars: HashMap<String, Value>,
+    kind: K,
+}
+
+impl<K> GraphQuery<K> {
+    fn new<S: Into<String>>(direction: Direction, start: S, kind: K) -> Self {
+        GraphQuery {
+            direction,
+            start: Value::String(start.into()),
+            edges: Edges::None,
+            depth: (1, 1),
+            filters: Vec::new(),
+            options: Map::new(),
+            limit: None,
+            bind_vars: HashMap::new(),
+            kind,
+        }
+    }
+
+    /// Follow the edges of a named graph.
+    pub fn graph<S: Into<String>>(mut self, name: S) -> Self {
+        self.edges = Edges::Graph(name.into());
+        self
+    }
+
+    /// Follow the edges of an edge collection, can be called several times.
+    /// Ignored once a named graph is set.
+    pub fn edge_collection<S: Into<String>>(mut self, name: S) -> Self {
+        match &mut self.edges {
+            Edges::Collections(names) => names.push(name.into()),
+            Edges::None => self.edges = Edges::Collections(vec![name.into()]),
+            Edges::Graph(_) => {}
+        }
+        self
+    }
+
+    /// Keep only results for which the condition holds, can be called several
+    /// times. The search itself is not affected, see
+    /// [`prune`](#method.prune) to stop following a path.
+    pub fn filter<S: Into<String>>(mut self, condition: S) -> Self {
+        self.filters.push(condition.into());
+        self
+    }
+
+    /// Return at most this many results.
+    pub fn limit(mut self, limit: u32) -> Self {
+        self.limit = Some(limit);
+        self
+    }
+
+    /// Bind a parameter used in a condition.
+    ///
+    /// # Panics
+    /// Panics when the name starts with `traversal_` or, for a collection
+    /// parameter, `@traversal_`.
+    pub fn bind_var<S, V>(mut self, key: S, value: V) -> Self
+    where
+        S: Into<String>,
+        V: Into<Value>,
+    {
+        let key = key.into();
+        assert!(
+            !key.trim_start_matches('@').starts_with(RESERVED_PREFIX),
+            "bind parameter {} uses the reserved prefix {}",
+            key,
+            RESERVED_PREFIX
+        );
+        self.bind_vars.insert(key, value.into());
+        self
+    }
+
+    /// Bind any serializable value as a parameter used in a condition.
+    pub fn try_bind<S, V>(self, key: S, value: V) -> Result<Self, serde_json::Error>
+    where
+        S: Into<String>,
+        V: Serialize,
+    {
+        Ok(self.bind_var(key, serde_json::to_value(value)?))
+    }
+
+    fn option<V: Serialize>(mut self, name: &str, value: V) -> Self {
+        self.options
+            .insert(name.to_string(), serde_json::to_value(value).unwrap());
+        self
+    }
+
+    /// Renders `DIRECTION [path] @start [TO @target] GRAPH @graph`, binding
+    /// the vertices and edges.
+    fn source(&mut self, path: &str, target: Option<Value>) -> String {
+        let mut source = format!(
+            "{} {}@{}start",
+            self.direction.keyword(),
+            path,
+            RESERVED_PREFIX
+        );
+        self.bind_vars
+            .insert(format!("{}start", RESERVED_PREFIX), self.start.take());
+        if let Some(target) = target {
+            source.push_str(&format!(" TO @{}target", RESERVED_PREFIX));
+            self.bind_vars
+                .insert(format!("{}target", RESERVED_PREFIX), target);
+        }
+        match &self.edges {
+            Edges::Graph(name) => {
+                source.push_str(&format!(" GRAPH @{}graph", RESERVED_PREFIX));
+                self.bind_vars.insert(
+                    format!("{}graph", RESERVED_PREFIX),
+                    Value::String(name.clone()),
+                );
+            }
+            Edges::Collections(names) => {
+                let mut parameters = Vec::new();
+                for (i, name) in names.iter().enumerate() {
+                    let key = format!("@{}edges{}", RESERVED_PREFIX, i);
+                    parameters.push(format!("@{}", key));
+                    self.bind_vars.insert(key, Value::String(name.clone()));
+                }
+                source.push(' ');
+                source.push_str(&parameters.join(", "));
+            }
+            Edges::None => unreachable!("checked by compile"),
+        }
+        source
+    }
+
+    /// Renders the clauses after the source and the `RETURN`.
+    fn tail(&mut self, prune: &[String], projection: &str) -> String {
+        let mut tail = String::new();
+        if !prune.is_empty() {
+            let conditions: Vec<String> = prune.iter().map(|x| format!("({})", x)).collect();
+            tail.push_str(&format!("\n  PRUNE {}", conditions.join(" OR ")));
+        }
+        if !self.options.is_empty() {
+            tail.push_str(&format!(
+                "\n  OPTIONS {}",
+                Value::Object(self.options.clone())
+            ));
+        }
+        for condition in &self.filters {
+            tail.push_str(&format!("\n  FILTER {}", condition));
+        }
+        if let Some(limit) = self.limit {
+            tail.push_str(&format!("\n  LIMIT @{}limit", RESERVED_PREFIX));
+            self.bind_vars
+                .insert(format!("{}limit", RESERVED_PREFIX), Value::from(limit));
+        }
+        tail.push_str(&format!("\n  RETURN {}", projection));
+        tail
+    }
+
+    fn compile<R>(
+        mut self,
+        head: &str,
+        path: &str,
+        target: Option<Value>,
+        prune: &[String],
+        projection: &str,
+    ) -> Result<CompiledQuery<R>, GraphQueryError> {
+        if let Edges::None = self.edges {
+            return Err(GraphQueryError::MissingEdges);
+        }
+        let source = self.source(path, target);
+        let tail = self.tail(prune, projection);
+        Ok(CompiledQuery {
+            query: format!("{} {}{}", head, source, tail),
+            bind_vars: self.bind_vars,
+            phantom: PhantomData,
+        })
+    }
+
+    fn depth_range(&self) -> Result<String, GraphQueryError> {
+        let (min, max) = self.depth;
+        if min > max {
+            return Err(GraphQueryError::InvalidDepth { min, max });
+        }
+        Ok(format!("{}..{}", min, max))
+    }
+}
+
+impl<K: Ranged> GraphQuery<K> {
+    /// Minimum and maximum number of edges between the start vertex and a
+    /// result, `1..1` unless set. `build` fails when `min` is greater than
+    /// `max`.
+    pub fn depth(mut self, min: u32, max: u32) -> Self {
+        self.depth = (min, max);
+        self
+    }
+}
+
+impl<K: Weighted> GraphQuery<K> {
+    /// Edge attribute holding the weight of an edge.
+    pub fn weight_attribute<S: Into<String>>(self, attribute: S) -> Self {
+        self.option("weightAttribute", attribute.into())
+    }
+
+    /// Weight of edges without the weight attribute, 1 unless set.
+    pub fn default_weight(self, weight: f64) -> Self {
+        self.option("defaultWeight", weight)
+    }
+}
+
+impl GraphQuery<Traversal> {
+    /// Visit the vertices reachable from `start`, a document id.
+    pub fn traversal<S: Into<String>>(direction: Direction, start: S) -> Self {
+        Self::new(direction, start, Traversal::default())
+    }
+
+    /// Do not follow a path any further once the condition holds, the vertex
+    /// itself is still returned. Several conditions are combined with `OR`.
+    pub fn prune<S: Into<String>>(mut self, condition: S) -> Self {
+        self.kind.prune.push(condition.into());
+        self
+    }
+
+    pub fn unique_vertices(self, unique: UniqueVertices) -> Self {
+        self.option("uniqueVertices", unique)
+    }
+
+    pub fn unique_edges(self, unique: UniqueEdges) -> Self {
+        self.option("uniqueEdges", unique)
+    }
+
+    pub fn order(self, order: Order) -> Self {
+        self.option("order", order)
+    }
+
+    /// Compile into a query returning one [`TraversalStep`] per visited
+    /// vertex.
+    ///
+    /// [`TraversalStep`]: struct.TraversalStep.html
+    pub fn build<V, E>(mut self) -> Result<CompiledQuery<TraversalStep<V, E>>, GraphQueryError> {
+        let prune = std::mem::take(&mut self.kind.prune);
+        let head = format!("FOR v, e, p IN {}", self.depth_range()?);
+        self.compile(&head, "", None, &prune, "{ vertex: v, edge: e, path: p }")
+    }
+}
+
+impl GraphQuery<ShortestPath> {
+    /// Find one shortest path between two vertices, given as document ids.
+    pub fn shortest_path<S, T>(direction: Direction, start: S, target: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let kind = ShortestPath {
+            target: Value::String(target.into()),
+        };
+        Self::new(direction, start, kind)
+    }
+
+    /// Compile into a query returning the vertices along the path, starting
+    /// with the start vertex.
+    pub fn build<V, E>(self) -> Result<CompiledQuery<PathStep<V, E>>, GraphQueryError> {
+        let target = self.kind.target.clone();
+        self.compile(
+            "FOR v, e IN",
+            "SHORTEST_PATH ",
+            Some(target),
+            &[],
+            "{ vertex: v, edge: e }",
+        )
+    }
+}
+
+impl GraphQuery<KShortestPaths> {
+    /// Find paths between two vertices, given as document ids, shortest first.
+    /// Use [`limit`](#method.limit), there may be a lot of them.
+    pub fn k_shortest_paths<S, T>(direction: Direction, start: S, target: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let kind = KShortestPaths {
+            target: Value::String(target.into()),
+        };
+        Self::new(direction, start, kind)
+    }
+
+    pub fn build<V, E>(self) -> Result<CompiledQuery<Path<V, E>>, GraphQueryError> {
+        let target = self.kind.target.clone();
+        self.compile("FOR p IN", "K_SHORTEST_PATHS ", Some(target), &[], "p")
+    }
+}
+
+impl GraphQuery<KPaths> {
+    /// Find all paths between two vertices, given as document ids, whose
+    /// length is within the depth range.
+    pub fn k_paths<S, T>(direction: Direction, start: S, target: T) -> Self
+    where
+        S: Into<String>,
+        T: Into<String>,
+    {
+        let kind = KPaths {
+            target: Value::String(target.into()),
+        };
+        Self::new(direction, start, kind)
+    }
+
+    pub fn build<V, E>(self) -> Result<CompiledQuery<Path<V, E>>, GraphQueryError> {
+        let target = self.kind.target.clone();
+        let head = format!("FOR p IN {}", self.depth_range()?);
+        self.compile(&head, "K_PATHS ", Some(target), &[], "p")
+    }
+}
+
+/// A graph query with its bind parameters, returning rows of type `R`.
+#[derive(Debug, Clone)]
+pub struct CompiledQuery<R> {
+    query: String,
+    bind_vars: HashMap<String, Value>,
+    phantom: PhantomData<R>,
+}
+
+impl<R> CompiledQuery<R> {
+    pub fn query(&self) -> &str {
+        &self.query
+    }
+
+    pub fn bind_vars(&self) -> &HashMap<String, Value> {
+        &self.bind_vars
+    }
+
+    /// The query to pass to `Database::aql_query` and the like, e.g. to set
+    /// a batch size.
+    pub fn aql(&self) -> AqlQuery<'_> {
+        let bind_vars = self
+            .bind_vars
+            .iter()
+            .map(|(k, v)| (k.as_str(), v.clone()))
+            .collect();
+        AqlQuery::builder()
+            .query(&self.query)
+            .bind_vars(bind_vars)
+            .build()
+    }
+}
+
+impl<R: DeserializeOwned> CompiledQuery<R> {
+    /// Execute the query and fetch all results.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn run<C: ClientExt>(&self, database: &Database<C>) -> Result<Vec<R>, ClientError> {
+        database.aql_query(self.aql()).await
+    }
+}
+
+/// A vertex visited by a traversal, with the edge and path leading to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct TraversalStep<V, E> {
+    pub vertex: V,
+    /// `None` for the start vertex when the minimum depth is 0.
+    pub edge: Option<E>,
+    pub path: Path<V, E>,
+}
+
+/// A vertex on a shortest path, with the edge leading to it.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PathStep<V, E> {
+    pub vertex: V,
+    /// `None` for the start vertex.
+    pub edge: Option<E>,
+}
+
+/// Vertices and edges of a path, from the start vertex on.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Path<V, E> {
+    pub vertices: Vec<V>,
+    pub edges: Vec<E>,
+    /// Sum of the edge weights, only set by `K_SHORTEST_PATHS`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub weight: Option<f64>,
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn traversal() {
+        let query = GraphQuery::traversal(Direction::Inbound, "users/alice")
+            .graph("org_chart")
+            .depth(1, 3)
+            .prune("v.role == @role")
+            .bind_var("role", "ceo")
+            .filter("e.active == true")
+            .unique_vertices(UniqueVertices::Path)
+            .limit(10)
+            .build::<Value, Value>()
+            .unwrap();
+        assert_eq!(
+            query.query(),
+            "FOR v, e, p IN 1..3 INBOUND @traversal_start GRAPH @traversal_graph\n  \
+             PRUNE (v.role == @role)\n  OPTIONS {\"uniqueVertices\":\"path\"}\n  \
+             FILTER e.active == true\n  LIMIT @traversal_limit\n  \
+             RETURN { vertex: v, edge: e, path: p }"
+        );
+        assert_eq!(query.bind_vars()["traversal_start"], json!("users/alice"));
+        assert_eq!(query.bind_vars()["traversal_graph"], json!("org_chart"));
+        assert_eq!(query.bind_vars()["traversal_limit"], json!(10));
+        assert_eq!(query.bind_vars()["role"], json!("ceo"));
+    }
+
+    #[test]
+    fn paths() {
+        let query = GraphQuery::shortest_path(Direction::Any, "users/a", "users/b")
+            .edge_collection("memberships")
+            .edge_collection("reports_to")
+            .weight_attribute("distance")
+            .build::<Value, Value>()
+            .unwrap();
+        assert_eq!(
+            query.query(),
+            "FOR v, e IN ANY SHORTEST_PATH @traversal_start TO @traversal_target \
+             @@traversal_edges0, @@traversal_edges1\n  \
+             OPTIONS {\"weightAttribute\":\"distance\"}\n  RETURN { vertex: v, edge: e }"
+        );
+        assert_eq!(query.bind_vars()["@traversal_edges1"], json!("reports_to"));
+
+        let query = GraphQuery::k_paths(Direction::Outbound, "users/a", "users/b")
+            .graph("org_chart")
+            .depth(2, 4)
+            .build::<Value, Value>()
+            .unwrap();
+        assert_eq!(
+            query.query(),
+            "FOR p IN 2..4 OUTBOUND K_PATHS @traversal_start TO @traversal_target \
+             GRAPH @traversal_graph\n  RETURN p"
+        );
+    }
+
+    #[test]
+    fn invalid_queries() {
+        let result = GraphQuery::traversal(Direction::Any, "users/a").build::<Value, Value>();
+        assert_eq!(result.unwrap_err(), GraphQueryError::MissingEdges);
+
+        let result = GraphQuery::k_paths(Direction::Any, "users/a", "users/b")
+            .graph("org_chart")
+            .depth(3, 2)
+            .build::<Value, Value>();
+        assert_eq!(
+            result.unwrap_err(),
+            GraphQueryError::InvalidDepth { min: 3, max: 2 }
+        );
+    }
+
+    #[test]
+    #[should_panic]
+    fn reserved_bind_var() {
+        GraphQuery::traversal(Direction::Any, "users/a").bind_var("traversal_start", "users/b");
+    }
+
+    #[test]
+    #[should_panic]
+    fn reserved_collection_bind_var() {
+        GraphQuery::traversal(Direction::Any, "users/a").bind_var("@traversal_edges0", "tags");
+    }
+}
@@ -0,0 +1,303 @@
+//! User management and access permissions.
+//!
+//! Users are managed through `GenericConnection<C, Admin>`, which requires
+//! read-write access to the `_system` database. Use
+//! [`into_admin`](../connection/struct.GenericConnection.html#method.into_admin)
+//! on a connection to obtain one.
+//!
+//! The various structures are following the HTTP specification as detailed in
+//! this ArangoDB [section](https://www.arangodb.com/docs/stable/http/user-management.html)
+use std::collections::HashMap;
+
+use maybe_async::maybe_async;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use typed_builder::TypedBuilder;
+use url::Url;
+
+use crate::{
+    client::ClientExt,
+    connection::{role::Admin, GenericConnection, Permission},
+    response::{deserialize_response, ArangoResult},
+    ClientError,
+};
+
+pub(crate) const USER_API_PATH: &str = "/_api/user/";
+
+/// Represents a user as returned by ArangoDB.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct User {
+    /// Name of the user
+    pub user: String,
+    /// Whether the user may log in
+    pub active: bool,
+    /// Arbitrary data stored with the user
+    #[serde(default)]
+    pub extra: Value,
+}
+
+/// Options for creating, updating or replacing a user.
+///
+/// Unset fields are left unchanged by an update, and take their default when
+/// a user is created or replaced.
+#[derive(Debug, Clone, Serialize, Deserialize, Default, TypedBuilder)]
+#[builder(doc)]
+pub struct UserOptions {
+    /// Password of the user, empty unless set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option, into))]
+    passwd: Option<String>,
+    /// Whether the user may log in, `true` unless set.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    active: Option<bool>,
+    /// Arbitrary data stored with the user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    #[builder(default, setter(strip_option))]
+    extra: Option<Value>,
+}
+
+#[derive(Debug, Serialize)]
+struct CreateUser<'a> {
+    user: &'a str,
+    #[serde(flatten)]
+    options: &'a UserOptions,
+}
+
+impl<C: ClientExt> GenericConnection<C, Admin> {
+    fn user_url(&self, path: &str) -> Url {
+        self.url().join(USER_API_PATH).unwrap().join(path).unwrap()
+    }
+
+    /// Create a user.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn create_user(&self, name: &str, options: UserOptions) -> Result<User, ClientError> {
+        let url = self.user_url("");
+        let body = CreateUser {
+            user: name,
+            options: &options,
+        };
+        let resp = self
+            .session()
+            .post(url, serde_json::to_string(&body)?)
+            .await?;
+        deserialize_response(resp.body())
+    }
+
+    /// Retrieve a user by name.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn user(&self, name: &str) -> Result<User, ClientError> {
+        let url = self.user_url(name);
+        let resp = self.session().get(url, "").await?;
+        deserialize_response(resp.body())
+    }
+
+    /// Retrieve all users.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn users(&self) -> Result<Vec<User>, ClientError> {
+        let url = self.user_url("");
+        let resp = self.session().get(url, "").await?;
+        let result: ArangoResult<Vec<User>> = deserialize_response(resp.body())?;
+        Ok(result.unwrap())
+    }
+
+    /// Change the fields of a user that are set in the options.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn update_user(&self, name: &str, options: UserOptions) -> Result<User, ClientError> {
+        let url = self.user_url(name);
+        let resp = self
+            .session()
+            .patch(url, serde_json::to_string(&options)?)
+            .await?;
+        deserialize_response(resp.body())
+    }
+
+    /// Replace the password, status and extra data of a user. Permissions are
+    /// not affected.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn replace_user(
+        &self,
+        name: &str,
+        options: UserOptions,
+    ) -> Result<User, ClientError> {
+        let url = self.user_url(name);
+        let resp = self
+            .session()
+            .put(url, serde_json::to_string(&options)?)
+            .await?;
+        deserialize_response(resp.body())
+    }
+
+    /// Delete a user together with its permissions.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn delete_user(&self, name: &str) -> Result<(), ClientError> {
+        let url = self.user_url(name);
+        let resp = self.session().delete(url, "").await?;
+        deserialize_response::<Value>(resp.body())?;
+        Ok(())
+    }
+
+    /// Databases a user can access, with the access level of each.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn user_databases(
+        &self,
+        name: &str,
+    ) -> Result<HashMap<String, Permission>, ClientError> {
+        let url = self.user_url(&format!("{}/database", name));
+        let resp = self.session().get(url, "").await?;
+        let result: ArangoResult<HashMap<String, Permission>> = deserialize_response(resp.body())?;
+        Ok(result.unwrap())
+    }
+
+    /// Access level of a user to a database.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn database_permission(
+        &self,
+        name: &str,
+        database: &str,
+    ) -> Result<Permission, ClientError> {
+        let url = self.user_url(&format!("{}/database/{}", name, database));
+        let resp = self.session().get(url, "").await?;
+        let result: ArangoResult<Permission> = deserialize_response(resp.body())?;
+        Ok(result.unwrap())
+    }
+
+    /// Set the access level of a user to a database.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn grant_database_permission(
+        &self,
+        name: &str,
+        database: &str,
+        permission: Permission,
+    ) -> Result<(), ClientError> {
+        let url = self.user_url(&format!("{}/database/{}", name, database));
+        let body = json!({ "grant": permission });
+        let resp = self.session().put(url, body.to_string()).await?;
+        deserialize_response::<Value>(resp.body())?;
+        Ok(())
+    }
+
+    /// Clear the access level of a user to a database, so that the default
+    /// of the user applies.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn revoke_database_permission(
+        &self,
+        name: &str,
+        database: &str,
+    ) -> Result<(), ClientError> {
+        let url = self.user_url(&format!("{}/database/{}", name, database));
+        let resp = self.session().delete(url, "").await?;
+        deserialize_response::<Value>(resp.body())?;
+        Ok(())
+    }
+
+    /// Access level of a user to a collection.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn collection_permission(
+        &self,
+        name: &str,
+        database: &str,
+        collection: &str,
+    ) -> Result<Permission, ClientError> {
+        let url = self.user_url(&format!("{}/database/{}/{}", name, database, collection));
+        let resp = self.session().get(url, "").await?;
+        let result: ArangoResult<Permission> = deserialize_response(resp.body())?;
+        Ok(result.unwrap())
+    }
+
+    /// Set the access level of a user to a collection.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn grant_collection_permission(
+        &self,
+        name: &str,
+        database: &str,
+        collection: &str,
+        permission: Permission,
+    ) -> Result<(), ClientError> {
+        let url = self.user_url(&format!("{}/database/{}/{}", name, database, collection));
+        let body = json!({ "grant": permission });
+        let resp = self.session().put(url, body.to_string()).await?;
+        deserialize_response::<Value>(resp.body())?;
+        Ok(())
+    }
+
+    /// Clear the access level of a user to a collection, so that the access
+    /// level to its database applies.
+    ///
+    /// # Note
+    /// this function would make a request to arango server.
+    #[maybe_async]
+    pub async fn revoke_collection_permission(
+        &self,
+        name: &str,
+        database: &str,
+        collection: &str,
+    ) -> Result<(), ClientError> {
+        let url = self.user_url(&format!("{}/database/{}/{}", name, database, collection));
+        let resp = self.session().delete(url, "").await?;
+        deserialize_response::<Value>(resp.body())?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn user_options() {
+        let options = UserOptions::builder().passwd("secret").active(true).build();
+        let body = CreateUser {
+            user: "groupware",
+            options: &options,
+        };
+        assert_eq!(
+            serde_json::to_value(&body).unwrap(),
+            json!({ "user": "groupware", "passwd": "secret", "active": true })
+        );
+        assert_eq!(
+            serde_json::to_value(UserOptions::default()).unwrap(),
+            json!({})
+        );
+
+        let text = "{\"error\":false,\"code\":200,\"result\":\"rw\"}";
+        let result = deserialize_response::<ArangoResult<Permission>>(text);
+        assert_eq!(result.unwrap().unwrap(), Permission::ReadWrite);
+    }
+}
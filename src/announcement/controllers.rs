@@ -0,0 +1,446 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::announcement::{
+    Announcement,
+    AnnouncementRequest,
+    AnnouncementResponse,
+    FindAnnouncementsParams,
+    Receipt,
+    ReceiptsResponse,
+    UnreadResponse,
+};
+use crate::database::{current_database, DbPool};
+use crate::job::{self, JobOptions};
+use crate::mailer::{MailPayload, SendMail};
+use crate::webhook::dispatch;
+
+const ACTIVE: &str = "FILTER a.expires == null OR a.expires > @now";
+
+// read state is the one of @user_key, null for company keys
+const ANNOUNCEMENT_TERMS: &str = r#"RETURN {
+        _key: a._key,
+        author_key: a.author_key,
+        author_name: DOCUMENT('users', a.author_key).name,
+        title: a.title,
+        body: a.body,
+        audience: a.audience,
+        pinned: a.pinned,
+        expires_at: a.expires_at,
+        acknowledgement_required: a.acknowledgement_required,
+        read_at: r.read_at,
+        acknowledged_at: r.acknowledged_at,
+        created_at: a.created_at,
+        modified_at: a.modified_at
+    }"#;
+
+const RECEIPT: &str = "LET r = FIRST(FOR x IN announcement_receipts FILTER x.announcement_key == a._key AND x.user_key == @user_key RETURN x)";
+
+// keeps announcements `a` addressed to the user whose key is `user`
+pub fn audience_filter(user: &str) -> String {
+    format!(r#"FILTER a.audience.everyone OR {0} IN a.audience.user_keys OR LENGTH(
+            FOR t IN taggings
+                FILTER t._from == CONCAT('users/', {0}) AND t.company_key == a.company_key AND t.name IN a.audience.tags
+                LIMIT 1
+                RETURN 1
+        ) > 0"#, user)
+}
+
+// members of the company the announcement `a` is addressed to
+fn audience_members() -> String {
+    format!(r#"LET audience = (
+            FOR m IN memberships
+                FILTER m._to == CONCAT('companies/', a.company_key)
+                LET u = PARSE_IDENTIFIER(m._from).key
+                {}
+                RETURN u
+        )"#, audience_filter("u"))
+}
+
+async fn find_announcement(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    user_key: Option<&str>,
+) -> Result<AnnouncementResponse, Error> {
+    let q = format!("FOR a IN announcements FILTER a._key == @key AND a.company_key == @company_key {} {}", RECEIPT, ANNOUNCEMENT_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<AnnouncementResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("announcement not found"))
+}
+
+// active and addressed to the user
+async fn is_addressed(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    user_key: &str,
+) -> Result<bool, Error> {
+    let q = format!(r#"FOR a IN announcements
+        FILTER a._key == @key AND a.company_key == @company_key
+        {} {}
+        RETURN 1"#, ACTIVE, audience_filter("@user_key"));
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("now", to_value(Utc::now().timestamp()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(!records.is_empty())
+}
+
+async fn write_receipt(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    user_key: &str,
+    acknowledged: bool,
+) -> Result<(), Error> {
+    let now = Utc::now();
+    let data = Receipt {
+        announcement_key: key.to_string(),
+        company_key: company_key.to_string(),
+        user_key: user_key.to_string(),
+        read_at: now,
+        acknowledged_at: if acknowledged { Some(now) } else { None },
+    };
+    let q = r#"UPSERT { announcement_key: @key, user_key: @user_key }
+        INSERT @data
+        UPDATE { acknowledged_at: OLD.acknowledged_at || @data.acknowledged_at }
+        IN announcement_receipts"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+fn to_announcement(
+    company_key: &str,
+    author_key: &str,
+    payload: &AnnouncementRequest,
+    created_at: DateTime<Utc>,
+) -> Announcement {
+    let mut audience = payload.audience.clone();
+    audience.tags.sort();
+    audience.tags.dedup();
+    audience.user_keys.sort();
+    audience.user_keys.dedup();
+    Announcement {
+        company_key: company_key.to_string(),
+        author_key: author_key.to_string(),
+        title: payload.title.trim().to_string(),
+        body: payload.body.clone(),
+        audience,
+        pinned: payload.pinned.unwrap_or(false),
+        expires_at: payload.expires_at,
+        expires: payload.expires_at.map(|x| x.timestamp()),
+        acknowledgement_required: payload.acknowledgement_required.unwrap_or(false),
+        created_at,
+        modified_at: Utc::now(),
+    }
+}
+
+// one queued mail per reader, a failed one is retried without holding back the others
+async fn notify_audience(
+    db: &Database<ReqwestClient>,
+    key: &str,
+    data: &Announcement,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let q = format!(r#"FOR a IN announcements
+        FILTER a._key == @key
+        {}
+        FOR u IN audience
+            LET email = DOCUMENT('users', u).email
+            FILTER email != null
+            RETURN email"#, audience_members());
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let emails: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+
+    let subject = data.title.clone();
+    let mut body = data.body.clone();
+    if data.acknowledgement_required {
+        body.push_str("\n\nPlease acknowledge this announcement once you have read it.");
+    }
+    for email in emails {
+        let payload = MailPayload {
+            to: email,
+            subject: subject.clone(),
+            body: body.clone(),
+        };
+        job::enqueue::<SendMail>(&payload, JobOptions::default(), pool).await?;
+    }
+    Ok(())
+}
+
+// pinned first, then newest; without a user the audience is not filtered
+#[instrument(skip_all)]
+pub async fn find_announcements(
+    company_key: &str,
+    user_key: Option<&str>,
+    params: &FindAnnouncementsParams,
+    pool: &DbPool,
+) -> Result<Vec<AnnouncementResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut filters = vec!["FILTER a.company_key == @company_key".to_string()];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    if !params.all.unwrap_or(false) {
+        filters.push(ACTIVE.to_string());
+        vars.insert("now", to_value(Utc::now().timestamp()).unwrap());
+        if user_key.is_some() {
+            filters.push(audience_filter("@user_key"));
+        }
+    }
+    let unread = if params.unread.unwrap_or(false) { "FILTER r == null" } else { "" };
+    let q = format!(
+        "FOR a IN announcements {} {} {} SORT a.pinned DESC, a.created_at DESC {}",
+        filters.join(" "), RECEIPT, unread, ANNOUNCEMENT_TERMS,
+    );
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<AnnouncementResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// reading an announcement addressed to the user leaves a receipt
+#[instrument(skip_all)]
+pub async fn show_announcement(
+    company_key: &str,
+    key: &str,
+    user_key: Option<&str>,
+    is_admin: bool,
+    pool: &DbPool,
+) -> Result<AnnouncementResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    if let Some(user_key) = user_key {
+        if is_addressed(&db, company_key, key, user_key).await? {
+            write_receipt(&db, company_key, key, user_key, false).await?;
+        } else if !is_admin {
+            return Err(ErrorNotFound("announcement not found"));
+        }
+    }
+    find_announcement(&db, company_key, key, user_key).await
+}
+
+#[instrument(skip_all)]
+pub async fn create_announcement(
+    company_key: &str,
+    author_key: &str,
+    payload: &AnnouncementRequest,
+    pool: &DbPool,
+) -> Result<AnnouncementResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("announcements").await.unwrap();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let data = to_announcement(company_key, author_key, payload, Utc::now());
+    let res: DocumentResponse<Document<Announcement>> = collection.create_document(Document::new(data.clone()), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+
+    dispatch(&db, "announcement.published", &[company_key.to_string()], None, Some(&data)).await;
+    if payload.notify.unwrap_or(false) {
+        notify_audience(&db, &header._key, &data, pool).await?;
+    }
+    find_announcement(&db, company_key, &header._key, None).await
+}
+
+// receipts survive edits, acknowledged readers are not asked again
+#[instrument(skip_all)]
+pub async fn update_announcement(
+    company_key: &str,
+    key: &str,
+    payload: &AnnouncementRequest,
+    pool: &DbPool,
+) -> Result<AnnouncementResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_announcement(&db, company_key, key, None).await?;
+    let data = to_announcement(company_key, &current.author_key, payload, current.created_at);
+    let q = "FOR a IN announcements FILTER a._key == @key REPLACE a WITH @data IN announcements";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_announcement(&db, company_key, key, None).await
+}
+
+#[instrument(skip_all)]
+pub async fn delete_announcement(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_announcement(&db, company_key, key, None).await?;
+    for q in [
+        "FOR r IN announcement_receipts FILTER r.announcement_key == @key REMOVE r IN announcement_receipts",
+        "REMOVE @key IN announcements",
+    ] {
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("key", to_value(key).unwrap());
+
+        let aql = AqlQuery::builder()
+            .query(q)
+            .bind_vars(vars)
+            .build();
+        let _records: Vec<Value> = db.aql_query(aql).await
+            .map_err(ErrorInternalServerError)?;
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn acknowledge_announcement(
+    company_key: &str,
+    key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<AnnouncementResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    if !is_addressed(&db, company_key, key, user_key).await? {
+        return Err(ErrorNotFound("announcement not found"));
+    }
+    write_receipt(&db, company_key, key, user_key, true).await?;
+    find_announcement(&db, company_key, key, Some(user_key)).await
+}
+
+#[instrument(skip_all)]
+pub async fn count_unread(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<UnreadResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(r#"FOR a IN announcements
+        FILTER a.company_key == @company_key
+        {} {} {}
+        COLLECT AGGREGATE
+            unread = SUM(r == null ? 1 : 0),
+            unacknowledged = SUM(a.acknowledgement_required AND r.acknowledged_at == null ? 1 : 0)
+        RETURN {{ unread: unread || 0, unacknowledged: unacknowledged || 0 }}"#, ACTIVE, audience_filter("@user_key"), RECEIPT);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("now", to_value(Utc::now().timestamp()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<UnreadResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records.pop().unwrap_or(UnreadResponse { unread: 0, unacknowledged: 0 }))
+}
+
+// who among the current audience has not acknowledged yet
+#[instrument(skip_all)]
+pub async fn find_receipts(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<ReceiptsResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(r#"FOR a IN announcements
+        FILTER a._key == @key AND a.company_key == @company_key
+        {}
+        LET receipts = (
+            FOR r IN announcement_receipts
+                FILTER r.announcement_key == a._key AND r.user_key IN audience
+                RETURN r
+        )
+        RETURN {{
+            audience: LENGTH(audience),
+            read: LENGTH(receipts),
+            acknowledged: LENGTH(receipts[* FILTER CURRENT.acknowledged_at != null]),
+            not_acknowledged: (
+                FOR u IN audience
+                    LET r = FIRST(receipts[* FILTER CURRENT.user_key == u])
+                    FILTER r.acknowledged_at == null
+                    LET user = DOCUMENT('users', u)
+                    SORT user.name ASC
+                    RETURN {{ user_key: u, name: user.name, email: user.email, read_at: r.read_at }}
+            )
+        }}"#, audience_members());
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<ReceiptsResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("announcement not found"))
+}
@@ -0,0 +1,84 @@
+use async_trait::async_trait;
+use arangors::AqlQuery;
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::HashMap;
+
+use crate::announcement::audience_filter;
+use crate::database::{current_database, DbPool};
+use crate::job::{enqueue, JobHandler, JobOptions};
+use crate::mailer::{MailPayload, SendMail};
+
+const DIGEST_DAYS: i64 = 7;
+
+#[derive(Deserialize)]
+struct Item {
+    company: String,
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct Digest {
+    email: String,
+    name: String,
+    items: Vec<Item>,
+}
+
+// mails every user the announcements of the past week that were addressed to them and are still unread
+pub struct WeeklyDigest;
+
+#[async_trait(?Send)]
+impl JobHandler for WeeklyDigest {
+    const KIND: &'static str = "weekly_digest";
+    const MAX_ATTEMPTS: u32 = 1;
+
+    type Payload = ();
+
+    async fn run(_payload: (), pool: &DbPool) -> Result<(), String> {
+        let client = pool.get().await.unwrap();
+        let db = client.db(&current_database()).await.map_err(|e| e.to_string())?;
+
+        let q = format!(r#"FOR u IN users
+            FILTER u.deleted_at == null
+            LET items = (
+                FOR m IN memberships
+                    FILTER m._from == u._id
+                    FOR a IN announcements
+                        FILTER a.company_key == PARSE_IDENTIFIER(m._to).key
+                        FILTER DATE_DIFF(a.created_at, DATE_NOW(), 'd') < @days
+                        FILTER a.expires == null OR a.expires > DATE_NOW() / 1000
+                        {}
+                        FILTER LENGTH(
+                            FOR x IN announcement_receipts
+                                FILTER x.announcement_key == a._key AND x.user_key == u._key
+                                LIMIT 1
+                                RETURN 1
+                        ) == 0
+                        SORT a.created_at DESC
+                        RETURN {{ company: DOCUMENT(m._to).name, title: a.title }}
+            )
+            FILTER LENGTH(items) > 0
+            RETURN {{ email: u.email, name: u.name, items }}"#, audience_filter("u._key"));
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("days", Value::from(DIGEST_DAYS));
+
+        let aql = AqlQuery::builder()
+            .query(&q)
+            .bind_vars(vars)
+            .build();
+        let records: Vec<Digest> = db.aql_query(aql).await.map_err(|e| e.to_string())?;
+        for record in records {
+            let lines: Vec<String> = record.items.iter()
+                .map(|x| format!("- {}: {}", x.company, x.title))
+                .collect();
+            let payload = MailPayload {
+                to: record.email,
+                subject: "Your weekly digest".to_string(),
+                body: format!("Hi {},\n\nthese announcements of the past week are still unread:\n\n{}", record.name, lines.join("\n")),
+            };
+            enqueue::<SendMail>(&payload, JobOptions::default(), pool).await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod routes;
+mod digest;
+
+pub use models::*;
+pub use controllers::*;
+pub use routes::init;
+pub use digest::WeeklyDigest;
@@ -0,0 +1,113 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+// departments are the company tags put on users
+#[derive(Clone, Debug, Validate, Serialize, Deserialize)]
+pub struct Audience {
+    #[serde(default)]
+    pub everyone: bool,
+    #[validate(length(max = 50))]
+    #[serde(default)]
+    pub tags: Vec<String>,
+    #[validate(length(max = 1000))]
+    #[serde(default)]
+    pub user_keys: Vec<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_announcement"))]
+pub struct AnnouncementRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(min = 1, max = 20000))]
+    pub body: String,
+    #[validate]
+    pub audience: Audience,
+    pub pinned: Option<bool>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub acknowledgement_required: Option<bool>,
+    pub notify: Option<bool>, // email the audience, on creation only
+}
+
+fn validate_announcement(payload: &AnnouncementRequest) -> Result<(), ValidationError> {
+    let audience = &payload.audience;
+    if !audience.everyone && audience.tags.is_empty() && audience.user_keys.is_empty() {
+        return Err(ValidationError::new("empty_audience"));
+    }
+    if payload.expires_at.map_or(false, |x| x <= Utc::now()) {
+        return Err(ValidationError::new("already_expired"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct FindAnnouncementsParams {
+    pub all: Option<bool>, // admins only, every announcement including expired ones
+    pub unread: Option<bool>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Announcement {
+    pub company_key: String,
+    pub author_key: String,
+    pub title: String,
+    pub body: String,
+    pub audience: Audience,
+    pub pinned: bool,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub expires_at: Option<DateTime<Utc>>,
+    pub expires: Option<i64>, // unix seconds of expires_at, compared numerically in queries
+    pub acknowledgement_required: bool,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+// one per user and announcement, written on first read
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Receipt {
+    pub announcement_key: String,
+    pub company_key: String,
+    pub user_key: String,
+    pub read_at: DateTime<Utc>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct AnnouncementResponse {
+    pub _key: String,
+    pub author_key: String,
+    pub author_name: Option<String>,
+    pub title: String,
+    pub body: String,
+    pub audience: Audience,
+    pub pinned: bool,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub acknowledgement_required: bool,
+    pub read_at: Option<DateTime<Utc>>,
+    pub acknowledged_at: Option<DateTime<Utc>>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct UnreadResponse {
+    pub unread: i64,
+    pub unacknowledged: i64,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PendingReader {
+    pub user_key: String,
+    pub name: Option<String>,
+    pub email: Option<String>,
+    pub read_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReceiptsResponse {
+    pub audience: i64,
+    pub read: i64,
+    pub acknowledged: i64,
+    pub not_acknowledged: Vec<PendingReader>,
+}
@@ -0,0 +1,161 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::announcement::{self, AnnouncementRequest, FindAnnouncementsParams};
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// members read what is addressed to them, company keys see everything
+async fn require_reader(
+    req: &HttpRequest,
+    key: &str,
+    scope: &str,
+    pool: &DbPool,
+) -> Result<Option<String>, Error> {
+    let identity = company::require_access(req, key, scope)?;
+    match identity.user_key() {
+        Some(user_key) if !company::is_company_member(key, user_key, pool).await => {
+            Err(ErrorForbidden("company member required"))
+        },
+        Some(user_key) => Ok(Some(user_key.to_string())),
+        None => Ok(None),
+    }
+}
+
+#[get("/companies/{key}/announcements")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindAnnouncementsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_reader(&req, &key, "announcements:read", &pool).await?;
+    let params: FindAnnouncementsParams = payload.into_inner();
+    if params.all.unwrap_or(false) {
+        company::require_admin(&req, &key, &pool).await?;
+    }
+    let result = announcement::find_announcements(&key, user_key.as_deref(), &params, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// registered before show so that it is not taken for a key
+#[get("/companies/{key}/announcements/unread")]
+async fn count_unread(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_reader(&req, &key, "announcements:read", &pool).await?
+        .ok_or_else(|| ErrorForbidden("read state belongs to users"))?;
+    let result = announcement::count_unread(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/companies/{key}/announcements/{announcement_key}")]
+async fn show(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, announcement_key) = path.into_inner();
+    let user_key = require_reader(&req, &key, "announcements:read", &pool).await?;
+    let is_admin = match &user_key {
+        Some(user_key) => company::is_company_admin(&key, user_key, &pool).await,
+        None => true,
+    };
+    let result = announcement::show_announcement(&key, &announcement_key, user_key.as_deref(), is_admin, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/announcements")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<AnnouncementRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = company::require_admin(&req, &key, &pool).await?;
+    let author_key = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("announcements are posted by users"))?;
+    let params: AnnouncementRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = announcement::create_announcement(&key, author_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/announcements/{announcement_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<AnnouncementRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, announcement_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: AnnouncementRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = announcement::update_announcement(&key, &announcement_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/announcements/{announcement_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, announcement_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    announcement::delete_announcement(&key, &announcement_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/companies/{key}/announcements/{announcement_key}/acknowledgement")]
+async fn acknowledge(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, announcement_key) = path.into_inner();
+    let user_key = require_reader(&req, &key, "announcements:write", &pool).await?
+        .ok_or_else(|| ErrorForbidden("announcements are acknowledged by users"))?;
+    let result = announcement::acknowledge_announcement(&key, &announcement_key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/companies/{key}/announcements/{announcement_key}/receipts")]
+async fn find_receipts(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, announcement_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let result = announcement::find_receipts(&key, &announcement_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(count_unread);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(acknowledge);
+    cfg.service(find_receipts);
+}
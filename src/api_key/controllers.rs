@@ -0,0 +1,239 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{
+        options::{InsertOptions, UpdateOptions},
+        response::DocumentResponse,
+    },
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::{prelude::*, Duration};
+use rand::{distributions::Alphanumeric, Rng};
+use serde_json::{json, to_value, Value};
+use sha2::{Digest, Sha256};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::api_key::{
+    ApiKey,
+    ApiKeyResponse,
+    CreateApiKeyRequest,
+    CreatedApiKeyResponse,
+    API_KEY_PREFIX,
+};
+use crate::auth::{Identity, Principal};
+use crate::database::{current_database, DbPool};
+
+const PREFIX_LENGTH: usize = 12;
+const SECRET_LENGTH: usize = 32;
+const LAST_USED_RESOLUTION_SECONDS: i64 = 60; // avoids a write on every request
+
+fn random_string(length: usize) -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(length)
+        .map(char::from)
+        .collect()
+}
+
+fn hash_secret(secret: &str) -> String {
+    format!("{:x}", Sha256::digest(secret.as_bytes()))
+}
+
+// tokens look like gw_{prefix}_{secret}
+fn parse_token(token: &str) -> Option<(&str, &str)> {
+    let (prefix, secret) = token.strip_prefix(API_KEY_PREFIX)?.split_once('_')?;
+    if prefix.len() != PREFIX_LENGTH || secret.len() != SECRET_LENGTH {
+        return None;
+    }
+    Some((prefix, secret))
+}
+
+fn to_response(key: &Document<ApiKey>) -> ApiKeyResponse {
+    ApiKeyResponse {
+        prefix: key.header._key.clone(),
+        name: key.name.clone(),
+        scopes: key.scopes.clone(),
+        created_by: key.created_by.clone(),
+        created_at: key.created_at,
+        last_used_at: key.last_used_at,
+        expires_at: key.expires_at,
+        revoked_at: key.revoked_at,
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn verify_api_key(
+    token: &str,
+    pool: &DbPool,
+) -> Option<Identity> {
+    let (prefix, secret) = parse_token(token)?;
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("api_keys").await.unwrap();
+    let key: Document<ApiKey> = collection.document(prefix).await.ok()?;
+    if key.secret != hash_secret(secret) || key.revoked_at.is_some() {
+        return None;
+    }
+    let now = Utc::now();
+    if key.expires_at.map_or(false, |x| x <= now) {
+        return None;
+    }
+
+    let stale = key.last_used_at
+        .map_or(true, |x| now - x > Duration::seconds(LAST_USED_RESOLUTION_SECONDS));
+    if stale {
+        let data: Value = json!({
+            "last_used_at": now,
+        });
+        let options: UpdateOptions = UpdateOptions::builder()
+            .return_new(false)
+            .build();
+        let _res: Result<DocumentResponse<Document<Value>>, _> = collection.update_document(prefix, Document::new(data), options).await;
+    }
+
+    let principal = match key.owner.split_once('/')? {
+        ("users", owner_key) => Principal::User(owner_key.to_string()),
+        ("companies", owner_key) => Principal::Company(owner_key.to_string()),
+        _ => return None,
+    };
+    Some(Identity {
+        principal,
+        session_key: None,
+        scopes: Some(key.document.scopes),
+        restricted: false,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn create_api_key(
+    owner: &str,
+    created_by: &str,
+    payload: &CreateApiKeyRequest,
+    pool: &DbPool,
+) -> Result<CreatedApiKeyResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("api_keys").await.unwrap();
+    let prefix = random_string(PREFIX_LENGTH);
+    let secret = random_string(SECRET_LENGTH);
+
+    let mut scopes = payload.scopes.clone();
+    scopes.sort();
+    scopes.dedup();
+
+    let mut doc = Document::new(ApiKey {
+        owner: owner.to_string(),
+        name: payload.name.trim().to_string(),
+        secret: hash_secret(&secret),
+        scopes,
+        created_by: created_by.to_string(),
+        created_at: Utc::now(),
+        last_used_at: None,
+        expires_at: payload.expires_at,
+        revoked_at: None,
+    });
+    doc.header._key = prefix.clone();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(true)
+        .build();
+
+    let res: DocumentResponse<Document<ApiKey>> = collection.create_document(doc, options).await
+        .map_err(ErrorInternalServerError)?;
+    let record = res.new_doc().unwrap();
+
+    Ok(CreatedApiKeyResponse {
+        token: format!("{}{}_{}", API_KEY_PREFIX, prefix, secret),
+        key: to_response(record),
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn find_api_keys(
+    owner: &str,
+    pool: &DbPool,
+) -> Result<Vec<ApiKeyResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR k IN api_keys
+        FILTER k.owner == @owner
+        SORT k.created_at DESC
+        RETURN {
+            prefix: k._key,
+            name: k.name,
+            scopes: k.scopes,
+            created_by: k.created_by,
+            created_at: k.created_at,
+            last_used_at: k.last_used_at,
+            expires_at: k.expires_at,
+            revoked_at: k.revoked_at
+        }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner", to_value(owner).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<ApiKeyResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// keys are kept after revocation so that their usage stays auditable
+#[instrument(skip_all)]
+pub async fn revoke_api_key(
+    owner: &str,
+    prefix: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR k IN api_keys
+        FILTER k._key == @prefix AND k.owner == @owner
+        UPDATE k WITH { revoked_at: NOT_NULL(k.revoked_at, @now) } IN api_keys
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("prefix", to_value(prefix).unwrap());
+    vars.insert("owner", to_value(owner).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let revoked: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if revoked.is_empty() {
+        return Err(ErrorNotFound("api key not found"));
+    }
+    Ok(())
+}
+
+// keys of a trashed or erased user stop working with it
+#[instrument(skip_all)]
+pub async fn revoke_owner_api_keys(
+    db: &Database<ReqwestClient>,
+    owner: &str,
+) {
+    let q = r#"FOR k IN api_keys
+        FILTER k.owner == @owner AND k.revoked_at == null
+        UPDATE k WITH { revoked_at: @now } IN api_keys"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner", to_value(owner).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+}
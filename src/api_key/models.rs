@@ -0,0 +1,80 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+pub const API_KEY_PREFIX: &str = "gw_";
+pub const SCOPES: [&str; 20] = [
+    "companies:read",
+    "companies:write",
+    "users:read",
+    "users:write",
+    "comments:read",
+    "comments:write",
+    "events:read",
+    "events:write",
+    "resources:read",
+    "resources:write",
+    "time:read",
+    "time:write",
+    "announcements:read",
+    "announcements:write",
+    "wiki:read",
+    "wiki:write",
+    "contacts:read",
+    "contacts:write",
+    "messages:read",
+    "messages:write",
+];
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(custom = "validate_scopes")]
+    pub scopes: Vec<String>,
+    pub expires_at: Option<DateTime<Utc>>,
+}
+
+fn validate_scopes(scopes: &[String]) -> Result<(), ValidationError> {
+    if scopes.is_empty() || !scopes.iter().all(|x| SCOPES.contains(&x.as_str())) {
+        return Err(ValidationError::new("wrong_scopes"));
+    }
+    Ok(())
+}
+
+// the document key doubles as the public prefix used to look a key up
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ApiKey {
+    pub owner: String, // users/{key} or companies/{key}
+    pub name: String,
+    pub secret: String, // sha256 hex of the secret half of the token
+    pub scopes: Vec<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub last_used_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub expires_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ApiKeyResponse {
+    pub prefix: String,
+    pub name: String,
+    pub scopes: Vec<String>,
+    pub created_by: String,
+    pub created_at: DateTime<Utc>,
+    pub last_used_at: Option<DateTime<Utc>>,
+    pub expires_at: Option<DateTime<Utc>>,
+    pub revoked_at: Option<DateTime<Utc>>,
+}
+
+// the token is only ever returned once, when the key is created
+#[derive(Debug, Serialize)]
+pub struct CreatedApiKeyResponse {
+    pub token: String,
+    #[serde(flatten)]
+    pub key: ApiKeyResponse,
+}
@@ -0,0 +1,126 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::api_key::{self, CreateApiKeyRequest};
+use crate::auth::{self, Identity};
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// users manage their own keys from a session
+fn require_self(req: &HttpRequest, key: &str) -> Result<Identity, Error> {
+    let identity = auth::authenticate_session(req)?;
+    if identity.user_key() != Some(key) {
+        return Err(ErrorForbidden("not allowed to manage api keys of another user"));
+    }
+    Ok(identity)
+}
+
+async fn require_company_admin(
+    req: &HttpRequest,
+    key: &str,
+    pool: &DbPool,
+) -> Result<Identity, Error> {
+    let identity = auth::authenticate_session(req)?;
+    let user_key = identity.user_key().unwrap_or("");
+    if !company::is_company_admin(key, user_key, pool).await {
+        return Err(ErrorForbidden("company admin required"));
+    }
+    Ok(identity)
+}
+
+async fn create(
+    owner: &str,
+    created_by: &str,
+    params: CreateApiKeyRequest,
+    pool: &DbPool,
+) -> Result<HttpResponse, Error> {
+    match params.validate() {
+        Ok(_) => {
+            let result = api_key::create_api_key(owner, created_by, &params, pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/users/{key}/api-keys")]
+async fn find_user_keys(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_self(&req, &key)?;
+    let result = api_key::find_api_keys(&format!("users/{}", key), &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/users/{key}/api-keys")]
+async fn create_user_key(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreateApiKeyRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_self(&req, &key)?;
+    create(&format!("users/{}", key), &key, payload.into_inner(), &pool).await
+}
+
+#[delete("/users/{key}/api-keys/{prefix}")]
+async fn revoke_user_key(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, prefix) = path.into_inner();
+    require_self(&req, &key)?;
+    api_key::revoke_api_key(&format!("users/{}", key), &prefix, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/api-keys")]
+async fn find_company_keys(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_company_admin(&req, &key, &pool).await?;
+    let result = api_key::find_api_keys(&format!("companies/{}", key), &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/api-keys")]
+async fn create_company_key(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreateApiKeyRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = require_company_admin(&req, &key, &pool).await?;
+    let created_by = identity.user_key().unwrap_or("");
+    create(&format!("companies/{}", key), created_by, payload.into_inner(), &pool).await
+}
+
+#[delete("/companies/{key}/api-keys/{prefix}")]
+async fn revoke_company_key(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, prefix) = path.into_inner();
+    require_company_admin(&req, &key, &pool).await?;
+    api_key::revoke_api_key(&format!("companies/{}", key), &prefix, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find_user_keys);
+    cfg.service(create_user_key);
+    cfg.service(revoke_user_key);
+    cfg.service(find_company_keys);
+    cfg.service(create_company_key);
+    cfg.service(revoke_company_key);
+}
@@ -0,0 +1,794 @@
+use actix_web::{
+    error::{
+        ErrorBadRequest,
+        ErrorConflict,
+        ErrorForbidden,
+        ErrorInternalServerError,
+        ErrorTooManyRequests,
+        ErrorUnauthorized,
+    },
+    http::{header::AUTHORIZATION, StatusCode},
+    Error,
+    HttpMessage,
+    HttpRequest,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{
+        options::{InsertOptions, RemoveOptions, UpdateOptions},
+        response::DocumentResponse,
+    },
+    AqlQuery, Collection, Database, Document,
+};
+use bcrypt::{DEFAULT_COST, hash, verify};
+use chrono::{prelude::*, Duration};
+use serde_json::{json, to_value, Value};
+use std::collections::HashMap;
+use tracing::{error, instrument};
+use uuid::Uuid;
+
+use crate::api_key::{verify_api_key, API_KEY_PREFIX};
+use crate::auth::{
+    totp,
+    ChallengeResponse,
+    ChangePasswordRequest,
+    Credentials,
+    DisableTwoFactorRequest,
+    ForgotPasswordRequest,
+    Identity,
+    LoginChallenge,
+    LoginRequest,
+    LoginResponse,
+    PasswordReset,
+    Principal,
+    RecoveryCodesResponse,
+    ResetPasswordRequest,
+    Session,
+    SessionResponse,
+    TwoFactor,
+    TwoFactorCodeRequest,
+    TwoFactorLoginRequest,
+    TwoFactorSetupResponse,
+    CHALLENGE_LIFETIME_MINUTES,
+    RESET_TOKEN_LIFETIME_MINUTES,
+    SESSION_LIFETIME_DAYS,
+    TWO_FACTOR_LOCKOUT_MINUTES,
+    TWO_FACTOR_MAX_FAILURES,
+};
+use crate::database::{current_database, DbPool};
+use crate::i18n::failure;
+use crate::mailer::send_mail;
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().to_simple(), Uuid::new_v4().to_simple())
+}
+
+pub fn bearer_token(req: &HttpRequest) -> Option<String> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let token = value.strip_prefix("Bearer ")?.trim();
+    if token.is_empty() {
+        return None;
+    }
+    Some(token.to_string())
+}
+
+const CREDENTIALS_TERMS: &str = r#"RETURN {
+        _key: x._key,
+        email: x.email,
+        password: x.password,
+        two_factor: x.two_factor,
+        two_factor_pending: x.two_factor_pending,
+        two_factor_required: LENGTH(
+            FOR c IN 1..1 OUTBOUND x memberships
+                FILTER c.require_two_factor == true
+                LIMIT 1
+                RETURN 1
+        ) > 0
+    }"#;
+
+async fn find_credentials(
+    db: &Database<ReqwestClient>,
+    email: &str,
+) -> Option<Credentials> {
+    let q = format!("FOR x IN users FILTER x.email == @email AND x.deleted_at == null LIMIT 1 {}", CREDENTIALS_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("email", to_value(email).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<Credentials> = db.aql_query(aql).await.unwrap();
+    records.pop()
+}
+
+async fn credentials_by_key(
+    db: &Database<ReqwestClient>,
+    key: &str,
+) -> Option<Credentials> {
+    let q = format!("FOR x IN users FILTER x._key == @key AND x.deleted_at == null LIMIT 1 {}", CREDENTIALS_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<Credentials> = db.aql_query(aql).await.unwrap();
+    records.pop()
+}
+
+async fn update_user_fields(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    data: Value,
+) -> Result<(), Error> {
+    let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
+    let options: UpdateOptions = UpdateOptions::builder()
+        .return_new(false)
+        .keep_null(false)
+        .merge_objects(false)
+        .build();
+
+    let _res: DocumentResponse<Document<Value>> = collection.update_document(user_key, Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+async fn create_session(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    restricted: bool,
+) -> SessionResponse {
+    let collection: Collection<ReqwestClient> = db.collection("sessions").await.unwrap();
+    let now = Utc::now();
+    let token = random_token();
+
+    let mut doc = Document::new(Session {
+        user_key: user_key.to_string(),
+        created_at: now,
+        expires_at: now + Duration::days(SESSION_LIFETIME_DAYS),
+        restricted,
+    });
+    doc.header._key = token.clone();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(true)
+        .build();
+
+    let res: DocumentResponse<Document<Session>> = collection.create_document(doc, options).await.unwrap();
+    let record: &Session = res.new_doc().unwrap();
+    SessionResponse {
+        token,
+        user_key: record.user_key.clone(),
+        expires_at: record.expires_at,
+        two_factor_enrolment_required: record.restricted,
+    }
+}
+
+async fn store_password(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    password: &str,
+) -> Result<(), Error> {
+    let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
+    let data: Value = json!({
+        "password": hash(password, DEFAULT_COST).unwrap(),
+        "modified_at": Utc::now(),
+    });
+    let options: UpdateOptions = UpdateOptions::builder()
+        .return_new(false)
+        .build();
+
+    let _res: DocumentResponse<Document<Value>> = collection.update_document(user_key, Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// drop every session of the user, optionally keeping the one that made the request
+#[instrument(skip_all)]
+pub async fn revoke_sessions(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    except: Option<&str>,
+) {
+    let q = "FOR s IN sessions FILTER s.user_key == @user_key AND s._key != @except REMOVE s IN sessions";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("except", to_value(except.unwrap_or("")).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+}
+
+async fn find_session(
+    token: &str,
+    pool: &DbPool,
+) -> Option<Identity> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("sessions").await.unwrap();
+    let session: Document<Session> = collection.document(token).await.ok()?;
+    if session.expires_at <= Utc::now() {
+        return None;
+    }
+    Some(Identity {
+        principal: Principal::User(session.user_key.clone()),
+        session_key: Some(session.header._key.clone()),
+        scopes: None,
+        restricted: session.restricted,
+    })
+}
+
+// resolves a bearer token, which is either a session or an api key
+#[instrument(skip_all)]
+pub async fn identify(
+    token: &str,
+    pool: &DbPool,
+) -> Option<Identity> {
+    if token.starts_with(API_KEY_PREFIX) {
+        verify_api_key(token, pool).await
+    } else {
+        find_session(token, pool).await
+    }
+}
+
+// basic credentials of clients that cannot log in interactively, such as calendar apps.
+// accounts protected by two-factor have to use an api key of their own as the password
+#[instrument(skip_all)]
+pub async fn identify_basic(
+    email: &str,
+    password: &str,
+    pool: &DbPool,
+) -> Option<Identity> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = find_credentials(&db, email).await?;
+    if password.starts_with(API_KEY_PREFIX) {
+        let identity = verify_api_key(password, pool).await?;
+        if identity.user_key() != Some(credentials._key.as_str()) {
+            return None;
+        }
+        return Some(identity);
+    }
+    if credentials.two_factor.is_some() || credentials.two_factor_required {
+        return None;
+    }
+    if !verify(password, &credentials.password).unwrap_or(false) {
+        return None;
+    }
+    Some(Identity {
+        principal: Principal::User(credentials._key.clone()),
+        session_key: None,
+        scopes: None,
+        restricted: false,
+    })
+}
+
+fn current_identity(req: &HttpRequest) -> Result<Identity, Error> {
+    req.extensions()
+        .get::<Identity>()
+        .cloned()
+        .ok_or_else(|| ErrorUnauthorized("authentication required"))
+}
+
+pub fn authenticate(req: &HttpRequest) -> Result<Identity, Error> {
+    let identity = current_identity(req)?;
+    if identity.restricted {
+        return Err(ErrorForbidden("two-factor enrolment required"));
+    }
+    Ok(identity)
+}
+
+// sessions pass every scope, api keys only the ones they were created with
+pub fn authorize(req: &HttpRequest, scope: &str) -> Result<Identity, Error> {
+    let identity = authenticate(req)?;
+    if !identity.has_scope(scope) {
+        return Err(failure(StatusCode::FORBIDDEN, "scope_required", &[("scope", scope)]));
+    }
+    Ok(identity)
+}
+
+// credentials and keys themselves can only be managed from an interactive session
+pub fn authenticate_session(req: &HttpRequest) -> Result<Identity, Error> {
+    let identity = authenticate(req)?;
+    if identity.session_key.is_none() {
+        return Err(ErrorForbidden("a user session is required"));
+    }
+    Ok(identity)
+}
+
+// also accepts sessions that were issued only to complete a required enrolment
+pub fn authenticate_for_enrolment(req: &HttpRequest) -> Result<Identity, Error> {
+    let identity = current_identity(req)?;
+    if identity.session_key.is_none() {
+        return Err(ErrorForbidden("a user session is required"));
+    }
+    Ok(identity)
+}
+
+#[instrument(skip_all)]
+pub async fn login(
+    payload: &LoginRequest,
+    pool: &DbPool,
+) -> Result<LoginResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = find_credentials(&db, &payload.email).await
+        .ok_or_else(|| ErrorUnauthorized("invalid credentials"))?;
+    if !verify(&payload.password, &credentials.password).unwrap_or(false) {
+        return Err(ErrorUnauthorized("invalid credentials"));
+    }
+
+    if credentials.two_factor.is_none() {
+        let session = create_session(&db, &credentials._key, credentials.two_factor_required).await;
+        return Ok(LoginResponse::Session(session));
+    }
+
+    // enrolled users have to present a code before a session is issued
+    let collection: Collection<ReqwestClient> = db.collection("login_challenges").await.unwrap();
+    let now = Utc::now();
+    let challenge = random_token();
+
+    let mut doc = Document::new(LoginChallenge {
+        user_key: credentials._key.clone(),
+        created_at: now,
+        expires_at: now + Duration::minutes(CHALLENGE_LIFETIME_MINUTES),
+    });
+    doc.header._key = challenge.clone();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(true)
+        .build();
+
+    let res: DocumentResponse<Document<LoginChallenge>> = collection.create_document(doc, options).await.unwrap();
+    let record: &LoginChallenge = res.new_doc().unwrap();
+    Ok(LoginResponse::Challenge(ChallengeResponse {
+        two_factor_required: true,
+        challenge,
+        expires_at: record.expires_at,
+    }))
+}
+
+// accepts the totp code if it is newer than the last accepted one
+async fn consume_code(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    two_factor: &TwoFactor,
+    code: &str,
+) -> bool {
+    let step = match totp::verify(&two_factor.secret, code, Utc::now().timestamp(), two_factor.last_step) {
+        Some(s) => s,
+        None => return false,
+    };
+
+    let q = r#"FOR x IN users
+        FILTER x._key == @key AND x.two_factor != null
+        FILTER x.two_factor.last_step == null OR x.two_factor.last_step < @step
+        UPDATE x WITH { two_factor: { last_step: @step } } IN users
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(user_key).unwrap());
+    vars.insert("step", to_value(step).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+// each recovery code can be used only once
+async fn consume_recovery_code(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    two_factor: &TwoFactor,
+    recovery_code: &str,
+) -> bool {
+    let recovery_code = recovery_code.trim().to_lowercase();
+    let hashed = match two_factor.recovery_codes.iter().find(|x| verify(&recovery_code, x).unwrap_or(false)) {
+        Some(h) => h,
+        None => return false,
+    };
+
+    let q = r#"FOR x IN users
+        FILTER x._key == @key AND POSITION(x.two_factor.recovery_codes, @hashed)
+        UPDATE x WITH { two_factor: { recovery_codes: REMOVE_VALUE(x.two_factor.recovery_codes, @hashed, 1) } } IN users
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(user_key).unwrap());
+    vars.insert("hashed", to_value(hashed).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+// every guess takes one of the attempts a user has within the lockout window, whatever the
+// challenge, so neither new challenges nor parallel requests give more guesses
+async fn reserve_attempt(db: &Database<ReqwestClient>, user_key: &str) -> bool {
+    let q = r#"FOR x IN users
+        FILTER x._key == @key AND x.two_factor != null
+        LET fresh = x.two_factor.failures_since == null OR x.two_factor.failures_since < DATE_NOW() - @window
+        LET failures = fresh ? 0 : x.two_factor.failures
+        FILTER failures < @max
+        UPDATE x WITH { two_factor: {
+            failures: failures + 1,
+            failures_since: fresh ? DATE_NOW() : x.two_factor.failures_since,
+        } } IN users
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(user_key).unwrap());
+    vars.insert("window", to_value(Duration::minutes(TWO_FACTOR_LOCKOUT_MINUTES).num_milliseconds()).unwrap());
+    vars.insert("max", to_value(TWO_FACTOR_MAX_FAILURES).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+async fn reset_attempts(db: &Database<ReqwestClient>, user_key: &str) {
+    let q = r#"FOR x IN users
+        FILTER x._key == @key AND x.two_factor != null
+        UPDATE x WITH { two_factor: { failures: 0, failures_since: null } } IN users"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+}
+
+#[instrument(skip_all)]
+pub async fn login_two_factor(
+    payload: &TwoFactorLoginRequest,
+    pool: &DbPool,
+) -> Result<SessionResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("login_challenges").await.unwrap();
+    let challenge: Document<LoginChallenge> = collection.document(&payload.challenge).await
+        .map_err(|_| ErrorUnauthorized("invalid challenge"))?;
+    if challenge.expires_at <= Utc::now() {
+        return Err(ErrorUnauthorized("challenge expired"));
+    }
+
+    let credentials = credentials_by_key(&db, &challenge.user_key).await
+        .ok_or_else(|| ErrorUnauthorized("invalid challenge"))?;
+    let two_factor = credentials.two_factor
+        .ok_or_else(|| ErrorUnauthorized("invalid challenge"))?;
+
+    if payload.code.is_none() && payload.recovery_code.is_none() {
+        return Err(ErrorBadRequest("code or recovery_code is required"));
+    }
+    if !reserve_attempt(&db, &credentials._key).await {
+        return Err(ErrorTooManyRequests("too many invalid codes"));
+    }
+    let accepted = match (&payload.code, &payload.recovery_code) {
+        (Some(code), _) => consume_code(&db, &credentials._key, &two_factor, code).await,
+        (_, Some(recovery_code)) => consume_recovery_code(&db, &credentials._key, &two_factor, recovery_code).await,
+        (None, None) => false,
+    };
+    if !accepted {
+        return Err(ErrorUnauthorized("invalid code"));
+    }
+    reset_attempts(&db, &credentials._key).await;
+
+    let options: RemoveOptions = RemoveOptions::builder()
+        .return_old(false)
+        .build();
+    let _res: DocumentResponse<Document<LoginChallenge>> = collection.remove_document(&payload.challenge, options, None).await
+        .map_err(|_| ErrorUnauthorized("invalid challenge"))?;
+
+    Ok(create_session(&db, &credentials._key, false).await)
+}
+
+#[instrument(skip_all)]
+pub async fn logout(
+    identity: &Identity,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let session_key = identity.session_key.as_deref().unwrap_or("");
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("sessions").await.unwrap();
+    let options: RemoveOptions = RemoveOptions::builder()
+        .return_old(false)
+        .build();
+
+    let _res: DocumentResponse<Document<Session>> = collection.remove_document(session_key, options, None).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// always succeeds so that callers cannot probe which emails are registered
+#[instrument(skip_all)]
+pub async fn forgot_password(
+    payload: &ForgotPasswordRequest,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = match find_credentials(&db, &payload.email).await {
+        Some(c) => c,
+        None => return Ok(()),
+    };
+
+    let collection: Collection<ReqwestClient> = db.collection("password_resets").await.unwrap();
+    let now = Utc::now();
+    let secret = random_token();
+
+    let mut doc = Document::new(PasswordReset {
+        user_key: credentials._key.clone(),
+        secret: hash(&secret, DEFAULT_COST).unwrap(),
+        created_at: now,
+        expires_at: now + Duration::minutes(RESET_TOKEN_LIFETIME_MINUTES),
+        used_at: None,
+    });
+    doc.header._key = Uuid::new_v4().to_simple().to_string();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<PasswordReset>> = collection.create_document(doc, options).await.unwrap();
+    let header = res.header().unwrap();
+
+    // the key locates the reset document, the secret is only stored hashed
+    let token = format!("{}.{}", header._key, secret);
+    let body = format!(
+        "Use the following token to reset your password. It expires in {} minutes.\n\n{}\n\nIf you did not ask for a password reset, you can ignore this email.",
+        RESET_TOKEN_LIFETIME_MINUTES,
+        token,
+    );
+    // a failure would tell that the email is registered, so it is only logged
+    if let Err(e) = send_mail(&credentials.email, "Reset your password", body).await {
+        error!(user_key = %credentials._key, error = %e, "failed to send password reset email");
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn reset_password(
+    payload: &ResetPasswordRequest,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let (key, secret) = payload.token.split_once('.')
+        .ok_or_else(|| ErrorBadRequest("invalid token"))?;
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("password_resets").await.unwrap();
+    let reset: Document<PasswordReset> = collection.document(key).await
+        .map_err(|_| ErrorBadRequest("invalid token"))?;
+    if !verify(secret, &reset.secret).unwrap_or(false) {
+        return Err(ErrorBadRequest("invalid token"));
+    }
+
+    // consume the token in one statement so that it cannot be used twice
+    let q = r#"FOR r IN password_resets
+        FILTER r._key == @key AND r.used_at == null AND r.expires_at > @now
+        UPDATE r WITH { used_at: @used_at } IN password_resets
+        RETURN NEW.user_key"#;
+    let now = Utc::now();
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("now", to_value(now.timestamp()).unwrap());
+    vars.insert("used_at", to_value(now).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut consumed: Vec<String> = db.aql_query(aql).await.unwrap();
+    let user_key = consumed.pop().ok_or_else(|| ErrorBadRequest("invalid token"))?;
+
+    store_password(&db, &user_key, &payload.password).await?;
+
+    // other outstanding tokens of the user are no longer needed
+    let q = "FOR r IN password_resets FILTER r.user_key == @user_key AND r.used_at == null REMOVE r IN password_resets";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user_key", to_value(&user_key).unwrap());
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+
+    revoke_sessions(&db, &user_key, None).await;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn change_password(
+    key: &String,
+    identity: &Identity,
+    payload: &ChangePasswordRequest,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    if identity.user_key() != Some(key.as_str()) {
+        return Err(ErrorForbidden("cannot change the password of another user"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
+    let user: Document<Value> = collection.document(key.as_ref()).await
+        .map_err(|_| ErrorUnauthorized("invalid session"))?;
+    let current = user.document["password"].as_str().unwrap_or("");
+    if !verify(&payload.current_password, current).unwrap_or(false) {
+        return Err(ErrorForbidden("current password does not match"));
+    }
+
+    store_password(&db, key, &payload.password).await?;
+    revoke_sessions(&db, key, identity.session_key.as_deref()).await;
+    Ok(())
+}
+
+fn hash_recovery_codes(codes: &[String]) -> Vec<String> {
+    codes.iter().map(|x| hash(x, DEFAULT_COST).unwrap()).collect()
+}
+
+#[instrument(skip_all)]
+pub async fn start_two_factor(
+    key: &String,
+    identity: &Identity,
+    pool: &DbPool,
+) -> Result<TwoFactorSetupResponse, Error> {
+    if identity.user_key() != Some(key.as_str()) {
+        return Err(ErrorForbidden("cannot enrol another user"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = credentials_by_key(&db, key).await
+        .ok_or_else(|| ErrorUnauthorized("invalid session"))?;
+    if credentials.two_factor.is_some() {
+        return Err(ErrorConflict("two-factor authentication is already enabled"));
+    }
+
+    // kept aside until a code proves that the authenticator app has it
+    let secret = totp::generate_secret();
+    update_user_fields(&db, key, json!({
+        "two_factor_pending": secret,
+    })).await?;
+
+    Ok(TwoFactorSetupResponse {
+        provisioning_uri: totp::provisioning_uri(&secret, &credentials.email),
+        secret,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn confirm_two_factor(
+    key: &String,
+    identity: &Identity,
+    payload: &TwoFactorCodeRequest,
+    pool: &DbPool,
+) -> Result<RecoveryCodesResponse, Error> {
+    if identity.user_key() != Some(key.as_str()) {
+        return Err(ErrorForbidden("cannot enrol another user"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = credentials_by_key(&db, key).await
+        .ok_or_else(|| ErrorUnauthorized("invalid session"))?;
+    let secret = credentials.two_factor_pending
+        .ok_or_else(|| ErrorBadRequest("two-factor enrolment has not been started"))?;
+    let step = totp::verify(&secret, &payload.code, Utc::now().timestamp(), None)
+        .ok_or_else(|| ErrorBadRequest("invalid code"))?;
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let two_factor = TwoFactor {
+        secret,
+        recovery_codes: hash_recovery_codes(&recovery_codes),
+        last_step: Some(step),
+        enabled_at: Utc::now(),
+    };
+    update_user_fields(&db, key, json!({
+        "two_factor": two_factor,
+        "two_factor_pending": null,
+    })).await?;
+
+    // sessions waiting for a required enrolment become regular sessions
+    let q = "FOR s IN sessions FILTER s.user_key == @user_key AND s.restricted == true UPDATE s WITH { restricted: false } IN sessions";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user_key", to_value(key).unwrap());
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+
+    Ok(RecoveryCodesResponse {
+        recovery_codes,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn regenerate_recovery_codes(
+    key: &String,
+    identity: &Identity,
+    payload: &TwoFactorCodeRequest,
+    pool: &DbPool,
+) -> Result<RecoveryCodesResponse, Error> {
+    if identity.user_key() != Some(key.as_str()) {
+        return Err(ErrorForbidden("cannot change another user"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = credentials_by_key(&db, key).await
+        .ok_or_else(|| ErrorUnauthorized("invalid session"))?;
+    let two_factor = credentials.two_factor
+        .ok_or_else(|| ErrorBadRequest("two-factor authentication is not enabled"))?;
+    if !consume_code(&db, key, &two_factor, &payload.code).await {
+        return Err(ErrorBadRequest("invalid code"));
+    }
+
+    let recovery_codes = totp::generate_recovery_codes();
+    let q = "FOR x IN users FILTER x._key == @key UPDATE x WITH { two_factor: { recovery_codes: @recovery_codes } } IN users";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("recovery_codes", to_value(hash_recovery_codes(&recovery_codes)).unwrap());
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await.unwrap();
+
+    Ok(RecoveryCodesResponse {
+        recovery_codes,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn disable_two_factor(
+    key: &String,
+    identity: &Identity,
+    payload: &DisableTwoFactorRequest,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    if identity.user_key() != Some(key.as_str()) {
+        return Err(ErrorForbidden("cannot change another user"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let credentials = credentials_by_key(&db, key).await
+        .ok_or_else(|| ErrorUnauthorized("invalid session"))?;
+    if !verify(&payload.password, &credentials.password).unwrap_or(false) {
+        return Err(ErrorForbidden("password does not match"));
+    }
+    if credentials.two_factor_required {
+        return Err(ErrorForbidden("two-factor authentication is required by your company"));
+    }
+
+    update_user_fields(&db, key, json!({
+        "two_factor": null,
+        "two_factor_pending": null,
+    })).await
+}
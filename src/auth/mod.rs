@@ -0,0 +1,10 @@
+mod models;
+mod controllers;
+mod middleware;
+mod routes;
+mod totp;
+
+pub use models::*;
+pub use controllers::*;
+pub use middleware::Authentication;
+pub use routes::init;
@@ -0,0 +1,182 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+pub const SESSION_LIFETIME_DAYS: i64 = 30;
+pub const RESET_TOKEN_LIFETIME_MINUTES: i64 = 60;
+pub const CHALLENGE_LIFETIME_MINUTES: i64 = 5;
+pub const TWO_FACTOR_MAX_FAILURES: u32 = 5; // per user within the lockout window
+pub const TWO_FACTOR_LOCKOUT_MINUTES: i64 = 15;
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct LoginRequest {
+    #[validate(email)]
+    pub email: String,
+    pub password: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct TwoFactorLoginRequest {
+    pub challenge: String,
+    #[validate(length(equal = 6))]
+    pub code: Option<String>,
+    pub recovery_code: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ForgotPasswordRequest {
+    #[validate(email)]
+    pub email: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ResetPasswordRequest {
+    pub token: String,
+    #[validate(length(min = 6))]
+    pub password: String,
+    #[validate(must_match = "password")]
+    pub password_confirmation: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ChangePasswordRequest {
+    pub current_password: String,
+    #[validate(length(min = 6))]
+    pub password: String,
+    #[validate(must_match = "password")]
+    pub password_confirmation: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct TwoFactorCodeRequest {
+    #[validate(length(equal = 6))]
+    pub code: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct DisableTwoFactorRequest {
+    pub password: String,
+}
+
+// subset of user document needed to check a password
+#[derive(Clone, Debug, Deserialize)]
+pub struct Credentials {
+    pub _key: String,
+    pub email: String,
+    pub password: String,
+    pub two_factor: Option<TwoFactor>,
+    pub two_factor_pending: Option<String>,
+    pub two_factor_required: bool, // any company of the user requires it
+}
+
+// stored in the user document under `two_factor` once enrolment is confirmed, along with the
+// count of failed codes (`failures`, `failures_since`) that login_two_factor maintains
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TwoFactor {
+    pub secret: String,
+    pub recovery_codes: Vec<String>, // bcrypt hashes, removed once used
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub last_step: Option<i64>,
+    pub enabled_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Principal {
+    User(String),
+    Company(String), // api keys owned by a company act on its behalf
+}
+
+// resolved by the authentication middleware from the bearer token
+#[derive(Clone, Debug)]
+pub struct Identity {
+    pub principal: Principal,
+    pub session_key: Option<String>, // set when authenticated by a session
+    pub scopes: Option<Vec<String>>, // none for sessions, which are not limited
+    pub restricted: bool,
+}
+
+impl Identity {
+    pub fn user_key(&self) -> Option<&str> {
+        match &self.principal {
+            Principal::User(key) => Some(key),
+            Principal::Company(_) => None,
+        }
+    }
+
+    pub fn company_key(&self) -> Option<&str> {
+        match &self.principal {
+            Principal::User(_) => None,
+            Principal::Company(key) => Some(key),
+        }
+    }
+
+    pub fn has_scope(&self, scope: &str) -> bool {
+        match &self.scopes {
+            Some(scopes) => scopes.iter().any(|x| x == scope),
+            None => true,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Session {
+    pub user_key: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")] // ttl index needs a numeric timestamp
+    pub expires_at: DateTime<Utc>,
+    #[serde(default)]
+    pub restricted: bool, // only allowed to enrol two-factor authentication
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LoginChallenge {
+    pub user_key: String,
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")] // ttl index needs a numeric timestamp
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct PasswordReset {
+    pub user_key: String,
+    pub secret: String, // bcrypt hash of the secret half of the token
+    pub created_at: DateTime<Utc>,
+    #[serde(with = "chrono::serde::ts_seconds")] // ttl index needs a numeric timestamp
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub used_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SessionResponse {
+    pub token: String,
+    pub user_key: String,
+    pub expires_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub two_factor_enrolment_required: bool,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ChallengeResponse {
+    pub two_factor_required: bool,
+    pub challenge: String,
+    pub expires_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(untagged)]
+pub enum LoginResponse {
+    Session(SessionResponse),
+    Challenge(ChallengeResponse),
+}
+
+#[derive(Debug, Serialize)]
+pub struct TwoFactorSetupResponse {
+    pub secret: String,
+    pub provisioning_uri: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct RecoveryCodesResponse {
+    pub recovery_codes: Vec<String>,
+}
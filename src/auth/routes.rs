@@ -0,0 +1,99 @@
+use actix_web::{post, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::auth::{
+    self,
+    ForgotPasswordRequest,
+    LoginRequest,
+    ResetPasswordRequest,
+    TwoFactorLoginRequest,
+};
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+#[post("/auth/login")]
+async fn login(
+    payload: web::Json<LoginRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: LoginRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = auth::login(&params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/auth/login/two-factor")]
+async fn login_two_factor(
+    payload: web::Json<TwoFactorLoginRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: TwoFactorLoginRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = auth::login_two_factor(&params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/auth/logout")]
+async fn logout(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authenticate_for_enrolment(&req)?;
+    auth::logout(&identity, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/auth/password/forgot")]
+async fn forgot_password(
+    payload: web::Json<ForgotPasswordRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: ForgotPasswordRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            auth::forgot_password(&params, &pool).await?;
+            Ok(HttpResponse::NoContent().finish())
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/auth/password/reset")]
+async fn reset_password(
+    payload: web::Json<ResetPasswordRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: ResetPasswordRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            auth::reset_password(&params, &pool).await?;
+            Ok(HttpResponse::NoContent().finish())
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(login);
+    cfg.service(login_two_factor);
+    cfg.service(logout);
+    cfg.service(forgot_password);
+    cfg.service(reset_password);
+}
@@ -0,0 +1,132 @@
+// RFC 6238 time-based one-time passwords, compatible with common authenticator apps
+use hmac::{Hmac, Mac, NewMac};
+use rand::{distributions::Alphanumeric, Rng, RngCore};
+use sha1::Sha1;
+
+const ISSUER: &str = "Groupware";
+const DIGITS: u32 = 6;
+const PERIOD: i64 = 30;
+const SKEW: i64 = 1; // accepted steps before and after the current one
+
+pub const RECOVERY_CODE_COUNT: usize = 10;
+
+pub fn generate_secret() -> String {
+    let mut bytes = [0u8; 20];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base32::encode(base32::Alphabet::RFC4648 { padding: false }, &bytes)
+}
+
+pub fn generate_recovery_codes() -> Vec<String> {
+    (0..RECOVERY_CODE_COUNT)
+        .map(|_| {
+            let text: String = rand::thread_rng()
+                .sample_iter(&Alphanumeric)
+                .take(10)
+                .map(char::from)
+                .collect::<String>()
+                .to_lowercase();
+            format!("{}-{}", &text[..5], &text[5..])
+        })
+        .collect()
+}
+
+fn encode_component(text: &str) -> String {
+    text.bytes()
+        .map(|b| match b {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' => (b as char).to_string(),
+            _ => format!("%{:02X}", b),
+        })
+        .collect()
+}
+
+pub fn provisioning_uri(secret: &str, account: &str) -> String {
+    format!(
+        "otpauth://totp/{}:{}?secret={}&issuer={}&algorithm=SHA1&digits={}&period={}",
+        encode_component(ISSUER),
+        encode_component(account),
+        secret,
+        encode_component(ISSUER),
+        DIGITS,
+        PERIOD,
+    )
+}
+
+fn hotp(key: &[u8], counter: u64) -> u32 {
+    let mut mac = Hmac::<Sha1>::new_from_slice(key).unwrap();
+    mac.update(&counter.to_be_bytes());
+    let digest = mac.finalize().into_bytes();
+
+    // dynamic truncation from RFC 4226
+    let offset = (digest[digest.len() - 1] & 0x0f) as usize;
+    let binary = ((digest[offset] as u32 & 0x7f) << 24)
+        | ((digest[offset + 1] as u32) << 16)
+        | ((digest[offset + 2] as u32) << 8)
+        | (digest[offset + 3] as u32);
+    binary % 10u32.pow(DIGITS)
+}
+
+// returns the matched time step, so that callers can refuse to accept it twice
+pub fn verify(
+    secret: &str,
+    code: &str,
+    timestamp: i64,
+    last_step: Option<i64>,
+) -> Option<i64> {
+    let code = code.trim();
+    if code.len() != DIGITS as usize || !code.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    let expected: u32 = code.parse().ok()?;
+    let key = base32::decode(base32::Alphabet::RFC4648 { padding: false }, secret)?;
+
+    let current = timestamp / PERIOD;
+    (current - SKEW..=current + SKEW)
+        .filter(|step| *step >= 0 && last_step.map_or(true, |last| *step > last))
+        .find(|step| hotp(&key, *step as u64) == expected)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the ascii secret "12345678901234567890" of the RFC 6238 test vectors
+    const SECRET: &str = "GEZDGNBVGY3TQOJQGEZDGNBVGY3TQOJQ";
+
+    #[test]
+    fn rfc_vectors() {
+        let key = base32::decode(base32::Alphabet::RFC4648 { padding: false }, SECRET).unwrap();
+        assert_eq!(hotp(&key, 0), 755224);
+        assert_eq!(hotp(&key, 1), 287082);
+        assert_eq!(verify(SECRET, "287082", 59, None), Some(1));
+        assert_eq!(verify(SECRET, "081804", 1111111109, None), Some(37037036));
+        assert_eq!(verify(SECRET, " 005924 ", 1234567890, None), Some(41152263));
+    }
+
+    #[test]
+    fn skew_and_replay() {
+        // the code of step 1 is accepted one step early and one step late, but not twice
+        assert_eq!(verify(SECRET, "287082", 15, None), Some(1));
+        assert_eq!(verify(SECRET, "287082", 89, None), Some(1));
+        assert_eq!(verify(SECRET, "287082", 90, None), None);
+        assert_eq!(verify(SECRET, "287082", 59, Some(1)), None);
+    }
+
+    #[test]
+    fn malformed_codes() {
+        assert_eq!(verify(SECRET, "28708", 59, None), None);
+        assert_eq!(verify(SECRET, "28708a", 59, None), None);
+        assert_eq!(verify("not base32!", "287082", 59, None), None);
+    }
+
+    #[test]
+    fn secrets_and_recovery_codes() {
+        let secret = generate_secret();
+        assert_eq!(secret.len(), 32);
+        assert!(provisioning_uri(&secret, "ann@example.com")
+            .starts_with(&format!("otpauth://totp/Groupware:ann%40example.com?secret={}&", secret)));
+
+        let codes = generate_recovery_codes();
+        assert_eq!(codes.len(), RECOVERY_CODE_COUNT);
+        assert!(codes.iter().all(|x| x.len() == 11 && x.as_bytes()[5] == b'-'));
+    }
+}
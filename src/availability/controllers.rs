@@ -0,0 +1,257 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError},
+    Error,
+};
+use arangors::{connection::ReqwestClient, AqlQuery, Database};
+use chrono::{prelude::*, Duration};
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::auth::Identity;
+use crate::availability::{
+    Attendee,
+    AvailabilityRequest,
+    AvailabilityResponse,
+    BusyResponse,
+    Interval,
+    Suggestion,
+    DEFAULT_WORK_DAYS,
+};
+use crate::database::{current_database, DbPool};
+use crate::event::{expand_events, parse_timezone, parse_weekday};
+
+// slots further than this from any busy interval all rank the same
+const COMFORT_MINUTES: i64 = 120;
+
+// users see the calendars of people they share a company with, company keys those of their members
+async fn check_visibility(
+    db: &Database<ReqwestClient>,
+    identity: &Identity,
+    user_keys: &[String],
+) -> Result<(), Error> {
+    let (viewer, others): (String, Vec<String>) = match (identity.user_key(), identity.company_key()) {
+        (Some(user_key), _) => (
+            format!("users/{}", user_key),
+            user_keys.iter().filter(|x| *x != user_key).map(|x| format!("users/{}", x)).collect(),
+        ),
+        (_, Some(company_key)) => (
+            format!("companies/{}", company_key),
+            user_keys.iter().map(|x| format!("users/{}", x)).collect(),
+        ),
+        _ => return Err(ErrorForbidden("unknown principal")),
+    };
+    if others.is_empty() {
+        return Ok(());
+    }
+
+    let q = r#"LET companies = PARSE_IDENTIFIER(@viewer).collection == 'companies'
+            ? [@viewer]
+            : (FOR m IN memberships FILTER m._from == @viewer RETURN m._to)
+        RETURN LENGTH(
+            FOR m IN memberships
+                FILTER m._to IN companies AND m._from IN @others
+                RETURN DISTINCT m._from
+        ) == LENGTH(UNIQUE(@others))"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("viewer", to_value(viewer).unwrap());
+    vars.insert("others", to_value(others).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<bool> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if !records.pop().unwrap_or(false) {
+        return Err(ErrorForbidden("attendee outside of your companies"));
+    }
+    Ok(())
+}
+
+fn merge(mut intervals: Vec<Interval>) -> Vec<Interval> {
+    intervals.sort_by_key(|x| x.start);
+    let mut merged: Vec<Interval> = vec![];
+    for interval in intervals {
+        match merged.last_mut() {
+            Some(last) if interval.start <= last.end => {
+                if interval.end > last.end {
+                    last.end = interval.end;
+                }
+            },
+            _ => merged.push(interval),
+        }
+    }
+    merged
+}
+
+// working hours of each local day touching the window, in UTC
+fn working_windows(
+    attendee: &Attendee,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<Interval> {
+    let tz = attendee.timezone.as_deref().and_then(parse_timezone).unwrap_or(chrono_tz::Tz::UTC);
+    let days: Vec<Weekday> = match &attendee.work_days {
+        Some(x) => x.iter().filter_map(|x| parse_weekday(x)).collect(),
+        None => DEFAULT_WORK_DAYS.iter().filter_map(|x| parse_weekday(x)).collect(),
+    };
+    let (work_start, work_end) = attendee.working_hours();
+    let last = to.with_timezone(&tz).naive_local().date() + Duration::days(1);
+    let mut date = from.with_timezone(&tz).naive_local().date() - Duration::days(1);
+    let mut windows = vec![];
+    while date <= last {
+        if days.contains(&date.weekday()) {
+            let start = tz.from_local_datetime(&date.and_time(work_start)).earliest();
+            let end = tz.from_local_datetime(&date.and_time(work_end)).latest();
+            if let (Some(start), Some(end)) = (start, end) {
+                let start = start.with_timezone(&Utc).max(from);
+                let end = end.with_timezone(&Utc).min(to);
+                if start < end {
+                    windows.push(Interval { start, end });
+                }
+            }
+        }
+        date += Duration::days(1);
+    }
+    windows
+}
+
+fn overlaps(intervals: &[Interval], start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    intervals.iter().any(|x| x.start < end && x.end > start)
+}
+
+fn contains(intervals: &[Interval], start: DateTime<Utc>, end: DateTime<Utc>) -> bool {
+    intervals.iter().any(|x| x.start <= start && x.end >= end)
+}
+
+// minutes between the slot and the closest busy interval
+fn buffer(intervals: &[Interval], start: DateTime<Utc>, end: DateTime<Utc>) -> i64 {
+    intervals.iter()
+        .map(|x| if x.end <= start { (start - x.end).num_minutes() } else { (x.start - end).num_minutes() })
+        .min()
+        .unwrap_or(COMFORT_MINUTES)
+}
+
+// slots free for everyone inside everyone's working hours, roomy and early ones first
+#[instrument(skip_all)]
+pub async fn find_availability(
+    identity: &Identity,
+    payload: &AvailabilityRequest,
+    pool: &DbPool,
+) -> Result<AvailabilityResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let user_keys: Vec<String> = payload.attendees.iter().map(|x| x.user_key.clone()).collect();
+    check_visibility(&db, identity, &user_keys).await?;
+
+    let events = expand_events(&db, &user_keys, payload.from, payload.to, true).await?;
+    let busy: Vec<BusyResponse> = user_keys.iter()
+        .map(|user_key| {
+            let intervals = events.iter()
+                .filter(|(event, _)| &event.owner_key == user_key || event.attendees.contains(user_key))
+                .flat_map(|(_, spans)| spans.iter().map(|(start, end)| Interval { start: *start, end: *end }))
+                .collect();
+            BusyResponse { user_key: user_key.clone(), intervals: merge(intervals) }
+        })
+        .collect();
+    let windows: Vec<Vec<Interval>> = payload.attendees.iter()
+        .map(|x| working_windows(x, payload.from, payload.to))
+        .collect();
+
+    let duration = Duration::minutes(payload.duration_minutes as i64);
+    let step = payload.step_minutes.unwrap_or(15) as i64 * 60;
+    let span = (payload.to - payload.from).num_seconds().max(1) as f64;
+    let mut candidates = vec![];
+    // candidate starts are aligned on the step, 10:00, 10:15, ...
+    let mut start = Utc.timestamp_opt((payload.from.timestamp() + step - 1).div_euclid(step) * step, 0).unwrap();
+    while start + duration <= payload.to {
+        let end = start + duration;
+        let free = busy.iter().zip(windows.iter())
+            .all(|(b, w)| contains(w, start, end) && !overlaps(&b.intervals, start, end));
+        if free {
+            let room = busy.iter()
+                .map(|b| buffer(&b.intervals, start, end))
+                .min()
+                .unwrap_or(COMFORT_MINUTES)
+                .min(COMFORT_MINUTES);
+            let earliness = 1.0 - (start - payload.from).num_seconds() as f64 / span;
+            let score = 0.6 * room as f64 / COMFORT_MINUTES as f64 + 0.4 * earliness;
+            candidates.push(Suggestion { start, end, score: (score * 1000.0).round() / 1000.0 });
+        }
+        start += Duration::seconds(step);
+    }
+    candidates.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap().then(a.start.cmp(&b.start)));
+
+    // overlapping slots would mostly repeat the same suggestion
+    let mut suggestions: Vec<Suggestion> = vec![];
+    for candidate in candidates {
+        if suggestions.len() >= payload.max_suggestions.unwrap_or(10) {
+            break;
+        }
+        if !suggestions.iter().any(|x| x.start < candidate.end && x.end > candidate.start) {
+            suggestions.push(candidate);
+        }
+    }
+    Ok(AvailabilityResponse { busy, suggestions })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn interval(start: &str, end: &str) -> Interval {
+        Interval { start: at(start), end: at(end) }
+    }
+
+    fn bounds(intervals: &[Interval]) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+        intervals.iter().map(|x| (x.start, x.end)).collect()
+    }
+
+    #[test]
+    fn merge_intervals() {
+        let merged = merge(vec![
+            interval("2021-01-04T13:00:00Z", "2021-01-04T14:00:00Z"),
+            interval("2021-01-04T09:00:00Z", "2021-01-04T10:00:00Z"),
+            interval("2021-01-04T09:30:00Z", "2021-01-04T09:45:00Z"),
+            interval("2021-01-04T10:00:00Z", "2021-01-04T11:00:00Z"),
+        ]);
+        assert_eq!(bounds(&merged), vec![
+            (at("2021-01-04T09:00:00Z"), at("2021-01-04T11:00:00Z")),
+            (at("2021-01-04T13:00:00Z"), at("2021-01-04T14:00:00Z")),
+        ]);
+    }
+
+    #[test]
+    fn working_windows_in_local_time() {
+        let attendee = Attendee {
+            user_key: "1".to_string(),
+            timezone: Some("Europe/Berlin".to_string()),
+            work_start: Some("08:00".to_string()),
+            work_end: None,
+            work_days: None,
+        };
+        // friday noon to monday noon, the weekend is skipped and both ends are clipped
+        let windows = working_windows(&attendee, at("2021-01-08T11:00:00Z"), at("2021-01-11T11:00:00Z"));
+        assert_eq!(bounds(&windows), vec![
+            (at("2021-01-08T11:00:00Z"), at("2021-01-08T16:00:00Z")),
+            (at("2021-01-11T07:00:00Z"), at("2021-01-11T11:00:00Z")),
+        ]);
+    }
+
+    #[test]
+    fn slots_against_busy_intervals() {
+        let busy = vec![interval("2021-01-04T10:00:00Z", "2021-01-04T11:00:00Z")];
+        assert!(overlaps(&busy, at("2021-01-04T10:30:00Z"), at("2021-01-04T11:30:00Z")));
+        assert!(!overlaps(&busy, at("2021-01-04T11:00:00Z"), at("2021-01-04T11:30:00Z")));
+        assert!(contains(&busy, at("2021-01-04T10:00:00Z"), at("2021-01-04T11:00:00Z")));
+        assert_eq!(buffer(&busy, at("2021-01-04T11:15:00Z"), at("2021-01-04T11:45:00Z")), 15);
+        assert_eq!(buffer(&busy, at("2021-01-04T08:00:00Z"), at("2021-01-04T09:00:00Z")), 60);
+        assert_eq!(buffer(&[], at("2021-01-04T08:00:00Z"), at("2021-01-04T09:00:00Z")), COMFORT_MINUTES);
+    }
+}
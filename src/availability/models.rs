@@ -0,0 +1,103 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::event::{validate_timezone, validate_weekdays};
+
+pub const MAX_WINDOW_DAYS: i64 = 31;
+pub const DEFAULT_WORK_START: &str = "09:00";
+pub const DEFAULT_WORK_END: &str = "17:00";
+pub const DEFAULT_WORK_DAYS: [&str; 5] = ["mo", "tu", "we", "th", "fr"];
+
+pub fn parse_clock(value: &str) -> Option<NaiveTime> {
+    NaiveTime::parse_from_str(value, "%H:%M").ok()
+}
+
+fn validate_clock(value: &str) -> Result<(), ValidationError> {
+    if parse_clock(value).is_none() {
+        return Err(ValidationError::new("wrong_time"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Serialize, Deserialize)]
+#[validate(schema(function = "validate_working_hours"))]
+pub struct Attendee {
+    #[validate(length(min = 1))]
+    pub user_key: String,
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<String>, // UTC by default
+    #[validate(custom = "validate_clock")]
+    pub work_start: Option<String>, // local HH:MM, 09:00 by default
+    #[validate(custom = "validate_clock")]
+    pub work_end: Option<String>, // local HH:MM, 17:00 by default
+    #[validate(custom = "validate_weekdays")]
+    pub work_days: Option<Vec<String>>, // mo to fr by default
+}
+
+impl Attendee {
+    pub fn working_hours(&self) -> (NaiveTime, NaiveTime) {
+        let start = self.work_start.as_deref().unwrap_or(DEFAULT_WORK_START);
+        let end = self.work_end.as_deref().unwrap_or(DEFAULT_WORK_END);
+        (parse_clock(start).unwrap(), parse_clock(end).unwrap())
+    }
+}
+
+fn validate_working_hours(attendee: &Attendee) -> Result<(), ValidationError> {
+    let start = attendee.work_start.as_deref().map_or(parse_clock(DEFAULT_WORK_START), parse_clock);
+    let end = attendee.work_end.as_deref().map_or(parse_clock(DEFAULT_WORK_END), parse_clock);
+    if let (Some(start), Some(end)) = (start, end) {
+        if end <= start {
+            return Err(ValidationError::new("working_hours_end_before_start"));
+        }
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_window"))]
+pub struct AvailabilityRequest {
+    #[validate(length(min = 1, max = 50))]
+    #[validate]
+    pub attendees: Vec<Attendee>,
+    #[validate(range(min = 5, max = 1440))]
+    pub duration_minutes: u32,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    #[validate(range(min = 5, max = 240))]
+    pub step_minutes: Option<u32>, // distance between candidate starts, 15 by default
+    #[validate(range(min = 1, max = 100))]
+    pub max_suggestions: Option<usize>, // 10 by default
+}
+
+fn validate_window(payload: &AvailabilityRequest) -> Result<(), ValidationError> {
+    if payload.to <= payload.from || payload.to - payload.from > chrono::Duration::days(MAX_WINDOW_DAYS) {
+        return Err(ValidationError::new("wrong_window"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct BusyResponse {
+    pub user_key: String,
+    pub intervals: Vec<Interval>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Suggestion {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub score: f64, // 0 to 1, higher is better
+}
+
+#[derive(Debug, Serialize)]
+pub struct AvailabilityResponse {
+    pub busy: Vec<BusyResponse>,
+    pub suggestions: Vec<Suggestion>,
+}
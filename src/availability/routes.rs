@@ -0,0 +1,31 @@
+use actix_web::{post, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::auth;
+use crate::availability::{self, AvailabilityRequest};
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+#[post("/availability")]
+async fn find(
+    req: HttpRequest,
+    payload: web::Json<AvailabilityRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authorize(&req, "events:read")?;
+    let params: AvailabilityRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = availability::find_availability(&identity, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+}
@@ -0,0 +1,332 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::comment::{
+    Comment,
+    CommentResponse,
+    CommentRevision,
+    CreateCommentRequest,
+    FindCommentsParams,
+    TargetKeys,
+    TargetOwner,
+    UpdateCommentRequest,
+};
+use crate::database::{current_database, DbPool};
+
+const COMMENT_TERMS: &str = r#"RETURN {
+        _key: c._key,
+        target: c.target,
+        parent_key: c.parent_key,
+        author_key: c.author_key,
+        author_name: DOCUMENT('users', c.author_key).name,
+        body: c.deleted_at == null ? c.body : null,
+        edited: LENGTH(c.history) > 0,
+        reactions: c.reactions,
+        created_at: c.created_at,
+        modified_at: c.modified_at,
+        deleted_at: c.deleted_at
+    }"#;
+
+async fn find_comment(
+    db: &Database<ReqwestClient>,
+    key: &str,
+) -> Result<CommentResponse, Error> {
+    let q = format!("FOR c IN comments FILTER c._key == @key {}", COMMENT_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<CommentResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("comment not found"))
+}
+
+// only the author may change a comment, and only while it exists
+async fn own_comment(
+    db: &Database<ReqwestClient>,
+    key: &str,
+    author_key: &str,
+) -> Result<Document<Comment>, Error> {
+    let collection: Collection<ReqwestClient> = db.collection("comments").await.unwrap();
+    let comment: Document<Comment> = collection.document(key).await
+        .map_err(|_| ErrorNotFound("comment not found"))?;
+    if comment.deleted_at.is_some() {
+        return Err(ErrorNotFound("comment not found"));
+    }
+    if comment.author_key != author_key {
+        return Err(ErrorForbidden("not the author of the comment"));
+    }
+    Ok(comment)
+}
+
+#[instrument(skip_all)]
+pub async fn target_owner(
+    target: &str,
+    pool: &DbPool,
+) -> Result<TargetOwner, Error> {
+    if let Some(("companies", key)) = target.split_once('/') {
+        return Ok(TargetOwner::Company(key.to_string()));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "LET d = DOCUMENT(@target) FILTER d != null RETURN { company_key: d.company_key, owner_key: d.owner_key }";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("target", to_value(target).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<TargetKeys> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    match records.pop() {
+        Some(TargetKeys { company_key: Some(key), .. }) => Ok(TargetOwner::Company(key)),
+        Some(TargetKeys { owner_key: Some(key), .. }) => Ok(TargetOwner::User(key)),
+        Some(_) => Ok(TargetOwner::Nobody),
+        None => Err(ErrorNotFound("target not found")),
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn comment_target(
+    key: &str,
+    pool: &DbPool,
+) -> Result<String, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "FOR c IN comments FILTER c._key == @key RETURN c.target";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("comment not found"))
+}
+
+// replies come right after the comments they answer when sorted by creation
+#[instrument(skip_all)]
+pub async fn find_comments(
+    params: FindCommentsParams,
+    pool: &DbPool,
+) -> Result<Vec<CommentResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!("FOR c IN comments FILTER c.target == @target SORT c.created_at ASC LIMIT 0, @limit {}", COMMENT_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("target", to_value(params.target).unwrap());
+    vars.insert("limit", to_value(params.limit.unwrap_or(100)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<CommentResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_comment(
+    key: &String,
+    pool: &DbPool,
+) -> Result<CommentResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_comment(&db, key).await
+}
+
+#[instrument(skip_all)]
+pub async fn create_comment(
+    author_key: &str,
+    payload: &CreateCommentRequest,
+    pool: &DbPool,
+) -> Result<CommentResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"RETURN {
+        target: DOCUMENT(@target) != null,
+        parent: @parent_key == null OR LENGTH(
+            FOR c IN comments
+                FILTER c._key == @parent_key AND c.target == @target AND c.deleted_at == null
+                RETURN 1
+        ) > 0
+    }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("target", to_value(&payload.target).unwrap());
+    vars.insert("parent_key", to_value(&payload.parent_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut checks: Vec<HashMap<String, bool>> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    let checks = checks.pop().unwrap();
+    if !checks["target"] {
+        return Err(ErrorNotFound("target not found"));
+    }
+    if !checks["parent"] {
+        return Err(ErrorBadRequest("parent comment not found on this target"));
+    }
+
+    let collection: Collection<ReqwestClient> = db.collection("comments").await.unwrap();
+    let now = Utc::now();
+    let data = Comment {
+        target: payload.target.clone(),
+        parent_key: payload.parent_key.clone(),
+        author_key: author_key.to_string(),
+        body: payload.body.trim().to_string(),
+        history: vec![],
+        reactions: HashMap::new(),
+        created_at: now,
+        modified_at: now,
+        deleted_at: None,
+    };
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<Comment>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+    find_comment(&db, &header._key).await
+}
+
+// the replaced body is kept in the history of the comment
+#[instrument(skip_all)]
+pub async fn update_comment(
+    key: &String,
+    author_key: &str,
+    payload: &UpdateCommentRequest,
+    pool: &DbPool,
+) -> Result<CommentResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    own_comment(&db, key, author_key).await?;
+
+    let q = r#"FOR c IN comments
+        FILTER c._key == @key AND c.body != @body
+        UPDATE c WITH {
+            body: @body,
+            history: PUSH(c.history, { body: c.body, edited_at: @now }),
+            modified_at: @now
+        } IN comments"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("body", to_value(payload.body.trim()).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_comment(&db, key).await
+}
+
+// keeps a placeholder so that replies do not lose their parent
+#[instrument(skip_all)]
+pub async fn delete_comment(
+    key: &String,
+    author_key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    own_comment(&db, key, author_key).await?;
+
+    let q = r#"FOR c IN comments
+        FILTER c._key == @key
+        UPDATE c WITH { body: '', history: [], reactions: {}, deleted_at: @now, modified_at: @now } IN comments
+        OPTIONS { mergeObjects: false }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn find_comment_history(
+    key: &String,
+    pool: &DbPool,
+) -> Result<Vec<CommentRevision>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("comments").await.unwrap();
+    let comment: Document<Comment> = collection.document(key).await
+        .map_err(|_| ErrorNotFound("comment not found"))?;
+    Ok(comment.document.history)
+}
+
+// reactions are sets, adding the same one twice has no effect
+#[instrument(skip_all)]
+pub async fn set_reaction(
+    key: &String,
+    reaction: &str,
+    user_key: &str,
+    reacted: bool,
+    pool: &DbPool,
+) -> Result<CommentResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR c IN comments
+        FILTER c._key == @key AND c.deleted_at == null
+        LET users = @reacted
+            ? UNION_DISTINCT(c.reactions[@reaction] || [], [@user_key])
+            : REMOVE_VALUE(c.reactions[@reaction] || [], @user_key)
+        UPDATE c WITH { reactions: { [@reaction]: LENGTH(users) > 0 ? users : null } } IN comments
+        OPTIONS { keepNull: false }
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("reaction", to_value(reaction).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("reacted", to_value(reacted).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let updated: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if updated.is_empty() {
+        return Err(ErrorNotFound("comment not found"));
+    }
+    find_comment(&db, key).await
+}
@@ -0,0 +1,7 @@
+mod models;
+mod controllers;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use routes::init;
@@ -0,0 +1,104 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use validator::{Validate, ValidationError};
+
+// collections whose documents can be discussed
+pub const COMMENTABLE: [&str; 7] = [
+    "announcements",
+    "companies",
+    "contacts",
+    "events",
+    "resources",
+    "users",
+    "wiki_pages",
+];
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindCommentsParams {
+    #[validate(custom = "validate_target")]
+    pub target: String,
+    #[validate(range(min = 1, max = 500))]
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct CreateCommentRequest {
+    #[validate(custom = "validate_target")]
+    pub target: String,
+    pub parent_key: Option<String>,
+    #[validate(length(min = 1, max = 10000))]
+    pub body: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct UpdateCommentRequest {
+    #[validate(length(min = 1, max = 10000))]
+    pub body: String,
+}
+
+pub fn validate_target(target: &str) -> Result<(), ValidationError> {
+    match target.split_once('/') {
+        Some((collection, key)) if COMMENTABLE.contains(&collection) && !key.is_empty() && !key.contains('/') => Ok(()),
+        _ => Err(ValidationError::new("wrong_target")),
+    }
+}
+
+pub fn validate_reaction(reaction: &str) -> Result<(), ValidationError> {
+    let length = reaction.chars().count();
+    if length == 0 || length > 32 || reaction.chars().any(char::is_whitespace) {
+        return Err(ValidationError::new("wrong_reaction"));
+    }
+    Ok(())
+}
+
+// comments are visible to whoever may see the discussed document
+#[derive(Clone, Debug, PartialEq)]
+pub enum TargetOwner {
+    Company(String),
+    User(String), // private documents such as events and contacts
+    Nobody,
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TargetKeys {
+    pub company_key: Option<String>,
+    pub owner_key: Option<String>,
+}
+
+// previous body of an edited comment
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CommentRevision {
+    pub body: String,
+    pub edited_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Comment {
+    pub target: String, // _id of the discussed document
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub parent_key: Option<String>,
+    pub author_key: String,
+    pub body: String,
+    pub history: Vec<CommentRevision>,
+    pub reactions: HashMap<String, Vec<String>>, // reaction to user keys
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub deleted_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CommentResponse {
+    pub _key: String,
+    pub target: String,
+    pub parent_key: Option<String>,
+    pub author_key: String,
+    pub author_name: Option<String>,
+    pub body: Option<String>, // none once the comment is deleted, replies stay in place
+    pub edited: bool,
+    pub reactions: HashMap<String, Vec<String>>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+    pub deleted_at: Option<DateTime<Utc>>,
+}
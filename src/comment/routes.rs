@@ -0,0 +1,200 @@
+use actix_web::{
+    delete,
+    error::{ErrorForbidden, ErrorNotFound},
+    get, post, put, web, Error, HttpRequest, HttpResponse,
+};
+use validator::Validate;
+
+use crate::auth::{self, Identity};
+use crate::comment::{
+    self,
+    validate_reaction,
+    CreateCommentRequest,
+    FindCommentsParams,
+    TargetOwner,
+    UpdateCommentRequest,
+};
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// comments are written by people, not by company keys
+fn require_author(req: &HttpRequest) -> Result<String, Error> {
+    let identity = auth::authorize(req, "comments:write")?;
+    identity.user_key()
+        .map(|x| x.to_string())
+        .ok_or_else(|| ErrorForbidden("comments can only be written by users"))
+}
+
+// comments are only as visible as the document they discuss
+async fn require_target(
+    req: &HttpRequest,
+    target: &str,
+    scope: &str,
+    pool: &DbPool,
+) -> Result<Identity, Error> {
+    let identity = auth::authorize(req, scope)?;
+    match comment::target_owner(target, pool).await? {
+        TargetOwner::Company(key) => {
+            company::require_access(req, &key, scope)?;
+            match identity.user_key() {
+                Some(user_key) if !company::is_company_member(&key, user_key, pool).await => {
+                    Err(ErrorNotFound("document not found"))
+                },
+                _ => Ok(identity),
+            }
+        },
+        TargetOwner::User(key) if identity.user_key() == Some(key.as_str()) => Ok(identity),
+        TargetOwner::User(_) => Err(ErrorForbidden("not the owner of the target")),
+        TargetOwner::Nobody if identity.company_key().is_some() => Err(ErrorForbidden("api key belongs to another company")),
+        TargetOwner::Nobody => Ok(identity),
+    }
+}
+
+async fn require_comment_target(
+    req: &HttpRequest,
+    key: &str,
+    scope: &str,
+    pool: &DbPool,
+) -> Result<Identity, Error> {
+    let target = comment::comment_target(key, pool).await?;
+    require_target(req, &target, scope, pool).await
+}
+
+#[get("/comments")]
+async fn find(
+    req: HttpRequest,
+    payload: web::Query<FindCommentsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    auth::authorize(&req, "comments:read")?;
+    let params: FindCommentsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            require_target(&req, &params.target, "comments:read", &pool).await?;
+            let result = comment::find_comments(params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/comments/{key}")]
+async fn show(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_comment_target(&req, &key, "comments:read", &pool).await?;
+    let result = comment::show_comment(&key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/comments")]
+async fn create(
+    req: HttpRequest,
+    payload: web::Json<CreateCommentRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let author_key = require_author(&req)?;
+    let params: CreateCommentRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            require_target(&req, &params.target, "comments:write", &pool).await?;
+            let result = comment::create_comment(&author_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/comments/{key}")]
+async fn update(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<UpdateCommentRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let author_key = require_author(&req)?;
+    let params: UpdateCommentRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = comment::update_comment(&key, &author_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/comments/{key}")]
+async fn delete(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let author_key = require_author(&req)?;
+    comment::delete_comment(&key, &author_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/comments/{key}/history")]
+async fn history(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_comment_target(&req, &key, "comments:read", &pool).await?;
+    let result = comment::find_comment_history(&key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/comments/{key}/reactions/{reaction}")]
+async fn add_reaction(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_author(&req)?;
+    let (key, reaction) = path.into_inner();
+    require_comment_target(&req, &key, "comments:write", &pool).await?;
+    match validate_reaction(&reaction) {
+        Ok(_) => {
+            let result = comment::set_reaction(&key, &reaction, &user_key, true, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Ok(HttpResponse::BadRequest().json(e))
+        },
+    }
+}
+
+#[delete("/comments/{key}/reactions/{reaction}")]
+async fn remove_reaction(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_author(&req)?;
+    let (key, reaction) = path.into_inner();
+    require_comment_target(&req, &key, "comments:write", &pool).await?;
+    let result = comment::set_reaction(&key, &reaction, &user_key, false, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(history);
+    cfg.service(add_reaction);
+    cfg.service(remove_reaction);
+}
@@ -1,35 +1,55 @@
-use actix_web::web;
+use actix_web::{
+    error::{ErrorBadRequest, ErrorNotFound},
+    web,
+    Error,
+};
 use arangors::{
     connection::ReqwestClient,
     document::{
         options::{InsertOptions, RemoveOptions, UpdateOptions},
         response::DocumentResponse,
     },
-    AqlQuery, Collection, Database, Document,
+    AqlQuery, ClientError, Collection, Database, Document,
 };
 use chrono::prelude::*;
+use serde::Deserialize;
 use serde_json::{from_str, json, to_string, to_value, Value};
 use std::collections::HashMap;
+use tracing::instrument;
 use validator::ValidationErrors;
 
-use crate::config::db_database;
-use crate::database::DbPool;
+use crate::custom_field::custom_query;
+use crate::database::{current_database, DbPool};
+use crate::tag::{parse_tag_names, tag_filter, tags_company};
+use crate::webhook::{dispatch, user_company_keys};
 use crate::company::{
+    AddMemberRequest,
     Company,
     DeleteCompanyParams,
     FindCompaniesParams,
+    MemberResponse,
+    Membership,
+    TwoFactorPolicyRequest,
 };
 
+#[instrument(skip_all)]
 pub async fn find_companies(
+    only: Option<&str>,
     params: FindCompaniesParams,
     pool: &DbPool,
 ) -> Result<Vec<Company>, ValidationErrors> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let mut terms = vec!["FOR c IN companies"];
     let mut vars: HashMap<&str, Value> = HashMap::new();
 
+    // company api keys only list their own company
+    if let Some(key) = only {
+        terms.push("FILTER c._key == @only");
+        vars.insert("only", to_value(key).unwrap());
+    }
+
     if params.search.is_some() {
         let search: String = params.search.unwrap().trim().to_string();
         if !search.is_empty() {
@@ -37,11 +57,40 @@ pub async fn find_companies(
             vars.insert("@search", to_value(search).unwrap());
         }
     }
+    let tag_names = params.tags.as_deref().map(parse_tag_names).unwrap_or_default();
+    let match_all = params.tags_mode.as_deref() == Some("all");
+    let tags_filter = tag_filter("c", match_all);
+    if !tag_names.is_empty() {
+        let company_key = tags_company(params.tags_company.as_deref())?;
+        terms.push(&tags_filter);
+        vars.insert("tags", to_value(&tag_names).unwrap());
+        vars.insert("tags_company", to_value(company_key).unwrap());
+        if match_all {
+            vars.insert("tag_count", to_value(tag_names.len()).unwrap());
+        }
+    }
+    let custom = custom_query(
+        &db,
+        "companies",
+        "c",
+        params.custom_company.as_deref(),
+        params.custom_field.as_deref(),
+        params.custom_value.as_deref(),
+        params.custom_sort.as_deref(),
+    ).await?;
+    if let Some(filter) = &custom.filter {
+        terms.push(filter);
+    }
+    vars.extend(custom.vars);
     if params.sort_by.is_some() {
         let sort_by: String = params.sort_by.unwrap();
         terms.push("SORT c.@@sort_by ASC");
         vars.insert("@sort_by", to_value(sort_by).unwrap());
     }
+    // applied last, so it takes precedence over sort_by
+    if let Some(sort) = &custom.sort {
+        terms.push(sort);
+    }
     if params.limit.is_some() {
         let limit: u32 = params.limit.unwrap();
         terms.push("LIMIT 0, @@limit");
@@ -59,12 +108,13 @@ pub async fn find_companies(
     Ok(records)
 }
 
+#[instrument(skip_all)]
 pub async fn show_company(
     key: &String,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let res: Document<Company> = collection.document(key.as_ref()).await.unwrap();
@@ -72,12 +122,14 @@ pub async fn show_company(
     Ok(record)
 }
 
+#[instrument(skip_all)]
 pub async fn create_company(
     payload: &web::Json<Company>,
+    creator: &str,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let now = Utc::now();
@@ -88,6 +140,8 @@ pub async fn create_company(
         created_at: Some(now),
         modified_at: Some(now),
         deleted_at: None,
+        require_two_factor: None,
+        custom: None,
     };
     let options: InsertOptions = InsertOptions::builder()
         .return_new(true)
@@ -95,16 +149,23 @@ pub async fn create_company(
 
     let res: DocumentResponse<Document<Company>> = collection.create_document(Document::new(data), options).await.unwrap();
     let record: &Company = res.new_doc().unwrap();
+
+    // whoever creates a company administers it
+    let header = res.header().unwrap();
+    insert_membership(&db, creator, &header._key, "admin").await.unwrap();
+
+    dispatch(&db, "company.created", &[header._key.clone()], None, res.new_doc()).await;
     Ok(record.clone())
 }
 
+#[instrument(skip_all)]
 pub async fn update_company(
     key: &String,
     payload: &web::Json<Company>,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let obj: Value = json!({
@@ -125,15 +186,18 @@ pub async fn update_company(
 
     let res: DocumentResponse<Document<Company>> = collection.update_document(key, Document::new(data), options).await.unwrap();
     let record: &Company = res.new_doc().unwrap();
+
+    dispatch(&db, "company.updated", &[key.clone()], res.old_doc(), res.new_doc()).await;
     Ok(record.clone())
 }
 
+#[instrument(skip_all)]
 pub async fn erase_company(
     key: &String,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let options: RemoveOptions = RemoveOptions::builder()
@@ -142,15 +206,18 @@ pub async fn erase_company(
 
     let res: DocumentResponse<Document<Company>> = collection.remove_document(key.as_ref(), options, None).await.unwrap();
     let record: &Company = res.old_doc().unwrap();
+
+    dispatch(&db, "company.erased", &[key.clone()], res.old_doc(), None).await;
     Ok(record.clone())
 }
 
+#[instrument(skip_all)]
 pub async fn trash_company(
     key: &String,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let obj = json!({
@@ -165,15 +232,18 @@ pub async fn trash_company(
 
     let res: DocumentResponse<Document<Company>> = collection.update_document(key, Document::new(data), options).await.unwrap();
     let record: &Company = res.new_doc().unwrap();
+
+    dispatch(&db, "company.trashed", &[key.clone()], res.old_doc(), res.new_doc()).await;
     Ok(record.clone())
 }
 
+#[instrument(skip_all)]
 pub async fn restore_company(
     key: &String,
     pool: &DbPool,
 ) -> Result<Company, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
     let data: Company = from_str::<Company>("{\"deleted_at\":null}").unwrap();
@@ -185,5 +255,254 @@ pub async fn restore_company(
 
     let res: DocumentResponse<Document<Company>> = collection.update_document(key, Document::new(data), options).await.unwrap();
     let record: &Company = res.new_doc().unwrap();
+
+    dispatch(&db, "company.restored", &[key.clone()], res.old_doc(), res.new_doc()).await;
+    Ok(record.clone())
+}
+
+async fn insert_membership(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    company_key: &str,
+    role: &str,
+) -> Result<(), Error> {
+    let data = Membership {
+        _from: format!("users/{}", user_key),
+        _to: format!("companies/{}", company_key),
+        role: role.to_string(),
+        created_at: Utc::now(),
+    };
+
+    // one edge per user and company, the role of an existing member is updated
+    let q = r#"UPSERT { _from: @from, _to: @to }
+        INSERT @data
+        UPDATE { role: @role }
+        IN memberships
+        RETURN OLD == null"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(&data._from).unwrap());
+    vars.insert("to", to_value(&data._to).unwrap());
+    vars.insert("role", to_value(role).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<bool> = db.aql_query(aql).await.map_err(ErrorBadRequest)?;
+    if records.first() == Some(&true) {
+        dispatch_user_joined(db, user_key, company_key).await;
+    }
+    Ok(())
+}
+
+// users sign up without a company, so `user.created` goes to the first company they join
+async fn dispatch_user_joined(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+    company_key: &str,
+) {
+    if user_company_keys(db, user_key).await.len() != 1 {
+        return;
+    }
+    let users: Collection<ReqwestClient> = db.collection("users").await.unwrap();
+    if let Ok(user) = users.document::<Value>(user_key).await {
+        dispatch(db, "user.created", &[company_key.to_string()], None, Some(&user)).await;
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn is_company_admin(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> bool {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "FOR m IN memberships FILTER m._from == @from AND m._to == @to AND m.role == 'admin' LIMIT 1 RETURN m._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(format!("users/{}", user_key)).unwrap());
+    vars.insert("to", to_value(format!("companies/{}", company_key)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+// managers approve the work of members, admins can do everything managers can
+#[instrument(skip_all)]
+pub async fn is_company_manager(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> bool {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "FOR m IN memberships FILTER m._from == @from AND m._to == @to AND m.role IN ['admin', 'manager'] LIMIT 1 RETURN m._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(format!("users/{}", user_key)).unwrap());
+    vars.insert("to", to_value(format!("companies/{}", company_key)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+#[instrument(skip_all)]
+pub async fn is_company_member(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> bool {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "FOR m IN memberships FILTER m._from == @from AND m._to == @to LIMIT 1 RETURN m._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(format!("users/{}", user_key)).unwrap());
+    vars.insert("to", to_value(format!("companies/{}", company_key)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await.unwrap();
+    !records.is_empty()
+}
+
+#[derive(Deserialize)]
+struct CompanyMembers {
+    company_key: String,
+    members: Vec<MemberResponse>,
+}
+
+// members of several companies in one query, each company sorted on its own
+pub async fn members_of(
+    db: &Database<ReqwestClient>,
+    company_keys: &[String],
+) -> Result<HashMap<String, Vec<MemberResponse>>, ClientError> {
+    let q = r#"FOR k IN @keys
+        RETURN {
+            company_key: k,
+            members: (
+                FOR u, m IN 1..1 INBOUND CONCAT('companies/', k) memberships
+                    SORT u.name ASC
+                    RETURN {
+                        user_key: u._key,
+                        name: u.name,
+                        email: u.email,
+                        role: m.role,
+                        two_factor_enabled: u.two_factor != null
+                    }
+            )
+        }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("keys", to_value(company_keys).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<CompanyMembers> = db.aql_query(aql).await?;
+    Ok(records.into_iter().map(|x| (x.company_key, x.members)).collect())
+}
+
+#[instrument(skip_all)]
+pub async fn find_members(
+    key: &String,
+    pool: &DbPool,
+) -> Result<Vec<MemberResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut records = members_of(&db, &[key.clone()]).await.map_err(ErrorNotFound)?;
+    Ok(records.remove(key).unwrap_or_default())
+}
+
+#[instrument(skip_all)]
+pub async fn add_member(
+    key: &String,
+    payload: &AddMemberRequest,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let users: Collection<ReqwestClient> = db.collection("users").await.unwrap();
+    let _user: Document<Value> = users.document(&payload.user_key).await.map_err(ErrorNotFound)?;
+
+    insert_membership(&db, &payload.user_key, key, &payload.role).await?;
+
+    let member = json!({
+        "user_key": payload.user_key,
+        "company_key": key,
+        "role": payload.role,
+    });
+    dispatch(&db, "member.added", &[key.clone()], None, Some(&member)).await;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn remove_member(
+    key: &String,
+    user_key: &String,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR m IN memberships
+        FILTER m._from == @from AND m._to == @to
+        REMOVE m IN memberships
+        RETURN { user_key: PARSE_IDENTIFIER(OLD._from).key, company_key: PARSE_IDENTIFIER(OLD._to).key, role: OLD.role }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(format!("users/{}", user_key)).unwrap());
+    vars.insert("to", to_value(format!("companies/{}", key)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<Value> = db.aql_query(aql).await.unwrap();
+    if records.is_empty() {
+        return Err(ErrorNotFound("membership not found"));
+    }
+
+    dispatch(&db, "member.removed", &[key.clone()], records.first(), None).await;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn set_two_factor_policy(
+    key: &String,
+    payload: &TwoFactorPolicyRequest,
+    pool: &DbPool,
+) -> Result<Company, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("companies").await.unwrap();
+    let obj: Value = json!({
+        "require_two_factor": payload.required,
+        "modified_at": Utc::now(),
+    });
+    let text: String = to_string(&obj).unwrap();
+    let data: Company = from_str::<Company>(&text).unwrap();
+    let options: UpdateOptions = UpdateOptions::builder()
+        .return_new(true)
+        .return_old(true)
+        .build();
+
+    let res: DocumentResponse<Document<Company>> = collection.update_document(key, Document::new(data), options).await
+        .map_err(ErrorNotFound)?;
+    let record: &Company = res.new_doc().unwrap();
     Ok(record.clone())
 }
@@ -4,4 +4,4 @@ mod routes;
 
 pub use models::*;
 pub use controllers::*;
-pub use routes::init;
+pub use routes::{init, require_access, require_admin};
@@ -1,7 +1,10 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use validator::{Validate, ValidationError};
 
+use crate::tag::validate_tags_mode;
+
 #[derive(Clone, Debug, Validate, Deserialize)]
 pub struct FindCompaniesParams {
     pub search: Option<String>,
@@ -9,11 +12,19 @@ pub struct FindCompaniesParams {
     pub sort_by: Option<String>,
     #[validate(range(min = 1, max = 100))]
     pub limit: Option<u32>,
+    pub tags: Option<String>, // comma separated tag names
+    #[validate(custom = "validate_tags_mode")]
+    pub tags_mode: Option<String>, // all or any, any by default
+    pub tags_company: Option<String>, // company whose tags are matched, required with tags
+    pub custom_company: Option<String>, // company whose custom fields are used below
+    pub custom_field: Option<String>,
+    pub custom_value: Option<String>,
+    pub custom_sort: Option<String>,
 }
 
 fn validate_sort_by(sort_by: &str) -> Result<(), ValidationError> {
     if sort_by != "name" && sort_by != "since" {
-        return Err(ValidationError::new("Wrong sort_by"));
+        return Err(ValidationError::new("wrong_sort_by"));
     }
     Ok(())
 }
@@ -26,11 +37,48 @@ pub struct DeleteCompanyParams {
 
 fn validate_mode(mode: &str) -> Result<(), ValidationError> {
     if mode != "erase" && mode != "trash" && mode != "restore" {
-        return Err(ValidationError::new("Wrong mode"));
+        return Err(ValidationError::new("wrong_mode"));
     }
     Ok(())
 }
 
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct AddMemberRequest {
+    pub user_key: String,
+    #[validate(custom = "validate_role")]
+    pub role: String,
+}
+
+fn validate_role(role: &str) -> Result<(), ValidationError> {
+    if role != "admin" && role != "manager" && role != "member" {
+        return Err(ValidationError::new("wrong_role"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize)]
+pub struct TwoFactorPolicyRequest {
+    pub required: bool,
+}
+
+// edge from users to companies
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Membership {
+    pub _from: String,
+    pub _to: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct MemberResponse {
+    pub user_key: String,
+    pub name: String,
+    pub email: String,
+    pub role: String,
+    pub two_factor_enabled: bool,
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Company {
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
@@ -43,4 +91,8 @@ pub struct Company {
     pub modified_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
     pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub require_two_factor: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub custom: Option<Value>, // custom field values by company, written through custom-values
 }
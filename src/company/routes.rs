@@ -1,65 +1,131 @@
-use actix_web::{delete, get, post, put, web, Error, HttpResponse};
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
 use validator::Validate;
 
+use crate::auth::{self, Identity};
 use crate::company::{
     self,
+    AddMemberRequest,
     Company,
     FindCompaniesParams,
     DeleteCompanyParams,
+    TwoFactorPolicyRequest,
 };
 use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// keys owned by a company cannot reach other companies
+pub fn require_access(
+    req: &HttpRequest,
+    key: &str,
+    scope: &str,
+) -> Result<Identity, Error> {
+    let identity = auth::authorize(req, scope)?;
+    if let Some(company_key) = identity.company_key() {
+        if company_key != key {
+            return Err(ErrorForbidden("api key belongs to another company"));
+        }
+    }
+    Ok(identity)
+}
+
+// members and their two-factor status are only shown within the company
+pub async fn require_member(
+    req: &HttpRequest,
+    key: &str,
+    scope: &str,
+    pool: &DbPool,
+) -> Result<Identity, Error> {
+    let identity = require_access(req, key, scope)?;
+    if let Some(user_key) = identity.user_key() {
+        if !company::is_company_member(key, user_key, pool).await {
+            return Err(ErrorForbidden("company member required"));
+        }
+    }
+    Ok(identity)
+}
+
+pub async fn require_admin(
+    req: &HttpRequest,
+    key: &str,
+    pool: &DbPool,
+) -> Result<Identity, Error> {
+    let identity = require_access(req, key, "companies:write")?;
+    if let Some(user_key) = identity.user_key() {
+        if !company::is_company_admin(key, user_key, pool).await {
+            return Err(ErrorForbidden("company admin required"));
+        }
+    }
+    Ok(identity)
+}
 
 #[get("/companies")]
 async fn find(
+    req: HttpRequest,
     payload: web::Query<FindCompaniesParams>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    let identity = auth::authorize(&req, "companies:read")?;
     let params: FindCompaniesParams = payload.into_inner();
     match params.validate() {
         Ok(_) => {
-            let result = company::find_companies(params, &pool).await.unwrap();
-            Ok(HttpResponse::Ok().json(result))
+            if let Some(key) = &params.tags_company {
+                require_access(&req, key, "companies:read")?;
+            }
+            match company::find_companies(identity.company_key(), params, &pool).await {
+                Ok(result) => Ok(HttpResponse::Ok().json(result)),
+                Err(e) => Err(Invalid(e).into()),
+            }
         },
         Err(e) => {
-            Ok(HttpResponse::BadRequest().json(e.errors()))
+            Err(Invalid(e).into())
         },
     }
 }
 
 #[get("/companies/{key}")]
 async fn show(
+    req: HttpRequest,
     key: web::Path<String>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    require_access(&req, &key, "companies:read")?;
     let result = company::show_company(&key, &pool).await.unwrap();
     Ok(HttpResponse::Ok().json(result))
 }
 
 #[post("/companies")]
 async fn create(
+    req: HttpRequest,
     payload: web::Json<Company>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
-    let result = company::create_company(&payload, &pool).await.unwrap();
+    let identity = auth::authorize(&req, "companies:write")?;
+    let creator = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("companies can only be created by users"))?;
+    let result = company::create_company(&payload, creator, &pool).await.unwrap();
     Ok(HttpResponse::Ok().json(result))
 }
 
 #[put("/companies/{key}")]
 async fn update(
+    req: HttpRequest,
     key: web::Path<String>,
     payload: web::Json<Company>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    require_admin(&req, &key, &pool).await?;
     let result = company::update_company(&key, &payload, &pool).await.unwrap();
     Ok(HttpResponse::Ok().json(result))
 }
 
 #[delete("/companies/{key}")]
 async fn delete(
+    req: HttpRequest,
     key: web::Path<String>,
     form: web::Form<DeleteCompanyParams>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    require_admin(&req, &key, &pool).await?;
     match form.mode.as_str() {
         "erase" => {
             let result = company::erase_company(&key, &pool).await.unwrap();
@@ -79,6 +145,61 @@ async fn delete(
     }
 }
 
+#[get("/companies/{key}/members")]
+async fn find_members(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_member(&req, &key, "companies:read", &pool).await?;
+    let result = company::find_members(&key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/members")]
+async fn add_member(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<AddMemberRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_admin(&req, &key, &pool).await?;
+    let params: AddMemberRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            company::add_member(&key, &params, &pool).await?;
+            Ok(HttpResponse::NoContent().finish())
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/members/{user_key}")]
+async fn remove_member(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, user_key) = path.into_inner();
+    require_admin(&req, &key, &pool).await?;
+    company::remove_member(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[put("/companies/{key}/two-factor")]
+async fn set_two_factor_policy(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<TwoFactorPolicyRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_admin(&req, &key, &pool).await?;
+    let result = company::set_two_factor_policy(&key, &payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
 // function that will be called on new Application to configure routes for this module
 pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(find);
@@ -86,4 +207,8 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(create);
     cfg.service(update);
     cfg.service(delete);
+    cfg.service(find_members);
+    cfg.service(add_member);
+    cfg.service(remove_member);
+    cfg.service(set_two_factor_policy);
 }
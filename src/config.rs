@@ -24,6 +24,69 @@ pub fn db_password() -> String {
   return env::var("DB_PASSWORD").expect("DB_PASSWORD must be set");
 }
 
+// provisions DB_USERNAME on startup and writes the tenant registry, the app serves requests as DB_USERNAME
+pub fn db_admin_username() -> String {
+  return env::var("DB_ADMIN_USERNAME").unwrap_or_default();
+}
+
+pub fn db_admin_password() -> String {
+  return env::var("DB_ADMIN_PASSWORD").unwrap_or_default();
+}
+
 pub fn db_database() -> String {
   return env::var("DB_DATABASE").expect("DB_DATABASE must be set");
 }
+
+pub fn mail_host() -> String {
+  return env::var("MAIL_HOST").expect("MAIL_HOST must be set");
+}
+
+pub fn mail_port() -> String {
+  return env::var("MAIL_PORT").expect("MAIL_PORT must be set");
+}
+
+pub fn mail_username() -> String {
+  return env::var("MAIL_USERNAME").expect("MAIL_USERNAME must be set");
+}
+
+pub fn mail_password() -> String {
+  return env::var("MAIL_PASSWORD").expect("MAIL_PASSWORD must be set");
+}
+
+pub fn mail_from() -> String {
+  return env::var("MAIL_FROM").expect("MAIL_FROM must be set");
+}
+
+// "single" uses DB_DATABASE for everything, "database" gives every tenant its own database
+pub fn tenancy_mode() -> String {
+  return env::var("TENANCY_MODE").unwrap_or_else(|_| "single".to_string());
+}
+
+// requests to {tenant}.TENANT_DOMAIN are routed to that tenant
+pub fn tenant_domain() -> String {
+  return env::var("TENANT_DOMAIN").unwrap_or_default();
+}
+
+pub fn tenant_admin_token() -> String {
+  return env::var("TENANT_ADMIN_TOKEN").unwrap_or_default();
+}
+
+// background workers started by each server for the job queue
+pub fn job_workers() -> String {
+  return env::var("JOB_WORKERS").unwrap_or_else(|_| "2".to_string());
+}
+
+// tracing filter directives, arangors emits a debug span for every call to the database
+pub fn log_filter() -> String {
+  return env::var("LOG_FILTER").unwrap_or_else(|_| "info,arangors=debug".to_string());
+}
+
+// bearer token required to scrape /metrics, every scrape is rejected when empty
+pub fn metrics_token() -> String {
+  return env::var("METRICS_TOKEN").unwrap_or_default();
+}
+
+// how often the business gauges are counted
+pub fn metrics_refresh_seconds() -> String {
+  return env::var("METRICS_REFRESH_SECONDS").unwrap_or_else(|_| "60".to_string());
+}
@@ -0,0 +1,294 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Map, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::company::is_company_member;
+use crate::contact::{Contact, ContactRequest, ContactResponse, FindContactsParams};
+use crate::custom_field::{check_custom_values, custom_query};
+use crate::database::{current_database, DbPool};
+use crate::i18n::Invalid;
+
+const CONTACT_TERMS: &str = "RETURN UNSET(c, '_id', '_rev')";
+
+async fn find_contact(
+    db: &Database<ReqwestClient>,
+    key: &str,
+) -> Result<ContactResponse, Error> {
+    let q = format!("FOR c IN contacts FILTER c._key == @key {}", CONTACT_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<ContactResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("contact not found"))
+}
+
+// custom values can only be given for companies the owner belongs to
+async fn check_custom(
+    owner_key: &str,
+    custom: Option<&Map<String, Value>>,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let custom = match custom {
+        Some(custom) => custom,
+        None => return Ok(()),
+    };
+    for company_key in custom.keys() {
+        if !is_company_member(company_key, owner_key, pool).await {
+            return Err(ErrorForbidden("company member required"));
+        }
+    }
+    check_custom_values("contacts", custom, pool).await
+        .map_err(Invalid)?;
+    Ok(())
+}
+
+fn to_contact(
+    payload: &ContactRequest,
+    owner_key: &str,
+    uid: String,
+    created_at: DateTime<Utc>,
+    custom: Option<Map<String, Value>>,
+) -> Contact {
+    let mut emails: Vec<String> = payload.emails.clone().unwrap_or_default()
+        .iter()
+        .map(|x| x.trim().to_lowercase())
+        .collect();
+    emails.sort();
+    emails.dedup();
+    Contact {
+        uid,
+        owner_key: owner_key.to_string(),
+        name: payload.name.trim().to_string(),
+        emails,
+        phones: payload.phones.clone().unwrap_or_default(),
+        organization: payload.organization.clone(),
+        note: payload.note.clone(),
+        custom,
+        created_at,
+        modified_at: Utc::now(),
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn find_contacts(
+    owner_key: &str,
+    params: FindContactsParams,
+    pool: &DbPool,
+) -> Result<Vec<ContactResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut terms = vec!["FOR c IN contacts FILTER c.owner_key == @owner_key"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+
+    if let Some(search) = params.search.as_deref().map(|x| x.trim().to_lowercase()) {
+        if !search.is_empty() {
+            terms.push(r#"FILTER CONTAINS(LOWER(c.name), @search)
+                OR CONTAINS(LOWER(c.organization), @search)
+                OR LENGTH(FOR e IN c.emails FILTER CONTAINS(e, @search) RETURN 1) > 0"#);
+            vars.insert("search", to_value(search).unwrap());
+        }
+    }
+    let custom = custom_query(
+        &db,
+        "contacts",
+        "c",
+        params.custom_company.as_deref(),
+        params.custom_field.as_deref(),
+        params.custom_value.as_deref(),
+        params.custom_sort.as_deref(),
+    ).await.map_err(Invalid)?;
+    if let Some(filter) = &custom.filter {
+        terms.push(filter);
+    }
+    vars.extend(custom.vars);
+    terms.push(custom.sort.as_deref().unwrap_or("SORT c.name ASC"));
+    if let Some(limit) = params.limit {
+        terms.push("LIMIT 0, @limit");
+        vars.insert("limit", to_value(limit).unwrap());
+    }
+    terms.push(CONTACT_TERMS);
+    let q = terms.join(" ");
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<ContactResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_contact(
+    key: &String,
+    owner_key: &str,
+    pool: &DbPool,
+) -> Result<ContactResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let contact = find_contact(&db, key).await?;
+    if contact.owner_key != owner_key {
+        return Err(ErrorNotFound("contact not found"));
+    }
+    Ok(contact)
+}
+
+#[instrument(skip_all)]
+pub async fn create_contact(
+    owner_key: &str,
+    payload: &ContactRequest,
+    pool: &DbPool,
+) -> Result<ContactResponse, Error> {
+    check_custom(owner_key, payload.custom.as_ref(), pool).await?;
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("contacts").await.unwrap();
+    let data = to_contact(payload, owner_key, Uuid::new_v4().to_string(), Utc::now(), payload.custom.clone());
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<Contact>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+    find_contact(&db, &header._key).await
+}
+
+#[instrument(skip_all)]
+pub async fn update_contact(
+    key: &String,
+    owner_key: &str,
+    payload: &ContactRequest,
+    pool: &DbPool,
+) -> Result<ContactResponse, Error> {
+    check_custom(owner_key, payload.custom.as_ref(), pool).await?;
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_contact(&db, key).await?;
+    if current.owner_key != owner_key {
+        return Err(ErrorNotFound("contact not found"));
+    }
+    let custom = payload.custom.clone().or(current.custom);
+    replace_contact(&db, key, &to_contact(payload, owner_key, current.uid, current.created_at, custom)).await?;
+    find_contact(&db, key).await
+}
+
+async fn replace_contact(
+    db: &Database<ReqwestClient>,
+    key: &str,
+    data: &Contact,
+) -> Result<(), Error> {
+    let q = "FOR c IN contacts FILTER c._key == @key REPLACE c WITH @data IN contacts";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", to_value(data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// creates or replaces the contact under a key chosen by the client, true when created
+#[instrument(skip_all)]
+pub async fn put_contact(
+    key: &str,
+    owner_key: &str,
+    uid: String,
+    payload: &ContactRequest,
+    pool: &DbPool,
+) -> Result<bool, Error> {
+    check_custom(owner_key, payload.custom.as_ref(), pool).await?;
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    match find_contact(&db, key).await {
+        Ok(current) => {
+            if current.owner_key != owner_key {
+                return Err(ErrorForbidden("not the owner of the contact"));
+            }
+            let custom = payload.custom.clone().or(current.custom);
+            replace_contact(&db, key, &to_contact(payload, owner_key, uid, current.created_at, custom)).await?;
+            Ok(false)
+        },
+        Err(_) => {
+            let collection: Collection<ReqwestClient> = db.collection("contacts").await.unwrap();
+            let mut doc = Document::new(to_contact(payload, owner_key, uid, Utc::now(), payload.custom.clone()));
+            doc.header._key = key.to_string();
+            let options: InsertOptions = InsertOptions::builder()
+                .return_new(false)
+                .build();
+
+            let _res: DocumentResponse<Document<Contact>> = collection.create_document(doc, options).await
+                .map_err(ErrorInternalServerError)?;
+            Ok(true)
+        },
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn delete_contact(
+    key: &str,
+    owner_key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR c IN contacts
+        FILTER c._key == @key AND c.owner_key == @owner_key
+        REMOVE c IN contacts
+        RETURN OLD._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        return Err(ErrorNotFound("contact not found"));
+    }
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn contact_owner(
+    key: &str,
+    pool: &DbPool,
+) -> Option<String> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_contact(&db, key).await.ok().map(|x| x.owner_key)
+}
@@ -0,0 +1,70 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use validator::{validate_email, Validate, ValidationError};
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindContactsParams {
+    pub search: Option<String>, // matches names, emails and organizations
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+    pub custom_company: Option<String>, // company whose custom fields are used below
+    pub custom_field: Option<String>,
+    pub custom_value: Option<String>,
+    pub custom_sort: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ContactRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub name: String,
+    #[validate(length(max = 20), custom = "validate_emails")]
+    pub emails: Option<Vec<String>>,
+    #[validate(length(max = 20))]
+    pub phones: Option<Vec<String>>,
+    #[validate(length(max = 200))]
+    pub organization: Option<String>,
+    #[validate(length(max = 10000))]
+    pub note: Option<String>,
+    pub custom: Option<Map<String, Value>>, // custom field values by company, kept when none
+}
+
+fn validate_emails(emails: &[String]) -> Result<(), ValidationError> {
+    if !emails.iter().all(validate_email) {
+        return Err(ValidationError::new("wrong_emails"));
+    }
+    Ok(())
+}
+
+// personal address book entry, also served over carddav
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Contact {
+    pub uid: String, // stable identifier for address book clients
+    pub owner_key: String,
+    pub name: String,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub organization: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub note: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub custom: Option<Map<String, Value>>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ContactResponse {
+    pub _key: String,
+    pub uid: String,
+    pub owner_key: String,
+    pub name: String,
+    pub emails: Vec<String>,
+    pub phones: Vec<String>,
+    pub organization: Option<String>,
+    pub note: Option<String>,
+    pub custom: Option<Map<String, Value>>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
@@ -0,0 +1,104 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::auth;
+use crate::contact::{self, ContactRequest, FindContactsParams};
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// address books belong to people, company keys have none
+fn require_user(req: &HttpRequest, scope: &str) -> Result<String, Error> {
+    let identity = auth::authorize(req, scope)?;
+    identity.user_key()
+        .map(|x| x.to_string())
+        .ok_or_else(|| ErrorForbidden("contacts belong to users"))
+}
+
+#[get("/contacts")]
+async fn find(
+    req: HttpRequest,
+    payload: web::Query<FindContactsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "contacts:read")?;
+    let params: FindContactsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = contact::find_contacts(&user_key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/contacts/{key}")]
+async fn show(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "contacts:read")?;
+    let result = contact::show_contact(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/contacts")]
+async fn create(
+    req: HttpRequest,
+    payload: web::Json<ContactRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "contacts:write")?;
+    let params: ContactRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = contact::create_contact(&user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/contacts/{key}")]
+async fn update(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<ContactRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "contacts:write")?;
+    let params: ContactRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = contact::update_contact(&key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/contacts/{key}")]
+async fn delete(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "contacts:write")?;
+    contact::delete_contact(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+}
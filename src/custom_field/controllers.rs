@@ -0,0 +1,477 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    collection::options::PropertiesOptions,
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::{prelude::*, NaiveDate};
+use serde_json::{json, to_value, Map, Value};
+use std::{borrow::Cow, collections::HashMap};
+use tracing::instrument;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::custom_field::{
+    CreateCustomFieldRequest,
+    CustomField,
+    CustomFieldResponse,
+    UpdateCustomFieldRequest,
+    TEXT_MAX_LENGTH,
+};
+use crate::database::{current_database, DbPool};
+use crate::i18n::Invalid;
+
+const FIELD_TERMS: &str = r#"RETURN {
+        _key: f._key,
+        entity: f.entity,
+        name: f.name,
+        label: f.label,
+        field_type: f.field_type,
+        required: f.required,
+        options: f.options,
+        reference: f.reference,
+        created_at: f.created_at,
+        modified_at: f.modified_at
+    }"#;
+
+// filter and sort on custom fields of list endpoints, bound to the `var` of their query
+pub struct CustomQuery {
+    pub filter: Option<String>,
+    pub sort: Option<String>,
+    pub vars: Vec<(&'static str, Value)>,
+}
+
+fn field_error(field: &'static str, code: &'static str) -> ValidationErrors {
+    let mut errors = ValidationErrors::new();
+    errors.add(field, ValidationError::new(code));
+    errors
+}
+
+async fn run_query(
+    db: &Database<ReqwestClient>,
+    q: &str,
+    vars: HashMap<&str, Value>,
+) -> Result<Vec<Value>, Error> {
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await.map_err(ErrorInternalServerError)
+}
+
+async fn definitions(
+    db: &Database<ReqwestClient>,
+    company_key: Option<&str>,
+    entity: &str,
+) -> Vec<CustomField> {
+    let q = "FOR f IN custom_fields FILTER f.entity == @entity AND (@company_key == null OR f.company_key == @company_key) RETURN f";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("entity", to_value(entity).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await.unwrap()
+}
+
+async fn find_field(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<CustomFieldResponse, Error> {
+    let q = format!("FOR f IN custom_fields FILTER f._key == @key AND f.company_key == @company_key {}", FIELD_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<CustomFieldResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("custom field not found"))
+}
+
+fn field_schema(field: &CustomField) -> Value {
+    match field.field_type.as_str() {
+        "number" => json!({ "type": "number" }),
+        "date" => json!({ "type": "string", "pattern": "^[0-9]{4}-[0-9]{2}-[0-9]{2}$" }),
+        "enum" => json!({ "type": "string", "enum": field.options }),
+        "reference" => json!({ "type": "string", "minLength": 1 }),
+        _ => json!({ "type": "string", "maxLength": TEXT_MAX_LENGTH }),
+    }
+}
+
+// JSON Schema of the `custom` attribute, values are grouped by the company that defined them
+pub fn custom_schema(fields: &[CustomField]) -> Value {
+    let mut companies: Map<String, Value> = Map::new();
+    for field in fields {
+        let company = companies.entry(field.company_key.clone()).or_insert_with(|| json!({
+            "type": "object",
+            "properties": {},
+            "additionalProperties": false,
+        }));
+        company["properties"][&field.name] = field_schema(field);
+        if field.required {
+            match company.get_mut("required") {
+                Some(Value::Array(required)) => required.push(to_value(&field.name).unwrap()),
+                _ => company["required"] = json!([field.name]),
+            }
+        }
+    }
+    json!({
+        "type": "object",
+        "properties": {
+            "custom": {
+                "type": "object",
+                "properties": companies,
+            },
+        },
+    })
+}
+
+// the database rejects documents that bypass the api, existing invalid ones stay writable
+async fn sync_schema(
+    db: &Database<ReqwestClient>,
+    entity: &str,
+) -> Result<(), Error> {
+    let fields = definitions(db, None, entity).await;
+    let schema = if fields.is_empty() {
+        Value::Null
+    } else {
+        json!({
+            "rule": custom_schema(&fields),
+            "level": "moderate",
+            "message": "custom field values do not match their definitions",
+        })
+    };
+
+    let collection: Collection<ReqwestClient> = db.collection(entity).await.unwrap();
+    let options = PropertiesOptions::builder()
+        .schema(schema)
+        .build();
+    collection.change_properties(options).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// same rules as the schema, plus the existence of referenced documents
+async fn check_values(
+    db: &Database<ReqwestClient>,
+    fields: &[CustomField],
+    values: &Map<String, Value>,
+) -> Result<(), ValidationErrors> {
+    let mut errors = ValidationErrors::new();
+    for (name, value) in values.iter() {
+        let field = match fields.iter().find(|x| &x.name == name) {
+            Some(field) => field,
+            None => {
+                errors.add("custom", ValidationError::new("unknown_field"));
+                continue;
+            },
+        };
+        let valid = match (field.field_type.as_str(), value) {
+            ("number", Value::Number(_)) => true,
+            ("date", Value::String(x)) => NaiveDate::parse_from_str(x, "%Y-%m-%d").is_ok(),
+            ("enum", Value::String(x)) => field.options.contains(x),
+            ("reference", Value::String(x)) => {
+                let q = "RETURN DOCUMENT(@id) != null";
+                let mut vars: HashMap<&str, Value> = HashMap::new();
+                vars.insert("id", to_value(format!("{}/{}", field.reference.as_deref().unwrap_or(""), x)).unwrap());
+                let exists = run_query(db, q, vars).await.unwrap_or_default();
+                !x.contains('/') && exists.first() == Some(&Value::Bool(true))
+            },
+            ("text", Value::String(x)) => x.chars().count() <= TEXT_MAX_LENGTH,
+            _ => false,
+        };
+        if !valid {
+            let mut error = ValidationError::new("wrong_value");
+            error.add_param(Cow::from("field"), name);
+            errors.add("custom", error);
+        }
+    }
+    for field in fields.iter().filter(|x| x.required) {
+        if !values.contains_key(&field.name) {
+            let mut error = ValidationError::new("required_field");
+            error.add_param(Cow::from("field"), &field.name);
+            errors.add("custom", error);
+        }
+    }
+
+    if errors.is_empty() {
+        Ok(())
+    } else {
+        Err(errors)
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn find_custom_fields(
+    company_key: &str,
+    entity: Option<String>,
+    pool: &DbPool,
+) -> Result<Vec<CustomFieldResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(
+        "FOR f IN custom_fields FILTER f.company_key == @company_key AND (@entity == null OR f.entity == @entity) SORT f.entity ASC, f.name ASC {}",
+        FIELD_TERMS,
+    );
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("entity", to_value(entity).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<CustomFieldResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn create_custom_field(
+    company_key: &str,
+    payload: &CreateCustomFieldRequest,
+    pool: &DbPool,
+) -> Result<CustomFieldResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("custom_fields").await.unwrap();
+    let now = Utc::now();
+    let data = CustomField {
+        company_key: company_key.to_string(),
+        entity: payload.entity.clone(),
+        name: payload.name.clone(),
+        label: payload.label.trim().to_string(),
+        field_type: payload.field_type.clone(),
+        required: payload.required.unwrap_or(false),
+        options: if payload.field_type == "enum" { payload.options.clone().unwrap_or_default() } else { vec![] },
+        reference: if payload.field_type == "reference" { payload.reference.clone() } else { None },
+        created_at: now,
+        modified_at: now,
+    };
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    // names are unique per company and entity
+    let res: DocumentResponse<Document<CustomField>> = collection.create_document(Document::new(data), options).await
+        .map_err(|_| ErrorConflict("custom field already exists"))?;
+    let header = res.header().unwrap();
+
+    sync_schema(&db, &payload.entity).await?;
+    find_field(&db, company_key, &header._key).await
+}
+
+// the name and type of a field are fixed, stored values would not match otherwise
+#[instrument(skip_all)]
+pub async fn update_custom_field(
+    company_key: &str,
+    key: &str,
+    payload: &UpdateCustomFieldRequest,
+    pool: &DbPool,
+) -> Result<CustomFieldResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let field = find_field(&db, company_key, key).await?;
+    if payload.options.is_some() && field.field_type != "enum" {
+        return Err(ErrorBadRequest("only enum fields have options"));
+    }
+
+    let mut data = json!({
+        "modified_at": Utc::now(),
+    });
+    if let Some(label) = &payload.label {
+        data["label"] = to_value(label.trim()).unwrap();
+    }
+    if let Some(required) = payload.required {
+        data["required"] = to_value(required).unwrap();
+    }
+    if let Some(options) = &payload.options {
+        data["options"] = to_value(options).unwrap();
+    }
+
+    let q = "UPDATE @key WITH @data IN custom_fields OPTIONS { mergeObjects: false }";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", data);
+    run_query(&db, q, vars).await?;
+
+    sync_schema(&db, &field.entity).await?;
+    find_field(&db, company_key, key).await
+}
+
+// stored values are removed once the schema no longer allows them
+#[instrument(skip_all)]
+pub async fn delete_custom_field(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let field = find_field(&db, company_key, key).await?;
+
+    let q = "REMOVE @key IN custom_fields";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    run_query(&db, q, vars).await?;
+
+    sync_schema(&db, &field.entity).await?;
+
+    let q = r#"FOR d IN @@entity
+        FILTER HAS(d.custom[@company_key] || {}, @name)
+        UPDATE d WITH { custom: { [@company_key]: { [@name]: null } } } IN @@entity
+        OPTIONS { keepNull: false }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("@entity", to_value(&field.entity).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("name", to_value(&field.name).unwrap());
+    run_query(&db, q, vars).await?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn show_custom_values(
+    company_key: &str,
+    entity: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<Value, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = "LET d = DOCUMENT(@id) FILTER d != null RETURN d.custom[@company_key] || {}";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("id", to_value(format!("{}/{}", entity, key)).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    let mut records = run_query(&db, q, vars).await?;
+    records.pop().ok_or_else(|| ErrorNotFound("document not found"))
+}
+
+// values are merged into the current ones, null removes a value
+#[instrument(skip_all)]
+pub async fn set_custom_values(
+    company_key: &str,
+    entity: &str,
+    key: &str,
+    values: &Map<String, Value>,
+    pool: &DbPool,
+) -> Result<Value, Error> {
+    let mut merged = match show_custom_values(company_key, entity, key, pool).await? {
+        Value::Object(map) => map,
+        _ => Map::new(),
+    };
+    for (name, value) in values.iter() {
+        if value.is_null() {
+            merged.remove(name);
+        } else {
+            merged.insert(name.clone(), value.clone());
+        }
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let fields = definitions(&db, Some(company_key), entity).await;
+    check_values(&db, &fields, &merged).await
+        .map_err(Invalid)?;
+
+    let q = r#"LET d = DOCUMENT(@id)
+        UPDATE d WITH { custom: MERGE(d.custom || {}, { [@company_key]: @values }) } IN @@entity
+        OPTIONS { mergeObjects: false }
+        RETURN NEW.custom[@company_key]"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("id", to_value(format!("{}/{}", entity, key)).unwrap());
+    vars.insert("@entity", to_value(entity).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("values", Value::Object(merged));
+    let mut records = run_query(&db, q, vars).await?;
+    Ok(records.pop().unwrap_or(Value::Null))
+}
+
+// values sent along with a document, grouped by the company that defined the fields
+#[instrument(skip_all)]
+pub async fn check_custom_values(
+    entity: &str,
+    custom: &Map<String, Value>,
+    pool: &DbPool,
+) -> Result<(), ValidationErrors> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    for (company_key, values) in custom.iter() {
+        let values = match values {
+            Value::Object(map) => map,
+            _ => {
+                let mut error = ValidationError::new("wrong_value");
+                error.add_param(Cow::from("field"), company_key);
+                let mut errors = ValidationErrors::new();
+                errors.add("custom", error);
+                return Err(errors);
+            },
+        };
+        let fields = definitions(&db, Some(company_key), entity).await;
+        check_values(&db, &fields, values).await?;
+    }
+    Ok(())
+}
+
+// values are compared with the type of their definition, query strings are always text
+#[instrument(skip_all)]
+pub async fn custom_query(
+    db: &Database<ReqwestClient>,
+    entity: &str,
+    var: &str,
+    company_key: Option<&str>,
+    field: Option<&str>,
+    value: Option<&str>,
+    sort: Option<&str>,
+) -> Result<CustomQuery, ValidationErrors> {
+    let mut query = CustomQuery {
+        filter: None,
+        sort: None,
+        vars: vec![],
+    };
+    if field.is_none() && sort.is_none() {
+        return Ok(query);
+    }
+    let company_key = company_key.ok_or_else(|| field_error("custom_company", "required_with_custom_fields"))?;
+    let fields = definitions(db, Some(company_key), entity).await;
+    query.vars.push(("custom_company", to_value(company_key).unwrap()));
+
+    if let Some(name) = field {
+        let definition = fields.iter().find(|x| x.name == name)
+            .ok_or_else(|| field_error("custom_field", "unknown_field"))?;
+        let text = value.ok_or_else(|| field_error("custom_value", "required_with_custom_field"))?;
+        let typed = if definition.field_type == "number" {
+            let number: f64 = text.parse().map_err(|_| field_error("custom_value", "wrong_number"))?;
+            to_value(number).unwrap()
+        } else {
+            to_value(text).unwrap()
+        };
+        query.filter = Some(format!("FILTER {}.custom[@custom_company][@custom_field] == @custom_value", var));
+        query.vars.push(("custom_field", to_value(name).unwrap()));
+        query.vars.push(("custom_value", typed));
+    }
+    if let Some(name) = sort {
+        if !fields.iter().any(|x| x.name == name) {
+            return Err(field_error("custom_sort", "unknown_field"));
+        }
+        query.sort = Some(format!("SORT {}.custom[@custom_company][@custom_sort] ASC", var));
+        query.vars.push(("custom_sort", to_value(name).unwrap()));
+    }
+    Ok(query)
+}
@@ -0,0 +1,105 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+// collections whose documents can carry custom fields, also the targets of reference fields
+pub const ENTITIES: [&str; 3] = ["companies", "contacts", "users"];
+pub const TEXT_MAX_LENGTH: usize = 1000;
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindCustomFieldsParams {
+    #[validate(custom = "validate_entity")]
+    pub entity: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_definition"))]
+pub struct CreateCustomFieldRequest {
+    #[validate(custom = "validate_entity")]
+    pub entity: String,
+    #[validate(custom = "validate_name")]
+    pub name: String,
+    #[validate(length(min = 1, max = 100))]
+    pub label: String,
+    #[validate(custom = "validate_field_type")]
+    pub field_type: String,
+    pub required: Option<bool>,
+    pub options: Option<Vec<String>>, // allowed values of an enum field
+    #[validate(custom = "validate_entity")]
+    pub reference: Option<String>, // collection a reference field points to
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct UpdateCustomFieldRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub label: Option<String>,
+    pub required: Option<bool>,
+    #[validate(length(min = 1))]
+    pub options: Option<Vec<String>>,
+}
+
+pub fn validate_entity(entity: &str) -> Result<(), ValidationError> {
+    if !ENTITIES.contains(&entity) {
+        return Err(ValidationError::new("wrong_entity"));
+    }
+    Ok(())
+}
+
+// names become attribute names in documents and schema rules
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    let valid = name.len() <= 40
+        && name.starts_with(|c: char| c.is_ascii_lowercase())
+        && name.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '_');
+    if !valid {
+        return Err(ValidationError::new("wrong_name"));
+    }
+    Ok(())
+}
+
+fn validate_field_type(field_type: &str) -> Result<(), ValidationError> {
+    match field_type {
+        "text" | "number" | "date" | "enum" | "reference" => Ok(()),
+        _ => Err(ValidationError::new("wrong_field_type")),
+    }
+}
+
+fn validate_definition(payload: &CreateCustomFieldRequest) -> Result<(), ValidationError> {
+    match payload.field_type.as_str() {
+        "enum" if payload.options.as_ref().map_or(true, |x| x.is_empty()) => {
+            Err(ValidationError::new("options_required"))
+        },
+        "reference" if payload.reference.is_none() => {
+            Err(ValidationError::new("reference_required"))
+        },
+        _ => Ok(()),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct CustomField {
+    pub company_key: String,
+    pub entity: String,
+    pub name: String,
+    pub label: String,
+    pub field_type: String,
+    pub required: bool,
+    pub options: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct CustomFieldResponse {
+    pub _key: String,
+    pub entity: String,
+    pub name: String,
+    pub label: String,
+    pub field_type: String,
+    pub required: bool,
+    pub options: Vec<String>,
+    pub reference: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
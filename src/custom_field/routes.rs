@@ -0,0 +1,147 @@
+use actix_web::{delete, error::ErrorNotFound, get, post, put, web, Error, HttpRequest, HttpResponse};
+use serde_json::{Map, Value};
+use validator::Validate;
+
+use crate::company;
+use crate::contact;
+use crate::custom_field::{
+    self,
+    validate_entity,
+    CreateCustomFieldRequest,
+    FindCustomFieldsParams,
+    UpdateCustomFieldRequest,
+};
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+
+// a company only holds values for itself, its members and their contacts
+async fn require_target(
+    key: &str,
+    entity: &str,
+    entity_key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let found = match entity {
+        "companies" => entity_key == key,
+        "users" => company::is_company_member(key, entity_key, pool).await,
+        "contacts" => match contact::contact_owner(entity_key, pool).await {
+            Some(owner_key) => company::is_company_member(key, &owner_key, pool).await,
+            None => false,
+        },
+        _ => false,
+    };
+    if validate_entity(entity).is_err() || !found {
+        return Err(ErrorNotFound("document not found"));
+    }
+    Ok(())
+}
+
+#[get("/companies/{key}/custom-fields")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindCustomFieldsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_access(&req, &key, "companies:read")?;
+    let params: FindCustomFieldsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = custom_field::find_custom_fields(&key, params.entity, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/custom-fields")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreateCustomFieldRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_admin(&req, &key, &pool).await?;
+    let params: CreateCustomFieldRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = custom_field::create_custom_field(&key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/custom-fields/{field_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<UpdateCustomFieldRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, field_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: UpdateCustomFieldRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = custom_field::update_custom_field(&key, &field_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/custom-fields/{field_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, field_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    custom_field::delete_custom_field(&key, &field_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/custom-values/{entity}/{entity_key}")]
+async fn show_values(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, entity, entity_key) = path.into_inner();
+    company::require_access(&req, &key, "companies:read")?;
+    require_target(&key, &entity, &entity_key, &pool).await?;
+    let result = custom_field::show_custom_values(&key, &entity, &entity_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/companies/{key}/custom-values/{entity}/{entity_key}")]
+async fn update_values(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    payload: web::Json<Map<String, Value>>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, entity, entity_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    require_target(&key, &entity, &entity_key, &pool).await?;
+    let result = custom_field::set_custom_values(&key, &entity, &entity_key, &payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(show_values);
+    cfg.service(update_values);
+}
@@ -1,12 +1,30 @@
 use mobc::Pool;
 use mobc_arangors::ArangoDBConnectionManager;
+use std::future::Future;
 
-use crate::config::{db_host, db_port, db_username, db_password};
+use crate::config::{db_database, db_host, db_port, db_username, db_password};
 
 pub type DbPool = Pool<ArangoDBConnectionManager>;
 
+tokio::task_local! {
+    // set by the tenancy middleware for the duration of a request
+    static TENANT_DATABASE: String;
+}
+
 pub fn init_pool() -> DbPool {
     let url = format!("http://{}:{}", db_host(), db_port());
     let manager = ArangoDBConnectionManager::new(&url, &db_username(), &db_password(), false, false);
     Pool::builder().max_open(15).build(manager)
 }
+
+// runs the future against the database of a tenant
+pub async fn with_database<F: Future>(name: String, f: F) -> F::Output {
+    TENANT_DATABASE.scope(name, f).await
+}
+
+// database that controllers should query, the configured one outside of a tenant
+pub fn current_database() -> String {
+    TENANT_DATABASE
+        .try_with(|x| x.clone())
+        .unwrap_or_else(|_| db_database())
+}
@@ -0,0 +1,114 @@
+// content lines shared by icalendar (rfc 5545) and vcard (rfc 6350)
+
+pub struct ContentLine {
+    pub name: String, // upper case, without the group prefix of vcard
+    pub params: Vec<(String, String)>,
+    pub value: String,
+}
+
+impl ContentLine {
+    pub fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(x, _)| x.eq_ignore_ascii_case(name))
+            .map(|(_, y)| y.as_str())
+    }
+}
+
+// splits on the separator outside of double quotes
+fn split_unquoted(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quoted = false;
+    let mut start = 0;
+    for (i, c) in value.char_indices() {
+        if c == '"' {
+            quoted = !quoted;
+        } else if c == separator && !quoted {
+            parts.push(&value[start..i]);
+            start = i + 1;
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn parse_line(line: &str) -> Option<ContentLine> {
+    let mut quoted = false;
+    let colon = line.char_indices().find(|&(_, c)| {
+        if c == '"' {
+            quoted = !quoted;
+        }
+        c == ':' && !quoted
+    })?.0;
+
+    let head = split_unquoted(&line[..colon], ';');
+    let name = head[0].rsplit('.').next().unwrap_or_default().trim().to_uppercase();
+    if name.is_empty() {
+        return None;
+    }
+    let params = head[1..].iter()
+        .filter_map(|x| x.split_once('='))
+        .map(|(k, v)| (k.trim().to_uppercase(), v.trim().trim_matches('"').to_string()))
+        .collect();
+    Some(ContentLine {
+        name,
+        params,
+        value: line[colon + 1..].to_string(),
+    })
+}
+
+// unfolds continuation lines, which start with a space or a tab
+pub fn content_lines(text: &str) -> Vec<ContentLine> {
+    let mut lines: Vec<String> = vec![];
+    for raw in text.split('\n') {
+        let raw = raw.strip_suffix('\r').unwrap_or(raw);
+        match (raw.strip_prefix(' ').or_else(|| raw.strip_prefix('\t')), lines.last_mut()) {
+            (Some(rest), Some(last)) => last.push_str(rest),
+            _ => lines.push(raw.to_string()),
+        }
+    }
+    lines.iter()
+        .filter(|x| !x.trim().is_empty())
+        .filter_map(|x| parse_line(x))
+        .collect()
+}
+
+// lines longer than 75 octets are folded, without splitting characters
+pub fn fold(line: &str) -> String {
+    let mut folded = String::with_capacity(line.len() + 8);
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > 75 {
+            folded.push_str("\r\n ");
+            width = 1;
+        }
+        folded.push(c);
+        width += c.len_utf8();
+    }
+    folded.push_str("\r\n");
+    folded
+}
+
+pub fn escape_text(value: &str) -> String {
+    value.replace('\\', "\\\\")
+        .replace(';', "\\;")
+        .replace(',', "\\,")
+        .replace("\r\n", "\\n")
+        .replace('\n', "\\n")
+}
+
+pub fn unescape_text(value: &str) -> String {
+    let mut result = String::with_capacity(value.len());
+    let mut chars = value.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            result.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') | Some('N') => result.push('\n'),
+            Some(x) => result.push(x),
+            None => result.push('\\'),
+        }
+    }
+    result
+}
@@ -0,0 +1,126 @@
+use actix_web::{error::ErrorInternalServerError, Error};
+use arangors::{connection::ReqwestClient, AqlQuery, Database};
+use chrono::prelude::*;
+use serde::{de::DeserializeOwned, Deserialize};
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::contact::ContactResponse;
+use crate::database::{current_database, DbPool};
+use crate::event::EventResponse;
+
+// a record with the revision its etag is derived from
+#[derive(Debug, Deserialize)]
+pub struct DavItem<T> {
+    pub rev: String,
+    pub record: T,
+}
+
+// calendars hold the events a user owns or attends, address books the contacts they own
+const CALENDAR_ITEMS: &str = "FOR x IN events FILTER x.owner_key == @user_key OR @user_key IN x.attendees";
+const ADDRESS_ITEMS: &str = "FOR x IN contacts FILTER x.owner_key == @user_key";
+
+// changes whenever any item is written, added or removed
+async fn collection_tag(
+    db: &Database<ReqwestClient>,
+    items: &str,
+    user_key: &str,
+) -> Result<String, Error> {
+    let q = format!(r#"RETURN SHA1(CONCAT_SEPARATOR(",", (
+        {} SORT x._key RETURN CONCAT(x._key, ":", x._rev)
+    )))"#, items);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user_key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records.pop().unwrap_or_default())
+}
+
+async fn find_items<T: DeserializeOwned>(
+    db: &Database<ReqwestClient>,
+    items: &str,
+    unset: &str,
+    user_key: &str,
+    keys: Option<&[String]>,
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+) -> Result<Vec<DavItem<T>>, Error> {
+    let mut terms = vec![items];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user_key", to_value(user_key).unwrap());
+
+    if let Some(keys) = keys {
+        terms.push("FILTER x._key IN @keys");
+        vars.insert("keys", to_value(keys).unwrap());
+    }
+    // series that may overlap the range, clients drop the occurrences they do not need
+    if let Some((from, to)) = range {
+        terms.push("FILTER x.starts_at < @to AND (x.series_ends_at == null OR x.series_ends_at > @from)");
+        vars.insert("from", to_value(from.timestamp()).unwrap());
+        vars.insert("to", to_value(to.timestamp()).unwrap());
+    }
+    let terms_end = format!("SORT x._key RETURN {{ rev: x._rev, record: {} }}", unset);
+    terms.push(&terms_end);
+    let q = terms.join(" ");
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<DavItem<T>> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn calendar_tag(
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<String, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    collection_tag(&db, CALENDAR_ITEMS, user_key).await
+}
+
+#[instrument(skip_all)]
+pub async fn find_calendar_items(
+    user_key: &str,
+    keys: Option<&[String]>,
+    range: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    pool: &DbPool,
+) -> Result<Vec<DavItem<EventResponse>>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let unset = "UNSET(x, '_id', '_rev', 'starts_at', 'series_ends_at')";
+    find_items(&db, CALENDAR_ITEMS, unset, user_key, keys, range).await
+}
+
+#[instrument(skip_all)]
+pub async fn address_book_tag(
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<String, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    collection_tag(&db, ADDRESS_ITEMS, user_key).await
+}
+
+#[instrument(skip_all)]
+pub async fn find_address_items(
+    user_key: &str,
+    keys: Option<&[String]>,
+    pool: &DbPool,
+) -> Result<Vec<DavItem<ContactResponse>>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_items(&db, ADDRESS_ITEMS, "UNSET(x, '_id', '_rev')", user_key, keys, None).await
+}
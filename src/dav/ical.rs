@@ -0,0 +1,336 @@
+use chrono::{prelude::*, Duration};
+use chrono_tz::Tz;
+
+use crate::dav::content::{content_lines, escape_text, fold, unescape_text, ContentLine};
+use crate::event::{parse_timezone, parse_weekday, to_utc, EventRequest, EventResponse, Recurrence};
+
+const PRODID: &str = "-//groupware-actix//EN";
+const UTC_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+const LOCAL_FORMAT: &str = "%Y%m%dT%H%M%S";
+
+// DTSTART and friends, in the wall clock of the event unless it is kept in UTC
+fn time_line(name: &str, times: &[DateTime<Utc>], tz: Option<&Tz>) -> String {
+    match tz {
+        Some(tz) => {
+            let values: Vec<String> = times.iter()
+                .map(|x| x.with_timezone(tz).format(LOCAL_FORMAT).to_string())
+                .collect();
+            fold(&format!("{};TZID={}:{}", name, tz, values.join(",")))
+        },
+        None => {
+            let values: Vec<String> = times.iter().map(|x| x.format(UTC_FORMAT).to_string()).collect();
+            fold(&format!("{}:{}", name, values.join(",")))
+        },
+    }
+}
+
+fn rrule(recurrence: &Recurrence) -> String {
+    let mut parts = vec![
+        format!("FREQ={}", recurrence.frequency.to_uppercase()),
+        format!("INTERVAL={}", recurrence.interval),
+    ];
+    if let Some(count) = recurrence.count {
+        parts.push(format!("COUNT={}", count));
+    }
+    if let Some(until) = recurrence.until {
+        parts.push(format!("UNTIL={}", until.format(UTC_FORMAT)));
+    }
+    if !recurrence.weekdays.is_empty() {
+        let days: Vec<String> = recurrence.weekdays.iter().map(|x| x.to_uppercase()).collect();
+        parts.push(format!("BYDAY={}", days.join(",")));
+    }
+    fold(&format!("RRULE:{}", parts.join(";")))
+}
+
+pub fn event_to_ics(event: &EventResponse) -> String {
+    let tz = match event.timezone.as_str() {
+        "UTC" => None,
+        x => parse_timezone(x),
+    };
+    let mut ics = String::new();
+    ics.push_str("BEGIN:VCALENDAR\r\nVERSION:2.0\r\n");
+    ics.push_str(&fold(&format!("PRODID:{}", PRODID)));
+    ics.push_str("BEGIN:VEVENT\r\n");
+    ics.push_str(&fold(&format!("UID:{}", escape_text(&event.uid))));
+    ics.push_str(&fold(&format!("DTSTAMP:{}", event.modified_at.format(UTC_FORMAT))));
+    ics.push_str(&fold(&format!("CREATED:{}", event.created_at.format(UTC_FORMAT))));
+    ics.push_str(&fold(&format!("LAST-MODIFIED:{}", event.modified_at.format(UTC_FORMAT))));
+    ics.push_str(&time_line("DTSTART", &[event.start], tz.as_ref()));
+    ics.push_str(&time_line("DTEND", &[event.end], tz.as_ref()));
+    ics.push_str(&fold(&format!("SUMMARY:{}", escape_text(&event.title))));
+    if let Some(description) = &event.description {
+        ics.push_str(&fold(&format!("DESCRIPTION:{}", escape_text(description))));
+    }
+    if let Some(location) = &event.location {
+        ics.push_str(&fold(&format!("LOCATION:{}", escape_text(location))));
+    }
+    if let Some(recurrence) = &event.recurrence {
+        ics.push_str(&rrule(recurrence));
+    }
+    if !event.exceptions.is_empty() {
+        ics.push_str(&time_line("EXDATE", &event.exceptions, tz.as_ref()));
+    }
+    ics.push_str(if event.busy { "TRANSP:OPAQUE\r\n" } else { "TRANSP:TRANSPARENT\r\n" });
+    ics.push_str("END:VEVENT\r\nEND:VCALENDAR\r\n");
+    ics
+}
+
+struct ParsedTime {
+    time: DateTime<Utc>,
+    tz: Option<Tz>,
+    all_day: bool,
+}
+
+// zones we do not know, such as the windows names of some clients, are read as UTC
+fn parse_time(value: &str, line: &ContentLine) -> Result<ParsedTime, &'static str> {
+    let value = value.trim();
+    if line.param("VALUE") == Some("DATE") || value.len() == 8 {
+        let date = NaiveDate::parse_from_str(value, "%Y%m%d").map_err(|_| "wrong date")?;
+        return Ok(ParsedTime {
+            time: Utc.from_utc_datetime(&date.and_hms_opt(0, 0, 0).ok_or("wrong date")?),
+            tz: None,
+            all_day: true,
+        });
+    }
+    if let Some(utc) = value.strip_suffix('Z') {
+        let time = NaiveDateTime::parse_from_str(utc, LOCAL_FORMAT).map_err(|_| "wrong date-time")?;
+        return Ok(ParsedTime { time: Utc.from_utc_datetime(&time), tz: None, all_day: false });
+    }
+    let local = NaiveDateTime::parse_from_str(value, LOCAL_FORMAT).map_err(|_| "wrong date-time")?;
+    match line.param("TZID").and_then(parse_timezone) {
+        Some(tz) => Ok(ParsedTime { time: to_utc(&tz, &local), tz: Some(tz), all_day: false }),
+        None => Ok(ParsedTime { time: Utc.from_utc_datetime(&local), tz: None, all_day: false }),
+    }
+}
+
+// P1W, P2D, PT1H30M and combinations of them
+fn parse_duration(value: &str) -> Result<Duration, &'static str> {
+    let value = value.trim();
+    let (negative, value) = match value.strip_prefix('-') {
+        Some(x) => (true, x),
+        None => (false, value.strip_prefix('+').unwrap_or(value)),
+    };
+    let value = value.strip_prefix('P').ok_or("wrong duration")?;
+    let mut duration = Duration::zero();
+    let mut number = String::new();
+    let mut in_time = false;
+    for c in value.chars() {
+        match c {
+            '0'..='9' => number.push(c),
+            'T' => in_time = true,
+            _ => {
+                let n: i64 = number.parse().map_err(|_| "wrong duration")?;
+                number.clear();
+                let part = match (c, in_time) {
+                    ('W', false) => Duration::weeks(n),
+                    ('D', false) => Duration::days(n),
+                    ('H', true) => Duration::hours(n),
+                    ('M', true) => Duration::minutes(n),
+                    ('S', true) => Duration::seconds(n),
+                    _ => return Err("wrong duration"),
+                };
+                duration = duration.checked_add(&part).ok_or("wrong duration")?;
+            },
+        }
+    }
+    Ok(if negative { -duration } else { duration })
+}
+
+// the subset of RRULE that our recurrences can express
+fn parse_rrule(value: &str, start: &ParsedTime) -> Result<Recurrence, &'static str> {
+    let local = match &start.tz {
+        Some(tz) => start.time.with_timezone(tz).naive_local(),
+        None => start.time.naive_utc(),
+    };
+    let mut recurrence = Recurrence {
+        frequency: String::new(),
+        interval: 1,
+        count: None,
+        until: None,
+        weekdays: vec![],
+    };
+    for part in value.split(';').filter(|x| !x.is_empty()) {
+        let (name, value) = part.split_once('=').ok_or("wrong recurrence")?;
+        match name.to_uppercase().as_str() {
+            "FREQ" => recurrence.frequency = value.to_lowercase(),
+            "INTERVAL" => recurrence.interval = value.parse().map_err(|_| "wrong recurrence")?,
+            "COUNT" => recurrence.count = Some(value.parse().map_err(|_| "wrong recurrence")?),
+            "UNTIL" => {
+                let until = parse_time(value, &ContentLine { name: "UNTIL".to_string(), params: vec![], value: value.to_string() })?;
+                // a date includes the whole day
+                recurrence.until = Some(if until.all_day { until.time + Duration::days(1) - Duration::seconds(1) } else { until.time });
+            },
+            "BYDAY" => {
+                for day in value.split(',') {
+                    let day = day.to_lowercase();
+                    if parse_weekday(&day).is_none() {
+                        return Err("unsupported recurrence");
+                    }
+                    recurrence.weekdays.push(day);
+                }
+            },
+            // clients repeat what DTSTART already says, anything else cannot be expressed
+            "BYMONTHDAY" if value == local.day().to_string() => {},
+            "BYMONTH" if value == local.month().to_string() => {},
+            "WKST" => {},
+            _ => return Err("unsupported recurrence"),
+        }
+    }
+    if !recurrence.weekdays.is_empty() && recurrence.frequency != "weekly" {
+        return Err("unsupported recurrence");
+    }
+    Ok(recurrence)
+}
+
+// reads the main VEVENT of a calendar object, overridden occurrences are not supported
+pub fn parse_ics(text: &str) -> Result<(Option<String>, EventRequest), &'static str> {
+    let lines = content_lines(text);
+    let mut depth = 0;
+    let mut in_event = false;
+    let mut found: Option<Vec<&ContentLine>> = None;
+    let mut current: Vec<&ContentLine> = vec![];
+    for line in &lines {
+        match line.name.as_str() {
+            "BEGIN" if line.value.eq_ignore_ascii_case("VEVENT") && !in_event => {
+                in_event = true;
+                depth = 0;
+                current.clear();
+            },
+            "BEGIN" if in_event => depth += 1,
+            "END" if in_event && depth > 0 => depth -= 1,
+            "END" if in_event && line.value.eq_ignore_ascii_case("VEVENT") => {
+                in_event = false;
+                if found.is_none() && current.iter().all(|x| x.name != "RECURRENCE-ID") {
+                    found = Some(current.clone());
+                }
+            },
+            _ if in_event && depth == 0 => current.push(line),
+            _ => {},
+        }
+    }
+    let props = found.ok_or("no event")?;
+    let prop = |name: &str| props.iter().find(|x| x.name == name).copied();
+
+    let start_line = prop("DTSTART").ok_or("no start")?;
+    let start = parse_time(&start_line.value, start_line)?;
+    let end = match (prop("DTEND"), prop("DURATION")) {
+        (Some(line), _) => parse_time(&line.value, line)?.time,
+        (None, Some(line)) => start.time + parse_duration(&line.value)?,
+        (None, None) if start.all_day => start.time + Duration::days(1),
+        (None, None) => start.time,
+    };
+    let recurrence = match prop("RRULE") {
+        Some(line) => Some(parse_rrule(&line.value, &start)?),
+        None => None,
+    };
+    let mut exceptions = vec![];
+    for line in props.iter().filter(|x| x.name == "EXDATE") {
+        for value in line.value.split(',') {
+            exceptions.push(parse_time(value, line)?.time);
+        }
+    }
+
+    let uid = prop("UID").map(|x| unescape_text(&x.value)).filter(|x| !x.is_empty());
+    let payload = EventRequest {
+        title: prop("SUMMARY").map(|x| unescape_text(&x.value))
+            .filter(|x| !x.trim().is_empty())
+            .unwrap_or_else(|| "Untitled".to_string()),
+        description: prop("DESCRIPTION").map(|x| unescape_text(&x.value)).filter(|x| !x.is_empty()),
+        location: prop("LOCATION").map(|x| unescape_text(&x.value)).filter(|x| !x.is_empty()),
+        start: start.time,
+        end,
+        timezone: start.tz.map(|x| x.to_string()),
+        recurrence,
+        exceptions: Some(exceptions),
+        attendees: None,
+        busy: Some(prop("TRANSP").map_or(true, |x| !x.value.eq_ignore_ascii_case("TRANSPARENT"))),
+    };
+    Ok((uid, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn event() -> EventResponse {
+        EventResponse {
+            _key: "1".to_string(),
+            uid: "abc@example.com".to_string(),
+            owner_key: "2".to_string(),
+            title: "Planning; Q1, Q2".to_string(),
+            description: Some("line one\nline two".to_string()),
+            location: None,
+            start: at("2021-01-04T09:00:00Z"),
+            end: at("2021-01-04T10:00:00Z"),
+            timezone: "Europe/Berlin".to_string(),
+            recurrence: Some(Recurrence {
+                frequency: "weekly".to_string(),
+                interval: 2,
+                count: Some(5),
+                until: None,
+                weekdays: vec!["mo".to_string(), "th".to_string()],
+            }),
+            exceptions: vec![at("2021-01-18T09:00:00Z")],
+            attendees: vec![],
+            busy: false,
+            created_at: at("2021-01-01T08:00:00Z"),
+            modified_at: at("2021-01-02T08:00:00Z"),
+        }
+    }
+
+    #[test]
+    fn event_output() {
+        assert_eq!(
+            event_to_ics(&event()),
+            "BEGIN:VCALENDAR\r\nVERSION:2.0\r\nPRODID:-//groupware-actix//EN\r\n\
+             BEGIN:VEVENT\r\nUID:abc@example.com\r\nDTSTAMP:20210102T080000Z\r\n\
+             CREATED:20210101T080000Z\r\nLAST-MODIFIED:20210102T080000Z\r\n\
+             DTSTART;TZID=Europe/Berlin:20210104T100000\r\nDTEND;TZID=Europe/Berlin:20210104T110000\r\n\
+             SUMMARY:Planning\\; Q1\\, Q2\r\nDESCRIPTION:line one\\nline two\r\n\
+             RRULE:FREQ=WEEKLY;INTERVAL=2;COUNT=5;BYDAY=MO,TH\r\n\
+             EXDATE;TZID=Europe/Berlin:20210118T100000\r\nTRANSP:TRANSPARENT\r\n\
+             END:VEVENT\r\nEND:VCALENDAR\r\n",
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (uid, payload) = parse_ics(&event_to_ics(&event())).unwrap();
+        assert_eq!(uid.as_deref(), Some("abc@example.com"));
+        assert_eq!(payload.title, "Planning; Q1, Q2");
+        assert_eq!(payload.description.as_deref(), Some("line one\nline two"));
+        assert_eq!((payload.start, payload.end), (event().start, event().end));
+        assert_eq!(payload.timezone.as_deref(), Some("Europe/Berlin"));
+        assert_eq!(payload.recurrence.unwrap().weekdays, vec!["mo", "th"]);
+        assert_eq!(payload.exceptions, Some(event().exceptions));
+        assert_eq!(payload.busy, Some(false));
+    }
+
+    #[test]
+    fn client_events() {
+        let text = "BEGIN:VCALENDAR\r\nBEGIN:VEVENT\r\nUID:x\r\nDTSTART;VALUE=DATE:20210104\r\n\
+                    RRULE:FREQ=MONTHLY;BYMONTHDAY=4;UNTIL=20210404\r\nBEGIN:VALARM\r\nSUMMARY:inner\r\n\
+                    END:VALARM\r\nEND:VEVENT\r\nEND:VCALENDAR\r\n";
+        let (_, payload) = parse_ics(text).unwrap();
+        assert_eq!(payload.title, "Untitled");
+        assert_eq!(payload.end, at("2021-01-05T00:00:00Z"));
+        assert_eq!(payload.recurrence.unwrap().until, Some(at("2021-04-04T23:59:59Z")));
+
+        let text = "BEGIN:VEVENT\r\nDTSTART:20210104T090000Z\r\nRRULE:FREQ=MONTHLY;BYMONTHDAY=5\r\nEND:VEVENT\r\n";
+        assert_eq!(parse_ics(text).unwrap_err(), "unsupported recurrence");
+        assert_eq!(parse_ics("BEGIN:VEVENT\r\nEND:VEVENT\r\n").unwrap_err(), "no start");
+    }
+
+    #[test]
+    fn durations() {
+        assert_eq!(parse_duration("P1W").unwrap(), Duration::weeks(1));
+        assert_eq!(parse_duration("PT1H30M").unwrap(), Duration::minutes(90));
+        assert_eq!(parse_duration("-P1DT2S").unwrap(), -(Duration::days(1) + Duration::seconds(2)));
+        assert!(parse_duration("P1H").is_err());
+        assert!(parse_duration("1D").is_err());
+    }
+}
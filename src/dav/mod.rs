@@ -0,0 +1,12 @@
+mod content;
+mod controllers;
+mod ical;
+mod vcard;
+mod xml;
+mod routes;
+
+pub use controllers::*;
+pub use ical::{event_to_ics, parse_ics};
+pub use vcard::{contact_to_vcard, parse_vcard};
+pub use xml::{multistatus, parse_propfind, parse_report, DavResponse, PropName, Report, CALDAV, CALENDARSERVER, CARDDAV, DAV};
+pub use routes::init;
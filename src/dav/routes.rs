@@ -0,0 +1,585 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorForbidden, ErrorNotFound, ErrorPreconditionFailed, InternalError},
+    http::{
+        header::{HeaderName, ALLOW, AUTHORIZATION, ETAG, IF_MATCH, IF_NONE_MATCH, LOCATION, WWW_AUTHENTICATE},
+        Method,
+        StatusCode,
+    },
+    web,
+    Error,
+    HttpRequest,
+    HttpResponse,
+};
+use validator::Validate;
+
+use crate::auth::{self, Identity};
+use crate::contact;
+use crate::database::DbPool;
+use crate::dav::{
+    self,
+    contact_to_vcard,
+    event_to_ics,
+    multistatus,
+    parse_ics,
+    parse_propfind,
+    parse_report,
+    parse_vcard,
+    xml::{escape, href},
+    DavResponse,
+    PropName,
+    Report,
+    CALDAV,
+    CALENDARSERVER,
+    CARDDAV,
+    DAV,
+};
+use crate::event;
+use crate::i18n::{failure, Invalid};
+use crate::user;
+
+const CALENDAR_CONTENT_TYPE: &str = "text/calendar; charset=utf-8";
+const VCARD_CONTENT_TYPE: &str = "text/vcard; charset=utf-8";
+
+fn method(name: &str) -> Method {
+    Method::from_bytes(name.as_bytes()).unwrap()
+}
+
+fn principal_path(user_key: &str) -> String {
+    format!("/dav/principals/{}/", user_key)
+}
+
+fn calendar_home_path(user_key: &str) -> String {
+    format!("/dav/calendars/{}/", user_key)
+}
+
+fn calendar_path(user_key: &str) -> String {
+    format!("/dav/calendars/{}/events/", user_key)
+}
+
+fn address_home_path(user_key: &str) -> String {
+    format!("/dav/addressbooks/{}/", user_key)
+}
+
+fn address_book_path(user_key: &str) -> String {
+    format!("/dav/addressbooks/{}/contacts/", user_key)
+}
+
+fn etag(rev: &str) -> String {
+    format!("\"{}\"", rev)
+}
+
+// clients ask again with credentials only after a challenge
+fn unauthorized() -> Error {
+    let response = HttpResponse::Unauthorized()
+        .insert_header((WWW_AUTHENTICATE, r#"Basic realm="groupware", charset="UTF-8""#))
+        .finish();
+    InternalError::from_response("authentication required", response).into()
+}
+
+fn basic_credentials(req: &HttpRequest) -> Option<(String, String)> {
+    let value = req.headers().get(AUTHORIZATION)?.to_str().ok()?;
+    let encoded = value.strip_prefix("Basic ")?.trim();
+    let decoded = String::from_utf8(base64::decode(encoded).ok()?).ok()?;
+    let (email, password) = decoded.split_once(':')?;
+    Some((email.to_string(), password.to_string()))
+}
+
+// bearer tokens work as everywhere else, basic credentials are what dav clients send
+async fn authenticate(req: &HttpRequest, pool: &DbPool) -> Result<Identity, Error> {
+    if let Ok(identity) = auth::authenticate(req) {
+        return Ok(identity);
+    }
+    let (email, password) = basic_credentials(req).ok_or_else(unauthorized)?;
+    auth::identify_basic(&email, &password, pool).await.ok_or_else(unauthorized)
+}
+
+// collections are only served to the user they belong to
+async fn require_owner(
+    req: &HttpRequest,
+    pool: &DbPool,
+    user_key: &str,
+    scope: Option<&str>,
+) -> Result<Identity, Error> {
+    let identity = authenticate(req, pool).await?;
+    if let Some(scope) = scope {
+        if !identity.has_scope(scope) {
+            return Err(failure(StatusCode::FORBIDDEN, "scope_required", &[("scope", scope)]));
+        }
+    }
+    if identity.user_key() != Some(user_key) {
+        return Err(ErrorForbidden("collections belong to their user"));
+    }
+    Ok(identity)
+}
+
+// infinity is answered like 1, collections here are never deeper than that
+fn depth(req: &HttpRequest) -> u8 {
+    match req.headers().get("Depth").and_then(|x| x.to_str().ok()) {
+        Some("0") => 0,
+        _ => 1,
+    }
+}
+
+// last segment of the href without its extension, which is the document key
+fn resource_key(name: &str, extension: &str) -> Option<String> {
+    let key = name.rsplit('/').next()?.strip_suffix(extension)?;
+    let valid = !key.is_empty() && key.len() <= 254 && key.chars()
+        .all(|c| c.is_ascii_alphanumeric() || "_-:.@()+,=;$!*'%".contains(c));
+    if valid {
+        Some(key.to_string())
+    } else {
+        None
+    }
+}
+
+// If-None-Match: * guards creation, If-Match the revision a client last saw
+fn check_preconditions(req: &HttpRequest, current: Option<&str>) -> Result<(), Error> {
+    let header = |name: HeaderName| req.headers().get(name).and_then(|x| x.to_str().ok());
+    if header(IF_NONE_MATCH) == Some("*") && current.is_some() {
+        return Err(ErrorPreconditionFailed("resource exists"));
+    }
+    if let Some(expected) = header(IF_MATCH) {
+        let matches = current.map_or(false, |x| expected == "*" || expected == etag(x));
+        if !matches {
+            return Err(ErrorPreconditionFailed("resource changed"));
+        }
+    }
+    Ok(())
+}
+
+fn multistatus_response(responses: &[DavResponse]) -> HttpResponse {
+    HttpResponse::build(StatusCode::MULTI_STATUS)
+        .content_type("application/xml; charset=utf-8")
+        .body(multistatus(responses))
+}
+
+fn privileges(identity: &Identity, scope: &str) -> String {
+    let mut privileges = "<d:privilege><d:read/></d:privilege>".to_string();
+    if identity.has_scope(scope) {
+        privileges.push_str("<d:privilege><d:write/></d:privilege>");
+    }
+    privileges
+}
+
+fn home_props(user_key: &str) -> Vec<(PropName, String)> {
+    vec![
+        (PropName::new(DAV, "resourcetype"), "<d:collection/>".to_string()),
+        (PropName::new(DAV, "current-user-principal"), href(&principal_path(user_key))),
+    ]
+}
+
+fn calendar_props(identity: &Identity, user_key: &str, ctag: &str) -> Vec<(PropName, String)> {
+    let reports = ["calendar-multiget", "calendar-query"].iter()
+        .map(|x| format!("<d:supported-report><d:report><c:{}/></d:report></d:supported-report>", x))
+        .collect::<String>();
+    vec![
+        (PropName::new(DAV, "resourcetype"), "<d:collection/><c:calendar/>".to_string()),
+        (PropName::new(DAV, "displayname"), "Calendar".to_string()),
+        (PropName::new(DAV, "current-user-principal"), href(&principal_path(user_key))),
+        (PropName::new(DAV, "owner"), href(&principal_path(user_key))),
+        (PropName::new(DAV, "current-user-privilege-set"), privileges(identity, "events:write")),
+        (PropName::new(DAV, "supported-report-set"), reports),
+        (PropName::new(CALDAV, "supported-calendar-component-set"), r#"<c:comp name="VEVENT"/>"#.to_string()),
+        (PropName::new(CALENDARSERVER, "getctag"), escape(ctag)),
+    ]
+}
+
+fn address_book_props(identity: &Identity, user_key: &str, ctag: &str) -> Vec<(PropName, String)> {
+    let reports = ["addressbook-multiget", "addressbook-query"].iter()
+        .map(|x| format!("<d:supported-report><d:report><card:{}/></d:report></d:supported-report>", x))
+        .collect::<String>();
+    vec![
+        (PropName::new(DAV, "resourcetype"), "<d:collection/><card:addressbook/>".to_string()),
+        (PropName::new(DAV, "displayname"), "Contacts".to_string()),
+        (PropName::new(DAV, "current-user-principal"), href(&principal_path(user_key))),
+        (PropName::new(DAV, "owner"), href(&principal_path(user_key))),
+        (PropName::new(DAV, "current-user-privilege-set"), privileges(identity, "contacts:write")),
+        (PropName::new(DAV, "supported-report-set"), reports),
+        (PropName::new(CALENDARSERVER, "getctag"), escape(ctag)),
+    ]
+}
+
+// the data itself is only rendered when it was asked for by name
+fn item_props(rev: &str, content_type: &str, data: Option<(PropName, String)>) -> Vec<(PropName, String)> {
+    let mut props = vec![
+        (PropName::new(DAV, "resourcetype"), String::new()),
+        (PropName::new(DAV, "getetag"), escape(&etag(rev))),
+        (PropName::new(DAV, "getcontenttype"), content_type.to_string()),
+    ];
+    if let Some((name, value)) = data {
+        props.push((name, escape(&value)));
+    }
+    props
+}
+
+fn wants(requested: &Option<Vec<PropName>>, name: &PropName) -> bool {
+    requested.as_ref().map_or(false, |x| x.contains(name))
+}
+
+async fn well_known() -> HttpResponse {
+    HttpResponse::MovedPermanently()
+        .insert_header((LOCATION, "/dav/"))
+        .finish()
+}
+
+async fn options() -> HttpResponse {
+    HttpResponse::Ok()
+        .insert_header(("DAV", "1, 3, calendar-access, addressbook"))
+        .insert_header((ALLOW, "OPTIONS, GET, PUT, DELETE, PROPFIND, REPORT"))
+        .finish()
+}
+
+// entry point of discovery, tells the client who it is logged in as
+async fn propfind_root(
+    req: HttpRequest,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = authenticate(&req, &pool).await?;
+    let user_key = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("calendars belong to users"))?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let available = vec![
+        (PropName::new(DAV, "resourcetype"), "<d:collection/>".to_string()),
+        (PropName::new(DAV, "current-user-principal"), href(&principal_path(user_key))),
+        (PropName::new(DAV, "principal-URL"), href(&principal_path(user_key))),
+    ];
+    Ok(multistatus_response(&[DavResponse::select("/dav/".to_string(), &requested, available)]))
+}
+
+async fn propfind_principal(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    require_owner(&req, &pool, &user_key, None).await?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let user = user::show_user(&user_key, &pool).await.map_err(ErrorNotFound)?;
+    let available = vec![
+        (PropName::new(DAV, "resourcetype"), "<d:principal/>".to_string()),
+        (PropName::new(DAV, "displayname"), escape(&user.name)),
+        (PropName::new(DAV, "current-user-principal"), href(&principal_path(&user_key))),
+        (PropName::new(DAV, "principal-URL"), href(&principal_path(&user_key))),
+        (PropName::new(CALDAV, "calendar-home-set"), href(&calendar_home_path(&user_key))),
+        (PropName::new(CALDAV, "calendar-user-address-set"), href(&format!("mailto:{}", user.email))),
+        (PropName::new(CARDDAV, "addressbook-home-set"), href(&address_home_path(&user_key))),
+    ];
+    Ok(multistatus_response(&[DavResponse::select(principal_path(&user_key), &requested, available)]))
+}
+
+async fn propfind_calendar_home(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    let identity = require_owner(&req, &pool, &user_key, Some("events:read")).await?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let mut responses = vec![DavResponse::select(calendar_home_path(&user_key), &requested, home_props(&user_key))];
+    if depth(&req) > 0 {
+        let ctag = dav::calendar_tag(&user_key, &pool).await?;
+        responses.push(DavResponse::select(calendar_path(&user_key), &requested, calendar_props(&identity, &user_key, &ctag)));
+    }
+    Ok(multistatus_response(&responses))
+}
+
+fn calendar_item_response(
+    user_key: &str,
+    item: &dav::DavItem<event::EventResponse>,
+    requested: &Option<Vec<PropName>>,
+) -> DavResponse {
+    let name = PropName::new(CALDAV, "calendar-data");
+    let data = if wants(requested, &name) { Some((name, event_to_ics(&item.record))) } else { None };
+    let href = format!("{}{}.ics", calendar_path(user_key), item.record._key);
+    DavResponse::select(href, requested, item_props(&item.rev, CALENDAR_CONTENT_TYPE, data))
+}
+
+async fn propfind_calendar(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    let identity = require_owner(&req, &pool, &user_key, Some("events:read")).await?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let ctag = dav::calendar_tag(&user_key, &pool).await?;
+    let mut responses = vec![DavResponse::select(calendar_path(&user_key), &requested, calendar_props(&identity, &user_key, &ctag))];
+    if depth(&req) > 0 {
+        let items = dav::find_calendar_items(&user_key, None, None, &pool).await?;
+        responses.extend(items.iter().map(|x| calendar_item_response(&user_key, x, &requested)));
+    }
+    Ok(multistatus_response(&responses))
+}
+
+async fn report_calendar(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("events:read")).await?;
+    match parse_report(&body).map_err(ErrorForbidden)? {
+        Report::Multiget { props, hrefs } => {
+            let keys: Vec<String> = hrefs.iter().filter_map(|x| resource_key(x, ".ics")).collect();
+            let items = dav::find_calendar_items(&user_key, Some(&keys), None, &pool).await?;
+            let responses: Vec<DavResponse> = hrefs.iter()
+                .map(|x| {
+                    let item = resource_key(x, ".ics")
+                        .and_then(|key| items.iter().find(|y| y.record._key == key));
+                    match item {
+                        Some(item) => calendar_item_response(&user_key, item, &props),
+                        None => DavResponse::not_found(x.clone()),
+                    }
+                })
+                .collect();
+            Ok(multistatus_response(&responses))
+        },
+        Report::Query { props, range } => {
+            let items = dav::find_calendar_items(&user_key, None, range, &pool).await?;
+            let responses: Vec<DavResponse> = items.iter()
+                .map(|x| calendar_item_response(&user_key, x, &props))
+                .collect();
+            Ok(multistatus_response(&responses))
+        },
+    }
+}
+
+async fn get_event(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("events:read")).await?;
+    let key = resource_key(&name, ".ics").ok_or_else(|| ErrorNotFound("event not found"))?;
+    let item = dav::find_calendar_items(&user_key, Some(&[key]), None, &pool).await?
+        .pop()
+        .ok_or_else(|| ErrorNotFound("event not found"))?;
+    Ok(HttpResponse::Ok()
+        .content_type(CALENDAR_CONTENT_TYPE)
+        .insert_header((ETAG, etag(&item.rev)))
+        .body(event_to_ics(&item.record)))
+}
+
+async fn put_event(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("events:write")).await?;
+    let key = resource_key(&name, ".ics").ok_or_else(|| ErrorBadRequest("wrong resource name"))?;
+    let (uid, payload) = parse_ics(&body).map_err(ErrorBadRequest)?;
+    if let Err(e) = payload.validate() {
+        return Err(Invalid(e).into());
+    }
+
+    let current = dav::find_calendar_items(&user_key, Some(&[key.clone()]), None, &pool).await?.pop();
+    check_preconditions(&req, current.as_ref().map(|x| x.rev.as_str()))?;
+    let uid = uid
+        .or_else(|| current.map(|x| x.record.uid))
+        .unwrap_or_else(|| key.clone());
+    let created = event::put_event(&key, &user_key, uid, &payload, &pool).await?;
+
+    let item = dav::find_calendar_items(&user_key, Some(&[key]), None, &pool).await?
+        .pop()
+        .ok_or_else(|| ErrorNotFound("event not found"))?;
+    let mut response = if created { HttpResponse::Created() } else { HttpResponse::NoContent() };
+    Ok(response.insert_header((ETAG, etag(&item.rev))).finish())
+}
+
+async fn delete_event(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("events:write")).await?;
+    let key = resource_key(&name, ".ics").ok_or_else(|| ErrorNotFound("event not found"))?;
+    let current = dav::find_calendar_items(&user_key, Some(&[key.clone()]), None, &pool).await?.pop();
+    check_preconditions(&req, current.as_ref().map(|x| x.rev.as_str()))?;
+    event::delete_event(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+async fn propfind_address_home(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    let identity = require_owner(&req, &pool, &user_key, Some("contacts:read")).await?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let mut responses = vec![DavResponse::select(address_home_path(&user_key), &requested, home_props(&user_key))];
+    if depth(&req) > 0 {
+        let ctag = dav::address_book_tag(&user_key, &pool).await?;
+        responses.push(DavResponse::select(address_book_path(&user_key), &requested, address_book_props(&identity, &user_key, &ctag)));
+    }
+    Ok(multistatus_response(&responses))
+}
+
+fn address_item_response(
+    user_key: &str,
+    item: &dav::DavItem<contact::ContactResponse>,
+    requested: &Option<Vec<PropName>>,
+) -> DavResponse {
+    let name = PropName::new(CARDDAV, "address-data");
+    let data = if wants(requested, &name) { Some((name, contact_to_vcard(&item.record))) } else { None };
+    let href = format!("{}{}.vcf", address_book_path(user_key), item.record._key);
+    DavResponse::select(href, requested, item_props(&item.rev, VCARD_CONTENT_TYPE, data))
+}
+
+async fn propfind_address_book(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    let identity = require_owner(&req, &pool, &user_key, Some("contacts:read")).await?;
+    let requested = parse_propfind(&body).map_err(ErrorBadRequest)?;
+    let ctag = dav::address_book_tag(&user_key, &pool).await?;
+    let mut responses = vec![DavResponse::select(address_book_path(&user_key), &requested, address_book_props(&identity, &user_key, &ctag))];
+    if depth(&req) > 0 {
+        let items = dav::find_address_items(&user_key, None, &pool).await?;
+        responses.extend(items.iter().map(|x| address_item_response(&user_key, x, &requested)));
+    }
+    Ok(multistatus_response(&responses))
+}
+
+// address book queries carry no filters we could use, so they list everything
+async fn report_address_book(
+    req: HttpRequest,
+    path: web::Path<String>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("contacts:read")).await?;
+    match parse_report(&body).map_err(ErrorForbidden)? {
+        Report::Multiget { props, hrefs } => {
+            let keys: Vec<String> = hrefs.iter().filter_map(|x| resource_key(x, ".vcf")).collect();
+            let items = dav::find_address_items(&user_key, Some(&keys), &pool).await?;
+            let responses: Vec<DavResponse> = hrefs.iter()
+                .map(|x| {
+                    let item = resource_key(x, ".vcf")
+                        .and_then(|key| items.iter().find(|y| y.record._key == key));
+                    match item {
+                        Some(item) => address_item_response(&user_key, item, &props),
+                        None => DavResponse::not_found(x.clone()),
+                    }
+                })
+                .collect();
+            Ok(multistatus_response(&responses))
+        },
+        Report::Query { props, .. } => {
+            let items = dav::find_address_items(&user_key, None, &pool).await?;
+            let responses: Vec<DavResponse> = items.iter()
+                .map(|x| address_item_response(&user_key, x, &props))
+                .collect();
+            Ok(multistatus_response(&responses))
+        },
+    }
+}
+
+async fn get_contact(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("contacts:read")).await?;
+    let key = resource_key(&name, ".vcf").ok_or_else(|| ErrorNotFound("contact not found"))?;
+    let item = dav::find_address_items(&user_key, Some(&[key]), &pool).await?
+        .pop()
+        .ok_or_else(|| ErrorNotFound("contact not found"))?;
+    Ok(HttpResponse::Ok()
+        .content_type(VCARD_CONTENT_TYPE)
+        .insert_header((ETAG, etag(&item.rev)))
+        .body(contact_to_vcard(&item.record)))
+}
+
+async fn put_contact(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    body: String,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("contacts:write")).await?;
+    let key = resource_key(&name, ".vcf").ok_or_else(|| ErrorBadRequest("wrong resource name"))?;
+    let (uid, payload) = parse_vcard(&body).map_err(ErrorBadRequest)?;
+    if let Err(e) = payload.validate() {
+        return Err(Invalid(e).into());
+    }
+
+    let current = dav::find_address_items(&user_key, Some(&[key.clone()]), &pool).await?.pop();
+    check_preconditions(&req, current.as_ref().map(|x| x.rev.as_str()))?;
+    let uid = uid
+        .or_else(|| current.map(|x| x.record.uid))
+        .unwrap_or_else(|| key.clone());
+    let created = contact::put_contact(&key, &user_key, uid, &payload, &pool).await?;
+
+    let item = dav::find_address_items(&user_key, Some(&[key]), &pool).await?
+        .pop()
+        .ok_or_else(|| ErrorNotFound("contact not found"))?;
+    let mut response = if created { HttpResponse::Created() } else { HttpResponse::NoContent() };
+    Ok(response.insert_header((ETAG, etag(&item.rev))).finish())
+}
+
+async fn delete_contact(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (user_key, name) = path.into_inner();
+    require_owner(&req, &pool, &user_key, Some("contacts:write")).await?;
+    let key = resource_key(&name, ".vcf").ok_or_else(|| ErrorNotFound("contact not found"))?;
+    let current = dav::find_address_items(&user_key, Some(&[key.clone()]), &pool).await?.pop();
+    check_preconditions(&req, current.as_ref().map(|x| x.rev.as_str()))?;
+    contact::delete_contact(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    // collections are matched with and without the trailing slash clients disagree on
+    cfg.service(web::resource(vec!["/.well-known/caldav", "/.well-known/carddav"]).to(well_known));
+    cfg.service(web::resource(vec!["/dav", "/dav/"])
+        .route(web::method(method("PROPFIND")).to(propfind_root))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource(vec!["/dav/principals/{user_key}", "/dav/principals/{user_key}/"])
+        .route(web::method(method("PROPFIND")).to(propfind_principal))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource(vec!["/dav/calendars/{user_key}", "/dav/calendars/{user_key}/"])
+        .route(web::method(method("PROPFIND")).to(propfind_calendar_home))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource(vec!["/dav/calendars/{user_key}/events", "/dav/calendars/{user_key}/events/"])
+        .route(web::method(method("PROPFIND")).to(propfind_calendar))
+        .route(web::method(method("REPORT")).to(report_calendar))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource("/dav/calendars/{user_key}/events/{name}")
+        .route(web::get().to(get_event))
+        .route(web::put().to(put_event))
+        .route(web::delete().to(delete_event))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource(vec!["/dav/addressbooks/{user_key}", "/dav/addressbooks/{user_key}/"])
+        .route(web::method(method("PROPFIND")).to(propfind_address_home))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource(vec!["/dav/addressbooks/{user_key}/contacts", "/dav/addressbooks/{user_key}/contacts/"])
+        .route(web::method(method("PROPFIND")).to(propfind_address_book))
+        .route(web::method(method("REPORT")).to(report_address_book))
+        .route(web::method(Method::OPTIONS).to(options)));
+    cfg.service(web::resource("/dav/addressbooks/{user_key}/contacts/{name}")
+        .route(web::get().to(get_contact))
+        .route(web::put().to(put_contact))
+        .route(web::delete().to(delete_contact))
+        .route(web::method(Method::OPTIONS).to(options)));
+}
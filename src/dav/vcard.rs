@@ -0,0 +1,144 @@
+use crate::contact::{ContactRequest, ContactResponse};
+use crate::dav::content::{content_lines, escape_text, fold, unescape_text};
+
+const PRODID: &str = "-//groupware-actix//EN";
+
+// first component of a structured value such as ORG
+fn first_component(value: &str) -> String {
+    let mut end = value.len();
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        if escaped {
+            escaped = false;
+        } else if c == '\\' {
+            escaped = true;
+        } else if c == ';' {
+            end = i;
+            break;
+        }
+    }
+    unescape_text(&value[..end])
+}
+
+pub fn contact_to_vcard(contact: &ContactResponse) -> String {
+    // N is required by vcard 3.0, the last word is taken as the family name
+    let (given, family) = match contact.name.rsplit_once(' ') {
+        Some((given, family)) => (given, family),
+        None => ("", contact.name.as_str()),
+    };
+    let mut card = String::new();
+    card.push_str("BEGIN:VCARD\r\nVERSION:3.0\r\n");
+    card.push_str(&fold(&format!("PRODID:{}", PRODID)));
+    card.push_str(&fold(&format!("UID:{}", escape_text(&contact.uid))));
+    card.push_str(&fold(&format!("FN:{}", escape_text(&contact.name))));
+    card.push_str(&fold(&format!("N:{};{};;;", escape_text(family), escape_text(given))));
+    for email in &contact.emails {
+        card.push_str(&fold(&format!("EMAIL;TYPE=INTERNET:{}", escape_text(email))));
+    }
+    for phone in &contact.phones {
+        card.push_str(&fold(&format!("TEL:{}", escape_text(phone))));
+    }
+    if let Some(organization) = &contact.organization {
+        card.push_str(&fold(&format!("ORG:{}", escape_text(organization))));
+    }
+    if let Some(note) = &contact.note {
+        card.push_str(&fold(&format!("NOTE:{}", escape_text(note))));
+    }
+    card.push_str(&fold(&format!("REV:{}", contact.modified_at.format("%Y%m%dT%H%M%SZ"))));
+    card.push_str("END:VCARD\r\n");
+    card
+}
+
+// properties we do not store, such as photos and addresses, are dropped
+pub fn parse_vcard(text: &str) -> Result<(Option<String>, ContactRequest), &'static str> {
+    let lines = content_lines(text);
+    if !lines.iter().any(|x| x.name == "BEGIN" && x.value.trim().eq_ignore_ascii_case("VCARD")) {
+        return Err("no vcard");
+    }
+    let prop = |name: &str| lines.iter().find(|x| x.name == name);
+    let values = |name: &str| -> Vec<String> {
+        lines.iter()
+            .filter(|x| x.name == name)
+            .map(|x| unescape_text(x.value.trim()))
+            .filter(|x| !x.is_empty())
+            .collect()
+    };
+
+    let name = match prop("FN").map(|x| unescape_text(x.value.trim())).filter(|x| !x.is_empty()) {
+        Some(name) => name,
+        None => {
+            let n = prop("N").ok_or("no name")?;
+            let parts: Vec<String> = n.value.split(';').map(unescape_text).collect();
+            let given = parts.get(1).cloned().unwrap_or_default();
+            let family = parts.first().cloned().unwrap_or_default();
+            format!("{} {}", given, family).trim().to_string()
+        },
+    };
+    let uid = prop("UID").map(|x| unescape_text(x.value.trim())).filter(|x| !x.is_empty());
+    let payload = ContactRequest {
+        name,
+        emails: Some(values("EMAIL")),
+        phones: Some(values("TEL")),
+        organization: prop("ORG").map(|x| first_component(&x.value)).filter(|x| !x.is_empty()),
+        note: prop("NOTE").map(|x| unescape_text(&x.value)).filter(|x| !x.is_empty()),
+        custom: None,
+    };
+    Ok((uid, payload))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::prelude::*;
+
+    fn contact() -> ContactResponse {
+        let modified_at = "2021-01-02T08:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        ContactResponse {
+            _key: "1".to_string(),
+            uid: "abc".to_string(),
+            owner_key: "2".to_string(),
+            name: "Ann Marie Doe".to_string(),
+            emails: vec!["ann@example.com".to_string()],
+            phones: vec!["+49 30 1234".to_string()],
+            organization: Some("Doe, Inc.".to_string()),
+            note: None,
+            custom: None,
+            created_at: modified_at,
+            modified_at,
+        }
+    }
+
+    #[test]
+    fn contact_output() {
+        assert_eq!(
+            contact_to_vcard(&contact()),
+            "BEGIN:VCARD\r\nVERSION:3.0\r\nPRODID:-//groupware-actix//EN\r\nUID:abc\r\n\
+             FN:Ann Marie Doe\r\nN:Doe;Ann Marie;;;\r\nEMAIL;TYPE=INTERNET:ann@example.com\r\n\
+             TEL:+49 30 1234\r\nORG:Doe\\, Inc.\r\nREV:20210102T080000Z\r\nEND:VCARD\r\n",
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let (uid, payload) = parse_vcard(&contact_to_vcard(&contact())).unwrap();
+        assert_eq!(uid.as_deref(), Some("abc"));
+        assert_eq!(payload.name, "Ann Marie Doe");
+        assert_eq!(payload.emails, Some(contact().emails));
+        assert_eq!(payload.phones, Some(contact().phones));
+        assert_eq!(payload.organization.as_deref(), Some("Doe, Inc."));
+        assert_eq!(payload.note, None);
+    }
+
+    #[test]
+    fn client_cards() {
+        let text = "BEGIN:VCARD\r\nVERSION:4.0\r\nN:Doe;Ann;;;\r\nORG:Acme\\; Labs;Research\r\nEND:VCARD\r\n";
+        let (uid, payload) = parse_vcard(text).unwrap();
+        assert_eq!(uid, None);
+        assert_eq!(payload.name, "Ann Doe");
+        assert_eq!(payload.organization.as_deref(), Some("Acme; Labs"));
+        assert_eq!(payload.emails, Some(vec![]));
+
+        assert_eq!(parse_vcard("BEGIN:VEVENT\r\nEND:VEVENT\r\n").unwrap_err(), "no vcard");
+        assert_eq!(parse_vcard("BEGIN:VCARD\r\nEND:VCARD\r\n").unwrap_err(), "no name");
+    }
+}
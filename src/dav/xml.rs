@@ -0,0 +1,175 @@
+use chrono::prelude::*;
+use roxmltree::{Document, Node};
+
+pub const DAV: &str = "DAV:";
+pub const CALDAV: &str = "urn:ietf:params:xml:ns:caldav";
+pub const CARDDAV: &str = "urn:ietf:params:xml:ns:carddav";
+pub const CALENDARSERVER: &str = "http://calendarserver.org/ns/";
+
+#[derive(Clone, Debug, PartialEq)]
+pub struct PropName {
+    pub namespace: String,
+    pub name: String,
+}
+
+impl PropName {
+    pub fn new(namespace: &str, name: &str) -> Self {
+        PropName {
+            namespace: namespace.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    fn render(&self, inner: &str) -> String {
+        let prefix = match self.namespace.as_str() {
+            DAV => "d",
+            CALDAV => "c",
+            CARDDAV => "card",
+            CALENDARSERVER => "cs",
+            _ => {
+                return format!("<x:{} xmlns:x=\"{}\">{}</x:{}>", self.name, escape(&self.namespace), inner, self.name);
+            },
+        };
+        if inner.is_empty() {
+            format!("<{}:{}/>", prefix, self.name)
+        } else {
+            format!("<{}:{}>{}</{}:{}>", prefix, self.name, inner, prefix, self.name)
+        }
+    }
+}
+
+pub enum Report {
+    Multiget { props: Option<Vec<PropName>>, hrefs: Vec<String> },
+    Query { props: Option<Vec<PropName>>, range: Option<(DateTime<Utc>, DateTime<Utc>)> },
+}
+
+// one entry of a multistatus, values are xml fragments and none for unknown properties
+pub struct DavResponse {
+    pub href: String,
+    pub props: Vec<(PropName, Option<String>)>,
+    pub found: bool,
+}
+
+impl DavResponse {
+    // picks what the client asked for, or everything for allprop
+    pub fn select(
+        href: String,
+        requested: &Option<Vec<PropName>>,
+        available: Vec<(PropName, String)>,
+    ) -> Self {
+        let props = match requested {
+            Some(requested) => requested.iter()
+                .map(|x| (x.clone(), available.iter().find(|(y, _)| y == x).map(|(_, v)| v.clone())))
+                .collect(),
+            None => available.into_iter().map(|(x, v)| (x, Some(v))).collect(),
+        };
+        DavResponse { href, props, found: true }
+    }
+
+    pub fn not_found(href: String) -> Self {
+        DavResponse { href, props: vec![], found: false }
+    }
+}
+
+pub fn escape(value: &str) -> String {
+    value.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+pub fn href(path: &str) -> String {
+    format!("<d:href>{}</d:href>", escape(path))
+}
+
+fn is(node: &Node, namespace: &str, name: &str) -> bool {
+    node.is_element() && node.tag_name().namespace() == Some(namespace) && node.tag_name().name() == name
+}
+
+fn prop_names(root: &Node) -> Option<Vec<PropName>> {
+    let prop = root.children().find(|x| is(x, DAV, "prop"))?;
+    Some(prop.children()
+        .filter(|x| x.is_element())
+        .map(|x| PropName::new(x.tag_name().namespace().unwrap_or_default(), x.tag_name().name()))
+        .collect())
+}
+
+// none stands for allprop, which is also what an empty body asks for
+pub fn parse_propfind(body: &str) -> Result<Option<Vec<PropName>>, &'static str> {
+    if body.trim().is_empty() {
+        return Ok(None);
+    }
+    let doc = Document::parse(body).map_err(|_| "malformed xml")?;
+    let root = doc.root_element();
+    if !is(&root, DAV, "propfind") {
+        return Err("propfind expected");
+    }
+    Ok(prop_names(&root))
+}
+
+fn parse_range_time(value: Option<&str>) -> Option<DateTime<Utc>> {
+    let value = value?.strip_suffix('Z')?;
+    NaiveDateTime::parse_from_str(value, "%Y%m%dT%H%M%S")
+        .ok()
+        .map(|x| Utc.from_utc_datetime(&x))
+}
+
+pub fn parse_report(body: &str) -> Result<Report, &'static str> {
+    let doc = Document::parse(body).map_err(|_| "malformed xml")?;
+    let root = doc.root_element();
+    let props = prop_names(&root);
+    if is(&root, CALDAV, "calendar-multiget") || is(&root, CARDDAV, "addressbook-multiget") {
+        let hrefs = root.children()
+            .filter(|x| is(x, DAV, "href"))
+            .filter_map(|x| x.text())
+            .map(|x| x.trim().to_string())
+            .collect();
+        return Ok(Report::Multiget { props, hrefs });
+    }
+    if is(&root, CALDAV, "calendar-query") || is(&root, CARDDAV, "addressbook-query") {
+        // open ends of a time range are taken as far past and far future
+        let range = root.descendants()
+            .find(|x| is(x, CALDAV, "time-range"))
+            .map(|x| (
+                parse_range_time(x.attribute("start")).unwrap_or_else(|| Utc.timestamp_opt(0, 0).unwrap()),
+                parse_range_time(x.attribute("end")).unwrap_or_else(|| {
+                    Utc.from_utc_datetime(&NaiveDate::from_ymd_opt(9999, 12, 31).unwrap().and_hms_opt(0, 0, 0).unwrap())
+                }),
+            ));
+        return Ok(Report::Query { props, range });
+    }
+    Err("unsupported report")
+}
+
+pub fn multistatus(responses: &[DavResponse]) -> String {
+    let mut xml = String::new();
+    xml.push_str(r#"<?xml version="1.0" encoding="utf-8"?>"#);
+    xml.push_str(&format!(
+        r#"<d:multistatus xmlns:d="{}" xmlns:c="{}" xmlns:card="{}" xmlns:cs="{}">"#,
+        DAV, CALDAV, CARDDAV, CALENDARSERVER,
+    ));
+    for response in responses {
+        xml.push_str("<d:response>");
+        xml.push_str(&href(&response.href));
+        if !response.found {
+            xml.push_str("<d:status>HTTP/1.1 404 Not Found</d:status></d:response>");
+            continue;
+        }
+        let found: Vec<String> = response.props.iter()
+            .filter_map(|(name, value)| value.as_ref().map(|x| name.render(x)))
+            .collect();
+        let missing: Vec<String> = response.props.iter()
+            .filter(|(_, value)| value.is_none())
+            .map(|(name, _)| name.render(""))
+            .collect();
+        if !found.is_empty() {
+            xml.push_str(&format!("<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 200 OK</d:status></d:propstat>", found.join("")));
+        }
+        if !missing.is_empty() {
+            xml.push_str(&format!("<d:propstat><d:prop>{}</d:prop><d:status>HTTP/1.1 404 Not Found</d:status></d:propstat>", missing.join("")));
+        }
+        xml.push_str("</d:response>");
+    }
+    xml.push_str("</d:multistatus>");
+    xml
+}
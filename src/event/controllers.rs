@@ -0,0 +1,329 @@
+use actix_web::{
+    error::{ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::database::{current_database, DbPool};
+use crate::event::{
+    occurrences,
+    series_end,
+    Event,
+    EventRequest,
+    EventResponse,
+    FindEventsParams,
+    OccurrenceResponse,
+};
+
+const EVENT_TERMS: &str = "RETURN UNSET(e, '_id', '_rev', 'starts_at', 'series_ends_at')";
+
+// events whose series may overlap [from, to), expansion happens on our side
+const CANDIDATES: &str = r#"FOR e IN events
+        FILTER e.starts_at < @to AND (e.series_ends_at == null OR e.series_ends_at > @from)"#;
+
+async fn find_event(
+    db: &Database<ReqwestClient>,
+    key: &str,
+) -> Result<EventResponse, Error> {
+    let q = format!("FOR e IN events FILTER e._key == @key {}", EVENT_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<EventResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("event not found"))
+}
+
+// only people sharing a company with the owner can be invited, and so block their time
+async fn check_attendees(
+    db: &Database<ReqwestClient>,
+    owner_key: &str,
+    attendees: &[String],
+) -> Result<(), Error> {
+    let others: Vec<String> = attendees.iter()
+        .filter(|x| *x != owner_key)
+        .map(|x| format!("users/{}", x))
+        .collect();
+    if others.is_empty() {
+        return Ok(());
+    }
+    let q = r#"LET companies = (FOR m IN memberships FILTER m._from == @owner RETURN m._to)
+        RETURN LENGTH(
+            FOR m IN memberships
+                FILTER m._to IN companies AND m._from IN @others
+                RETURN DISTINCT m._from
+        ) == LENGTH(UNIQUE(@others))"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner", to_value(format!("users/{}", owner_key)).unwrap());
+    vars.insert("others", to_value(others).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<bool> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if !records.pop().unwrap_or(false) {
+        return Err(ErrorForbidden("attendee outside of your companies"));
+    }
+    Ok(())
+}
+
+fn to_event(
+    payload: &EventRequest,
+    owner_key: &str,
+    uid: String,
+    created_at: DateTime<Utc>,
+) -> Event {
+    let mut attendees = payload.attendees.clone().unwrap_or_default();
+    attendees.sort();
+    attendees.dedup();
+    let mut event = Event {
+        uid,
+        owner_key: owner_key.to_string(),
+        title: payload.title.trim().to_string(),
+        description: payload.description.clone(),
+        location: payload.location.clone(),
+        start: payload.start,
+        end: payload.end,
+        timezone: payload.timezone.clone().unwrap_or_else(|| "UTC".to_string()),
+        recurrence: payload.recurrence.clone(),
+        exceptions: payload.exceptions.clone().unwrap_or_default(),
+        attendees,
+        busy: payload.busy.unwrap_or(true),
+        starts_at: payload.start.timestamp(),
+        series_ends_at: None,
+        created_at,
+        modified_at: Utc::now(),
+    };
+    event.series_ends_at = series_end(&event).map(|x| x.timestamp());
+    event
+}
+
+// series are loaded whole and expanded in the timezone they were created in
+#[instrument(skip_all)]
+pub async fn expand_events(
+    db: &Database<ReqwestClient>,
+    user_keys: &[String],
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+    busy_only: bool,
+) -> Result<Vec<(Document<Event>, Vec<(DateTime<Utc>, DateTime<Utc>)>)>, Error> {
+    let q = format!(r#"{}
+        FILTER e.owner_key IN @users OR LENGTH(INTERSECTION(e.attendees, @users)) > 0
+        FILTER !@busy_only OR e.busy
+        RETURN e"#, CANDIDATES);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("users", to_value(user_keys).unwrap());
+    vars.insert("from", to_value(from.timestamp()).unwrap());
+    vars.insert("to", to_value(to.timestamp()).unwrap());
+    vars.insert("busy_only", to_value(busy_only).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<Document<Event>> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records.into_iter()
+        .map(|x| {
+            let spans = occurrences(&x, from, to);
+            (x, spans)
+        })
+        .filter(|(_, spans)| !spans.is_empty())
+        .collect())
+}
+
+#[instrument(skip_all)]
+pub async fn find_events(
+    user_key: &str,
+    params: FindEventsParams,
+    pool: &DbPool,
+) -> Result<Vec<OccurrenceResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let events = expand_events(&db, &[user_key.to_string()], params.from, params.to, false).await?;
+    let mut result: Vec<OccurrenceResponse> = events.iter()
+        .flat_map(|(event, spans)| spans.iter().map(move |(start, end)| OccurrenceResponse {
+            event_key: event.header._key.clone(),
+            title: event.title.clone(),
+            location: event.location.clone(),
+            start: *start,
+            end: *end,
+            recurring: event.recurrence.is_some(),
+            busy: event.busy,
+        }))
+        .collect();
+    result.sort_by_key(|x| x.start);
+    Ok(result)
+}
+
+// owners and attendees can see an event, only the owner can change it
+#[instrument(skip_all)]
+pub async fn show_event(
+    key: &String,
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<EventResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let event = find_event(&db, key).await?;
+    if event.owner_key != user_key && !event.attendees.iter().any(|x| x == user_key) {
+        return Err(ErrorNotFound("event not found"));
+    }
+    Ok(event)
+}
+
+#[instrument(skip_all)]
+pub async fn create_event(
+    owner_key: &str,
+    payload: &EventRequest,
+    pool: &DbPool,
+) -> Result<EventResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    check_attendees(&db, owner_key, payload.attendees.as_deref().unwrap_or_default()).await?;
+
+    let collection: Collection<ReqwestClient> = db.collection("events").await.unwrap();
+    let data = to_event(payload, owner_key, Uuid::new_v4().to_string(), Utc::now());
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<Event>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+    find_event(&db, &header._key).await
+}
+
+#[instrument(skip_all)]
+pub async fn update_event(
+    key: &String,
+    owner_key: &str,
+    payload: &EventRequest,
+    pool: &DbPool,
+) -> Result<EventResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_event(&db, key).await?;
+    if current.owner_key != owner_key {
+        return Err(ErrorForbidden("not the owner of the event"));
+    }
+    check_attendees(&db, owner_key, payload.attendees.as_deref().unwrap_or_default()).await?;
+
+    let data = to_event(payload, owner_key, current.uid, current.created_at);
+    let q = "FOR e IN events FILTER e._key == @key REPLACE e WITH @data IN events";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_event(&db, key).await
+}
+
+// creates or replaces the event under a key chosen by a calendar client, true when created
+#[instrument(skip_all)]
+pub async fn put_event(
+    key: &str,
+    owner_key: &str,
+    uid: String,
+    payload: &EventRequest,
+    pool: &DbPool,
+) -> Result<bool, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_event(&db, key).await.ok();
+    if let Some(current) = &current {
+        if current.owner_key != owner_key {
+            return Err(ErrorForbidden("not the owner of the event"));
+        }
+    }
+    // clients that do not know about attendees leave them untouched
+    let mut payload = payload.clone();
+    if payload.attendees.is_none() {
+        payload.attendees = current.as_ref().map(|x| x.attendees.clone());
+    }
+    check_attendees(&db, owner_key, payload.attendees.as_deref().unwrap_or_default()).await?;
+
+    match current {
+        Some(current) => {
+            let data = to_event(&payload, owner_key, uid, current.created_at);
+            let q = "FOR e IN events FILTER e._key == @key REPLACE e WITH @data IN events";
+            let mut vars: HashMap<&str, Value> = HashMap::new();
+            vars.insert("key", to_value(key).unwrap());
+            vars.insert("data", to_value(&data).unwrap());
+
+            let aql = AqlQuery::builder()
+                .query(q)
+                .bind_vars(vars)
+                .build();
+            let _records: Vec<Value> = db.aql_query(aql).await
+                .map_err(ErrorInternalServerError)?;
+            Ok(false)
+        },
+        None => {
+            let collection: Collection<ReqwestClient> = db.collection("events").await.unwrap();
+            let mut doc = Document::new(to_event(&payload, owner_key, uid, Utc::now()));
+            doc.header._key = key.to_string();
+            let options: InsertOptions = InsertOptions::builder()
+                .return_new(false)
+                .build();
+
+            let _res: DocumentResponse<Document<Event>> = collection.create_document(doc, options).await
+                .map_err(ErrorInternalServerError)?;
+            Ok(true)
+        },
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn delete_event(
+    key: &str,
+    owner_key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR e IN events
+        FILTER e._key == @key AND e.owner_key == @owner_key
+        REMOVE e IN events
+        RETURN OLD._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        return Err(ErrorNotFound("event not found"));
+    }
+    Ok(())
+}
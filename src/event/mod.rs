@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod recurrence;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use recurrence::{occurrences, parse_timezone, parse_weekday, series_end, to_utc};
+pub use routes::init;
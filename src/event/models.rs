@@ -0,0 +1,144 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+use crate::event::{parse_timezone, parse_weekday};
+
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_range"))]
+pub struct FindEventsParams {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn validate_range(params: &FindEventsParams) -> Result<(), ValidationError> {
+    if params.to <= params.from || params.to - params.from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(ValidationError::new("wrong_range"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Serialize, Deserialize)]
+pub struct Recurrence {
+    #[validate(custom = "validate_frequency")]
+    pub frequency: String,
+    #[validate(range(min = 1, max = 1000))]
+    #[serde(default = "default_interval")]
+    pub interval: u32,
+    #[validate(range(min = 1, max = 1000))]
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub count: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub until: Option<DateTime<Utc>>,
+    #[validate(custom = "validate_weekdays")]
+    #[serde(default)]
+    pub weekdays: Vec<String>, // mo to su, weekly events only
+}
+
+fn default_interval() -> u32 {
+    1
+}
+
+fn validate_frequency(frequency: &str) -> Result<(), ValidationError> {
+    match frequency {
+        "daily" | "weekly" | "monthly" | "yearly" => Ok(()),
+        _ => Err(ValidationError::new("wrong_frequency")),
+    }
+}
+
+pub fn validate_weekdays(weekdays: &[String]) -> Result<(), ValidationError> {
+    if !weekdays.iter().all(|x| parse_weekday(x).is_some()) {
+        return Err(ValidationError::new("wrong_weekdays"));
+    }
+    Ok(())
+}
+
+pub fn validate_timezone(timezone: &str) -> Result<(), ValidationError> {
+    if parse_timezone(timezone).is_none() {
+        return Err(ValidationError::new("wrong_timezone"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_event"))]
+pub struct EventRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(max = 10000))]
+    pub description: Option<String>,
+    #[validate(length(max = 200))]
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[validate(custom = "validate_timezone")]
+    pub timezone: Option<String>, // recurrences follow the wall clock of this zone, UTC by default
+    #[validate]
+    pub recurrence: Option<Recurrence>,
+    pub exceptions: Option<Vec<DateTime<Utc>>>, // starts of cancelled occurrences
+    #[validate(length(max = 100))]
+    pub attendees: Option<Vec<String>>,
+    pub busy: Option<bool>, // false for events that do not block time
+}
+
+fn validate_event(payload: &EventRequest) -> Result<(), ValidationError> {
+    if payload.end <= payload.start {
+        return Err(ValidationError::new("end_before_start"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Event {
+    pub uid: String, // stable identifier for calendar clients
+    pub owner_key: String,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub timezone: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub recurrence: Option<Recurrence>,
+    pub exceptions: Vec<DateTime<Utc>>,
+    pub attendees: Vec<String>,
+    pub busy: bool,
+    pub starts_at: i64, // unix seconds of start, compared numerically in queries
+    pub series_ends_at: Option<i64>, // unix seconds of the last end, none when it repeats forever
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct OccurrenceResponse {
+    pub event_key: String,
+    pub title: String,
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub recurring: bool,
+    pub busy: bool,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct EventResponse {
+    pub _key: String,
+    pub uid: String,
+    pub owner_key: String,
+    pub title: String,
+    pub description: Option<String>,
+    pub location: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub timezone: String,
+    pub recurrence: Option<Recurrence>,
+    pub exceptions: Vec<DateTime<Utc>>,
+    pub attendees: Vec<String>,
+    pub busy: bool,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
@@ -0,0 +1,238 @@
+use chrono::{prelude::*, Duration};
+use chrono_tz::Tz;
+
+use crate::event::Event;
+
+// guards against series that never reach the requested range
+const MAX_ITERATIONS: usize = 100_000;
+
+pub fn parse_weekday(value: &str) -> Option<Weekday> {
+    match value {
+        "mo" => Some(Weekday::Mon),
+        "tu" => Some(Weekday::Tue),
+        "we" => Some(Weekday::Wed),
+        "th" => Some(Weekday::Thu),
+        "fr" => Some(Weekday::Fri),
+        "sa" => Some(Weekday::Sat),
+        "su" => Some(Weekday::Sun),
+        _ => None,
+    }
+}
+
+pub fn parse_timezone(value: &str) -> Option<Tz> {
+    value.parse::<Tz>().ok()
+}
+
+// wall clock times skipped by a DST change move forward by the size of the gap
+pub fn to_utc(tz: &Tz, local: &NaiveDateTime) -> DateTime<Utc> {
+    match tz.from_local_datetime(local).earliest() {
+        Some(x) => x.with_timezone(&Utc),
+        None => tz.from_local_datetime(&(*local + Duration::hours(1)))
+            .earliest()
+            .map(|x| x.with_timezone(&Utc))
+            .unwrap_or_else(|| Utc.from_utc_datetime(local)),
+    }
+}
+
+fn add_months(date: NaiveDate, months: i64) -> Option<NaiveDate> {
+    let total = date.year() as i64 * 12 + date.month0() as i64 + months;
+    let year = total.div_euclid(12) as i32;
+    let month = total.rem_euclid(12) as u32 + 1;
+    NaiveDate::from_ymd_opt(year, month, date.day())
+}
+
+// local dates of the n-th period of the series, days that do not exist (31st, 29th of February) are skipped
+fn period_dates(event: &Event, start: NaiveDate, n: i64) -> Vec<NaiveDate> {
+    let recurrence = event.recurrence.as_ref().unwrap();
+    let step = recurrence.interval as i64 * n;
+    match recurrence.frequency.as_str() {
+        "daily" => vec![start + Duration::days(step)],
+        "weekly" => {
+            let mut weekdays: Vec<Weekday> = recurrence.weekdays.iter()
+                .filter_map(|x| parse_weekday(x))
+                .collect();
+            if weekdays.is_empty() {
+                weekdays.push(start.weekday());
+            }
+            weekdays.sort_by_key(|x| x.num_days_from_monday());
+            weekdays.dedup();
+            let monday = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+            weekdays.iter()
+                .map(|x| monday + Duration::weeks(step) + Duration::days(x.num_days_from_monday() as i64))
+                .filter(|x| *x >= start)
+                .collect()
+        },
+        "monthly" => add_months(start, step).into_iter().collect(),
+        "yearly" => add_months(start, step * 12).into_iter().collect(),
+        _ => vec![],
+    }
+}
+
+// calls visit with the start of every occurrence in order until it returns false or the series ends
+fn walk<F>(event: &Event, mut visit: F)
+where
+    F: FnMut(DateTime<Utc>) -> bool,
+{
+    let recurrence = match &event.recurrence {
+        Some(x) => x,
+        None => {
+            visit(event.start);
+            return;
+        },
+    };
+    let tz = parse_timezone(&event.timezone).unwrap_or(Tz::UTC);
+    let local = event.start.with_timezone(&tz).naive_local();
+    let mut emitted = 0;
+    for n in 0..MAX_ITERATIONS as i64 {
+        for date in period_dates(event, local.date(), n) {
+            let start = to_utc(&tz, &date.and_time(local.time()));
+            if recurrence.count.map_or(false, |x| emitted >= x) {
+                return;
+            }
+            if recurrence.until.map_or(false, |x| start > x) {
+                return;
+            }
+            emitted += 1;
+            if !visit(start) {
+                return;
+            }
+        }
+    }
+}
+
+// occurrences overlapping [from, to), cancelled ones left out
+pub fn occurrences(
+    event: &Event,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let duration = event.end - event.start;
+    let mut result = vec![];
+    walk(event, |start| {
+        if start >= to {
+            return false;
+        }
+        let end = start + duration;
+        if end > from && !event.exceptions.contains(&start) {
+            result.push((start, end));
+        }
+        true
+    });
+    result
+}
+
+// end of the last occurrence, none when the series repeats forever
+pub fn series_end(event: &Event) -> Option<DateTime<Utc>> {
+    let recurrence = match &event.recurrence {
+        Some(x) => x,
+        None => return Some(event.end),
+    };
+    if recurrence.count.is_none() && recurrence.until.is_none() {
+        return None;
+    }
+    let mut last = event.start;
+    walk(event, |start| {
+        last = start;
+        true
+    });
+    Some(last + (event.end - event.start))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::event::Recurrence;
+
+    fn at(text: &str) -> DateTime<Utc> {
+        DateTime::parse_from_rfc3339(text).unwrap().with_timezone(&Utc)
+    }
+
+    fn event(start: &str, timezone: &str, recurrence: Option<Recurrence>) -> Event {
+        let start = at(start);
+        Event {
+            uid: "uid".to_string(),
+            owner_key: "1".to_string(),
+            title: "standup".to_string(),
+            description: None,
+            location: None,
+            start,
+            end: start + Duration::minutes(30),
+            timezone: timezone.to_string(),
+            recurrence,
+            exceptions: vec![],
+            attendees: vec![],
+            busy: true,
+            starts_at: start.timestamp(),
+            series_ends_at: None,
+            created_at: start,
+            modified_at: start,
+        }
+    }
+
+    fn every(frequency: &str, count: Option<u32>, weekdays: &[&str]) -> Option<Recurrence> {
+        Some(Recurrence {
+            frequency: frequency.to_string(),
+            interval: 1,
+            count,
+            until: None,
+            weekdays: weekdays.iter().map(|x| x.to_string()).collect(),
+        })
+    }
+
+    fn starts(event: &Event) -> Vec<String> {
+        occurrences(event, at("2021-01-01T00:00:00Z"), at("2022-01-01T00:00:00Z"))
+            .iter()
+            .map(|(start, _)| start.format("%F %R").to_string())
+            .collect()
+    }
+
+    #[test]
+    fn weekly_on_weekdays() {
+        // 2021-01-06 is a Wednesday, days before it in the first week are not part of the series
+        let event = event("2021-01-06T10:00:00Z", "UTC", every("weekly", Some(5), &["fr", "mo", "we"]));
+        assert_eq!(starts(&event), vec![
+            "2021-01-06 10:00",
+            "2021-01-08 10:00",
+            "2021-01-11 10:00",
+            "2021-01-13 10:00",
+            "2021-01-15 10:00",
+        ]);
+        assert_eq!(series_end(&event), Some(at("2021-01-15T10:30:00Z")));
+    }
+
+    #[test]
+    fn missing_days_are_skipped() {
+        let event = event("2021-01-31T10:00:00Z", "UTC", every("monthly", Some(3), &[]));
+        assert_eq!(starts(&event), vec!["2021-01-31 10:00", "2021-03-31 10:00", "2021-05-31 10:00"]);
+    }
+
+    #[test]
+    fn local_time_across_dst() {
+        // Berlin moves from UTC+1 to UTC+2 on 2021-03-28
+        let event = event("2021-03-27T08:00:00Z", "Europe/Berlin", every("daily", Some(3), &[]));
+        assert_eq!(starts(&event), vec!["2021-03-27 08:00", "2021-03-28 07:00", "2021-03-29 07:00"]);
+
+        let skipped = NaiveDate::from_ymd_opt(2021, 3, 28).unwrap().and_hms_opt(2, 30, 0).unwrap();
+        assert_eq!(to_utc(&parse_timezone("Europe/Berlin").unwrap(), &skipped), at("2021-03-28T01:30:00Z"));
+    }
+
+    #[test]
+    fn range_and_exceptions() {
+        let mut event = event("2021-01-04T10:00:00Z", "UTC", every("daily", None, &[]));
+        event.exceptions.push(at("2021-01-05T10:00:00Z"));
+        let found: Vec<DateTime<Utc>> = occurrences(&event, at("2021-01-04T10:15:00Z"), at("2021-01-07T10:00:00Z"))
+            .into_iter()
+            .map(|(start, _)| start)
+            .collect();
+        // the first one still overlaps the range, the last one starts at its end
+        assert_eq!(found, vec![at("2021-01-04T10:00:00Z"), at("2021-01-06T10:00:00Z")]);
+        assert_eq!(series_end(&event), None);
+    }
+
+    #[test]
+    fn single_event() {
+        let event = event("2021-01-04T10:00:00Z", "UTC", None);
+        assert_eq!(starts(&event), vec!["2021-01-04 10:00"]);
+        assert_eq!(series_end(&event), Some(event.end));
+    }
+}
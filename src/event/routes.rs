@@ -0,0 +1,104 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::auth;
+use crate::database::DbPool;
+use crate::event::{self, EventRequest, FindEventsParams};
+use crate::i18n::Invalid;
+
+// calendars belong to people, company keys have none
+fn require_user(req: &HttpRequest, scope: &str) -> Result<String, Error> {
+    let identity = auth::authorize(req, scope)?;
+    identity.user_key()
+        .map(|x| x.to_string())
+        .ok_or_else(|| ErrorForbidden("events belong to users"))
+}
+
+#[get("/events")]
+async fn find(
+    req: HttpRequest,
+    payload: web::Query<FindEventsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "events:read")?;
+    let params: FindEventsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = event::find_events(&user_key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/events/{key}")]
+async fn show(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "events:read")?;
+    let result = event::show_event(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/events")]
+async fn create(
+    req: HttpRequest,
+    payload: web::Json<EventRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "events:write")?;
+    let params: EventRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = event::create_event(&user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/events/{key}")]
+async fn update(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<EventRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "events:write")?;
+    let params: EventRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = event::update_event(&key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/events/{key}")]
+async fn delete(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "events:write")?;
+    event::delete_event(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+}
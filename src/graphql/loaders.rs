@@ -0,0 +1,167 @@
+use arangors::AqlQuery;
+use async_graphql::dataloader::{DataLoader, Loader};
+use async_trait::async_trait;
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+
+use crate::company::{self, Company, MemberResponse};
+use crate::database::{current_database, DbPool};
+use crate::user::UserResponse;
+
+// document paired with its key, companies do not carry their own
+#[derive(Debug, Deserialize)]
+struct Keyed<T> {
+    key: String,
+    record: T,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MembershipRecord {
+    pub company_key: String,
+    pub role: String,
+    pub created_at: DateTime<Utc>,
+}
+
+// loads run on spawned tasks, which do not see the tenant of the request
+pub struct CompanyLoader {
+    pool: DbPool,
+    database: String,
+}
+
+pub struct UserLoader {
+    pool: DbPool,
+    database: String,
+}
+
+// memberships of users, keyed by user
+pub struct MembershipLoader {
+    pool: DbPool,
+    database: String,
+}
+
+// members of companies, keyed by company
+pub struct MemberLoader {
+    pool: DbPool,
+    database: String,
+}
+
+async fn load_keyed<T>(
+    pool: &DbPool,
+    database: &str,
+    q: &str,
+    keys: &[String],
+) -> Result<Vec<Keyed<T>>, String>
+where
+    T: serde::de::DeserializeOwned,
+{
+    let client = pool.get().await.map_err(|e| e.to_string())?;
+    let db = client.db(database).await.map_err(|e| e.to_string())?;
+
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("keys", to_value(keys).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await.map_err(|e| e.to_string())
+}
+
+#[async_trait]
+impl Loader<String> for CompanyLoader {
+    type Value = Company;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Company>, String> {
+        // one DOCUMENT() lookup for every company requested in the same tick
+        let q = r#"FOR c IN DOCUMENT('companies', @keys)
+            FILTER c.deleted_at == null
+            RETURN { key: c._key, record: c }"#;
+        let records: Vec<Keyed<Company>> = load_keyed(&self.pool, &self.database, q, keys).await?;
+        Ok(records.into_iter().map(|x| (x.key, x.record)).collect())
+    }
+}
+
+#[async_trait]
+impl Loader<String> for UserLoader {
+    type Value = UserResponse;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, UserResponse>, String> {
+        let q = r#"FOR u IN DOCUMENT('users', @keys)
+            FILTER u.deleted_at == null
+            RETURN { key: u._key, record: UNSET(u, 'password', 'two_factor', 'two_factor_pending') }"#;
+        let records: Vec<Keyed<UserResponse>> = load_keyed(&self.pool, &self.database, q, keys).await?;
+        Ok(records.into_iter().map(|x| (x.key, x.record)).collect())
+    }
+}
+
+#[async_trait]
+impl Loader<String> for MembershipLoader {
+    type Value = Vec<MembershipRecord>;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<MembershipRecord>>, String> {
+        let q = r#"FOR m IN memberships
+            FILTER m._from IN (FOR k IN @keys RETURN CONCAT('users/', k))
+            SORT m.created_at ASC
+            COLLECT key = PARSE_IDENTIFIER(m._from).key INTO groups
+            RETURN {
+                key: key,
+                record: (FOR g IN groups RETURN {
+                    company_key: PARSE_IDENTIFIER(g.m._to).key,
+                    role: g.m.role,
+                    created_at: g.m.created_at
+                })
+            }"#;
+        let records: Vec<Keyed<Vec<MembershipRecord>>> =
+            load_keyed(&self.pool, &self.database, q, keys).await?;
+        Ok(records.into_iter().map(|x| (x.key, x.record)).collect())
+    }
+}
+
+#[async_trait]
+impl Loader<String> for MemberLoader {
+    type Value = Vec<MemberResponse>;
+    type Error = String;
+
+    async fn load(&self, keys: &[String]) -> Result<HashMap<String, Vec<MemberResponse>>, String> {
+        let client = self.pool.get().await.map_err(|e| e.to_string())?;
+        let db = client.db(&self.database).await.map_err(|e| e.to_string())?;
+        company::members_of(&db, keys).await.map_err(|e| e.to_string())
+    }
+}
+
+// loaders live for a single request, so nothing is cached across users
+pub struct Loaders {
+    pub companies: DataLoader<CompanyLoader>,
+    pub users: DataLoader<UserLoader>,
+    pub memberships: DataLoader<MembershipLoader>,
+    pub members: DataLoader<MemberLoader>,
+}
+
+impl Loaders {
+    pub fn new(pool: &DbPool) -> Self {
+        let database = current_database();
+        Loaders {
+            companies: DataLoader::new(
+                CompanyLoader { pool: pool.clone(), database: database.clone() },
+                actix_web::rt::spawn,
+            ),
+            users: DataLoader::new(
+                UserLoader { pool: pool.clone(), database: database.clone() },
+                actix_web::rt::spawn,
+            ),
+            memberships: DataLoader::new(
+                MembershipLoader { pool: pool.clone(), database: database.clone() },
+                actix_web::rt::spawn,
+            ),
+            members: DataLoader::new(
+                MemberLoader { pool: pool.clone(), database },
+                actix_web::rt::spawn,
+            ),
+        }
+    }
+}
@@ -0,0 +1,7 @@
+mod loaders;
+mod schema;
+mod routes;
+
+pub use loaders::*;
+pub use schema::*;
+pub use routes::init;
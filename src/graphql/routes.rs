@@ -0,0 +1,36 @@
+use actix_web::{get, post, web, Error, HttpRequest, HttpResponse};
+use async_graphql::http::{playground_source, GraphQLPlaygroundConfig};
+use async_graphql_actix_web::{GraphQLRequest, GraphQLResponse};
+
+use crate::auth;
+use crate::database::DbPool;
+use crate::graphql::{AppSchema, Loaders};
+
+#[post("/graphql")]
+async fn execute(
+    req: HttpRequest,
+    payload: GraphQLRequest,
+    schema: web::Data<AppSchema>,
+    pool: web::Data<DbPool>,
+) -> Result<GraphQLResponse, Error> {
+    // scopes are checked field by field, so any usable identity gets in
+    let identity = auth::authenticate(&req)?;
+    let request = payload.into_inner()
+        .data(identity)
+        .data(pool.get_ref().clone())
+        .data(Loaders::new(&pool));
+    Ok(schema.execute(request).await.into())
+}
+
+#[get("/graphql")]
+async fn playground() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type("text/html; charset=utf-8")
+        .body(playground_source(GraphQLPlaygroundConfig::new("/api/graphql")))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(execute);
+    cfg.service(playground);
+}
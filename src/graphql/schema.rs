@@ -0,0 +1,259 @@
+use actix_web::http::StatusCode;
+use async_graphql::{Context, EmptyMutation, EmptySubscription, Error, ErrorExtensions, Object, Result, Schema, ID};
+use chrono::prelude::*;
+use validator::Validate;
+
+use crate::auth::Identity;
+use crate::company::{Company, MemberResponse};
+use crate::database::DbPool;
+use crate::graphql::{Loaders, MembershipRecord};
+use crate::i18n::failure;
+use crate::user::{self, FindUsersParams, UserResponse};
+
+pub type AppSchema = Schema<QueryRoot, EmptyMutation, EmptySubscription>;
+
+// nesting is what makes graphql cheap to ask and expensive to answer
+const MAX_DEPTH: usize = 8;
+const MAX_COMPLEXITY: usize = 500;
+
+pub fn build_schema() -> AppSchema {
+    Schema::build(QueryRoot, EmptyMutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+// same rules as auth::authorize, the identity is put in the request data by the route
+fn authorize<'a>(ctx: &Context<'a>, scope: &str) -> Result<&'a Identity> {
+    let identity = ctx.data::<Identity>()?;
+    if !identity.has_scope(scope) {
+        // the code lets clients translate the message like the rest api does
+        let text = failure(StatusCode::FORBIDDEN, "scope_required", &[("scope", scope)]).to_string();
+        return Err(Error::new(text).extend_with(|_, e| e.set("code", "scope_required")));
+    }
+    Ok(identity)
+}
+
+// same rules as company::require_access
+fn require_access<'a>(ctx: &Context<'a>, key: &str) -> Result<&'a Identity> {
+    let identity = authorize(ctx, "companies:read")?;
+    if let Some(company_key) = identity.company_key() {
+        if company_key != key {
+            return Err(Error::new("api key belongs to another company"));
+        }
+    }
+    Ok(identity)
+}
+
+pub struct QueryRoot;
+
+#[Object]
+impl QueryRoot {
+    async fn company(&self, ctx: &Context<'_>, key: ID) -> Result<Option<CompanyObject>> {
+        require_access(ctx, &key)?;
+        let loaders = ctx.data::<Loaders>()?;
+        let record = loaders.companies.load_one(key.to_string()).await?;
+        Ok(record.map(|x| CompanyObject::new(key.to_string(), x)))
+    }
+
+    async fn companies(&self, ctx: &Context<'_>, keys: Vec<ID>) -> Result<Vec<CompanyObject>> {
+        for key in &keys {
+            require_access(ctx, key)?;
+        }
+        let loaders = ctx.data::<Loaders>()?;
+        let mut records = loaders.companies
+            .load_many(keys.iter().map(|x| x.to_string()))
+            .await?;
+        // keeps the order that was asked for
+        Ok(keys.iter()
+            .filter_map(|x| records.remove(x.as_str()).map(|y| CompanyObject::new(x.to_string(), y)))
+            .collect())
+    }
+
+    async fn user(&self, ctx: &Context<'_>, key: ID) -> Result<Option<UserObject>> {
+        authorize(ctx, "users:read")?;
+        let loaders = ctx.data::<Loaders>()?;
+        let record = loaders.users.load_one(key.to_string()).await?;
+        Ok(record.map(UserObject))
+    }
+
+    async fn users(
+        &self,
+        ctx: &Context<'_>,
+        search: Option<String>,
+        sort_by: Option<String>,
+        limit: Option<u32>,
+    ) -> Result<Vec<UserObject>> {
+        authorize(ctx, "users:read")?;
+        let pool = ctx.data::<DbPool>()?;
+        let params = FindUsersParams {
+            search,
+            sort_by,
+            limit: Some(limit.unwrap_or(20)),
+            tags: None,
+            tags_mode: None,
+            tags_company: None,
+            custom_company: None,
+            custom_field: None,
+            custom_value: None,
+            custom_sort: None,
+        };
+        params.validate().map_err(|e| Error::new(e.to_string()))?;
+        let records = user::find_users(params, pool)
+            .await
+            .map_err(|e| Error::new(e.to_string()))?;
+        Ok(records.into_iter().map(UserObject).collect())
+    }
+
+    // none for company api keys, which have no user behind them
+    async fn me(&self, ctx: &Context<'_>) -> Result<Option<UserObject>> {
+        let identity = ctx.data::<Identity>()?;
+        let user_key = match identity.user_key() {
+            Some(x) => x.to_string(),
+            None => return Ok(None),
+        };
+        let loaders = ctx.data::<Loaders>()?;
+        let record = loaders.users.load_one(user_key).await?;
+        Ok(record.map(UserObject))
+    }
+}
+
+pub struct CompanyObject {
+    key: String,
+    record: Company,
+}
+
+impl CompanyObject {
+    fn new(key: String, record: Company) -> Self {
+        CompanyObject { key, record }
+    }
+}
+
+#[Object(name = "Company")]
+impl CompanyObject {
+    async fn key(&self) -> ID {
+        ID::from(self.key.clone())
+    }
+
+    async fn name(&self) -> Option<&str> {
+        self.record.name.as_deref()
+    }
+
+    async fn since(&self) -> Option<DateTime<Utc>> {
+        self.record.since
+    }
+
+    async fn created_at(&self) -> Option<DateTime<Utc>> {
+        self.record.created_at
+    }
+
+    async fn modified_at(&self) -> Option<DateTime<Utc>> {
+        self.record.modified_at
+    }
+
+    async fn require_two_factor(&self) -> bool {
+        self.record.require_two_factor.unwrap_or(false)
+    }
+
+    // batched with the members of every other company in the same query, only shown to members
+    async fn members(&self, ctx: &Context<'_>) -> Result<Vec<MemberObject>> {
+        let identity = require_access(ctx, &self.key)?;
+        let loaders = ctx.data::<Loaders>()?;
+        if let Some(user_key) = identity.user_key() {
+            let memberships = loaders.memberships
+                .load_one(user_key.to_string())
+                .await?
+                .unwrap_or_default();
+            if !memberships.iter().any(|x| x.company_key == self.key) {
+                return Err(Error::new("company member required"));
+            }
+        }
+        let records = loaders.members.load_one(self.key.clone()).await?;
+        Ok(records.unwrap_or_default().into_iter().map(MemberObject).collect())
+    }
+}
+
+pub struct MemberObject(MemberResponse);
+
+#[Object(name = "Member")]
+impl MemberObject {
+    async fn role(&self) -> &str {
+        &self.0.role
+    }
+
+    async fn two_factor_enabled(&self) -> bool {
+        self.0.two_factor_enabled
+    }
+
+    // batched with every other member resolved in the same query
+    async fn user(&self, ctx: &Context<'_>) -> Result<Option<UserObject>> {
+        authorize(ctx, "users:read")?;
+        let loaders = ctx.data::<Loaders>()?;
+        let record = loaders.users.load_one(self.0.user_key.clone()).await?;
+        Ok(record.map(UserObject))
+    }
+}
+
+pub struct UserObject(UserResponse);
+
+#[Object(name = "User")]
+impl UserObject {
+    async fn key(&self) -> ID {
+        ID::from(self.0._key.clone())
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn email(&self) -> &str {
+        &self.0.email
+    }
+
+    async fn avatar(&self) -> &str {
+        &self.0.avatar
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn modified_at(&self) -> DateTime<Utc> {
+        self.0.modified_at
+    }
+
+    // company api keys only see the membership in their own company
+    async fn memberships(&self, ctx: &Context<'_>) -> Result<Vec<MembershipObject>> {
+        let identity = authorize(ctx, "companies:read")?;
+        let loaders = ctx.data::<Loaders>()?;
+        let records = loaders.memberships
+            .load_one(self.0._key.clone())
+            .await?
+            .unwrap_or_default();
+        Ok(records.into_iter()
+            .filter(|x| identity.company_key().map_or(true, |y| y == x.company_key))
+            .map(MembershipObject)
+            .collect())
+    }
+}
+
+pub struct MembershipObject(MembershipRecord);
+
+#[Object(name = "Membership")]
+impl MembershipObject {
+    async fn role(&self) -> &str {
+        &self.0.role
+    }
+
+    async fn created_at(&self) -> DateTime<Utc> {
+        self.0.created_at
+    }
+
+    async fn company(&self, ctx: &Context<'_>) -> Result<Option<CompanyObject>> {
+        require_access(ctx, &self.0.company_key)?;
+        let loaders = ctx.data::<Loaders>()?;
+        let key = self.0.company_key.clone();
+        let record = loaders.companies.load_one(key.clone()).await?;
+        Ok(record.map(|x| CompanyObject::new(key, x)))
+    }
+}
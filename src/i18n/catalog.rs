@@ -0,0 +1,172 @@
+use crate::i18n::Locale;
+
+// code, english, korean and german. codes of api errors are their original message in snake case,
+// placeholders in braces are filled from the parameters of the error
+const MESSAGES: &[(&str, &str, &str, &str)] = &[
+    // responses
+    ("invalid", "Some fields are invalid", "일부 항목이 올바르지 않습니다", "Einige Felder sind ungültig"),
+    ("malformed_request", "The request could not be read", "요청을 읽을 수 없습니다", "Die Anfrage konnte nicht gelesen werden"),
+    ("internal_error", "Something went wrong, please try again later", "문제가 발생했습니다. 잠시 후 다시 시도해 주세요", "Etwas ist schiefgelaufen, bitte versuchen Sie es später erneut"),
+    ("password_has_its_own_endpoint", "Change the password with the current one at /users/{key}/password", "비밀번호는 현재 비밀번호와 함께 /users/{key}/password에서 변경하세요", "Ändern Sie das Passwort mit dem aktuellen unter /users/{key}/password"),
+    ("scope_required", "The scope {scope} is required", "{scope} 권한이 필요합니다", "Der Bereich {scope} ist erforderlich"),
+    ("timesheet_already_submitted", "The timesheet of {week} is already submitted", "{week} 주의 근무표는 이미 제출되었습니다", "Der Stundenzettel für {week} wurde bereits eingereicht"),
+    // validation
+    ("required", "This field is required", "필수 항목입니다", "Dieses Feld ist erforderlich"),
+    ("email", "Enter a valid email address", "올바른 이메일 주소를 입력하세요", "Geben Sie eine gültige E-Mail-Adresse ein"),
+    ("url", "Enter a valid URL", "올바른 URL을 입력하세요", "Geben Sie eine gültige URL ein"),
+    ("must_match", "Does not match {other}", "{other} 항목과 일치하지 않습니다", "Stimmt nicht mit {other} überein"),
+    ("length_between", "Must be between {min} and {max} long", "길이는 {min}에서 {max} 사이여야 합니다", "Die Länge muss zwischen {min} und {max} liegen"),
+    ("length_min", "Must be at least {min} long", "길이는 {min} 이상이어야 합니다", "Die Länge muss mindestens {min} betragen"),
+    ("length_max", "Must be at most {max} long", "길이는 {max} 이하여야 합니다", "Die Länge darf höchstens {max} betragen"),
+    ("length_equal", "Must be exactly {equal} long", "길이는 정확히 {equal}이어야 합니다", "Die Länge muss genau {equal} betragen"),
+    ("range_between", "Must be between {min} and {max}", "{min}에서 {max} 사이의 값이어야 합니다", "Muss zwischen {min} und {max} liegen"),
+    ("range_min", "Must be at least {min}", "{min} 이상이어야 합니다", "Muss mindestens {min} sein"),
+    ("range_max", "Must be at most {max}", "{max} 이하여야 합니다", "Darf höchstens {max} sein"),
+    ("already_expired", "The expiry is already in the past", "만료 시각이 이미 지났습니다", "Der Ablaufzeitpunkt liegt bereits in der Vergangenheit"),
+    ("empty_audience", "Choose at least one recipient", "받는 사람을 한 명 이상 선택하세요", "Wählen Sie mindestens einen Empfänger"),
+    ("end_before_start", "The end is before the start", "종료 시각이 시작 시각보다 빠릅니다", "Das Ende liegt vor dem Beginn"),
+    ("end_or_duration_required", "Give either an end or a duration", "종료 시각이나 기간 중 하나를 입력하세요", "Geben Sie entweder ein Ende oder eine Dauer an"),
+    ("options_required", "Enum fields need options", "선택형 항목에는 선택지가 필요합니다", "Auswahlfelder benötigen Optionen"),
+    ("reference_required", "Reference fields need a reference", "참조형 항목에는 참조 대상이 필요합니다", "Verweisfelder benötigen ein Verweisziel"),
+    ("required_field", "The custom field {field} is required", "사용자 정의 항목 {field}은(는) 필수입니다", "Das benutzerdefinierte Feld {field} ist erforderlich"),
+    ("required_with_custom_field", "Required together with custom_field", "custom_field와 함께 입력해야 합니다", "Zusammen mit custom_field erforderlich"),
+    ("required_with_custom_fields", "Required when filtering by custom fields", "사용자 정의 항목으로 필터링할 때 필요합니다", "Beim Filtern nach benutzerdefinierten Feldern erforderlich"),
+    ("required_with_tags", "Required when filtering by tags", "태그로 필터링할 때 필요합니다", "Beim Filtern nach Tags erforderlich"),
+    ("unknown_field", "Unknown custom field", "알 수 없는 사용자 정의 항목입니다", "Unbekanntes benutzerdefiniertes Feld"),
+    ("working_hours_end_before_start", "Working hours end before they start", "근무 종료 시각이 시작 시각보다 빠릅니다", "Die Arbeitszeit endet, bevor sie beginnt"),
+    ("wrong_colour", "Unknown colour", "알 수 없는 색상입니다", "Unbekannte Farbe"),
+    ("wrong_emails", "Contains an invalid email address", "올바르지 않은 이메일 주소가 있습니다", "Enthält eine ungültige E-Mail-Adresse"),
+    ("wrong_end", "The end must be after the start and within a day", "종료 시각은 시작 이후 하루 이내여야 합니다", "Das Ende muss nach dem Beginn und innerhalb eines Tages liegen"),
+    ("wrong_entity", "Unknown kind of record", "알 수 없는 레코드 종류입니다", "Unbekannte Art von Datensatz"),
+    ("wrong_events", "Contains an unknown event", "알 수 없는 이벤트가 있습니다", "Enthält ein unbekanntes Ereignis"),
+    ("wrong_field_type", "Unknown field type", "알 수 없는 항목 유형입니다", "Unbekannter Feldtyp"),
+    ("wrong_format", "Unknown format", "알 수 없는 형식입니다", "Unbekanntes Format"),
+    ("wrong_frequency", "Unknown frequency", "알 수 없는 반복 주기입니다", "Unbekannte Häufigkeit"),
+    ("wrong_group_by", "Unknown grouping", "알 수 없는 그룹 기준입니다", "Unbekannte Gruppierung"),
+    ("wrong_kind", "Unknown kind", "알 수 없는 종류입니다", "Unbekannte Art"),
+    ("wrong_locale", "Unsupported language", "지원하지 않는 언어입니다", "Nicht unterstützte Sprache"),
+    ("wrong_mode", "Unknown mode", "알 수 없는 모드입니다", "Unbekannter Modus"),
+    ("wrong_name", "Contains characters that are not allowed", "허용되지 않는 문자가 있습니다", "Enthält nicht erlaubte Zeichen"),
+    ("wrong_number", "Enter a number", "숫자를 입력하세요", "Geben Sie eine Zahl ein"),
+    ("wrong_range", "The range is empty or too long", "기간이 비어 있거나 너무 깁니다", "Der Zeitraum ist leer oder zu lang"),
+    ("wrong_reaction", "Unsupported reaction", "지원하지 않는 반응입니다", "Nicht unterstützte Reaktion"),
+    ("wrong_role", "Unknown role", "알 수 없는 역할입니다", "Unbekannte Rolle"),
+    ("wrong_scopes", "Contains an unknown scope", "알 수 없는 권한이 있습니다", "Enthält einen unbekannten Bereich"),
+    ("wrong_slug", "Use lower case letters, digits and dashes", "영문 소문자, 숫자, 하이픈만 사용하세요", "Verwenden Sie Kleinbuchstaben, Ziffern und Bindestriche"),
+    ("wrong_sort_by", "Unknown sort order", "알 수 없는 정렬 기준입니다", "Unbekannte Sortierung"),
+    ("wrong_status", "Unknown status", "알 수 없는 상태입니다", "Unbekannter Status"),
+    ("wrong_tags_mode", "Use all or any", "all 또는 any를 사용하세요", "Verwenden Sie all oder any"),
+    ("wrong_target", "Unknown kind of target", "알 수 없는 대상 종류입니다", "Unbekannte Art von Ziel"),
+    ("wrong_time", "Expected a time such as 09:30", "09:30과 같은 시각을 입력하세요", "Erwartet wird eine Uhrzeit wie 09:30"),
+    ("wrong_timezone", "Unknown time zone", "알 수 없는 시간대입니다", "Unbekannte Zeitzone"),
+    ("wrong_value", "Wrong value for the custom field {field}", "사용자 정의 항목 {field}의 값이 올바르지 않습니다", "Falscher Wert für das benutzerdefinierte Feld {field}"),
+    ("wrong_week", "Expected a week such as 2021-W07", "2021-W07과 같은 주를 입력하세요", "Erwartet wird eine Woche wie 2021-W07"),
+    ("wrong_weekdays", "Contains an unknown weekday", "알 수 없는 요일이 있습니다", "Enthält einen unbekannten Wochentag"),
+    ("wrong_window", "The booking window is not valid", "예약 가능 기간이 올바르지 않습니다", "Das Buchungsfenster ist ungültig"),
+    // api errors
+    ("a_page_cannot_move_below_itself", "A page cannot move below itself", "페이지를 자기 자신의 하위로 옮길 수 없습니다", "Eine Seite kann nicht unter sich selbst verschoben werden"),
+    ("a_timer_is_already_running", "A timer is already running", "이미 실행 중인 타이머가 있습니다", "Es läuft bereits ein Timer"),
+    ("a_user_session_is_required", "A user session is required", "사용자 세션이 필요합니다", "Eine Benutzersitzung ist erforderlich"),
+    ("announcement_not_found", "Announcement not found", "공지를 찾을 수 없습니다", "Ankündigung nicht gefunden"),
+    ("announcements_are_acknowledged_by_users", "Announcements are acknowledged by users", "공지는 사용자가 확인해야 합니다", "Ankündigungen werden von Benutzern bestätigt"),
+    ("announcements_are_posted_by_users", "Announcements are posted by users", "공지는 사용자가 게시해야 합니다", "Ankündigungen werden von Benutzern veröffentlicht"),
+    ("api_key_belongs_to_another_company", "The API key belongs to another company", "다른 회사의 API 키입니다", "Der API-Schlüssel gehört zu einer anderen Firma"),
+    ("api_key_not_found", "API key not found", "API 키를 찾을 수 없습니다", "API-Schlüssel nicht gefunden"),
+    ("archive_is_too_large", "The archive is too large", "보관 파일이 너무 큽니다", "Das Archiv ist zu groß"),
+    ("attendee_outside_of_your_companies", "The attendee is not in any of your companies", "참석자가 소속 회사에 없습니다", "Der Teilnehmer gehört zu keiner Ihrer Firmen"),
+    ("authentication_required", "Authentication required", "로그인이 필요합니다", "Anmeldung erforderlich"),
+    ("booking_is_too_long", "The booking is too long", "예약 시간이 너무 깁니다", "Die Buchung ist zu lang"),
+    ("booking_not_found", "Booking not found", "예약을 찾을 수 없습니다", "Buchung nicht gefunden"),
+    ("booking_starts_too_soon", "The booking starts too soon", "예약 시작 시각이 너무 이릅니다", "Die Buchung beginnt zu früh"),
+    ("calendars_belong_to_users", "Calendars belong to users", "캘린더는 사용자에게 속합니다", "Kalender gehören Benutzern"),
+    ("cannot_change_another_user", "You cannot change another user", "다른 사용자를 변경할 수 없습니다", "Sie können keinen anderen Benutzer ändern"),
+    ("cannot_change_the_password_of_another_user", "You cannot change the password of another user", "다른 사용자의 비밀번호를 변경할 수 없습니다", "Sie können das Passwort eines anderen Benutzers nicht ändern"),
+    ("cannot_decide_your_own_timesheet", "You cannot decide your own timesheet", "본인의 근무표는 결정할 수 없습니다", "Sie können nicht über Ihren eigenen Stundenzettel entscheiden"),
+    ("cannot_enrol_another_user", "You cannot enrol another user", "다른 사용자를 등록할 수 없습니다", "Sie können keinen anderen Benutzer registrieren"),
+    ("cannot_merge_a_tag_into_itself", "A tag cannot be merged into itself", "태그를 자기 자신에 병합할 수 없습니다", "Ein Tag kann nicht mit sich selbst zusammengeführt werden"),
+    ("challenge_expired", "The challenge has expired", "인증 요청이 만료되었습니다", "Die Anfrage ist abgelaufen"),
+    ("code_or_recovery_code_is_required", "A code or a recovery code is required", "인증 코드나 복구 코드가 필요합니다", "Ein Code oder ein Wiederherstellungscode ist erforderlich"),
+    ("collections_belong_to_their_user", "Collections belong to their user", "컬렉션은 해당 사용자에게 속합니다", "Sammlungen gehören ihrem Benutzer"),
+    ("comment_not_found", "Comment not found", "댓글을 찾을 수 없습니다", "Kommentar nicht gefunden"),
+    ("comments_can_only_be_written_by_users", "Comments can only be written by users", "댓글은 사용자만 작성할 수 있습니다", "Kommentare können nur von Benutzern geschrieben werden"),
+    ("companies_can_only_be_created_by_users", "Companies can only be created by users", "회사는 사용자만 만들 수 있습니다", "Firmen können nur von Benutzern angelegt werden"),
+    ("company_admin_required", "Only company admins can do this", "회사 관리자만 할 수 있습니다", "Nur Firmenadministratoren können das tun"),
+    ("company_manager_required", "Only company managers can do this", "회사 매니저만 할 수 있습니다", "Nur Firmenmanager können das tun"),
+    ("company_member_required", "Only company members can do this", "회사 구성원만 할 수 있습니다", "Nur Firmenmitglieder können das tun"),
+    ("contact_not_found", "Contact not found", "연락처를 찾을 수 없습니다", "Kontakt nicht gefunden"),
+    ("contacts_belong_to_users", "Contacts belong to users", "연락처는 사용자에게 속합니다", "Kontakte gehören zu Benutzern"),
+    ("current_password_does_not_match", "The current password is wrong", "현재 비밀번호가 일치하지 않습니다", "Das aktuelle Passwort ist falsch"),
+    ("custom_field_already_exists", "The custom field already exists", "사용자 정의 항목이 이미 있습니다", "Das benutzerdefinierte Feld existiert bereits"),
+    ("custom_field_not_found", "Custom field not found", "사용자 정의 항목을 찾을 수 없습니다", "Benutzerdefiniertes Feld nicht gefunden"),
+    ("delivery_not_found", "Delivery not found", "전송 기록을 찾을 수 없습니다", "Zustellung nicht gefunden"),
+    ("document_not_found", "Document not found", "문서를 찾을 수 없습니다", "Dokument nicht gefunden"),
+    ("event_not_found", "Event not found", "일정을 찾을 수 없습니다", "Termin nicht gefunden"),
+    ("events_belong_to_users", "Events belong to users", "일정은 사용자에게 속합니다", "Termine gehören zu Benutzern"),
+    ("invalid_challenge", "The challenge is not valid", "인증 요청이 올바르지 않습니다", "Die Anfrage ist ungültig"),
+    ("invalid_code", "The code is not valid", "인증 코드가 올바르지 않습니다", "Der Code ist ungültig"),
+    ("invalid_credentials", "Wrong email or password", "이메일 또는 비밀번호가 올바르지 않습니다", "E-Mail-Adresse oder Passwort ist falsch"),
+    ("invalid_session", "The session is not valid", "세션이 올바르지 않습니다", "Die Sitzung ist ungültig"),
+    ("invalid_token", "The token is not valid", "토큰이 올바르지 않습니다", "Das Token ist ungültig"),
+    ("membership_not_found", "Membership not found", "소속 정보를 찾을 수 없습니다", "Mitgliedschaft nicht gefunden"),
+    ("message_not_found", "Message not found", "메일을 찾을 수 없습니다", "Nachricht nicht gefunden"),
+    ("messages_belong_to_users", "Messages belong to users", "메일은 사용자에게 속합니다", "Nachrichten gehören zu Benutzern"),
+    ("metrics_token_required", "The metrics token is required", "메트릭 토큰이 필요합니다", "Das Metrik-Token ist erforderlich"),
+    ("no_timer_is_running", "No timer is running", "실행 중인 타이머가 없습니다", "Es läuft kein Timer"),
+    ("not_allowed_to_manage_api_keys_of_another_user", "You cannot manage the API keys of another user", "다른 사용자의 API 키를 관리할 수 없습니다", "Sie können die API-Schlüssel eines anderen Benutzers nicht verwalten"),
+    ("not_the_author_of_the_comment", "You are not the author of the comment", "댓글 작성자가 아닙니다", "Sie sind nicht der Verfasser des Kommentars"),
+    ("not_the_owner_of_the_contact", "You are not the owner of the contact", "연락처의 소유자가 아닙니다", "Der Kontakt gehört Ihnen nicht"),
+    ("not_the_owner_of_the_event", "You are not the owner of the event", "일정의 소유자가 아닙니다", "Der Termin gehört Ihnen nicht"),
+    ("not_the_owner_of_the_target", "You are not the owner of the discussed document", "논의 대상 문서의 소유자가 아닙니다", "Das besprochene Dokument gehört Ihnen nicht"),
+    ("not_your_booking", "This is not your booking", "본인의 예약이 아닙니다", "Das ist nicht Ihre Buchung"),
+    ("only_enum_fields_have_options", "Only enum fields have options", "선택형 항목에만 선택지가 있습니다", "Nur Auswahlfelder haben Optionen"),
+    ("operator_token_required", "The operator token is required", "운영자 토큰이 필요합니다", "Das Betreiber-Token ist erforderlich"),
+    ("page_has_children", "The page has child pages", "하위 페이지가 있습니다", "Die Seite hat Unterseiten"),
+    ("page_not_found", "Page not found", "페이지를 찾을 수 없습니다", "Seite nicht gefunden"),
+    ("page_was_changed_since_the_base_revision", "The page was changed in the meantime", "그 사이에 페이지가 변경되었습니다", "Die Seite wurde inzwischen geändert"),
+    ("pages_are_edited_by_users", "Pages are edited by users", "페이지는 사용자가 편집해야 합니다", "Seiten werden von Benutzern bearbeitet"),
+    ("parent_comment_not_found_on_this_target", "The parent comment is not on this target", "상위 댓글을 이 대상에서 찾을 수 없습니다", "Der übergeordnete Kommentar gehört nicht zu diesem Ziel"),
+    ("parent_page_not_found", "Parent page not found", "상위 페이지를 찾을 수 없습니다", "Übergeordnete Seite nicht gefunden"),
+    ("password_does_not_match", "The password is wrong", "비밀번호가 일치하지 않습니다", "Das Passwort ist falsch"),
+    ("pending_booking_not_found", "Pending booking not found", "대기 중인 예약을 찾을 수 없습니다", "Ausstehende Buchung nicht gefunden"),
+    ("read_state_belongs_to_users", "Read state belongs to users", "읽음 상태는 사용자에게 속합니다", "Der Lesestatus gehört zu Benutzern"),
+    ("resource_changed", "The resource was changed", "자원이 변경되었습니다", "Die Ressource wurde geändert"),
+    ("resource_exists", "The resource already exists", "자원이 이미 존재합니다", "Die Ressource existiert bereits"),
+    ("resource_is_too_small", "The resource is too small", "자원의 수용 인원이 부족합니다", "Die Ressource ist zu klein"),
+    ("resource_not_found", "Resource not found", "자원을 찾을 수 없습니다", "Ressource nicht gefunden"),
+    ("resources_can_only_be_booked_by_users", "Resources can only be booked by users", "자원은 사용자만 예약할 수 있습니다", "Ressourcen können nur von Benutzern gebucht werden"),
+    ("revision_not_found", "Revision not found", "수정 이력을 찾을 수 없습니다", "Version nicht gefunden"),
+    ("slot_already_booked", "The slot is already booked", "이미 예약된 시간입니다", "Der Zeitraum ist bereits gebucht"),
+    ("slug_already_used", "The slug is already used", "이미 사용 중인 주소입니다", "Die Kurzadresse wird bereits verwendet"),
+    ("stop_the_running_timer_first", "Stop the running timer first", "실행 중인 타이머를 먼저 멈추세요", "Stoppen Sie zuerst den laufenden Timer"),
+    ("submitted_timesheet_not_found", "Submitted timesheet not found", "제출된 근무표를 찾을 수 없습니다", "Eingereichter Stundenzettel nicht gefunden"),
+    ("tag_already_exists", "The tag already exists", "태그가 이미 있습니다", "Der Tag existiert bereits"),
+    ("tag_not_applied_to_target", "The tag is not applied to the target", "대상에 적용된 태그가 아닙니다", "Der Tag ist dem Ziel nicht zugeordnet"),
+    ("tag_not_found", "Tag not found", "태그를 찾을 수 없습니다", "Tag nicht gefunden"),
+    ("target_not_found", "Target not found", "대상을 찾을 수 없습니다", "Ziel nicht gefunden"),
+    ("tenancy_is_disabled", "Tenancy is disabled", "멀티 테넌시가 꺼져 있습니다", "Mandantenfähigkeit ist deaktiviert"),
+    ("tenant_already_exists", "The tenant already exists", "테넌트가 이미 있습니다", "Der Mandant existiert bereits"),
+    ("tenant_required", "A tenant is required", "테넌트를 지정해야 합니다", "Ein Mandant ist erforderlich"),
+    ("thread_not_found", "Thread not found", "대화를 찾을 수 없습니다", "Unterhaltung nicht gefunden"),
+    ("time_entry_not_found", "Time entry not found", "근무 기록을 찾을 수 없습니다", "Zeiteintrag nicht gefunden"),
+    ("time_is_tracked_by_users", "Time is tracked by users", "근무 시간은 사용자가 기록해야 합니다", "Zeiten werden von Benutzern erfasst"),
+    ("timesheet_not_found", "Timesheet not found", "근무표를 찾을 수 없습니다", "Stundenzettel nicht gefunden"),
+    ("too_many_invalid_codes", "Too many invalid codes, try again later", "잘못된 코드가 너무 많습니다. 나중에 다시 시도하세요", "Zu viele ungültige Codes, versuchen Sie es später erneut"),
+    ("two_factor_authentication_is_already_enabled", "Two-factor authentication is already enabled", "2단계 인증이 이미 켜져 있습니다", "Die Zwei-Faktor-Authentifizierung ist bereits aktiviert"),
+    ("two_factor_authentication_is_not_enabled", "Two-factor authentication is not enabled", "2단계 인증이 켜져 있지 않습니다", "Die Zwei-Faktor-Authentifizierung ist nicht aktiviert"),
+    ("two_factor_authentication_is_required_by_your_company", "Your company requires two-factor authentication", "회사에서 2단계 인증을 요구합니다", "Ihre Firma verlangt die Zwei-Faktor-Authentifizierung"),
+    ("two_factor_enrolment_has_not_been_started", "Two-factor enrolment has not been started", "2단계 인증 등록이 시작되지 않았습니다", "Die Einrichtung der Zwei-Faktor-Authentifizierung wurde nicht begonnen"),
+    ("two_factor_enrolment_required", "Set up two-factor authentication first", "먼저 2단계 인증을 등록하세요", "Richten Sie zuerst die Zwei-Faktor-Authentifizierung ein"),
+    ("unknown_principal", "Unknown principal", "알 수 없는 사용자입니다", "Unbekannter Benutzer"),
+    ("unknown_tenant", "Unknown tenant", "알 수 없는 테넌트입니다", "Unbekannter Mandant"),
+    ("webhook_not_found", "Webhook not found", "웹훅을 찾을 수 없습니다", "Webhook nicht gefunden"),
+    ("wrong_resource_name", "Wrong resource name", "잘못된 자원 이름입니다", "Falscher Ressourcenname"),
+];
+
+pub fn message(locale: Locale, code: &str) -> Option<&'static str> {
+    let (_, en, ko, de) = MESSAGES.iter().find(|(x, _, _, _)| *x == code)?;
+    Some(match locale {
+        Locale::En => en,
+        Locale::Ko => ko,
+        Locale::De => de,
+    })
+}
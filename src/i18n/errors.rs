@@ -0,0 +1,193 @@
+use actix_web::{
+    error::{JsonPayloadError, PathError, QueryPayloadError, UrlencodedError},
+    http::StatusCode,
+    Error,
+    HttpResponse,
+    ResponseError,
+};
+use serde_json::{json, Map, Value};
+use std::{borrow::Cow, collections::HashMap, fmt};
+use validator::{ValidationError, ValidationErrors, ValidationErrorsKind};
+
+use crate::i18n::{message, Locale};
+
+// api errors without a code of their own use their message, as in "page not found"
+pub fn code_of(text: &str) -> String {
+    text.to_lowercase()
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|x| !x.is_empty())
+        .collect::<Vec<&str>>()
+        .join("_")
+}
+
+fn fill(template: &str, params: &HashMap<Cow<'static, str>, Value>) -> String {
+    let mut text = template.to_string();
+    for (name, value) in params {
+        let value = match value {
+            Value::String(x) => x.clone(),
+            x => x.to_string(),
+        };
+        text = text.replace(&format!("{{{}}}", name), &value);
+    }
+    text
+}
+
+// built-in validators share one code whatever bounds they were given
+fn validation_code(error: &ValidationError) -> Cow<'static, str> {
+    let has = |x: &str| error.params.contains_key(x);
+    match error.code.as_ref() {
+        "length" if has("equal") => Cow::from("length_equal"),
+        "length" | "range" => match (has("min"), has("max")) {
+            (true, true) => Cow::from(format!("{}_between", error.code)),
+            (true, false) => Cow::from(format!("{}_min", error.code)),
+            _ => Cow::from(format!("{}_max", error.code)),
+        },
+        _ => error.code.clone(),
+    }
+}
+
+pub fn translate(
+    locale: Locale,
+    code: &str,
+    params: &HashMap<Cow<'static, str>, Value>,
+) -> Option<String> {
+    message(locale, code).map(|x| fill(x, params))
+}
+
+fn render_field(errors: &[ValidationError], locale: Locale) -> Value {
+    let items: Vec<Value> = errors.iter()
+        .map(|error| {
+            let code = validation_code(error);
+            let text = translate(locale, &code, &error.params)
+                .or_else(|| error.message.as_ref().map(|x| x.to_string()))
+                .unwrap_or_else(|| code.to_string());
+            json!({
+                "code": code,
+                "message": text,
+                "params": error.params,
+            })
+        })
+        .collect();
+    Value::Array(items)
+}
+
+// keeps the shape of serialized ValidationErrors, with every message filled in
+pub fn render_errors(errors: &ValidationErrors, locale: Locale) -> Value {
+    let mut map = Map::new();
+    for (field, kind) in errors.errors() {
+        let value = match kind {
+            ValidationErrorsKind::Field(x) => render_field(x, locale),
+            ValidationErrorsKind::Struct(x) => render_errors(x, locale),
+            ValidationErrorsKind::List(x) => Value::Object(x.iter()
+                .map(|(i, x)| (i.to_string(), render_errors(x, locale)))
+                .collect()),
+        };
+        map.insert(field.to_string(), value);
+    }
+    Value::Object(map)
+}
+
+fn invalid_body(errors: &ValidationErrors, locale: Locale) -> Value {
+    json!({
+        "code": "invalid",
+        "message": translate(locale, "invalid", &HashMap::new()),
+        "errors": render_errors(errors, locale),
+    })
+}
+
+// rejected input, rendered field by field
+#[derive(Debug)]
+pub struct Invalid(pub ValidationErrors);
+
+impl fmt::Display for Invalid {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+impl ResponseError for Invalid {
+    fn status_code(&self) -> StatusCode {
+        StatusCode::BAD_REQUEST
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::BadRequest().json(invalid_body(&self.0, Locale::En))
+    }
+}
+
+// api error whose message needs values, such as the missing scope
+#[derive(Debug)]
+pub struct Failure {
+    status: StatusCode,
+    code: &'static str,
+    params: HashMap<Cow<'static, str>, Value>,
+}
+
+pub fn failure(status: StatusCode, code: &'static str, params: &[(&'static str, &str)]) -> Error {
+    Failure {
+        status,
+        code,
+        params: params.iter().map(|(k, v)| (Cow::from(*k), Value::from(*v))).collect(),
+    }.into()
+}
+
+impl fmt::Display for Failure {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = translate(Locale::En, self.code, &self.params).unwrap_or_else(|| self.code.to_string());
+        write!(f, "{}", text)
+    }
+}
+
+impl ResponseError for Failure {
+    fn status_code(&self) -> StatusCode {
+        self.status
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        HttpResponse::build(self.status).json(json!({
+            "code": self.code,
+            "message": self.to_string(),
+        }))
+    }
+}
+
+fn malformed(error: &Error) -> bool {
+    error.as_error::<JsonPayloadError>().is_some()
+        || error.as_error::<QueryPayloadError>().is_some()
+        || error.as_error::<PathError>().is_some()
+        || error.as_error::<UrlencodedError>().is_some()
+}
+
+// code and message of any error, internal details are not shown to clients
+pub fn error_body(error: &Error, locale: Locale) -> Value {
+    let none = HashMap::new();
+    if let Some(Invalid(errors)) = error.as_error::<Invalid>() {
+        return invalid_body(errors, locale);
+    }
+    if let Some(failure) = error.as_error::<Failure>() {
+        return json!({
+            "code": failure.code,
+            "message": translate(locale, failure.code, &failure.params).unwrap_or_else(|| failure.to_string()),
+        });
+    }
+    if malformed(error) {
+        return json!({
+            "code": "malformed_request",
+            "message": translate(locale, "malformed_request", &none),
+            "detail": error.to_string(),
+        });
+    }
+    if error.as_response_error().status_code().is_server_error() {
+        return json!({
+            "code": "internal_error",
+            "message": translate(locale, "internal_error", &none),
+        });
+    }
+    let text = error.to_string();
+    let code = code_of(&text);
+    let message = translate(locale, &code, &none).unwrap_or(text);
+    json!({
+        "code": code,
+        "message": message,
+    })
+}
@@ -0,0 +1,55 @@
+use validator::ValidationError;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Locale {
+    En,
+    Ko,
+    De,
+}
+
+impl Locale {
+    // language tags such as ko-KR fall back to their primary language
+    pub fn parse(tag: &str) -> Option<Locale> {
+        let language = tag.trim().split(&['-', '_'][..]).next()?.to_lowercase();
+        match language.as_str() {
+            "en" => Some(Locale::En),
+            "ko" => Some(Locale::Ko),
+            "de" => Some(Locale::De),
+            _ => None,
+        }
+    }
+
+    pub fn code(&self) -> &'static str {
+        match self {
+            Locale::En => "en",
+            Locale::Ko => "ko",
+            Locale::De => "de",
+        }
+    }
+}
+
+// the supported language with the highest quality, earlier ones win ties
+pub fn from_accept_language(header: &str) -> Option<Locale> {
+    let mut best: Option<(Locale, f32)> = None;
+    for item in header.split(',') {
+        let mut parts = item.split(';');
+        let tag = parts.next().unwrap_or_default();
+        let quality = parts
+            .filter_map(|x| x.trim().strip_prefix("q="))
+            .find_map(|x| x.trim().parse::<f32>().ok())
+            .unwrap_or(1.0);
+        if let Some(locale) = Locale::parse(tag) {
+            if quality > 0.0 && best.map_or(true, |(_, q)| quality > q) {
+                best = Some((locale, quality));
+            }
+        }
+    }
+    best.map(|(x, _)| x)
+}
+
+pub fn validate_locale(locale: &str) -> Result<(), ValidationError> {
+    match Locale::parse(locale) {
+        Some(x) if x.code() == locale => Ok(()),
+        _ => Err(ValidationError::new("wrong_locale")),
+    }
+}
@@ -0,0 +1,130 @@
+use actix_web::{
+    body::{EitherBody, MessageBody},
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    http::header::{ACCEPT_LANGUAGE, CONTENT_LANGUAGE, CONTENT_LENGTH, CONTENT_TYPE},
+    web,
+    Error,
+    HttpMessage,
+    HttpResponse,
+};
+use arangors::AqlQuery;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use serde_json::{to_value, Value};
+use std::{collections::HashMap, rc::Rc};
+
+use crate::auth::Identity;
+use crate::database::{current_database, DbPool};
+use crate::i18n::{error_body, from_accept_language, Locale};
+
+// dav clients expect their own error bodies
+const LOCALIZED_PATH: &str = "/api";
+
+async fn user_locale(user_key: &str, pool: &DbPool) -> Option<Locale> {
+    let client = pool.get().await.ok()?;
+    let db = client.db(&current_database()).await.ok()?;
+
+    let q = "RETURN DOCUMENT('users', @key).locale";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<Option<String>> = db.aql_query(aql).await.ok()?;
+    Locale::parse(&records.pop()??)
+}
+
+fn header_locale(req: &ServiceRequest) -> Option<Locale> {
+    req.headers().get(ACCEPT_LANGUAGE)
+        .and_then(|x| x.to_str().ok())
+        .and_then(from_accept_language)
+}
+
+// for errors raised by middlewares that run before this one, such as tenancy, only the header is known then
+pub fn localized_error<B>(req: ServiceRequest, error: Error) -> Result<ServiceResponse<EitherBody<B>>, Error> {
+    if !req.path().starts_with(LOCALIZED_PATH) {
+        return Err(error);
+    }
+    let locale = header_locale(&req).unwrap_or(Locale::En);
+    let response = HttpResponse::build(error.as_response_error().status_code())
+        .insert_header((CONTENT_LANGUAGE, locale.code()))
+        .json(error_body(&error, locale));
+    Ok(req.into_response(response).map_into_right_body())
+}
+
+// renders error responses of the api as json with a code and a localised message.
+// the preference of the user wins over the header, so this must be wrapped by the authentication middleware
+pub struct Localization;
+
+impl<S, B> Transform<S, ServiceRequest> for Localization
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = LocalizationMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(LocalizationMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct LocalizationMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for LocalizationMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: MessageBody + 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            if !req.path().starts_with(LOCALIZED_PATH) {
+                return service.call(req).await.map(|x| x.map_into_left_body());
+            }
+            let user_key = req.extensions().get::<Identity>()
+                .and_then(|x| x.user_key().map(|x| x.to_string()));
+            let accept_language = header_locale(&req);
+            let pool = req.app_data::<web::Data<DbPool>>().cloned();
+
+            let res = service.call(req).await?;
+            if res.response().error().is_none() {
+                return Ok(res.map_into_left_body());
+            }
+            // looked up only now, successful responses have no text to translate
+            let preferred = match (user_key, pool) {
+                (Some(user_key), Some(pool)) => user_locale(&user_key, &pool).await,
+                _ => None,
+            };
+            let locale = preferred.or(accept_language).unwrap_or(Locale::En);
+            let body = error_body(res.response().error().unwrap(), locale);
+
+            let mut builder = HttpResponse::build(res.status());
+            for (name, value) in res.headers().iter().filter(|(x, _)| **x != CONTENT_TYPE && **x != CONTENT_LENGTH) {
+                builder.append_header((name.clone(), value.clone()));
+            }
+            let response = builder
+                .insert_header((CONTENT_LANGUAGE, locale.code()))
+                .json(body);
+            let (req, _) = res.into_parts();
+            Ok(ServiceResponse::new(req, response).map_into_right_body())
+        })
+    }
+}
@@ -0,0 +1,9 @@
+mod locale;
+mod catalog;
+mod errors;
+mod middleware;
+
+pub use locale::*;
+pub use catalog::message;
+pub use errors::*;
+pub use middleware::{localized_error, Localization};
@@ -0,0 +1,182 @@
+use actix_web::{error::ErrorInternalServerError, Error};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    transaction::{TransactionCollections, TransactionSettings},
+    AqlQuery, ClientError, Collection, Database, Document,
+};
+use chrono::{prelude::*, Duration};
+use rand::Rng;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::database::{current_database, DbPool};
+use crate::job::{Job, JobHandler, JobOptions, RETENTION_DAYS, RETRY_BASE_SECONDS};
+
+const LOCK_TIMEOUT: usize = 10;
+
+// queues the job in the database of the current tenant, returns its key
+#[instrument(skip_all)]
+pub async fn enqueue<H: JobHandler>(
+    payload: &H::Payload,
+    options: JobOptions,
+    pool: &DbPool,
+) -> Result<String, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let now = Utc::now();
+    let data = Job {
+        kind: H::KIND.to_string(),
+        payload: to_value(payload).map_err(ErrorInternalServerError)?,
+        status: "queued".to_string(),
+        priority: options.priority,
+        attempts: 0,
+        max_attempts: H::MAX_ATTEMPTS,
+        lease_seconds: H::LEASE_SECONDS,
+        run_at: (now + Duration::seconds(options.delay_seconds)).timestamp(),
+        lease_expires_at: None,
+        lease_token: None,
+        last_error: None,
+        created_at: now,
+        finished_at: None,
+        expires_at: None,
+    };
+    let collection: Collection<ReqwestClient> = db.collection("jobs").await.unwrap();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<Job>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(res.header().unwrap()._key.clone())
+}
+
+// the exclusive lock serializes claims, so two workers never lease the same job
+#[instrument(skip_all)]
+pub async fn claim_job(
+    db: &Database<ReqwestClient>,
+    kinds: &[&str],
+) -> Result<Option<Document<Job>>, ClientError> {
+    let settings = TransactionSettings::builder()
+        .collections(
+            TransactionCollections::builder()
+                .write(vec![])
+                .exclusive(vec!["jobs".to_string()])
+                .build(),
+        )
+        .lock_timeout(LOCK_TIMEOUT)
+        .build();
+    let tx = db.begin_transaction(settings).await?;
+    let now = Utc::now();
+
+    // jobs whose worker died on their last attempt are not reclaimed but dead lettered
+    let q = r#"FOR j IN jobs
+        FILTER j.status == 'running' AND j.lease_expires_at <= @now AND j.attempts >= j.max_attempts
+        UPDATE j WITH {
+            status: 'dead',
+            lease_expires_at: null,
+            lease_token: null,
+            last_error: 'lease expired',
+            finished_at: @finished_at,
+            expires_at: @expires_at
+        } IN jobs OPTIONS { keepNull: true }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("now", to_value(now.timestamp()).unwrap());
+    vars.insert("finished_at", to_value(now).unwrap());
+    vars.insert("expires_at", to_value((now + Duration::days(RETENTION_DAYS)).timestamp()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    if let Err(e) = tx.aql_query::<Value>(aql).await {
+        tx.abort().await.ok();
+        return Err(e);
+    }
+
+    let q = r#"FOR j IN jobs
+        FILTER (j.status == 'queued' AND j.run_at <= @now)
+            OR (j.status == 'running' AND j.lease_expires_at <= @now AND j.attempts < j.max_attempts)
+        FILTER j.kind IN @kinds
+        SORT j.priority DESC, j.run_at ASC
+        LIMIT 1
+        UPDATE j WITH {
+            status: 'running',
+            attempts: j.attempts + 1,
+            lease_expires_at: @now + j.lease_seconds,
+            lease_token: @token
+        } IN jobs
+        RETURN NEW"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("now", to_value(now.timestamp()).unwrap());
+    vars.insert("kinds", to_value(kinds).unwrap());
+    vars.insert("token", to_value(Uuid::new_v4().to_string()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<Document<Job>> = match tx.aql_query(aql).await {
+        Ok(x) => x,
+        Err(e) => {
+            tx.abort().await.ok();
+            return Err(e);
+        },
+    };
+    tx.commit().await?;
+    Ok(records.pop())
+}
+
+// exponential, with jitter so that jobs failing together do not retry together
+fn retry_at(attempts: u32) -> i64 {
+    let exponent = attempts.saturating_sub(1).min(16);
+    let delay = RETRY_BASE_SECONDS * 2i64.pow(exponent);
+    let jitter = rand::thread_rng().gen_range(0..=delay / 5);
+    (Utc::now() + Duration::seconds(delay + jitter)).timestamp()
+}
+
+// records how the run went, unless the lease was lost to another worker in the meantime
+#[instrument(skip_all)]
+pub async fn finish_job(
+    db: &Database<ReqwestClient>,
+    job: &Document<Job>,
+    result: Result<(), String>,
+) -> Result<(), ClientError> {
+    let now = Utc::now();
+    let finished = result.is_ok() || job.attempts >= job.max_attempts;
+    let status = match &result {
+        Ok(_) => "succeeded",
+        Err(_) if finished => "dead",
+        Err(_) => "queued",
+    };
+
+    let q = r#"FOR j IN jobs
+        FILTER j._key == @key AND j.lease_token == @token
+        UPDATE j WITH {
+            status: @status,
+            run_at: @run_at,
+            lease_expires_at: null,
+            lease_token: null,
+            last_error: @error,
+            finished_at: @finished_at,
+            expires_at: @expires_at
+        } IN jobs OPTIONS { keepNull: true }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(&job.header._key).unwrap());
+    vars.insert("token", to_value(&job.lease_token).unwrap());
+    vars.insert("status", to_value(status).unwrap());
+    vars.insert("run_at", to_value(if finished { job.run_at } else { retry_at(job.attempts) }).unwrap());
+    vars.insert("error", to_value(result.err()).unwrap());
+    vars.insert("finished_at", to_value(if finished { Some(now) } else { None }).unwrap());
+    vars.insert("expires_at", to_value(if finished { Some((now + Duration::days(RETENTION_DAYS)).timestamp()) } else { None }).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await?;
+    Ok(())
+}
@@ -0,0 +1,53 @@
+use async_trait::async_trait;
+use futures::future::LocalBoxFuture;
+use serde::{de::DeserializeOwned, Serialize};
+use serde_json::{from_value, Value};
+use std::collections::HashMap;
+
+use crate::database::DbPool;
+use crate::job::{DEFAULT_LEASE_SECONDS, DEFAULT_MAX_ATTEMPTS};
+
+// a kind of job with a typed payload, errors are retried with backoff
+#[async_trait(?Send)]
+pub trait JobHandler: 'static {
+    const KIND: &'static str;
+    const MAX_ATTEMPTS: u32 = DEFAULT_MAX_ATTEMPTS;
+    const LEASE_SECONDS: i64 = DEFAULT_LEASE_SECONDS; // how long a run may take before another worker retries it
+
+    type Payload: Serialize + DeserializeOwned;
+
+    async fn run(payload: Self::Payload, pool: &DbPool) -> Result<(), String>;
+}
+
+type Runner = Box<dyn Fn(Value, DbPool) -> LocalBoxFuture<'static, Result<(), String>>>;
+
+// handlers known to the workers of this instance, jobs of other kinds are left to other instances
+#[derive(Default)]
+pub struct JobRegistry {
+    runners: HashMap<&'static str, Runner>,
+}
+
+impl JobRegistry {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn register<H: JobHandler>(&mut self) {
+        let runner: Runner = Box::new(|payload, pool| Box::pin(async move {
+            let payload: H::Payload = from_value(payload).map_err(|e| format!("wrong payload: {}", e))?;
+            H::run(payload, &pool).await
+        }));
+        self.runners.insert(H::KIND, runner);
+    }
+
+    pub fn kinds(&self) -> Vec<&'static str> {
+        self.runners.keys().copied().collect()
+    }
+
+    pub async fn run(&self, kind: &str, payload: Value, pool: DbPool) -> Result<(), String> {
+        match self.runners.get(kind) {
+            Some(runner) => runner(payload, pool).await,
+            None => Err(format!("no handler for {}", kind)),
+        }
+    }
+}
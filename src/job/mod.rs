@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod handler;
+mod worker;
+
+pub use models::*;
+pub use controllers::*;
+pub use handler::{JobHandler, JobRegistry};
+pub use worker::start_workers;
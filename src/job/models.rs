@@ -0,0 +1,34 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const DEFAULT_MAX_ATTEMPTS: u32 = 5;
+pub const DEFAULT_LEASE_SECONDS: i64 = 300;
+pub const RETRY_BASE_SECONDS: i64 = 10;
+pub const RETENTION_DAYS: i64 = 7; // finished jobs are kept this long for inspection
+
+// queued -> running -> succeeded, or back to queued until the attempts run out and it is dead
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Job {
+    pub kind: String,
+    pub payload: Value,
+    pub status: String,
+    pub priority: i32, // higher runs first
+    pub attempts: u32,
+    pub max_attempts: u32,
+    pub lease_seconds: i64, // visibility timeout, a running job whose lease ran out is claimed again
+    pub run_at: i64, // unix seconds, not claimed before
+    pub lease_expires_at: Option<i64>,
+    pub lease_token: Option<String>, // identifies the claim, so a late worker cannot finish a reclaimed job
+    pub last_error: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub expires_at: Option<i64>, // ttl index removes finished jobs
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct JobOptions {
+    pub priority: i32,
+    pub delay_seconds: i64,
+}
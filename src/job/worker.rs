@@ -0,0 +1,61 @@
+use arangors::ClientError;
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::database::{current_database, with_database, DbPool};
+use crate::job::{claim_job, finish_job, JobRegistry};
+use crate::tenant::tenant_databases;
+
+const POLL_INTERVAL_SECONDS: u64 = 2;
+
+// runs one job of the current database, false when there was none
+async fn work_one(
+    pool: &DbPool,
+    registry: &JobRegistry,
+    kinds: &[&str],
+) -> Result<bool, ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await?;
+
+    let job = match claim_job(&db, kinds).await? {
+        Some(x) => x,
+        None => return Ok(false),
+    };
+    let result = registry.run(&job.kind, job.payload.clone(), pool.clone()).await;
+    if let Err(e) = &result {
+        tracing::warn!(job = %job.header._key, kind = %job.kind, attempt = job.attempts, error = %e, "job failed");
+    }
+    finish_job(&db, &job, result).await?;
+    Ok(true)
+}
+
+// each worker goes through every database and only sleeps once none of them had work
+pub fn start_workers(pool: DbPool, registry: Rc<JobRegistry>, workers: usize) {
+    let instance = Uuid::new_v4().to_simple().to_string();
+    for n in 0..workers {
+        let pool = pool.clone();
+        let registry = Rc::clone(&registry);
+        let worker = format!("{}-{}", &instance[..8], n);
+        actix_web::rt::spawn(async move {
+            let kinds = registry.kinds();
+            loop {
+                let mut idle = true;
+                match tenant_databases(&pool).await {
+                    Ok(databases) => {
+                        for database in databases {
+                            match with_database(database.clone(), work_one(&pool, &registry, &kinds)).await {
+                                Ok(true) => idle = false,
+                                Ok(false) => {},
+                                Err(e) => tracing::error!(worker = %worker, database = %database, error = %e, "job worker failed"),
+                            }
+                        }
+                    },
+                    Err(e) => tracing::error!(worker = %worker, error = %e, "job worker cannot list databases"),
+                }
+                if idle {
+                    actix_web::rt::time::sleep(std::time::Duration::from_secs(POLL_INTERVAL_SECONDS)).await;
+                }
+            }
+        });
+    }
+}
@@ -0,0 +1,19 @@
+mod middleware;
+
+use tracing_subscriber::{fmt::format::FmtSpan, EnvFilter};
+
+use crate::config::log_filter;
+
+pub use middleware::RequestId;
+
+// one json object per line, every event carries the spans it happened in so that
+// an arangodb call can be traced back to its controller and request id
+pub fn init() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::new(log_filter()))
+        .with_span_events(FmtSpan::CLOSE)
+        .with_current_span(true)
+        .with_span_list(true)
+        .init();
+}
@@ -0,0 +1,56 @@
+use async_trait::async_trait;
+use lettre::{
+    transport::smtp::{authentication::Credentials, Error},
+    AsyncSmtpTransport,
+    AsyncTransport,
+    Message,
+    Tokio1Executor,
+};
+use serde::{Deserialize, Serialize};
+
+use crate::config::{mail_from, mail_host, mail_password, mail_port, mail_username};
+use crate::database::DbPool;
+use crate::job::JobHandler;
+
+pub async fn send_mail(
+    to: &str,
+    subject: &str,
+    body: String,
+) -> Result<(), Error> {
+    let email = Message::builder()
+        .from(mail_from().parse().unwrap())
+        .to(to.parse().unwrap())
+        .subject(subject)
+        .body(body)
+        .unwrap();
+
+    let transport: AsyncSmtpTransport<Tokio1Executor> = AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(&mail_host())?
+        .port(mail_port().parse().unwrap())
+        .credentials(Credentials::new(mail_username(), mail_password()))
+        .build();
+    transport.send(email).await?;
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MailPayload {
+    pub to: String,
+    pub subject: String,
+    pub body: String,
+}
+
+// mail sent through the job queue, so a relay outage is retried instead of lost
+pub struct SendMail;
+
+#[async_trait(?Send)]
+impl JobHandler for SendMail {
+    const KIND: &'static str = "send_mail";
+    const MAX_ATTEMPTS: u32 = 8;
+
+    type Payload = MailPayload;
+
+    async fn run(payload: MailPayload, _pool: &DbPool) -> Result<(), String> {
+        send_mail(&payload.to, &payload.subject, payload.body).await
+            .map_err(|e| e.to_string())
+    }
+}
@@ -1,36 +1,120 @@
-use actix_web::{middleware, web, App, HttpServer};
+use actix_web::{web, App, HttpServer};
 use dotenv::dotenv;
-use std::time::Duration;
+use std::{env, rc::Rc, time::Duration};
 
 mod config;
+mod logging;
 mod database;
+mod mailer;
+mod migrations;
+mod i18n;
+mod storage;
+mod auth;
+mod api_key;
 mod company;
 mod user;
+mod tenant;
+mod webhook;
+mod comment;
+mod tag;
+mod custom_field;
+mod event;
+mod availability;
+mod resource;
+mod timesheet;
+mod announcement;
+mod wiki;
+mod graphql;
+mod contact;
+mod dav;
+mod job;
+mod schedule;
+mod message;
+mod metrics;
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
 
-    println!("Hello, world!");
+    logging::init();
 
+    let admin = migrations::admin_connection().await.expect("admin connection failed");
+    migrations::provision_user(&admin).await.expect("database user provisioning failed");
     let pool = database::init_pool();
+    migrations::run(&pool, &config::db_database()).await.expect("migrations failed");
+    if tenant::tenancy_enabled() {
+        tenant::migrate_tenants(&pool).await.expect("tenant migrations failed");
+    }
 
+    // one-off commands run instead of the server
+    let args: Vec<String> = env::args().collect();
+    if args.get(1).map(String::as_str) == Some("import-mail") {
+        if let Err(e) = message::run_import(&args[2..], &pool).await {
+            eprintln!("{}", e);
+            std::process::exit(1);
+        }
+        return Ok(());
+    }
+
+    webhook::start_dispatcher(pool.clone());
+    let mut jobs = job::JobRegistry::new();
+    jobs.register::<mailer::SendMail>();
+    jobs.register::<schedule::PurgeTrash>();
+    jobs.register::<timesheet::TimesheetReminders>();
+    jobs.register::<timesheet::WeeklyTimeReports>();
+    jobs.register::<announcement::WeeklyDigest>();
+    let jobs = Rc::new(jobs);
+    job::start_workers(pool.clone(), jobs.clone(), config::job_workers().parse().unwrap());
+    let scheduler = schedule::Scheduler::new()
+        .add::<schedule::PurgeTrash>("purge-trash", "0 3 * * *", &())
+        .add::<timesheet::TimesheetReminders>("timesheet-reminders", "0 15 * * fri", &())
+        .add::<timesheet::WeeklyTimeReports>("time-reports", "0 6 * * mon", &())
+        .add::<announcement::WeeklyDigest>("weekly-digest", "0 7 * * mon", &());
+    schedule::start_scheduler(pool.clone(), scheduler, jobs);
+    metrics::start_collector(pool.clone());
+    let schema = graphql::build_schema();
+
+    let admin = web::Data::new(admin);
     let app = move || {
         App::new()
             .app_data(web::Data::new(pool.clone()))
-            .wrap(middleware::Logger::default())
+            .app_data(web::Data::new(schema.clone()))
+            .app_data(admin.clone())
+            .wrap(i18n::Localization)
+            .wrap(auth::Authentication)
+            .wrap(tenant::Tenancy)
+            .wrap(metrics::HttpMetrics)
+            .wrap(logging::RequestId)
             // .wrap(throttle)
+            .configure(dav::init)
+            .configure(metrics::init)
             .service(
-                web::scope("/api").service(
+                web::scope("/api").configure(graphql::init).service(
                     web::scope("/v1")
+                        .configure(auth::init)
+                        .configure(api_key::init)
                         .configure(company::init)
                         .configure(user::init)
+                        .configure(tenant::init)
+                        .configure(webhook::init)
+                        .configure(comment::init)
+                        .configure(tag::init)
+                        .configure(custom_field::init)
+                        .configure(event::init)
+                        .configure(availability::init)
+                        .configure(resource::init)
+                        .configure(timesheet::init)
+                        .configure(announcement::init)
+                        .configure(wiki::init)
+                        .configure(contact::init)
+                        .configure(message::init)
                 )
             )
     };
 
     // start http server
     let endpoint = format!("{}:{}", config::host(), config::port());
+    tracing::info!(endpoint = %endpoint, "starting server");
     HttpServer::new(app)
         .bind(endpoint)?
         .run()
@@ -0,0 +1,81 @@
+use arangors::AqlQuery;
+use serde_json::{to_value, Value};
+use std::{collections::HashMap, path::PathBuf};
+
+use crate::database::{current_database, with_database, DbPool};
+use crate::message::import_messages;
+
+const USAGE: &str = "usage: import-mail [--database <name>] <user email> <file or directory>...";
+
+async fn find_user_key(email: &str, pool: &DbPool) -> Option<String> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.ok()?;
+
+    let q = "FOR u IN users FILTER u.email == @email AND u.deleted_at == null LIMIT 1 RETURN u._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("email", to_value(email.trim().to_lowercase()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<String> = db.aql_query(aql).await.ok()?;
+    records.pop()
+}
+
+// directories are read one level deep, in name order so that archives split by year stay ordered
+async fn read_files(paths: &[String]) -> std::io::Result<Vec<(PathBuf, Vec<u8>)>> {
+    let mut files = vec![];
+    for path in paths {
+        let path = PathBuf::from(path);
+        if tokio::fs::metadata(&path).await?.is_dir() {
+            let mut entries = vec![];
+            let mut dir = tokio::fs::read_dir(&path).await?;
+            while let Some(entry) = dir.next_entry().await? {
+                if entry.file_type().await?.is_file() {
+                    entries.push(entry.path());
+                }
+            }
+            entries.sort();
+            for entry in entries {
+                let data = tokio::fs::read(&entry).await?;
+                files.push((entry, data));
+            }
+        } else {
+            let data = tokio::fs::read(&path).await?;
+            files.push((path, data));
+        }
+    }
+    Ok(files)
+}
+
+async fn import(email: &str, paths: &[String], pool: &DbPool) -> Result<(), String> {
+    let user_key = find_user_key(email, pool).await
+        .ok_or_else(|| format!("no user with email {}", email))?;
+    let files = read_files(paths).await.map_err(|e| e.to_string())?;
+    for (path, data) in files {
+        let result = import_messages(&user_key, &[data], pool).await
+            .map_err(|e| format!("{}: {}", path.display(), e))?;
+        println!(
+            "{}: {} imported, {} duplicates, {} attachments, {} new contacts",
+            path.display(), result.imported, result.duplicates, result.attachments, result.contacts,
+        );
+    }
+    Ok(())
+}
+
+// imports mail for a user from the command line, as in import-mail jane@example.com ~/Mail
+pub async fn run_import(args: &[String], pool: &DbPool) -> Result<(), String> {
+    let (database, args) = match args {
+        [flag, name, rest @ ..] if flag == "--database" => (Some(name.clone()), rest),
+        _ => (None, args),
+    };
+    let (email, paths) = match args {
+        [email, paths @ ..] if !paths.is_empty() => (email, paths),
+        _ => return Err(USAGE.to_string()),
+    };
+    match database {
+        Some(database) => with_database(database, import(email, paths, pool)).await,
+        None => import(email, paths, pool).await,
+    }
+}
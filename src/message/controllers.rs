@@ -0,0 +1,350 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use sha1::{Digest, Sha1};
+use std::{collections::HashMap, path::Path};
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::contact::{create_contact, ContactRequest};
+use crate::database::{current_database, DbPool};
+use crate::message::{
+    is_mbox,
+    parse_message,
+    split_mbox,
+    Address,
+    Attachment,
+    FindThreadsParams,
+    ImportResponse,
+    Message,
+    MessageResponse,
+    ParsedMessage,
+    ThreadResponse,
+};
+use crate::storage::store_file;
+
+const MESSAGE_TERMS: &str = "RETURN UNSET(m, '_id', '_rev', 'owner_key', 'references')";
+
+#[instrument(skip_all)]
+pub async fn find_threads(
+    owner_key: &str,
+    params: FindThreadsParams,
+    pool: &DbPool,
+) -> Result<Vec<ThreadResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut terms = vec![r#"FOR m IN messages FILTER m.owner_key == @owner_key
+        COLLECT thread_key = m.thread_key INTO g = { subject: m.subject, date: m.date, from: m.from }
+        LET first = FIRST(FOR x IN g SORT x.date ASC RETURN x)
+        LET last_date = MAX(g[*].date)"#];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+
+    if let Some(search) = params.search.as_deref().map(|x| x.trim().to_lowercase()) {
+        if !search.is_empty() {
+            terms.push(r#"FILTER CONTAINS(LOWER(first.subject), @search)
+                OR LENGTH(FOR x IN g FILTER CONTAINS(x.from.email, @search) OR CONTAINS(LOWER(x.from.name), @search) RETURN 1) > 0"#);
+            vars.insert("search", to_value(search).unwrap());
+        }
+    }
+    terms.push("SORT last_date DESC");
+    if let Some(limit) = params.limit {
+        terms.push("LIMIT 0, @limit");
+        vars.insert("limit", to_value(limit).unwrap());
+    }
+    terms.push(r#"RETURN {
+        thread_key,
+        subject: first.subject,
+        participants: UNIQUE(g[* FILTER CURRENT.from != null].from.email),
+        message_count: LENGTH(g),
+        first_date: first.date,
+        last_date
+    }"#);
+    let q = terms.join(" ");
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<ThreadResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// messages of the thread, oldest first
+#[instrument(skip_all)]
+pub async fn show_thread(
+    thread_key: &str,
+    owner_key: &str,
+    pool: &DbPool,
+) -> Result<Vec<MessageResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(r#"FOR m IN messages
+        FILTER m.owner_key == @owner_key AND m.thread_key == @thread_key
+        SORT m.date ASC
+        {}"#, MESSAGE_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+    vars.insert("thread_key", to_value(thread_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<MessageResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        return Err(ErrorNotFound("thread not found"));
+    }
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_message(
+    key: &str,
+    owner_key: &str,
+    pool: &DbPool,
+) -> Result<MessageResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!("FOR m IN messages FILTER m._key == @key AND m.owner_key == @owner_key {}", MESSAGE_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<MessageResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("message not found"))
+}
+
+// messages without an id get one derived from their content, so that importing them twice is noticed
+fn message_id_of(parsed: &ParsedMessage, raw: &[u8]) -> String {
+    parsed.message_id.clone().unwrap_or_else(|| {
+        let digest = Sha1::digest(raw);
+        let hex: String = digest.iter().map(|x| format!("{:02x}", x)).collect();
+        format!("<{}@import.invalid>", hex)
+    })
+}
+
+async fn message_exists(
+    db: &Database<ReqwestClient>,
+    owner_key: &str,
+    message_id: &str,
+) -> Result<bool, Error> {
+    let q = r#"FOR m IN messages
+        FILTER m.owner_key == @owner_key AND m.message_id == @message_id
+        LIMIT 1
+        RETURN 1"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+    vars.insert("message_id", to_value(message_id).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(!records.is_empty())
+}
+
+// joins the thread of the messages it refers to, or of replies imported before it
+async fn thread_key_for(
+    db: &Database<ReqwestClient>,
+    owner_key: &str,
+    message_id: &str,
+    related: &[String],
+) -> Result<String, Error> {
+    let q = r#"FOR m IN messages
+        FILTER m.owner_key == @owner_key
+        FILTER m.message_id IN @related OR m.in_reply_to == @message_id
+        SORT m.date ASC
+        RETURN DISTINCT m.thread_key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+    vars.insert("message_id", to_value(message_id).unwrap());
+    vars.insert("related", to_value(related).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut thread_keys: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if thread_keys.is_empty() {
+        return Ok(Uuid::new_v4().to_string());
+    }
+    let thread_key = thread_keys.remove(0);
+    if thread_keys.is_empty() {
+        return Ok(thread_key);
+    }
+
+    // this message links threads that were started apart, they become one
+    let q = r#"FOR m IN messages
+        FILTER m.owner_key == @owner_key AND m.thread_key IN @others
+        UPDATE m WITH { thread_key: @thread_key } IN messages"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+    vars.insert("others", to_value(&thread_keys).unwrap());
+    vars.insert("thread_key", to_value(&thread_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(thread_key)
+}
+
+// true when the sender was added to the address book
+async fn remember_sender(
+    db: &Database<ReqwestClient>,
+    owner_key: &str,
+    sender: &Address,
+    pool: &DbPool,
+) -> Result<bool, Error> {
+    let q = r#"LET owner = DOCUMENT('users', @owner_key)
+        RETURN owner.email == @email OR LENGTH(
+            FOR c IN contacts
+                FILTER c.owner_key == @owner_key AND @email IN c.emails
+                LIMIT 1
+                RETURN 1
+        ) > 0"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("owner_key", to_value(owner_key).unwrap());
+    vars.insert("email", to_value(&sender.email).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<bool> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.pop().unwrap_or(true) {
+        return Ok(false);
+    }
+
+    let name = sender.name.clone()
+        .filter(|x| !x.trim().is_empty())
+        .unwrap_or_else(|| sender.email.split('@').next().unwrap_or_default().to_string());
+    let payload = ContactRequest {
+        name: name.chars().take(200).collect(),
+        emails: Some(vec![sender.email.clone()]),
+        phones: None,
+        organization: None,
+        note: Some("Added by the mail import".to_string()),
+        custom: None,
+    };
+    create_contact(owner_key, &payload, pool).await?;
+    Ok(true)
+}
+
+async fn import_message(
+    db: &Database<ReqwestClient>,
+    owner_key: &str,
+    raw: &[u8],
+    result: &mut ImportResponse,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let parsed = parse_message(raw);
+    let message_id = message_id_of(&parsed, raw);
+    if message_exists(db, owner_key, &message_id).await? {
+        result.duplicates += 1;
+        return Ok(());
+    }
+
+    let mut related = parsed.references.clone();
+    related.extend(parsed.in_reply_to.clone());
+    let thread_key = thread_key_for(db, owner_key, &message_id, &related).await?;
+
+    let mut attachments = vec![];
+    for attachment in &parsed.attachments {
+        let extension = Path::new(&attachment.filename)
+            .extension()
+            .and_then(|x| x.to_str())
+            .filter(|x| x.chars().all(|c| c.is_ascii_alphanumeric()))
+            .unwrap_or("bin");
+        let path = store_file(extension, &attachment.data).await
+            .map_err(ErrorInternalServerError)?;
+        attachments.push(Attachment {
+            filename: attachment.filename.clone(),
+            content_type: attachment.content_type.clone(),
+            size: attachment.data.len(),
+            path,
+        });
+    }
+    result.attachments += attachments.len() as u32;
+
+    if let Some(sender) = &parsed.from {
+        if remember_sender(db, owner_key, sender, pool).await? {
+            result.contacts += 1;
+        }
+    }
+
+    let now = Utc::now();
+    let data = Message {
+        owner_key: owner_key.to_string(),
+        thread_key,
+        message_id,
+        in_reply_to: parsed.in_reply_to,
+        references: parsed.references,
+        subject: parsed.subject.unwrap_or_default(),
+        from: parsed.from,
+        to: parsed.to,
+        cc: parsed.cc,
+        date: parsed.date.unwrap_or(now),
+        text: parsed.text,
+        html: parsed.html.map(|x| ammonia::clean(&x)),
+        attachments,
+        imported_at: now,
+    };
+    let collection: Collection<ReqwestClient> = db.collection("messages").await.unwrap();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let _res: DocumentResponse<Document<Message>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    result.imported += 1;
+    Ok(())
+}
+
+// each file is either a single .eml message or an mbox archive
+#[instrument(skip_all)]
+pub async fn import_messages(
+    owner_key: &str,
+    files: &[Vec<u8>],
+    pool: &DbPool,
+) -> Result<ImportResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut result = ImportResponse::default();
+    for file in files {
+        if is_mbox(file) {
+            for raw in split_mbox(file) {
+                import_message(&db, owner_key, &raw, &mut result, pool).await?;
+            }
+        } else {
+            import_message(&db, owner_key, file, &mut result, pool).await?;
+        }
+    }
+    Ok(result)
+}
@@ -0,0 +1,74 @@
+// mbox archives, where every message starts with a "From " line
+
+pub fn is_mbox(raw: &[u8]) -> bool {
+    raw.starts_with(b"From ")
+}
+
+// lines quoted as >From (and >>From and so on) lose one level of quoting
+fn unquote_line(line: &[u8]) -> &[u8] {
+    let depth = line.iter().take_while(|&&x| x == b'>').count();
+    if depth > 0 && line[depth..].starts_with(b"From ") {
+        &line[1..]
+    } else {
+        line
+    }
+}
+
+pub fn split_mbox(raw: &[u8]) -> Vec<Vec<u8>> {
+    let mut messages = vec![];
+    let mut current: Option<Vec<u8>> = None;
+    let mut previous_blank = true;
+    for line in raw.split_inclusive(|&x| x == b'\n') {
+        if line.starts_with(b"From ") && previous_blank {
+            if let Some(message) = current.take() {
+                messages.push(message);
+            }
+            current = Some(vec![]);
+            previous_blank = false;
+            continue;
+        }
+        previous_blank = line == b"\n" || line == b"\r\n";
+        if let Some(message) = current.as_mut() {
+            message.extend_from_slice(unquote_line(line));
+        }
+    }
+    if let Some(message) = current {
+        messages.push(message);
+    }
+    messages
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn split_messages() {
+        let raw = b"From ann@example.com Mon Jan  4 10:00:00 2021\n\
+            Subject: one\n\
+            \n\
+            hello\n\
+            From here on, not after a blank line\n\
+            >From quoted\n\
+            >>From twice\n\
+            \n\
+            From bob@example.com Mon Jan  4 11:00:00 2021\n\
+            Subject: two\n\
+            \n\
+            body\n";
+        assert!(is_mbox(raw));
+        let messages = split_mbox(raw);
+        assert_eq!(messages.len(), 2);
+        assert_eq!(
+            String::from_utf8_lossy(&messages[0]),
+            "Subject: one\n\nhello\nFrom here on, not after a blank line\nFrom quoted\n>From twice\n\n",
+        );
+        assert_eq!(String::from_utf8_lossy(&messages[1]), "Subject: two\n\nbody\n");
+    }
+
+    #[test]
+    fn single_message() {
+        assert!(!is_mbox(b"Subject: hi\n\nbody\n"));
+        assert!(split_mbox(b"Subject: hi\n\nbody\n").is_empty());
+    }
+}
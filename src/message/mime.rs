@@ -0,0 +1,516 @@
+// internet messages (rfc 5322) with mime parts (rfc 2045, 2046, 2047, 2231)
+use chrono::prelude::*;
+
+use crate::message::Address;
+
+pub struct ParsedAttachment {
+    pub filename: String,
+    pub content_type: String,
+    pub data: Vec<u8>,
+}
+
+#[derive(Default)]
+pub struct ParsedMessage {
+    pub message_id: Option<String>,
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub subject: Option<String>,
+    pub from: Option<Address>,
+    pub to: Vec<Address>,
+    pub cc: Vec<Address>,
+    pub date: Option<DateTime<Utc>>,
+    pub text: Option<String>,
+    pub html: Option<String>,
+    pub attachments: Vec<ParsedAttachment>,
+}
+
+struct ContentType {
+    mime: String, // lower case, as in text/plain
+    params: Vec<(String, String)>,
+}
+
+impl ContentType {
+    fn param(&self, name: &str) -> Option<&str> {
+        self.params.iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, y)| y.as_str())
+    }
+}
+
+// headers end at the first empty line
+fn split_head(raw: &[u8]) -> (&[u8], &[u8]) {
+    let mut start = 0;
+    while start < raw.len() {
+        let end = raw[start..].iter().position(|&x| x == b'\n').map(|x| start + x).unwrap_or(raw.len());
+        let line = &raw[start..end];
+        if line.is_empty() || line == b"\r" {
+            return (&raw[..start], &raw[(end + 1).min(raw.len())..]);
+        }
+        start = end + 1;
+    }
+    (raw, &[])
+}
+
+// unfolded headers with lower case names, in order of appearance
+fn parse_headers(head: &[u8]) -> Vec<(String, String)> {
+    let text = String::from_utf8_lossy(head);
+    let mut headers: Vec<(String, String)> = vec![];
+    for line in text.lines() {
+        if line.starts_with(' ') || line.starts_with('\t') {
+            if let Some((_, value)) = headers.last_mut() {
+                value.push(' ');
+                value.push_str(line.trim());
+            }
+        } else if let Some((name, value)) = line.split_once(':') {
+            headers.push((name.trim().to_lowercase(), value.trim().to_string()));
+        }
+    }
+    headers
+}
+
+fn header<'a>(headers: &'a [(String, String)], name: &str) -> Option<&'a str> {
+    headers.iter()
+        .find(|(x, _)| x == name)
+        .map(|(_, y)| y.as_str())
+}
+
+// latin-1 and windows-1252 are told apart only by characters nobody uses in mail
+fn decode_charset(data: &[u8], charset: &str) -> String {
+    match charset.to_lowercase().as_str() {
+        "iso-8859-1" | "latin1" | "windows-1252" | "cp1252" => data.iter().map(|&x| x as char).collect(),
+        _ => String::from_utf8_lossy(data).to_string(),
+    }
+}
+
+fn hex_value(c: u8) -> Option<u8> {
+    (c as char).to_digit(16).map(|x| x as u8)
+}
+
+// in headers an underscore stands for a space
+fn decode_quoted_printable(data: &[u8], header: bool) -> Vec<u8> {
+    let mut out = Vec::with_capacity(data.len());
+    let mut i = 0;
+    while i < data.len() {
+        match data[i] {
+            // soft line breaks
+            b'=' if data[i + 1..].starts_with(b"\r\n") => i += 3,
+            b'=' if data[i + 1..].starts_with(b"\n") => i += 2,
+            b'=' => match (data.get(i + 1).and_then(|&x| hex_value(x)), data.get(i + 2).and_then(|&x| hex_value(x))) {
+                (Some(high), Some(low)) => {
+                    out.push(high << 4 | low);
+                    i += 3;
+                },
+                _ => {
+                    out.push(b'=');
+                    i += 1;
+                },
+            },
+            b'_' if header => {
+                out.push(b' ');
+                i += 1;
+            },
+            x => {
+                out.push(x);
+                i += 1;
+            },
+        }
+    }
+    out
+}
+
+fn decode_base64(data: &[u8]) -> Vec<u8> {
+    let clean: Vec<u8> = data.iter().copied().filter(|x| !x.is_ascii_whitespace()).collect();
+    base64::decode(&clean).unwrap_or_default()
+}
+
+fn decode_word(word: &str) -> Option<String> {
+    let inner = word.strip_prefix("=?")?.strip_suffix("?=")?;
+    let mut parts = inner.splitn(3, '?');
+    let charset = parts.next()?.split('*').next()?; // drops the language of rfc 2231
+    let encoding = parts.next()?;
+    let text = parts.next()?;
+    let data = match encoding {
+        "B" | "b" => decode_base64(text.as_bytes()),
+        "Q" | "q" => decode_quoted_printable(text.as_bytes(), true),
+        _ => return None,
+    };
+    Some(decode_charset(&data, charset))
+}
+
+// encoded words of rfc 2047, the space between two of them is not part of the text
+pub fn decode_header(value: &str) -> String {
+    let mut out = String::new();
+    let mut pending_space = String::new();
+    let mut previous_encoded = false;
+    for token in value.split_inclusive(char::is_whitespace) {
+        let word = token.trim_end();
+        let space = &token[word.len()..];
+        match decode_word(word) {
+            Some(decoded) => {
+                if !previous_encoded {
+                    out.push_str(&pending_space);
+                }
+                out.push_str(&decoded);
+                previous_encoded = true;
+            },
+            None => {
+                out.push_str(&pending_space);
+                out.push_str(word);
+                previous_encoded = false;
+            },
+        }
+        pending_space = space.to_string();
+    }
+    out
+}
+
+// splits on the separator outside of quotes, angle brackets and comments
+fn split_outside(value: &str, separator: char) -> Vec<&str> {
+    let mut parts = vec![];
+    let mut quoted = false;
+    let mut depth = 0;
+    let mut start = 0;
+    let mut escaped = false;
+    for (i, c) in value.char_indices() {
+        match c {
+            _ if escaped => escaped = false,
+            '\\' if quoted => escaped = true,
+            '"' => quoted = !quoted,
+            '<' | '(' if !quoted => depth += 1,
+            '>' | ')' if !quoted && depth > 0 => depth -= 1,
+            _ if c == separator && !quoted && depth == 0 => {
+                parts.push(&value[start..i]);
+                start = i + c.len_utf8();
+            },
+            _ => {},
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+fn percent_decode(value: &str) -> Vec<u8> {
+    let bytes = value.as_bytes();
+    let mut out = Vec::with_capacity(bytes.len());
+    let mut i = 0;
+    while i < bytes.len() {
+        match (bytes[i], bytes.get(i + 1).and_then(|&x| hex_value(x)), bytes.get(i + 2).and_then(|&x| hex_value(x))) {
+            (b'%', Some(high), Some(low)) => {
+                out.push(high << 4 | low);
+                i += 3;
+            },
+            (x, _, _) => {
+                out.push(x);
+                i += 1;
+            },
+        }
+    }
+    out
+}
+
+// parameters of content-type and content-disposition, names in lower case
+fn parse_params(value: &str) -> (String, Vec<(String, String)>) {
+    let parts = split_outside(value, ';');
+    let mut params: Vec<(String, String)> = vec![];
+    for part in &parts[1..] {
+        let (name, value) = match part.split_once('=') {
+            Some(x) => x,
+            None => continue,
+        };
+        let name = name.trim().to_lowercase();
+        let value = value.trim().trim_matches('"').to_string();
+        match name.strip_suffix('*') {
+            // extended value, as in filename*=utf-8''na%C3%AFve.txt
+            Some(name) => {
+                let decoded = match value.splitn(3, '\'').collect::<Vec<&str>>()[..] {
+                    [charset, _, text] => decode_charset(&percent_decode(text), charset),
+                    _ => value,
+                };
+                params.retain(|(x, _)| x != name);
+                params.push((name.to_string(), decoded));
+            },
+            None if !params.iter().any(|(x, _)| *x == name) => params.push((name, decode_header(&value))),
+            None => {},
+        }
+    }
+    (parts[0].trim().to_lowercase(), params)
+}
+
+fn content_type(headers: &[(String, String)], default: &str) -> ContentType {
+    let (mime, params) = parse_params(header(headers, "content-type").unwrap_or(default));
+    ContentType {
+        mime: if mime.contains('/') { mime } else { default.to_string() },
+        params,
+    }
+}
+
+fn decode_body(headers: &[(String, String)], body: &[u8]) -> Vec<u8> {
+    match header(headers, "content-transfer-encoding").map(|x| x.trim().to_lowercase()).as_deref() {
+        Some("base64") => decode_base64(body),
+        Some("quoted-printable") => decode_quoted_printable(body, false),
+        _ => body.to_vec(),
+    }
+}
+
+// parts between the boundary lines, preamble and epilogue are dropped
+fn split_multipart<'a>(body: &'a [u8], boundary: &str) -> Vec<&'a [u8]> {
+    let delimiter = format!("--{}", boundary);
+    let mut parts = vec![];
+    let mut start: Option<usize> = None;
+    let mut position = 0;
+    while position < body.len() {
+        let end = body[position..].iter().position(|&x| x == b'\n').map(|x| position + x).unwrap_or(body.len());
+        let line = body[position..end].strip_suffix(b"\r").unwrap_or(&body[position..end]);
+        if line.starts_with(delimiter.as_bytes()) {
+            let rest = &line[delimiter.len()..];
+            let closing = rest.starts_with(b"--");
+            if closing || rest.iter().all(|x| x.is_ascii_whitespace()) {
+                if let Some(start) = start {
+                    // the line break before the delimiter belongs to it
+                    let mut part_end = position;
+                    if part_end > start && body[part_end - 1] == b'\n' {
+                        part_end -= 1;
+                    }
+                    if part_end > start && body[part_end - 1] == b'\r' {
+                        part_end -= 1;
+                    }
+                    parts.push(&body[start..part_end]);
+                }
+                if closing {
+                    return parts;
+                }
+                start = Some(end + 1);
+            }
+        }
+        position = end + 1;
+    }
+    if let Some(start) = start {
+        if start < body.len() {
+            parts.push(&body[start..]);
+        }
+    }
+    parts
+}
+
+fn extension_of(mime: &str) -> &'static str {
+    match mime {
+        "message/rfc822" => "eml",
+        "text/plain" => "txt",
+        "text/html" => "html",
+        "text/calendar" => "ics",
+        "application/pdf" => "pdf",
+        "image/png" => "png",
+        "image/jpeg" => "jpg",
+        "image/gif" => "gif",
+        _ => "bin",
+    }
+}
+
+fn walk(raw: &[u8], default_type: &str, message: &mut ParsedMessage) {
+    let (head, body) = split_head(raw);
+    let headers = parse_headers(head);
+    let content_type = content_type(&headers, default_type);
+
+    if content_type.mime.starts_with("multipart/") {
+        if let Some(boundary) = content_type.param("boundary") {
+            // parts of a digest are messages unless they say otherwise
+            let default = if content_type.mime == "multipart/digest" { "message/rfc822" } else { "text/plain" };
+            for part in split_multipart(body, boundary) {
+                walk(part, default, message);
+            }
+            return;
+        }
+    }
+
+    let (disposition, disposition_params) = header(&headers, "content-disposition")
+        .map(parse_params)
+        .unwrap_or_default();
+    let filename = disposition_params.iter()
+        .find(|(x, _)| x == "filename")
+        .map(|(_, y)| y.clone())
+        .or_else(|| content_type.param("name").map(|x| x.to_string()));
+    let data = decode_body(&headers, body);
+
+    let inline_text = disposition != "attachment" && filename.is_none();
+    match content_type.mime.as_str() {
+        "text/plain" if inline_text && message.text.is_none() => {
+            message.text = Some(decode_charset(&data, content_type.param("charset").unwrap_or("utf-8")));
+        },
+        "text/html" if inline_text && message.html.is_none() => {
+            message.html = Some(decode_charset(&data, content_type.param("charset").unwrap_or("utf-8")));
+        },
+        mime => {
+            let filename = filename.unwrap_or_else(|| {
+                format!("part{}.{}", message.attachments.len() + 1, extension_of(mime))
+            });
+            message.attachments.push(ParsedAttachment {
+                filename,
+                content_type: mime.to_string(),
+                data,
+            });
+        },
+    }
+}
+
+// drops the quotes of quoted strings and the backslashes escaping characters inside them
+fn unquote(value: &str) -> String {
+    let mut out = String::with_capacity(value.len());
+    let mut escaped = false;
+    for c in value.chars() {
+        match c {
+            _ if escaped => {
+                out.push(c);
+                escaped = false;
+            },
+            '\\' => escaped = true,
+            '"' => {},
+            _ => out.push(c),
+        }
+    }
+    out
+}
+
+pub fn parse_address(value: &str) -> Option<Address> {
+    let value = value.trim();
+    let (name, email) = match (value.rfind('<'), value.rfind('>')) {
+        (Some(open), Some(close)) if open < close => (&value[..open], &value[open + 1..close]),
+        _ => ("", value.split('(').next().unwrap_or_default()),
+    };
+    let email = email.trim().to_lowercase();
+    if !email.contains('@') {
+        return None;
+    }
+    let name = decode_header(&unquote(name.trim()));
+    let name = name.trim();
+    Some(Address {
+        name: if name.is_empty() { None } else { Some(name.to_string()) },
+        email,
+    })
+}
+
+pub fn parse_addresses(value: &str) -> Vec<Address> {
+    split_outside(value, ',').iter()
+        .filter_map(|x| parse_address(x))
+        .collect()
+}
+
+// message ids keep their angle brackets, which makes them unique across formats
+fn parse_ids(value: &str) -> Vec<String> {
+    value.split('<')
+        .skip(1)
+        .filter_map(|x| x.split_once('>'))
+        .map(|(id, _)| format!("<{}>", id.trim()))
+        .collect()
+}
+
+fn parse_date(value: &str) -> Option<DateTime<Utc>> {
+    // a trailing comment such as (UTC) is not part of rfc 2822 dates in chrono
+    let value = value.split('(').next().unwrap_or_default().trim();
+    DateTime::parse_from_rfc2822(value).ok().map(|x| x.with_timezone(&Utc))
+}
+
+pub fn parse_message(raw: &[u8]) -> ParsedMessage {
+    let (head, _) = split_head(raw);
+    let headers = parse_headers(head);
+
+    let mut message = ParsedMessage {
+        message_id: header(&headers, "message-id").and_then(|x| parse_ids(x).pop()),
+        in_reply_to: header(&headers, "in-reply-to").and_then(|x| parse_ids(x).pop()),
+        references: header(&headers, "references").map(parse_ids).unwrap_or_default(),
+        subject: header(&headers, "subject").map(decode_header),
+        from: header(&headers, "from").and_then(|x| parse_addresses(x).into_iter().next()),
+        to: header(&headers, "to").map(parse_addresses).unwrap_or_default(),
+        cc: header(&headers, "cc").map(parse_addresses).unwrap_or_default(),
+        date: header(&headers, "date").and_then(parse_date),
+        ..Default::default()
+    };
+    walk(raw, "text/plain", &mut message);
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encoded_words() {
+        assert_eq!(decode_header("=?UTF-8?Q?Gr=C3=BC=C3=9Fe_aus?= =?utf-8?b?Y2Fmw6k=?= Berlin"), "Grüße auscafé Berlin");
+        assert_eq!(decode_header("=?iso-8859-1?q?caf=E9?="), "café");
+        assert_eq!(decode_header("plain  text"), "plain  text");
+    }
+
+    #[test]
+    fn addresses() {
+        let addresses = parse_addresses(r#""Doe, Ann" <Ann@Example.com>, bob@example.com (Bob), =?utf-8?q?J=C3=BCrgen?= <j@example.com>, nobody"#);
+        let found: Vec<(Option<&str>, &str)> = addresses.iter().map(|x| (x.name.as_deref(), x.email.as_str())).collect();
+        assert_eq!(found, vec![
+            (Some("Doe, Ann"), "ann@example.com"),
+            (None, "bob@example.com"),
+            (Some("Jürgen"), "j@example.com"),
+        ]);
+    }
+
+    #[test]
+    fn headers_of_a_message() {
+        let raw = b"Message-ID: <1@example.com>\r\n\
+            In-Reply-To: <0@example.com>\r\n\
+            References: <a@example.com>\r\n <0@example.com>\r\n\
+            Subject: folded\r\n subject\r\n\
+            From: Ann <ann@example.com>\r\n\
+            Date: Mon, 4 Jan 2021 10:00:00 +0100 (CET)\r\n\
+            \r\n\
+            hello\r\n";
+        let message = parse_message(raw);
+        assert_eq!(message.message_id.as_deref(), Some("<1@example.com>"));
+        assert_eq!(message.in_reply_to.as_deref(), Some("<0@example.com>"));
+        assert_eq!(message.references, vec!["<a@example.com>", "<0@example.com>"]);
+        assert_eq!(message.subject.as_deref(), Some("folded subject"));
+        assert_eq!(message.from.unwrap().email, "ann@example.com");
+        assert_eq!(message.date, "2021-01-04T09:00:00Z".parse().ok());
+        assert_eq!(message.text.as_deref(), Some("hello\r\n"));
+    }
+
+    #[test]
+    fn multipart_message() {
+        let raw = b"Content-Type: multipart/mixed; boundary=\"outer\"\n\
+            \n\
+            preamble\n\
+            --outer\n\
+            Content-Type: multipart/alternative; boundary=inner\n\
+            \n\
+            --inner\n\
+            Content-Type: text/plain; charset=utf-8\n\
+            Content-Transfer-Encoding: quoted-printable\n\
+            \n\
+            Gr=C3=BC=C3=9Fe, a long =\n\
+            line\n\
+            --inner\n\
+            Content-Type: text/html\n\
+            \n\
+            <p>hi</p>\n\
+            --inner--\n\
+            --outer\n\
+            Content-Type: application/pdf\n\
+            Content-Disposition: attachment; filename*=utf-8''na%C3%AFve.pdf\n\
+            Content-Transfer-Encoding: base64\n\
+            \n\
+            JVBE\n\
+            Rg==\n\
+            --outer\n\
+            Content-Type: image/png\n\
+            \n\
+            png\n\
+            --outer--\n\
+            epilogue\n";
+        let message = parse_message(raw);
+        assert_eq!(message.text.as_deref(), Some("Grüße, a long line"));
+        assert_eq!(message.html.as_deref(), Some("<p>hi</p>"));
+        let attachments: Vec<(&str, &str, &[u8])> = message.attachments.iter()
+            .map(|x| (x.filename.as_str(), x.content_type.as_str(), x.data.as_slice()))
+            .collect();
+        assert_eq!(attachments, vec![
+            ("naïve.pdf", "application/pdf", &b"%PDF"[..]),
+            ("part2.png", "image/png", &b"png"[..]),
+        ]);
+    }
+}
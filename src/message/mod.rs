@@ -0,0 +1,13 @@
+mod models;
+mod mime;
+mod mbox;
+mod controllers;
+mod routes;
+mod cli;
+
+pub use models::*;
+pub use mime::{parse_message, ParsedMessage};
+pub use mbox::{is_mbox, split_mbox};
+pub use controllers::*;
+pub use routes::init;
+pub use cli::run_import;
@@ -0,0 +1,85 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::Validate;
+
+pub const MAX_IMPORT_BYTES: usize = 256 * 1024 * 1024;
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindThreadsParams {
+    pub search: Option<String>, // matches subjects and senders
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Address {
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub name: Option<String>,
+    pub email: String, // lower case
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Attachment {
+    pub filename: String,
+    pub content_type: String,
+    pub size: usize,
+    pub path: String, // in the upload storage
+}
+
+// imported mail, messages answering each other share a thread key
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Message {
+    pub owner_key: String,
+    pub thread_key: String,
+    pub message_id: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub in_reply_to: Option<String>,
+    pub references: Vec<String>,
+    pub subject: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub from: Option<Address>,
+    pub to: Vec<Address>,
+    pub cc: Vec<Address>,
+    pub date: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub html: Option<String>,
+    pub attachments: Vec<Attachment>,
+    pub imported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct MessageResponse {
+    pub _key: String,
+    pub thread_key: String,
+    pub message_id: String,
+    pub in_reply_to: Option<String>,
+    pub subject: String,
+    pub from: Option<Address>,
+    pub to: Vec<Address>,
+    pub cc: Vec<Address>,
+    pub date: DateTime<Utc>,
+    pub text: Option<String>,
+    pub html: Option<String>, // sanitized when the message is imported
+    pub attachments: Vec<Attachment>,
+    pub imported_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ThreadResponse {
+    pub thread_key: String,
+    pub subject: String,
+    pub participants: Vec<String>,
+    pub message_count: u32,
+    pub first_date: DateTime<Utc>,
+    pub last_date: DateTime<Utc>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize)]
+pub struct ImportResponse {
+    pub imported: u32,
+    pub duplicates: u32, // already imported, found by message id
+    pub attachments: u32,
+    pub contacts: u32, // created for senders that were not in the address book
+}
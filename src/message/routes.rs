@@ -0,0 +1,97 @@
+use actix_multipart::Multipart;
+use actix_web::{error::{ErrorForbidden, ErrorPayloadTooLarge}, get, post, web, Error, HttpRequest, HttpResponse};
+use futures::TryStreamExt; // for try_next of Multipart
+use validator::Validate;
+
+use crate::auth;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::message::{self, FindThreadsParams, MAX_IMPORT_BYTES};
+
+// mailboxes belong to people, company keys have none
+fn require_user(req: &HttpRequest, scope: &str) -> Result<String, Error> {
+    let identity = auth::authorize(req, scope)?;
+    identity.user_key()
+        .map(|x| x.to_string())
+        .ok_or_else(|| ErrorForbidden("messages belong to users"))
+}
+
+// every field of the form is a file, .eml or mbox
+async fn accept_files(mut payload: Multipart) -> Result<Vec<Vec<u8>>, Error> {
+    let mut files = vec![];
+    let mut total = 0;
+    while let Some(mut field) = payload.try_next().await? {
+        let mut body = Vec::with_capacity(512);
+        // field data may be larger than 64KB or it may be on page boundary
+        while let Some(chunk) = field.try_next().await? {
+            total += chunk.len();
+            if total > MAX_IMPORT_BYTES {
+                return Err(ErrorPayloadTooLarge("archive is too large"));
+            }
+            body.extend_from_slice(&chunk);
+        }
+        files.push(body);
+    }
+    Ok(files)
+}
+
+#[get("/messages/threads")]
+async fn find_threads(
+    req: HttpRequest,
+    payload: web::Query<FindThreadsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "messages:read")?;
+    let params: FindThreadsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = message::find_threads(&user_key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/messages/threads/{key}")]
+async fn show_thread(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "messages:read")?;
+    let result = message::show_thread(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/messages/{key}")]
+async fn show(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "messages:read")?;
+    let result = message::show_message(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/messages/import")]
+async fn import(
+    req: HttpRequest,
+    payload: Multipart,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_user(&req, "messages:write")?;
+    let files = accept_files(payload).await?;
+    let result = message::import_messages(&user_key, &files, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find_threads);
+    cfg.service(show_thread);
+    cfg.service(show);
+    cfg.service(import);
+}
@@ -0,0 +1,42 @@
+use arangors::ClientError;
+
+use crate::config::metrics_refresh_seconds;
+use crate::database::{current_database, with_database, DbPool};
+use crate::metrics::{count_records, BusinessCounts, COMPANIES, USERS};
+use crate::tenant::tenant_databases;
+
+async fn collect(pool: &DbPool) -> Result<BusinessCounts, ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await?;
+    count_records(&db).await
+}
+
+// counting is too slow for every scrape, so the business gauges are refreshed in the background
+pub fn start_collector(pool: DbPool) {
+    let interval = metrics_refresh_seconds().parse().unwrap();
+
+    actix_web::rt::spawn(async move {
+        loop {
+            match tenant_databases(&pool).await {
+                Ok(databases) => {
+                    let mut counts = Vec::new();
+                    for database in databases {
+                        match with_database(database.clone(), collect(&pool)).await {
+                            Ok(x) => counts.push((database, x)),
+                            Err(e) => tracing::error!(database = %database, error = %e, "metrics collection failed"),
+                        }
+                    }
+                    // databases of removed tenants drop out of the gauges
+                    COMPANIES.reset();
+                    USERS.reset();
+                    for (database, x) in counts {
+                        COMPANIES.with_label_values(&[&database]).set(x.companies);
+                        USERS.with_label_values(&[&database]).set(x.users);
+                    }
+                },
+                Err(e) => tracing::error!(error = %e, "metrics collector cannot list databases"),
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_secs(interval)).await;
+        }
+    });
+}
@@ -0,0 +1,47 @@
+use actix_web::{error::ErrorInternalServerError, Error};
+use arangors::{connection::ReqwestClient, AqlQuery, ClientError, Database};
+use prometheus::{Encoder, TextEncoder};
+
+use crate::database::DbPool;
+use crate::metrics::{
+    BusinessCounts,
+    POOL_IDLE,
+    POOL_IN_USE,
+    POOL_MAX_OPEN,
+    POOL_OPEN,
+    POOL_WAIT_COUNT,
+    POOL_WAIT_SECONDS,
+};
+
+// the pool keeps its own statistics, they are copied over on every scrape
+pub async fn record_pool(pool: &DbPool) {
+    let state = pool.state().await;
+    POOL_MAX_OPEN.set(state.max_open as i64);
+    POOL_OPEN.set(state.connections as i64);
+    POOL_IN_USE.set(state.in_use as i64);
+    POOL_IDLE.set(state.idle as i64);
+    POOL_WAIT_COUNT.set(state.wait_count as i64);
+    POOL_WAIT_SECONDS.set(state.wait_duration.as_secs_f64());
+}
+
+pub async fn count_records(db: &Database<ReqwestClient>) -> Result<BusinessCounts, ClientError> {
+    let aql = AqlQuery::builder()
+        .query("
+            RETURN {
+                companies: COUNT(FOR c IN companies FILTER c.deleted_at == null RETURN 1),
+                users: COUNT(FOR u IN users FILTER u.deleted_at == null RETURN 1)
+            }
+        ")
+        .build();
+    let mut records: Vec<BusinessCounts> = db.aql_query(aql).await?;
+    Ok(records.pop().unwrap_or_default())
+}
+
+// every registered metric in the prometheus text format, including those of arangors
+pub fn render_metrics() -> Result<String, Error> {
+    let mut buffer = Vec::new();
+    TextEncoder::new()
+        .encode(&prometheus::gather(), &mut buffer)
+        .map_err(ErrorInternalServerError)?;
+    String::from_utf8(buffer).map_err(ErrorInternalServerError)
+}
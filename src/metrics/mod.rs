@@ -0,0 +1,11 @@
+mod models;
+mod controllers;
+mod collector;
+mod middleware;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use collector::start_collector;
+pub use middleware::HttpMetrics;
+pub use routes::init;
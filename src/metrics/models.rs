@@ -0,0 +1,55 @@
+use lazy_static::lazy_static;
+use prometheus::{
+    register_gauge, register_histogram_vec, register_int_counter_vec, register_int_gauge,
+    register_int_gauge_vec, Gauge, HistogramVec, IntCounterVec, IntGauge, IntGaugeVec,
+};
+use serde::Deserialize;
+
+pub const METRICS_PATH: &str = "/metrics"; // served outside of any tenant
+pub const UNMATCHED_ROUTE: &str = "unmatched"; // keeps unknown paths out of the label values
+
+lazy_static! {
+    pub static ref HTTP_REQUESTS: IntCounterVec = register_int_counter_vec!(
+        "http_requests_total",
+        "Handled requests by route and status",
+        &["method", "route", "status"]
+    ).unwrap();
+    pub static ref HTTP_DURATION: HistogramVec = register_histogram_vec!(
+        "http_request_duration_seconds",
+        "Latency of requests by route",
+        &["method", "route"],
+        vec![0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0]
+    ).unwrap();
+
+    pub static ref POOL_MAX_OPEN: IntGauge = register_int_gauge!(
+        "db_pool_max_open", "Maximum number of open database connections"
+    ).unwrap();
+    pub static ref POOL_OPEN: IntGauge = register_int_gauge!(
+        "db_pool_connections", "Open database connections"
+    ).unwrap();
+    pub static ref POOL_IN_USE: IntGauge = register_int_gauge!(
+        "db_pool_in_use", "Database connections currently checked out"
+    ).unwrap();
+    pub static ref POOL_IDLE: IntGauge = register_int_gauge!(
+        "db_pool_idle", "Idle database connections"
+    ).unwrap();
+    pub static ref POOL_WAIT_COUNT: IntGauge = register_int_gauge!(
+        "db_pool_wait_count", "Checkouts that had to wait for a connection since startup"
+    ).unwrap();
+    pub static ref POOL_WAIT_SECONDS: Gauge = register_gauge!(
+        "db_pool_wait_seconds", "Time spent waiting for a connection since startup"
+    ).unwrap();
+
+    pub static ref COMPANIES: IntGaugeVec = register_int_gauge_vec!(
+        "groupware_companies", "Companies that are not in the trash", &["database"]
+    ).unwrap();
+    pub static ref USERS: IntGaugeVec = register_int_gauge_vec!(
+        "groupware_users", "Users that are not in the trash", &["database"]
+    ).unwrap();
+}
+
+#[derive(Debug, Default, Deserialize)]
+pub struct BusinessCounts {
+    pub companies: i64,
+    pub users: i64,
+}
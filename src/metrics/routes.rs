@@ -0,0 +1,31 @@
+use actix_web::{error::ErrorUnauthorized, get, web, Error, HttpRequest, HttpResponse};
+
+use crate::auth;
+use crate::config::metrics_token;
+use crate::database::DbPool;
+use crate::metrics;
+
+// scrapers authenticate with METRICS_TOKEN, the labels name tenants, so without it nobody can
+fn require_scraper(req: &HttpRequest) -> Result<(), Error> {
+    let expected = metrics_token();
+    match auth::bearer_token(req) {
+        Some(token) if !expected.is_empty() && token == expected => Ok(()),
+        _ => Err(ErrorUnauthorized("metrics token required")),
+    }
+}
+
+#[get("/metrics")]
+async fn scrape(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_scraper(&req)?;
+    metrics::record_pool(&pool).await;
+    let body = metrics::render_metrics()?;
+    Ok(HttpResponse::Ok().content_type("text/plain; version=0.0.4").body(body))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(scrape);
+}
@@ -0,0 +1,410 @@
+use arangors::{
+    collection::{
+        options::{CreateOptions, CreateParameters},
+        CollectionType,
+    },
+    connection::{Permission, ReqwestClient},
+    index::{Index, IndexSettings},
+    user::UserOptions,
+    view::{ArangoSearchViewLink, ArangoSearchViewPropertiesOptions, ViewOptions},
+    AqlQuery, ClientError, Connection, Database,
+};
+use std::collections::HashMap;
+
+use crate::config::{
+    db_admin_password,
+    db_admin_username,
+    db_database,
+    db_host,
+    db_password,
+    db_port,
+    db_username,
+};
+use crate::database::DbPool;
+use crate::tenant::tenancy_enabled;
+use crate::wiki::SEARCH_VIEW;
+
+pub const REGISTRY_DATABASE: &str = "_system";
+
+async fn ensure_collection(
+    db: &Database<ReqwestClient>,
+    existing: &[String],
+    name: &str,
+) -> Result<(), ClientError> {
+    if !existing.iter().any(|x| x == name) {
+        db.create_collection(name).await?;
+    }
+    Ok(())
+}
+
+async fn ensure_edge_collection(
+    db: &Database<ReqwestClient>,
+    existing: &[String],
+    name: &str,
+) -> Result<(), ClientError> {
+    if !existing.iter().any(|x| x == name) {
+        let options = CreateOptions::builder()
+            .name(name)
+            .collection_type(CollectionType::Edge)
+            .build();
+        db.create_collection_with_options(options, CreateParameters::default()).await?;
+    }
+    Ok(())
+}
+
+// creating an index is idempotent as long as its definition does not change
+async fn ensure_index(
+    db: &Database<ReqwestClient>,
+    collection: &str,
+    name: &str,
+    fields: &[&str],
+    settings: IndexSettings,
+) -> Result<(), ClientError> {
+    let index = Index::builder()
+        .name(name)
+        .fields(fields.iter().map(|x| x.to_string()).collect())
+        .settings(settings)
+        .build();
+    db.create_index(collection, &index).await?;
+    Ok(())
+}
+
+// views are only created, changing the links of an existing one is left to a later migration
+async fn ensure_view(
+    db: &Database<ReqwestClient>,
+    name: &str,
+    links: HashMap<String, ArangoSearchViewLink>,
+) -> Result<(), ClientError> {
+    if !db.list_views().await?.iter().any(|x| x.name == name) {
+        let options = ViewOptions::builder()
+            .name(name.to_string())
+            .properties(ArangoSearchViewPropertiesOptions::builder().links(links).build())
+            .build();
+        db.create_view(options).await?;
+    }
+    Ok(())
+}
+
+// connection with the admin credentials, none when the app itself runs as the admin
+pub struct AdminConnection(pub Option<Connection>);
+
+// opened once on startup and shared, basic auth so that there is no token to expire meanwhile
+pub async fn admin_connection() -> Result<AdminConnection, ClientError> {
+    let admin_username = db_admin_username();
+    if admin_username.is_empty() || admin_username == db_username() {
+        return Ok(AdminConnection(None));
+    }
+    let url = format!("http://{}:{}", db_host(), db_port());
+    let conn = Connection::establish_basic_auth(&url, &admin_username, &db_admin_password()).await?;
+    Ok(AdminConnection(Some(conn)))
+}
+
+// lets the app user read and write a database it serves
+pub async fn grant_app_database(conn: &Connection, database: &str) -> Result<(), ClientError> {
+    let admin = conn.clone().into_admin().await?;
+    admin.grant_database_permission(&db_username(), database, Permission::ReadWrite).await
+}
+
+// the app connects as DB_USERNAME with read-write access to the databases it serves and read-only
+// access to the tenant registry. the user is created or updated with the admin credentials, which
+// are otherwise only needed to provision tenants
+pub async fn provision_user(admin: &AdminConnection) -> Result<(), ClientError> {
+    let conn = match &admin.0 {
+        Some(conn) => conn,
+        None => return Ok(()),
+    };
+    let username = db_username();
+    let database = db_database();
+    if !conn.accessible_databases().await?.contains_key(&database) {
+        conn.create_database(&database).await?;
+    }
+
+    let mut databases = vec![database];
+    if tenancy_enabled() {
+        let registry = conn.db(REGISTRY_DATABASE).await?;
+        ensure_registry(&registry).await?;
+        let aql = AqlQuery::builder()
+            .query("FOR t IN tenants RETURN t.database")
+            .build();
+        let tenants: Vec<String> = registry.aql_query(aql).await?;
+        databases.extend(tenants);
+    }
+
+    let admin = conn.clone().into_admin().await?;
+    let options = UserOptions::builder()
+        .passwd(db_password())
+        .active(true)
+        .build();
+    match admin.user(&username).await {
+        Ok(_) => admin.update_user(&username, options).await?,
+        Err(ClientError::Arango(e)) if e.code() == 404 => admin.create_user(&username, options).await?,
+        Err(e) => return Err(e),
+    };
+    for database in databases.iter() {
+        admin.grant_database_permission(&username, database, Permission::ReadWrite).await?;
+    }
+    if tenancy_enabled() {
+        admin.grant_database_permission(&username, REGISTRY_DATABASE, Permission::ReadOnly).await?;
+    }
+    Ok(())
+}
+
+async fn ensure_registry(db: &Database<ReqwestClient>) -> Result<(), ClientError> {
+    let existing: Vec<String> = db.accessible_collections().await?
+        .into_iter()
+        .map(|x| x.name)
+        .collect();
+
+    ensure_collection(db, &existing, "tenants").await?;
+
+    Ok(())
+}
+
+// tenant registry, shared by all tenants
+pub async fn run_registry(pool: &DbPool) -> Result<(), ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(REGISTRY_DATABASE).await?;
+    ensure_registry(&db).await
+}
+
+pub async fn run(pool: &DbPool, database: &str) -> Result<(), ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(database).await?;
+
+    let existing: Vec<String> = db.accessible_collections().await?
+        .into_iter()
+        .map(|x| x.name)
+        .collect();
+
+    ensure_collection(&db, &existing, "companies").await?;
+    ensure_collection(&db, &existing, "users").await?;
+    ensure_collection(&db, &existing, "sessions").await?;
+    ensure_collection(&db, &existing, "password_resets").await?;
+    ensure_collection(&db, &existing, "login_challenges").await?;
+    ensure_collection(&db, &existing, "api_keys").await?;
+    ensure_collection(&db, &existing, "webhooks").await?;
+    ensure_collection(&db, &existing, "webhook_deliveries").await?;
+    ensure_collection(&db, &existing, "comments").await?;
+    ensure_collection(&db, &existing, "tags").await?;
+    ensure_collection(&db, &existing, "custom_fields").await?;
+    ensure_collection(&db, &existing, "events").await?;
+    ensure_collection(&db, &existing, "resources").await?;
+    ensure_collection(&db, &existing, "bookings").await?;
+    ensure_collection(&db, &existing, "time_entries").await?;
+    ensure_collection(&db, &existing, "timesheets").await?;
+    ensure_collection(&db, &existing, "announcements").await?;
+    ensure_collection(&db, &existing, "announcement_receipts").await?;
+    ensure_collection(&db, &existing, "wiki_pages").await?;
+    ensure_collection(&db, &existing, "wiki_revisions").await?;
+    ensure_collection(&db, &existing, "contacts").await?;
+    ensure_collection(&db, &existing, "messages").await?;
+    ensure_collection(&db, &existing, "jobs").await?;
+    ensure_collection(&db, &existing, "schedules").await?;
+    ensure_collection(&db, &existing, "schedule_locks").await?;
+    ensure_collection(&db, &existing, "schedule_runs").await?;
+    ensure_edge_collection(&db, &existing, "memberships").await?;
+    ensure_edge_collection(&db, &existing, "taggings").await?;
+    ensure_edge_collection(&db, &existing, "wiki_links").await?;
+
+    ensure_index(&db, "users", "users_email", &["email"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "sessions", "sessions_user_key", &["user_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "sessions", "sessions_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "password_resets", "password_resets_user_key", &["user_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "password_resets", "password_resets_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "login_challenges", "login_challenges_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "api_keys", "api_keys_owner", &["owner"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "webhooks", "webhooks_company_key", &["company_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "webhook_deliveries", "webhook_deliveries_due", &["status", "next_attempt_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "webhook_deliveries", "webhook_deliveries_webhook_key", &["webhook_key", "created_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "comments", "comments_target", &["target", "created_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "tags", "tags_company_key_name", &["company_key", "name"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "taggings", "taggings_from_to", &["_from", "_to"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "taggings", "taggings_from_name", &["_from", "name"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "custom_fields", "custom_fields_company_key_entity_name", &["company_key", "entity", "name"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "events", "events_owner_key_starts_at", &["owner_key", "starts_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "events", "events_attendees", &["attendees[*]"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: true,
+    }).await?;
+    ensure_index(&db, "resources", "resources_company_key", &["company_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "bookings", "bookings_resource_key_starts_at", &["resource_key", "starts_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "time_entries", "time_entries_company_key_starts_at", &["company_key", "starts_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "time_entries", "time_entries_user_key_week", &["user_key", "week"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    // at most one running timer per user and company
+    ensure_index(&db, "time_entries", "time_entries_running", &["running"], IndexSettings::Persistent {
+        unique: true,
+        sparse: true,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "timesheets", "timesheets_company_key_user_key_week", &["company_key", "user_key", "week"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "announcements", "announcements_company_key_created_at", &["company_key", "created_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "announcement_receipts", "announcement_receipts_announcement_key_user_key", &["announcement_key", "user_key"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "wiki_pages", "wiki_pages_company_key_slug", &["company_key", "slug"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "wiki_pages", "wiki_pages_company_key_parent_key", &["company_key", "parent_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "wiki_pages", "wiki_pages_ancestors", &["ancestors[*]"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: true,
+    }).await?;
+    ensure_index(&db, "wiki_pages", "wiki_pages_links", &["links[*]"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: true,
+    }).await?;
+    ensure_index(&db, "wiki_revisions", "wiki_revisions_page_key_number", &["page_key", "number"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "contacts", "contacts_owner_key_name", &["owner_key", "name"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "messages", "messages_owner_key_message_id", &["owner_key", "message_id"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "messages", "messages_owner_key_thread_key", &["owner_key", "thread_key"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "messages", "messages_owner_key_in_reply_to", &["owner_key", "in_reply_to"], IndexSettings::Persistent {
+        unique: false,
+        sparse: true,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "jobs", "jobs_status_priority_run_at", &["status", "priority", "run_at"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "jobs", "jobs_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "schedule_locks", "schedule_locks_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "schedule_runs", "schedule_runs_schedule_slot", &["schedule", "slot"], IndexSettings::Persistent {
+        unique: false,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+    ensure_index(&db, "schedule_runs", "schedule_runs_expires_at", &["expires_at"], IndexSettings::Ttl {
+        expire_after: 0,
+    }).await?;
+    ensure_index(&db, "memberships", "memberships_from_to", &["_from", "_to"], IndexSettings::Persistent {
+        unique: true,
+        sparse: false,
+        deduplicate: false,
+    }).await?;
+
+    // titles and bodies are searched as English text, company keys as exact values
+    let text = || ArangoSearchViewLink::builder().analyzers(vec!["text_en".to_string()]).build();
+    let mut fields = HashMap::new();
+    fields.insert("title".to_string(), text());
+    fields.insert("body".to_string(), text());
+    fields.insert("company_key".to_string(), ArangoSearchViewLink::builder().build());
+    let mut links = HashMap::new();
+    links.insert("wiki_pages".to_string(), ArangoSearchViewLink::builder().fields(fields).build());
+    ensure_view(&db, SEARCH_VIEW, links).await?;
+
+    Ok(())
+}
@@ -0,0 +1,436 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    transaction::{TransactionCollections, TransactionSettings},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::{prelude::*, Duration};
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::database::{current_database, DbPool};
+use crate::resource::{
+    Booking,
+    BookingRequest,
+    BookingResponse,
+    BookingRules,
+    DecideBookingRequest,
+    FindBookingsParams,
+    FindResourcesParams,
+    Resource,
+    ResourceRequest,
+    ResourceResponse,
+};
+
+const RESOURCE_TERMS: &str = "RETURN UNSET(r, '_id', '_rev')";
+
+const BOOKING_TERMS: &str = r#"RETURN {
+        _key: b._key,
+        resource_key: b.resource_key,
+        resource_name: DOCUMENT('resources', b.resource_key).name,
+        user_key: b.user_key,
+        title: b.title,
+        start: b.start,
+        end: b.end,
+        headcount: b.headcount,
+        status: b.status,
+        decided_by: b.decided_by,
+        created_at: b.created_at,
+        modified_at: b.modified_at
+    }"#;
+
+// seconds to wait for a concurrent booking to finish
+const LOCK_TIMEOUT: usize = 10;
+
+async fn find_resource(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<ResourceResponse, Error> {
+    let q = format!("FOR r IN resources FILTER r._key == @key AND r.company_key == @company_key {}", RESOURCE_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<ResourceResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("resource not found"))
+}
+
+async fn find_booking(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<BookingResponse, Error> {
+    let q = format!("FOR b IN bookings FILTER b._key == @key AND b.company_key == @company_key {}", BOOKING_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<BookingResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("booking not found"))
+}
+
+fn to_resource(company_key: &str, payload: &ResourceRequest, created_at: DateTime<Utc>) -> Resource {
+    Resource {
+        company_key: company_key.to_string(),
+        name: payload.name.trim().to_string(),
+        kind: payload.kind.clone(),
+        capacity: payload.capacity,
+        location: payload.location.clone(),
+        rules: payload.rules.clone().unwrap_or(BookingRules {
+            max_duration_minutes: None,
+            lead_time_minutes: None,
+            approval_required: false,
+        }),
+        created_at,
+        modified_at: Utc::now(),
+    }
+}
+
+#[instrument(skip_all)]
+pub async fn find_resources(
+    company_key: &str,
+    params: FindResourcesParams,
+    pool: &DbPool,
+) -> Result<Vec<ResourceResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut filters = vec!["FILTER r.company_key == @company_key"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    if let Some(kind) = params.kind {
+        filters.push("FILTER r.kind == @kind");
+        vars.insert("kind", to_value(kind).unwrap());
+    }
+    if let Some(min_capacity) = params.min_capacity {
+        filters.push("FILTER r.capacity >= @min_capacity");
+        vars.insert("min_capacity", to_value(min_capacity).unwrap());
+    }
+    let q = format!("FOR r IN resources {} SORT r.name ASC {}", filters.join(" "), RESOURCE_TERMS);
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<ResourceResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_resource(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<ResourceResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_resource(&db, company_key, key).await
+}
+
+#[instrument(skip_all)]
+pub async fn create_resource(
+    company_key: &str,
+    payload: &ResourceRequest,
+    pool: &DbPool,
+) -> Result<ResourceResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("resources").await.unwrap();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let data = to_resource(company_key, payload, Utc::now());
+    let res: DocumentResponse<Document<Resource>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+    find_resource(&db, company_key, &header._key).await
+}
+
+// existing bookings keep their slots even when the new rules would not allow them
+#[instrument(skip_all)]
+pub async fn update_resource(
+    company_key: &str,
+    key: &str,
+    payload: &ResourceRequest,
+    pool: &DbPool,
+) -> Result<ResourceResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_resource(&db, company_key, key).await?;
+    let data = to_resource(company_key, payload, current.created_at);
+    let q = "FOR r IN resources FILTER r._key == @key REPLACE r WITH @data IN resources";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_resource(&db, company_key, key).await
+}
+
+// bookings go with their resource
+#[instrument(skip_all)]
+pub async fn delete_resource(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_resource(&db, company_key, key).await?;
+    for q in [
+        "FOR b IN bookings FILTER b.resource_key == @key REMOVE b IN bookings",
+        "REMOVE @key IN resources",
+    ] {
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("key", to_value(key).unwrap());
+
+        let aql = AqlQuery::builder()
+            .query(q)
+            .bind_vars(vars)
+            .build();
+        let _records: Vec<Value> = db.aql_query(aql).await
+            .map_err(ErrorInternalServerError)?;
+    }
+    Ok(())
+}
+
+// bookings still holding a slot that overlaps the range
+#[instrument(skip_all)]
+pub async fn find_bookings(
+    company_key: &str,
+    resource_key: &str,
+    params: FindBookingsParams,
+    pool: &DbPool,
+) -> Result<Vec<BookingResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_resource(&db, company_key, resource_key).await?;
+    let q = format!(r#"FOR b IN bookings
+        FILTER b.resource_key == @resource_key AND b.status IN ['pending', 'confirmed']
+        FILTER b.starts_at < @to AND b.ends_at > @from
+        SORT b.starts_at ASC {}"#, BOOKING_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("resource_key", to_value(resource_key).unwrap());
+    vars.insert("from", to_value(params.from.timestamp()).unwrap());
+    vars.insert("to", to_value(params.to.timestamp()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<BookingResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+fn check_rules(resource: &ResourceResponse, payload: &BookingRequest) -> Result<(), Error> {
+    let rules = &resource.rules;
+    let lead_time = Duration::minutes(rules.lead_time_minutes.unwrap_or(0) as i64);
+    if payload.start < Utc::now() + lead_time {
+        return Err(ErrorBadRequest("booking starts too soon"));
+    }
+    if let Some(max_duration) = rules.max_duration_minutes {
+        if payload.end - payload.start > Duration::minutes(max_duration as i64) {
+            return Err(ErrorBadRequest("booking is too long"));
+        }
+    }
+    if let (Some(capacity), Some(headcount)) = (resource.capacity, payload.headcount) {
+        if headcount > capacity {
+            return Err(ErrorBadRequest("resource is too small"));
+        }
+    }
+    Ok(())
+}
+
+// the overlap check and the insert run in one stream transaction holding an exclusive lock on
+// bookings, so a concurrent request for the same slot waits and then sees this booking
+async fn reserve(
+    db: &Database<ReqwestClient>,
+    data: Booking,
+) -> Result<String, Error> {
+    let settings = TransactionSettings::builder()
+        .collections(
+            TransactionCollections::builder()
+                .write(vec![])
+                .exclusive(vec!["bookings".to_string()])
+                .build(),
+        )
+        .lock_timeout(LOCK_TIMEOUT)
+        .build();
+    let tx = db.begin_transaction(settings).await
+        .map_err(ErrorInternalServerError)?;
+
+    let q = r#"FOR b IN bookings
+        FILTER b.resource_key == @resource_key AND b.status IN ['pending', 'confirmed']
+        FILTER b.starts_at < @ends_at AND b.ends_at > @starts_at
+        LIMIT 1
+        RETURN b._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("resource_key", to_value(&data.resource_key).unwrap());
+    vars.insert("starts_at", to_value(data.starts_at).unwrap());
+    vars.insert("ends_at", to_value(data.ends_at).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let clashes: Vec<String> = match tx.aql_query(aql).await {
+        Ok(x) => x,
+        Err(e) => {
+            tx.abort().await.ok();
+            return Err(ErrorInternalServerError(e));
+        },
+    };
+    if !clashes.is_empty() {
+        tx.abort().await.ok();
+        return Err(ErrorConflict("slot already booked"));
+    }
+
+    let collection: Collection<ReqwestClient> = tx.collection("bookings").await
+        .map_err(ErrorInternalServerError)?;
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+    let key = match collection.create_document(Document::new(data), options).await {
+        Ok(res) => {
+            let res: DocumentResponse<Document<Booking>> = res;
+            res.header().unwrap()._key.clone()
+        },
+        Err(e) => {
+            tx.abort().await.ok();
+            return Err(ErrorInternalServerError(e));
+        },
+    };
+    tx.commit().await
+        .map_err(ErrorInternalServerError)?;
+    Ok(key)
+}
+
+// bookings of resources that need approval wait for a company admin
+#[instrument(skip_all)]
+pub async fn create_booking(
+    company_key: &str,
+    resource_key: &str,
+    user_key: &str,
+    payload: &BookingRequest,
+    pool: &DbPool,
+) -> Result<BookingResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let resource = find_resource(&db, company_key, resource_key).await?;
+    check_rules(&resource, payload)?;
+
+    let now = Utc::now();
+    let data = Booking {
+        company_key: company_key.to_string(),
+        resource_key: resource_key.to_string(),
+        user_key: user_key.to_string(),
+        title: payload.title.trim().to_string(),
+        start: payload.start,
+        end: payload.end,
+        starts_at: payload.start.timestamp(),
+        ends_at: payload.end.timestamp(),
+        headcount: payload.headcount,
+        status: if resource.rules.approval_required { "pending" } else { "confirmed" }.to_string(),
+        decided_by: None,
+        created_at: now,
+        modified_at: now,
+    };
+    let key = reserve(&db, data).await?;
+    find_booking(&db, company_key, &key).await
+}
+
+#[instrument(skip_all)]
+pub async fn decide_booking(
+    company_key: &str,
+    key: &str,
+    decided_by: &str,
+    payload: &DecideBookingRequest,
+    pool: &DbPool,
+) -> Result<BookingResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR b IN bookings
+        FILTER b._key == @key AND b.company_key == @company_key AND b.status == 'pending'
+        UPDATE b WITH { status: @status, decided_by: @decided_by, modified_at: @now } IN bookings
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("status", to_value(if payload.approved { "confirmed" } else { "rejected" }).unwrap());
+    vars.insert("decided_by", to_value(decided_by).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        return Err(ErrorNotFound("pending booking not found"));
+    }
+    find_booking(&db, company_key, key).await
+}
+
+// only_user limits cancelling to the bookings of that user
+#[instrument(skip_all)]
+pub async fn cancel_booking(
+    company_key: &str,
+    key: &str,
+    only_user: Option<&str>,
+    pool: &DbPool,
+) -> Result<BookingResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let booking = find_booking(&db, company_key, key).await?;
+    if only_user.map_or(false, |x| x != booking.user_key) {
+        return Err(ErrorForbidden("not your booking"));
+    }
+
+    let q = r#"FOR b IN bookings
+        FILTER b._key == @key AND b.status IN ['pending', 'confirmed']
+        UPDATE b WITH { status: 'cancelled', modified_at: @now } IN bookings"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_booking(&db, company_key, key).await
+}
@@ -0,0 +1,147 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+pub const KINDS: [&str; 4] = ["room", "equipment", "vehicle", "other"];
+pub const MAX_RANGE_DAYS: i64 = 93;
+
+fn validate_kind(kind: &str) -> Result<(), ValidationError> {
+    if !KINDS.contains(&kind) {
+        return Err(ValidationError::new("wrong_kind"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Serialize, Deserialize)]
+pub struct BookingRules {
+    #[validate(range(min = 5, max = 43200))]
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub max_duration_minutes: Option<u32>,
+    #[validate(range(max = 525600))]
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub lead_time_minutes: Option<u32>, // minimum notice before the booking starts
+    #[serde(default)]
+    pub approval_required: bool,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ResourceRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+    #[validate(custom = "validate_kind")]
+    pub kind: String,
+    #[validate(range(min = 1, max = 10000))]
+    pub capacity: Option<u32>, // people for rooms and vehicles, none for equipment
+    #[validate(length(max = 200))]
+    pub location: Option<String>,
+    #[validate]
+    pub rules: Option<BookingRules>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Resource {
+    pub company_key: String,
+    pub name: String,
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub capacity: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub location: Option<String>,
+    pub rules: BookingRules,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ResourceResponse {
+    pub _key: String,
+    pub company_key: String,
+    pub name: String,
+    pub kind: String,
+    pub capacity: Option<u32>,
+    pub location: Option<String>,
+    pub rules: BookingRules,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindResourcesParams {
+    #[validate(custom = "validate_kind")]
+    pub kind: Option<String>,
+    #[validate(range(min = 1))]
+    pub min_capacity: Option<u32>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_range"))]
+pub struct FindBookingsParams {
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn validate_range(params: &FindBookingsParams) -> Result<(), ValidationError> {
+    if params.to <= params.from || params.to - params.from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(ValidationError::new("wrong_range"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_booking"))]
+pub struct BookingRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    #[validate(range(min = 1, max = 10000))]
+    pub headcount: Option<u32>,
+}
+
+fn validate_booking(payload: &BookingRequest) -> Result<(), ValidationError> {
+    if payload.end <= payload.start {
+        return Err(ValidationError::new("end_before_start"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DecideBookingRequest {
+    pub approved: bool,
+}
+
+// pending and confirmed bookings hold their slot, rejected and cancelled ones free it
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Booking {
+    pub company_key: String,
+    pub resource_key: String,
+    pub user_key: String,
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub starts_at: i64, // unix seconds, compared numerically in overlap checks
+    pub ends_at: i64,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub headcount: Option<u32>,
+    pub status: String, // pending, confirmed, rejected or cancelled
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub decided_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct BookingResponse {
+    pub _key: String,
+    pub resource_key: String,
+    pub resource_name: Option<String>,
+    pub user_key: String,
+    pub title: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub headcount: Option<u32>,
+    pub status: String,
+    pub decided_by: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
@@ -0,0 +1,207 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::resource::{
+    self,
+    BookingRequest,
+    DecideBookingRequest,
+    FindBookingsParams,
+    FindResourcesParams,
+    ResourceRequest,
+};
+
+// resources and their bookings are only shown within the company
+async fn require_reader(req: &HttpRequest, key: &str, pool: &DbPool) -> Result<(), Error> {
+    let identity = company::require_access(req, key, "resources:read")?;
+    if let Some(user_key) = identity.user_key() {
+        if !company::is_company_member(key, user_key, pool).await {
+            return Err(ErrorForbidden("company member required"));
+        }
+    }
+    Ok(())
+}
+
+// bookings are made by members for themselves
+async fn require_member(req: &HttpRequest, key: &str, pool: &DbPool) -> Result<String, Error> {
+    let identity = company::require_access(req, key, "resources:write")?;
+    let user_key = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("resources can only be booked by users"))?;
+    if !company::is_company_member(key, user_key, pool).await {
+        return Err(ErrorForbidden("company member required"));
+    }
+    Ok(user_key.to_string())
+}
+
+#[get("/companies/{key}/resources")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindResourcesParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_reader(&req, &key, &pool).await?;
+    let params: FindResourcesParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = resource::find_resources(&key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/companies/{key}/resources/{resource_key}")]
+async fn show(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, resource_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = resource::show_resource(&key, &resource_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/resources")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<ResourceRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_admin(&req, &key, &pool).await?;
+    let params: ResourceRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = resource::create_resource(&key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/resources/{resource_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<ResourceRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, resource_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: ResourceRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = resource::update_resource(&key, &resource_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/resources/{resource_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, resource_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    resource::delete_resource(&key, &resource_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/resources/{resource_key}/bookings")]
+async fn find_bookings(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Query<FindBookingsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, resource_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let params: FindBookingsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = resource::find_bookings(&key, &resource_key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/resources/{resource_key}/bookings")]
+async fn create_booking(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<BookingRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, resource_key) = path.into_inner();
+    let user_key = require_member(&req, &key, &pool).await?;
+    let params: BookingRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = resource::create_booking(&key, &resource_key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/bookings/{booking_key}/decision")]
+async fn decide_booking(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<DecideBookingRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, booking_key) = path.into_inner();
+    let identity = company::require_admin(&req, &key, &pool).await?;
+    let decided_by = identity.user_key().map_or_else(|| format!("companies/{}", key), |x| format!("users/{}", x));
+    let result = resource::decide_booking(&key, &booking_key, &decided_by, &payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// members cancel their own bookings, admins and company keys any of them
+#[delete("/companies/{key}/bookings/{booking_key}")]
+async fn cancel_booking(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, booking_key) = path.into_inner();
+    let identity = company::require_access(&req, &key, "resources:write")?;
+    let only_user = match identity.user_key() {
+        Some(user_key) if !company::is_company_admin(&key, user_key, &pool).await => Some(user_key),
+        _ => None,
+    };
+    let result = resource::cancel_booking(&key, &booking_key, only_user, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(find_bookings);
+    cfg.service(create_booking);
+    cfg.service(decide_booking);
+    cfg.service(cancel_booking);
+}
@@ -0,0 +1,119 @@
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, ClientError, Collection, Database, Document,
+};
+use chrono::{prelude::*, Duration};
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::schedule::{Schedule, ScheduleLock, ScheduleRun, HISTORY_DAYS, LOCK_TTL_SECONDS};
+
+const UNIQUE_CONSTRAINT_VIOLATED: u16 = 1210;
+
+#[instrument(skip_all)]
+pub async fn stored_schedules(
+    db: &Database<ReqwestClient>,
+) -> Result<Vec<(String, Schedule)>, ClientError> {
+    let q = "FOR s IN schedules FILTER s.enabled RETURN { name: s._key, schedule: UNSET(s, '_id', '_key', '_rev') }";
+    let aql = AqlQuery::builder()
+        .query(q)
+        .build();
+    let records: Vec<Value> = db.aql_query(aql).await?;
+    Ok(records.into_iter()
+        .filter_map(|x| {
+            let name = x["name"].as_str()?.to_string();
+            let schedule: Schedule = serde_json::from_value(x["schedule"].clone()).ok()?;
+            Some((name, schedule))
+        })
+        .collect())
+}
+
+// true when this instance got the slot, false when another one was first
+#[instrument(skip_all)]
+pub async fn acquire_lock(
+    db: &Database<ReqwestClient>,
+    name: &str,
+    slot: DateTime<Utc>,
+    instance: &str,
+) -> Result<bool, ClientError> {
+    let data = ScheduleLock {
+        schedule: name.to_string(),
+        slot,
+        instance: instance.to_string(),
+        expires_at: (Utc::now() + Duration::seconds(LOCK_TTL_SECONDS)).timestamp(),
+    };
+    let mut doc = Document::new(data);
+    doc.header._key = format!("{}-{}", name, slot.timestamp());
+
+    let collection: Collection<ReqwestClient> = db.collection("schedule_locks").await?;
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+    let res: Result<DocumentResponse<Document<ScheduleLock>>, ClientError> = collection.create_document(doc, options).await;
+    match res {
+        Ok(_) => Ok(true),
+        Err(ClientError::Arango(e)) if e.error_num() == UNIQUE_CONSTRAINT_VIOLATED => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+// returns the key of the run, to be finished with finish_run
+#[instrument(skip_all)]
+pub async fn start_run(
+    db: &Database<ReqwestClient>,
+    name: &str,
+    kind: &str,
+    slot: DateTime<Utc>,
+    instance: &str,
+) -> Result<String, ClientError> {
+    let now = Utc::now();
+    let data = ScheduleRun {
+        schedule: name.to_string(),
+        kind: kind.to_string(),
+        slot,
+        instance: instance.to_string(),
+        status: "running".to_string(),
+        error: None,
+        started_at: now,
+        finished_at: None,
+        duration_ms: None,
+        expires_at: (now + Duration::days(HISTORY_DAYS)).timestamp(),
+    };
+    let collection: Collection<ReqwestClient> = db.collection("schedule_runs").await?;
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<ScheduleRun>> = collection.create_document(Document::new(data), options).await?;
+    Ok(res.header().unwrap()._key.clone())
+}
+
+#[instrument(skip_all)]
+pub async fn finish_run(
+    db: &Database<ReqwestClient>,
+    key: &str,
+    result: Result<(), String>,
+) -> Result<(), ClientError> {
+    let q = r#"FOR r IN schedule_runs
+        FILTER r._key == @key
+        LET finished_at = DATE_NOW()
+        UPDATE r WITH {
+            status: @status,
+            error: @error,
+            finished_at: DATE_ISO8601(finished_at),
+            duration_ms: finished_at - DATE_TIMESTAMP(r.started_at)
+        } IN schedule_runs"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("status", to_value(if result.is_ok() { "succeeded" } else { "failed" }).unwrap());
+    vars.insert("error", to_value(result.err()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await?;
+    Ok(())
+}
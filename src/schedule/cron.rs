@@ -0,0 +1,176 @@
+use chrono::{prelude::*, Duration};
+
+const MONTHS: [&str; 12] = ["jan", "feb", "mar", "apr", "may", "jun", "jul", "aug", "sep", "oct", "nov", "dec"];
+const WEEKDAYS: [&str; 7] = ["sun", "mon", "tue", "wed", "thu", "fri", "sat"];
+
+// gives up on expressions that never match, such as the 31st of February
+const MAX_ITERATIONS: usize = 100_000;
+
+// the five fields of crontab(5), evaluated in UTC
+#[derive(Clone, Debug)]
+pub struct CronSchedule {
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+fn parse_value(value: &str, min: u32, names: &[&str]) -> Result<u32, String> {
+    let lower = value.to_lowercase();
+    if let Some(i) = names.iter().position(|x| *x == lower) {
+        return Ok(i as u32 + min);
+    }
+    value.parse().map_err(|_| format!("wrong value {}", value))
+}
+
+// lists of values, ranges and steps, as in 1,15 or 9-17 or */5
+fn parse_field(field: &str, min: u32, max: u32, names: &[&str]) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| format!("wrong step {}", step))?),
+            None => (part, 1),
+        };
+        if step == 0 {
+            return Err("step must not be 0".to_string());
+        }
+        let (from, to) = match range {
+            "*" => (min, max),
+            _ => match range.split_once('-') {
+                Some((from, to)) => (parse_value(from, min, names)?, parse_value(to, min, names)?),
+                None => {
+                    let value = parse_value(range, min, names)?;
+                    // a single value with a step runs to the end of the range
+                    (value, if part.contains('/') { max } else { value })
+                },
+            },
+        };
+        if from < min || to > max || from > to {
+            return Err(format!("{} is out of range", part));
+        }
+        for value in (from..=to).step_by(step as usize) {
+            bits |= 1 << value;
+        }
+    }
+    Ok(bits)
+}
+
+impl CronSchedule {
+    pub fn parse(expression: &str) -> Result<Self, String> {
+        let expression = match expression.trim() {
+            "@yearly" | "@annually" => "0 0 1 1 *",
+            "@monthly" => "0 0 1 * *",
+            "@weekly" => "0 0 * * 0",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@hourly" => "0 * * * *",
+            x => x,
+        };
+        let fields: Vec<&str> = expression.split_whitespace().collect();
+        if fields.len() != 5 {
+            return Err("expected five fields".to_string());
+        }
+        // both 0 and 7 are Sunday
+        let mut weekdays = parse_field(fields[4], 0, 7, &WEEKDAYS)?;
+        if weekdays & (1 << 7) != 0 {
+            weekdays = (weekdays | 1) & !(1 << 7);
+        }
+        Ok(CronSchedule {
+            minutes: parse_field(fields[0], 0, 59, &[])?,
+            hours: parse_field(fields[1], 0, 23, &[])?,
+            days: parse_field(fields[2], 1, 31, &[])?,
+            months: parse_field(fields[3], 1, 12, &MONTHS)?,
+            weekdays,
+            any_day: fields[2] == "*",
+            any_weekday: fields[4] == "*",
+        })
+    }
+
+    // when both day fields are restricted, either of them matching is enough
+    fn matches_day(&self, date: NaiveDate) -> bool {
+        let day = self.days & (1 << date.day()) != 0;
+        let weekday = self.weekdays & (1 << date.weekday().num_days_from_sunday()) != 0;
+        match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        }
+    }
+
+    // the first minute strictly after the given moment, none past the end of the calendar
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        let start = after.naive_utc().date().and_hms_opt(after.hour(), after.minute(), 0)? + Duration::minutes(1);
+        let mut t = start;
+        for _ in 0..MAX_ITERATIONS {
+            if self.months & (1 << t.month()) == 0 {
+                let (year, month) = if t.month() == 12 { (t.year() + 1, 1) } else { (t.year(), t.month() + 1) };
+                t = NaiveDate::from_ymd_opt(year, month, 1)?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if !self.matches_day(t.date()) {
+                t = t.date().succ_opt()?.and_hms_opt(0, 0, 0)?;
+                continue;
+            }
+            if self.hours & (1 << t.hour()) == 0 {
+                t = t.date().and_hms_opt(t.hour(), 0, 0)? + Duration::hours(1);
+                continue;
+            }
+            if self.minutes & (1 << t.minute()) == 0 {
+                t += Duration::minutes(1);
+                continue;
+            }
+            return Some(Utc.from_utc_datetime(&t));
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn next(expression: &str, after: &str) -> Option<String> {
+        let after = DateTime::parse_from_rfc3339(after).unwrap().with_timezone(&Utc);
+        CronSchedule::parse(expression).unwrap().next_after(after).map(|x| x.to_rfc3339())
+    }
+
+    #[test]
+    fn parse() {
+        assert!(CronSchedule::parse("0 6 * * mon").is_ok());
+        assert!(CronSchedule::parse("*/5 9-17 1,15 jan-jun *").is_ok());
+        assert!(CronSchedule::parse("@weekly").is_ok());
+        assert!(CronSchedule::parse("* * *").is_err());
+        assert!(CronSchedule::parse("60 * * * *").is_err());
+        assert!(CronSchedule::parse("*/0 * * * *").is_err());
+        assert!(CronSchedule::parse("5-1 * * * *").is_err());
+        assert!(CronSchedule::parse("0 0 0 * *").is_err());
+        assert!(CronSchedule::parse("0 0 * * someday").is_err());
+    }
+
+    #[test]
+    fn next_after() {
+        // 2021-01-01 is a Friday
+        let after = "2021-01-01T12:07:30+00:00";
+        assert_eq!(next("*/15 * * * *", after).unwrap(), "2021-01-01T12:15:00+00:00");
+        assert_eq!(next("@hourly", "2021-01-01T12:00:00+00:00").unwrap(), "2021-01-01T13:00:00+00:00");
+        assert_eq!(next("0 6 * * mon", after).unwrap(), "2021-01-04T06:00:00+00:00");
+        assert_eq!(next("0 0 * * 7", after).unwrap(), "2021-01-03T00:00:00+00:00");
+        assert_eq!(next("0 9 1 jan-mar *", "2021-03-02T00:00:00+00:00").unwrap(), "2022-01-01T09:00:00+00:00");
+        assert_eq!(next("30 23 31 12 *", after).unwrap(), "2021-12-31T23:30:00+00:00");
+    }
+
+    #[test]
+    fn either_day_field() {
+        // the 13th or any Friday, whichever comes first
+        assert_eq!(next("0 0 13 * fri", "2021-01-01T12:00:00+00:00").unwrap(), "2021-01-08T00:00:00+00:00");
+        assert_eq!(next("0 0 13 * fri", "2021-01-08T12:00:00+00:00").unwrap(), "2021-01-13T00:00:00+00:00");
+    }
+
+    #[test]
+    fn never() {
+        assert_eq!(next("0 0 31 2 *", "2021-01-01T00:00:00+00:00"), None);
+    }
+}
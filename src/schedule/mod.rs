@@ -0,0 +1,11 @@
+mod cron;
+mod models;
+mod controllers;
+mod scheduler;
+mod tasks;
+
+pub use cron::CronSchedule;
+pub use models::*;
+pub use controllers::*;
+pub use scheduler::{start_scheduler, Scheduler};
+pub use tasks::PurgeTrash;
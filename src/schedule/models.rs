@@ -0,0 +1,42 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+pub const LOCK_TTL_SECONDS: i64 = 86400; // a slot is only ever run once, the lock outlives any sane run
+pub const HISTORY_DAYS: i64 = 90;
+
+// schedules stored in the database, keyed by their name, next to the ones registered in code
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Schedule {
+    pub cron: String,
+    pub kind: String, // a job kind known to the job registry
+    #[serde(default)]
+    pub payload: Value,
+    pub enabled: bool,
+}
+
+// taken by inserting a document keyed by schedule and slot, which only one instance can do
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleLock {
+    pub schedule: String,
+    pub slot: DateTime<Utc>,
+    pub instance: String,
+    pub expires_at: i64, // ttl index removes the lock
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct ScheduleRun {
+    pub schedule: String,
+    pub kind: String,
+    pub slot: DateTime<Utc>,
+    pub instance: String,
+    pub status: String, // running, succeeded or failed
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub error: Option<String>,
+    pub started_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub finished_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub duration_ms: Option<u64>,
+    pub expires_at: i64, // ttl index keeps the history bounded
+}
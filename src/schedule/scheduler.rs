@@ -0,0 +1,143 @@
+use arangors::ClientError;
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use std::rc::Rc;
+use uuid::Uuid;
+
+use crate::database::{current_database, with_database, DbPool};
+use crate::job::{JobHandler, JobRegistry};
+use crate::schedule::{acquire_lock, finish_run, start_run, stored_schedules, CronSchedule};
+use crate::tenant::tenant_databases;
+
+const TICK_SECONDS: u64 = 20;
+
+#[derive(Clone)]
+struct Entry {
+    name: String,
+    cron: CronSchedule,
+    kind: String,
+    payload: Value,
+}
+
+// schedules registered in code, they run in every tenant database
+#[derive(Default)]
+pub struct Scheduler {
+    entries: Vec<Entry>,
+}
+
+impl Scheduler {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // the handler must also be registered with the job registry
+    pub fn add<H: JobHandler>(mut self, name: &str, expression: &str, payload: &H::Payload) -> Self {
+        let cron = CronSchedule::parse(expression)
+            .unwrap_or_else(|e| panic!("wrong cron expression for {}: {}", name, e));
+        self.entries.push(Entry {
+            name: name.to_string(),
+            cron,
+            kind: H::KIND.to_string(),
+            payload: to_value(payload).unwrap(),
+        });
+        self
+    }
+}
+
+// the latest slot in (last, now], missed slots in between are not caught up
+fn due_slot(cron: &CronSchedule, last: DateTime<Utc>, now: DateTime<Utc>) -> Option<DateTime<Utc>> {
+    let mut slot = None;
+    let mut after = last;
+    while let Some(next) = cron.next_after(after) {
+        if next > now {
+            break;
+        }
+        slot = Some(next);
+        after = next;
+    }
+    slot
+}
+
+async fn run_entry(
+    pool: DbPool,
+    registry: Rc<JobRegistry>,
+    entry: Entry,
+    slot: DateTime<Utc>,
+    instance: Rc<String>,
+) -> Result<(), ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await?;
+
+    if !acquire_lock(&db, &entry.name, slot, &instance).await? {
+        return Ok(());
+    }
+    let key = start_run(&db, &entry.name, &entry.kind, slot, &instance).await?;
+    let result = registry.run(&entry.kind, entry.payload, pool.clone()).await;
+    if let Err(e) = &result {
+        tracing::warn!(schedule = %entry.name, kind = %entry.kind, database = %current_database(), error = %e, "schedule failed");
+    }
+    finish_run(&db, &key, result).await
+}
+
+// entries of the current database that are due, code ones first
+async fn due_entries(
+    pool: &DbPool,
+    entries: &[Entry],
+    last: DateTime<Utc>,
+    now: DateTime<Utc>,
+) -> Result<Vec<(Entry, DateTime<Utc>)>, ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await?;
+
+    let mut all = entries.to_vec();
+    for (name, schedule) in stored_schedules(&db).await? {
+        match CronSchedule::parse(&schedule.cron) {
+            Ok(cron) => all.push(Entry {
+                name,
+                cron,
+                kind: schedule.kind,
+                payload: schedule.payload,
+            }),
+            Err(e) => tracing::warn!(schedule = %name, database = %current_database(), error = %e, "schedule skipped"),
+        }
+    }
+    Ok(all.into_iter()
+        .filter_map(|x| due_slot(&x.cron, last, now).map(|slot| (x, slot)))
+        .collect())
+}
+
+// every instance ticks, the lock on each slot lets only one of them run it
+pub fn start_scheduler(pool: DbPool, scheduler: Scheduler, registry: Rc<JobRegistry>) {
+    let instance = Rc::new(Uuid::new_v4().to_simple().to_string());
+    actix_web::rt::spawn(async move {
+        let mut last = Utc::now();
+        loop {
+            actix_web::rt::time::sleep(std::time::Duration::from_secs(TICK_SECONDS)).await;
+            let now = Utc::now();
+            match tenant_databases(&pool).await {
+                Ok(databases) => {
+                    for database in databases {
+                        let due = match with_database(database.clone(), due_entries(&pool, &scheduler.entries, last, now)).await {
+                            Ok(x) => x,
+                            Err(e) => {
+                                tracing::error!(database = %database, error = %e, "scheduler cannot read schedules");
+                                continue;
+                            },
+                        };
+                        for (entry, slot) in due {
+                            let run = run_entry(pool.clone(), Rc::clone(&registry), entry, slot, Rc::clone(&instance));
+                            let database = database.clone();
+                            actix_web::rt::spawn(async move {
+                                if let Err(e) = with_database(database.clone(), run).await {
+                                    tracing::error!(database = %database, error = %e, "scheduler failed");
+                                }
+                            });
+                        }
+                    }
+                },
+                Err(e) => tracing::error!(error = %e, "scheduler cannot list databases"),
+            }
+            last = now;
+        }
+    });
+}
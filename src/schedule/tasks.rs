@@ -0,0 +1,48 @@
+use async_trait::async_trait;
+use arangors::AqlQuery;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+
+use crate::company::erase_company;
+use crate::database::{current_database, DbPool};
+use crate::job::JobHandler;
+use crate::user::erase_user;
+
+const TRASH_RETENTION_DAYS: i64 = 30;
+
+// erases users and companies that have been in the trash for long enough
+pub struct PurgeTrash;
+
+#[async_trait(?Send)]
+impl JobHandler for PurgeTrash {
+    const KIND: &'static str = "purge_trash";
+    const MAX_ATTEMPTS: u32 = 1;
+
+    type Payload = ();
+
+    async fn run(_payload: (), pool: &DbPool) -> Result<(), String> {
+        let client = pool.get().await.unwrap();
+        let db = client.db(&current_database()).await.map_err(|e| e.to_string())?;
+
+        let q = r#"FOR x IN @@collection
+            FILTER x.deleted_at != null AND DATE_DIFF(x.deleted_at, DATE_NOW(), 'd') >= @days
+            RETURN x._key"#;
+        for collection in ["users", "companies"] {
+            let mut vars: HashMap<&str, Value> = HashMap::new();
+            vars.insert("@collection", to_value(collection).unwrap());
+            vars.insert("days", to_value(TRASH_RETENTION_DAYS).unwrap());
+            let aql = AqlQuery::builder()
+                .query(q)
+                .bind_vars(vars)
+                .build();
+            let keys: Vec<String> = db.aql_query(aql).await.map_err(|e| e.to_string())?;
+            for key in keys {
+                match collection {
+                    "users" => erase_user(&key, pool).await.map(|_| ())?,
+                    _ => erase_company(&key, pool).await.map(|_| ())?,
+                }
+            }
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,16 @@
+use std::{env, io};
+use uuid::Uuid;
+
+// files are kept under ./storage with a unique name, the returned path is what records refer to
+pub async fn store_file(
+    extension: &str,
+    data: &[u8],
+) -> Result<String, io::Error> {
+    let uniqname = format!("{}.{}", Uuid::new_v4(), extension);
+    let mut filepath = env::current_dir()?;
+    filepath.push("storage");
+    tokio::fs::create_dir_all(&filepath).await?;
+    filepath.push(&uniqname);
+    tokio::fs::write(&filepath, data).await?;
+    Ok(format!("/storage/{}", uniqname))
+}
@@ -0,0 +1,320 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{json, to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+use validator::{ValidationError, ValidationErrors};
+
+use crate::database::{current_database, DbPool};
+use crate::tag::{
+    CreateTagRequest,
+    FindTagsParams,
+    Tag,
+    TagResponse,
+    Tagging,
+    UpdateTagRequest,
+};
+
+const TAG_TERMS: &str = r#"RETURN {
+        _key: t._key,
+        name: t.name,
+        colour: t.colour,
+        usage: LENGTH(FOR e IN taggings FILTER e._to == t._id RETURN 1),
+        created_at: t.created_at,
+        modified_at: t.modified_at
+    }"#;
+
+// splits ?tags=a,b into distinct names
+pub fn parse_tag_names(text: &str) -> Vec<String> {
+    let mut names: Vec<String> = text.split(',')
+        .map(|x| x.trim().to_string())
+        .filter(|x| !x.is_empty())
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+// tags are named per company, so filtering by them needs the company
+pub fn tags_company(company_key: Option<&str>) -> Result<&str, ValidationErrors> {
+    company_key.ok_or_else(|| {
+        let mut errors = ValidationErrors::new();
+        errors.add("tags_company", ValidationError::new("required_with_tags"));
+        errors
+    })
+}
+
+// filter on documents bound to `var`, expects @tags, @tags_company and, when matching all, @tag_count
+pub fn tag_filter(var: &str, match_all: bool) -> String {
+    if match_all {
+        format!("FILTER LENGTH(UNIQUE(FOR e IN taggings FILTER e._from == {}._id AND e.company_key == @tags_company AND e.name IN @tags RETURN e.name)) == @tag_count", var)
+    } else {
+        format!("FILTER LENGTH(FOR e IN taggings FILTER e._from == {}._id AND e.company_key == @tags_company AND e.name IN @tags LIMIT 1 RETURN 1) > 0", var)
+    }
+}
+
+async fn find_tag(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<TagResponse, Error> {
+    let q = format!("FOR t IN tags FILTER t._key == @key AND t.company_key == @company_key {}", TAG_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<TagResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("tag not found"))
+}
+
+async fn run_query(
+    db: &Database<ReqwestClient>,
+    q: &str,
+    vars: HashMap<&str, Value>,
+) -> Result<Vec<Value>, Error> {
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await.map_err(ErrorInternalServerError)
+}
+
+#[instrument(skip_all)]
+pub async fn find_tags(
+    company_key: &str,
+    params: FindTagsParams,
+    pool: &DbPool,
+) -> Result<Vec<TagResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut terms = vec!["FOR t IN tags FILTER t.company_key == @company_key"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    if let Some(target) = params.target {
+        terms.push("FILTER LENGTH(FOR e IN taggings FILTER e._from == @target AND e._to == t._id RETURN 1) > 0");
+        vars.insert("target", to_value(target).unwrap());
+    }
+    terms.push("SORT t.name ASC");
+    terms.push(TAG_TERMS);
+    let q = terms.join(" ");
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<TagResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn create_tag(
+    company_key: &str,
+    payload: &CreateTagRequest,
+    pool: &DbPool,
+) -> Result<TagResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("tags").await.unwrap();
+    let now = Utc::now();
+    let data = Tag {
+        company_key: company_key.to_string(),
+        name: payload.name.clone(),
+        colour: payload.colour.to_lowercase(),
+        created_at: now,
+        modified_at: now,
+    };
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    // names are unique per company
+    let res: DocumentResponse<Document<Tag>> = collection.create_document(Document::new(data), options).await
+        .map_err(|_| ErrorConflict("tag already exists"))?;
+    let header = res.header().unwrap();
+    find_tag(&db, company_key, &header._key).await
+}
+
+// renaming also renames the copies kept on the edges
+#[instrument(skip_all)]
+pub async fn update_tag(
+    company_key: &str,
+    key: &str,
+    payload: &UpdateTagRequest,
+    pool: &DbPool,
+) -> Result<TagResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_tag(&db, company_key, key).await?;
+
+    let mut data = json!({
+        "modified_at": Utc::now(),
+    });
+    if let Some(name) = &payload.name {
+        data["name"] = to_value(name).unwrap();
+    }
+    if let Some(colour) = &payload.colour {
+        data["colour"] = to_value(colour.to_lowercase()).unwrap();
+    }
+
+    let q = "UPDATE @key WITH @data IN tags";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", data);
+    run_query(&db, q, vars).await
+        .map_err(|_| ErrorConflict("tag already exists"))?;
+
+    if let Some(name) = &payload.name {
+        let q = "FOR e IN taggings FILTER e._to == @tag UPDATE e WITH { name: @name } IN taggings";
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("tag", to_value(format!("tags/{}", key)).unwrap());
+        vars.insert("name", to_value(name).unwrap());
+        run_query(&db, q, vars).await?;
+    }
+    find_tag(&db, company_key, key).await
+}
+
+#[instrument(skip_all)]
+pub async fn delete_tag(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_tag(&db, company_key, key).await?;
+
+    let q = "FOR e IN taggings FILTER e._to == @tag REMOVE e IN taggings";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("tag", to_value(format!("tags/{}", key)).unwrap());
+    run_query(&db, q, vars).await?;
+
+    let q = "REMOVE @key IN tags";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    run_query(&db, q, vars).await?;
+    Ok(())
+}
+
+// moves every edge of the merged tag to the remaining one, then removes the merged tag
+#[instrument(skip_all)]
+pub async fn merge_tag(
+    company_key: &str,
+    key: &str,
+    into: &str,
+    pool: &DbPool,
+) -> Result<TagResponse, Error> {
+    if key == into {
+        return Err(ErrorBadRequest("cannot merge a tag into itself"));
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_tag(&db, company_key, key).await?;
+    let target = find_tag(&db, company_key, into).await?;
+
+    // documents that already carry both tags keep a single edge
+    let q = r#"FOR e IN taggings
+        FILTER e._to == @source
+        FILTER LENGTH(FOR d IN taggings FILTER d._from == e._from AND d._to == @target RETURN 1) > 0
+        REMOVE e IN taggings"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("source", to_value(format!("tags/{}", key)).unwrap());
+    vars.insert("target", to_value(format!("tags/{}", into)).unwrap());
+    run_query(&db, q, vars).await?;
+
+    let q = "FOR e IN taggings FILTER e._to == @source UPDATE e WITH { _to: @target, name: @name } IN taggings";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("source", to_value(format!("tags/{}", key)).unwrap());
+    vars.insert("target", to_value(format!("tags/{}", into)).unwrap());
+    vars.insert("name", to_value(&target.name).unwrap());
+    run_query(&db, q, vars).await?;
+
+    let q = "REMOVE @key IN tags";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    run_query(&db, q, vars).await?;
+
+    find_tag(&db, company_key, into).await
+}
+
+#[instrument(skip_all)]
+pub async fn tag_target(
+    company_key: &str,
+    key: &str,
+    target: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let tag = find_tag(&db, company_key, key).await?;
+
+    let q = "RETURN DOCUMENT(@target) != null";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("target", to_value(target).unwrap());
+    let exists = run_query(&db, q, vars).await?;
+    if exists.first() != Some(&Value::Bool(true)) {
+        return Err(ErrorNotFound("target not found"));
+    }
+
+    let data = Tagging {
+        _from: target.to_string(),
+        _to: format!("tags/{}", key),
+        company_key: company_key.to_string(),
+        name: tag.name,
+        created_at: Utc::now(),
+    };
+    let q = r#"UPSERT { _from: @from, _to: @to }
+        INSERT @data
+        UPDATE {}
+        IN taggings"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(&data._from).unwrap());
+    vars.insert("to", to_value(&data._to).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+    run_query(&db, q, vars).await?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn untag_target(
+    company_key: &str,
+    key: &str,
+    target: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_tag(&db, company_key, key).await?;
+
+    let q = "FOR e IN taggings FILTER e._from == @from AND e._to == @to REMOVE e IN taggings RETURN OLD._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(target).unwrap());
+    vars.insert("to", to_value(format!("tags/{}", key)).unwrap());
+    let removed = run_query(&db, q, vars).await?;
+    if removed.is_empty() {
+        return Err(ErrorNotFound("tag not applied to target"));
+    }
+    Ok(())
+}
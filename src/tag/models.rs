@@ -0,0 +1,91 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+// collections whose documents can be tagged
+pub const TAGGABLE: [&str; 2] = ["companies", "users"];
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindTagsParams {
+    #[validate(custom = "validate_target")]
+    pub target: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct CreateTagRequest {
+    #[validate(length(min = 1, max = 50), custom = "validate_name")]
+    pub name: String,
+    #[validate(custom = "validate_colour")]
+    pub colour: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct UpdateTagRequest {
+    #[validate(length(min = 1, max = 50), custom = "validate_name")]
+    pub name: Option<String>,
+    #[validate(custom = "validate_colour")]
+    pub colour: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct MergeTagRequest {
+    pub into: String, // key of the tag that remains
+}
+
+// names are used in comma separated filters
+fn validate_name(name: &str) -> Result<(), ValidationError> {
+    if name.contains(',') || name.trim() != name {
+        return Err(ValidationError::new("wrong_name"));
+    }
+    Ok(())
+}
+
+fn validate_colour(colour: &str) -> Result<(), ValidationError> {
+    match colour.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => Ok(()),
+        _ => Err(ValidationError::new("wrong_colour")),
+    }
+}
+
+pub fn validate_target(target: &str) -> Result<(), ValidationError> {
+    match target.split_once('/') {
+        Some((collection, key)) if TAGGABLE.contains(&collection) && !key.is_empty() && !key.contains('/') => Ok(()),
+        _ => Err(ValidationError::new("wrong_target")),
+    }
+}
+
+pub fn validate_tags_mode(mode: &str) -> Result<(), ValidationError> {
+    match mode {
+        "all" | "any" => Ok(()),
+        _ => Err(ValidationError::new("wrong_tags_mode")),
+    }
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tag {
+    pub company_key: String,
+    pub name: String,
+    pub colour: String,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+// edge from the tagged document to the tag, the name is copied for filtering
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tagging {
+    pub _from: String,
+    pub _to: String,
+    pub company_key: String,
+    pub name: String,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TagResponse {
+    pub _key: String,
+    pub name: String,
+    pub colour: String,
+    pub usage: u32,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
@@ -0,0 +1,144 @@
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::tag::{
+    self,
+    validate_target,
+    CreateTagRequest,
+    FindTagsParams,
+    MergeTagRequest,
+    UpdateTagRequest,
+};
+
+#[get("/companies/{key}/tags")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindTagsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_access(&req, &key, "companies:read")?;
+    let params: FindTagsParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = tag::find_tags(&key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/tags")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreateTagRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_admin(&req, &key, &pool).await?;
+    let params: CreateTagRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = tag::create_tag(&key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/tags/{tag_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<UpdateTagRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, tag_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: UpdateTagRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = tag::update_tag(&key, &tag_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/tags/{tag_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, tag_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    tag::delete_tag(&key, &tag_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[post("/companies/{key}/tags/{tag_key}/merge")]
+async fn merge(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<MergeTagRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, tag_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let result = tag::merge_tag(&key, &tag_key, &payload.into, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/companies/{key}/tags/{tag_key}/targets/{collection}/{target_key}")]
+async fn tag_target(
+    req: HttpRequest,
+    path: web::Path<(String, String, String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, tag_key, collection, target_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let target = format!("{}/{}", collection, target_key);
+    match validate_target(&target) {
+        Ok(_) => {
+            tag::tag_target(&key, &tag_key, &target, &pool).await?;
+            Ok(HttpResponse::NoContent().finish())
+        },
+        Err(e) => {
+            Ok(HttpResponse::BadRequest().json(e))
+        },
+    }
+}
+
+#[delete("/companies/{key}/tags/{tag_key}/targets/{collection}/{target_key}")]
+async fn untag_target(
+    req: HttpRequest,
+    path: web::Path<(String, String, String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, tag_key, collection, target_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let target = format!("{}/{}", collection, target_key);
+    tag::untag_target(&key, &tag_key, &target, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(merge);
+    cfg.service(tag_target);
+    cfg.service(untag_target);
+}
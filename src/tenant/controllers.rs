@@ -0,0 +1,164 @@
+use actix_web::{
+    error::{ErrorConflict, ErrorInternalServerError},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{
+        options::{InsertOptions, UpdateOptions},
+        response::DocumentResponse,
+    },
+    AqlQuery, ClientError, Collection, Connection, Document,
+};
+use chrono::prelude::*;
+use serde_json::{json, Value};
+use tracing::instrument;
+
+use crate::config::{db_database, tenancy_mode};
+use crate::database::DbPool;
+use crate::migrations::{self, AdminConnection, REGISTRY_DATABASE};
+use crate::tenant::{ProvisionTenantRequest, Tenant, TenantResponse};
+
+pub fn tenancy_enabled() -> bool {
+    tenancy_mode() == "database"
+}
+
+fn to_response(tenant: &Document<Tenant>) -> TenantResponse {
+    TenantResponse {
+        slug: tenant.header._key.clone(),
+        name: tenant.name.clone(),
+        database: tenant.database.clone(),
+        status: tenant.status.clone(),
+        created_at: tenant.created_at,
+    }
+}
+
+// only tenants whose database is ready are served
+#[instrument(skip_all)]
+pub async fn resolve_tenant(
+    slug: &str,
+    pool: &DbPool,
+) -> Option<Document<Tenant>> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(REGISTRY_DATABASE).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("tenants").await.unwrap();
+    let tenant: Document<Tenant> = collection.document(slug).await.ok()?;
+    if tenant.status != "active" {
+        return None;
+    }
+    Some(tenant)
+}
+
+#[instrument(skip_all)]
+pub async fn find_tenants(pool: &DbPool) -> Result<Vec<TenantResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(REGISTRY_DATABASE).await.unwrap();
+
+    let q = r#"FOR t IN tenants
+        SORT t._key ASC
+        RETURN {
+            slug: t._key,
+            name: t.name,
+            database: t.database,
+            status: t.status,
+            created_at: t.created_at
+        }"#;
+    let aql = AqlQuery::builder()
+        .query(q)
+        .build();
+    let records: Vec<TenantResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// safe to call again for a tenant whose provisioning was interrupted
+#[instrument(skip_all)]
+pub async fn provision_tenant(
+    payload: &ProvisionTenantRequest,
+    admin: &AdminConnection,
+    pool: &DbPool,
+) -> Result<TenantResponse, Error> {
+    // the app user only reads the registry, tenants are written with the admin credentials
+    let client = pool.get().await.unwrap();
+    let conn: &Connection = admin.0.as_ref().unwrap_or(&*client);
+    let registry = conn.db(REGISTRY_DATABASE).await
+        .map_err(ErrorInternalServerError)?;
+    let collection: Collection<ReqwestClient> = registry.collection("tenants").await.unwrap();
+
+    let database = match collection.document::<Tenant>(&payload.slug).await {
+        Ok(tenant) if tenant.status == "active" => {
+            return Err(ErrorConflict("tenant already exists"));
+        },
+        Ok(tenant) => tenant.document.database,
+        Err(_) => {
+            let database = format!("tenant_{}", payload.slug.replace('-', "_"));
+            let mut doc = Document::new(Tenant {
+                name: payload.name.trim().to_string(),
+                database: database.clone(),
+                status: "provisioning".to_string(),
+                created_at: Utc::now(),
+            });
+            doc.header._key = payload.slug.clone();
+            let options: InsertOptions = InsertOptions::builder()
+                .return_new(false)
+                .build();
+            let _res: DocumentResponse<Document<Tenant>> = collection.create_document(doc, options).await
+                .map_err(|_| ErrorConflict("tenant already exists"))?;
+            database
+        },
+    };
+
+    let databases = conn.accessible_databases().await
+        .map_err(ErrorInternalServerError)?;
+    if !databases.contains_key(&database) {
+        conn.create_database(&database).await
+            .map_err(ErrorInternalServerError)?;
+    }
+    if admin.0.is_some() {
+        migrations::grant_app_database(conn, &database).await
+            .map_err(ErrorInternalServerError)?;
+    }
+    migrations::run(pool, &database).await
+        .map_err(ErrorInternalServerError)?;
+
+    let data: Value = json!({
+        "status": "active",
+    });
+    let options: UpdateOptions = UpdateOptions::builder()
+        .return_new(false)
+        .build();
+    let _res: DocumentResponse<Document<Value>> = collection.update_document(&payload.slug, Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+
+    let tenant: Document<Tenant> = collection.document(&payload.slug).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(to_response(&tenant))
+}
+
+// every database that holds application data, for work done outside of requests
+#[instrument(skip_all)]
+pub async fn tenant_databases(pool: &DbPool) -> Result<Vec<String>, ClientError> {
+    if !tenancy_enabled() {
+        return Ok(vec![db_database()]);
+    }
+
+    let client = pool.get().await.unwrap();
+    let db = client.db(REGISTRY_DATABASE).await?;
+    let aql = AqlQuery::builder()
+        .query("FOR t IN tenants FILTER t.status == 'active' RETURN t.database")
+        .build();
+    let databases: Vec<String> = db.aql_query(aql).await?;
+    Ok(databases)
+}
+
+// brings every tenant database up to date on startup
+#[instrument(skip_all)]
+pub async fn migrate_tenants(pool: &DbPool) -> Result<(), ClientError> {
+    migrations::run_registry(pool).await?;
+
+    for database in tenant_databases(pool).await? {
+        migrations::run(pool, &database).await?;
+    }
+    Ok(())
+}
@@ -0,0 +1,96 @@
+use actix_web::{
+    body::EitherBody,
+    dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform},
+    error::{ErrorBadRequest, ErrorNotFound},
+    web,
+    Error,
+};
+use futures::future::{ready, LocalBoxFuture, Ready};
+use std::rc::Rc;
+
+use crate::config::tenant_domain;
+use crate::database::{with_database, DbPool};
+use crate::i18n::localized_error;
+use crate::metrics::METRICS_PATH;
+use crate::tenant::{resolve_tenant, tenancy_enabled, TENANTS_PATH, TENANT_HEADER};
+
+// the header wins over the subdomain so that api clients can use a single host
+fn tenant_slug(req: &ServiceRequest) -> Option<String> {
+    if let Some(value) = req.headers().get(TENANT_HEADER) {
+        return value.to_str().ok().map(|x| x.trim().to_lowercase());
+    }
+
+    let domain = tenant_domain();
+    if domain.is_empty() {
+        return None;
+    }
+    let info = req.connection_info();
+    let host = info.host().split(':').next()?;
+    let slug = host.strip_suffix(domain.as_str())?.strip_suffix('.')?;
+    if slug.is_empty() || slug.contains('.') {
+        return None;
+    }
+    Some(slug.to_lowercase())
+}
+
+// routes every request to the database of its tenant, must wrap the authentication middleware
+pub struct Tenancy;
+
+impl<S, B> Transform<S, ServiceRequest> for Tenancy
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Transform = TenancyMiddleware<S>;
+    type InitError = ();
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TenancyMiddleware {
+            service: Rc::new(service),
+        }))
+    }
+}
+
+pub struct TenancyMiddleware<S> {
+    service: Rc<S>,
+}
+
+impl<S, B> Service<ServiceRequest> for TenancyMiddleware<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<EitherBody<B>>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let service = Rc::clone(&self.service);
+
+        Box::pin(async move {
+            if !tenancy_enabled() || req.path().starts_with(TENANTS_PATH) || req.path() == METRICS_PATH {
+                return service.call(req).await.map(|x| x.map_into_left_body());
+            }
+
+            // this runs outside of the localization middleware, so its errors are rendered here
+            let slug = match tenant_slug(&req) {
+                Some(slug) => slug,
+                None => return localized_error(req, ErrorBadRequest("tenant required")),
+            };
+            let pool = req.app_data::<web::Data<DbPool>>().cloned().unwrap();
+            let tenant = match resolve_tenant(&slug, &pool).await {
+                Some(tenant) => tenant,
+                None => return localized_error(req, ErrorNotFound("unknown tenant")),
+            };
+            with_database(tenant.document.database, service.call(req)).await
+                .map(|x| x.map_into_left_body())
+        })
+    }
+}
@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod middleware;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use middleware::Tenancy;
+pub use routes::init;
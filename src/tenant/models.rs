@@ -0,0 +1,45 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+pub const TENANT_HEADER: &str = "X-Tenant";
+pub const TENANTS_PATH: &str = "/api/v1/tenants"; // served outside of any tenant
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct ProvisionTenantRequest {
+    #[validate(custom = "validate_slug")]
+    pub slug: String,
+    #[validate(length(min = 1, max = 100))]
+    pub name: String,
+}
+
+// slugs end up in host names and database names
+fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    let valid = slug.len() >= 3
+        && slug.len() <= 32
+        && slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-')
+        && !slug.starts_with('-')
+        && !slug.ends_with('-');
+    if !valid {
+        return Err(ValidationError::new("wrong_slug"));
+    }
+    Ok(())
+}
+
+// registry document in the _system database, keyed by slug
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Tenant {
+    pub name: String,
+    pub database: String,
+    pub status: String, // provisioning until its database is migrated, then active
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TenantResponse {
+    pub slug: String,
+    pub name: String,
+    pub database: String,
+    pub status: String,
+    pub created_at: DateTime<Utc>,
+}
@@ -0,0 +1,60 @@
+use actix_web::{
+    error::{ErrorNotFound, ErrorUnauthorized},
+    get, post, web, Error, HttpRequest, HttpResponse,
+};
+use validator::Validate;
+
+use crate::auth;
+use crate::config::tenant_admin_token;
+use crate::database::DbPool;
+use crate::migrations::AdminConnection;
+use crate::i18n::Invalid;
+use crate::tenant::{self, ProvisionTenantRequest};
+
+// tenants are managed by the operator of the installation, not by its users
+fn require_operator(req: &HttpRequest) -> Result<(), Error> {
+    if !tenant::tenancy_enabled() {
+        return Err(ErrorNotFound("tenancy is disabled"));
+    }
+    let expected = tenant_admin_token();
+    match auth::bearer_token(req) {
+        Some(token) if !expected.is_empty() && token == expected => Ok(()),
+        _ => Err(ErrorUnauthorized("operator token required")),
+    }
+}
+
+#[get("/tenants")]
+async fn find(
+    req: HttpRequest,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_operator(&req)?;
+    let result = tenant::find_tenants(&pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/tenants")]
+async fn provision(
+    req: HttpRequest,
+    payload: web::Json<ProvisionTenantRequest>,
+    admin: web::Data<AdminConnection>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_operator(&req)?;
+    let params: ProvisionTenantRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = tenant::provision_tenant(&params, &admin, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(provision);
+}
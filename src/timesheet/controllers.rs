@@ -0,0 +1,635 @@
+use actix_web::{
+    error::{ErrorConflict, ErrorForbidden, ErrorInternalServerError, ErrorNotFound},
+    http::StatusCode,
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{options::InsertOptions, response::DocumentResponse},
+    AqlQuery, ClientError, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::database::{current_database, DbPool};
+use crate::i18n::failure;
+use crate::timesheet::{
+    week_of,
+    DecideTimesheetRequest,
+    FindTimeEntriesParams,
+    FindTimesheetsParams,
+    ReportParams,
+    ReportRow,
+    StartTimerRequest,
+    TimeEntry,
+    TimeEntryRequest,
+    TimeEntryResponse,
+    Timesheet,
+    TimesheetResponse,
+};
+
+const ENTRY_TERMS: &str = "RETURN UNSET(e, '_id', '_rev', 'company_key', 'starts_at', 'running')";
+
+const UNIQUE_CONSTRAINT_VIOLATED: u16 = 1210;
+
+const TIMESHEET_TERMS: &str = r#"RETURN {
+        _key: t._key,
+        user_key: t.user_key,
+        user_name: DOCUMENT('users', t.user_key).name,
+        week: t.week,
+        status: t.status,
+        total_seconds: t.total_seconds,
+        billable_seconds: t.billable_seconds,
+        submitted_at: t.submitted_at,
+        decided_by: t.decided_by,
+        decided_at: t.decided_at,
+        comment: t.comment
+    }"#;
+
+async fn find_entry(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    user_key: &str,
+    key: &str,
+) -> Result<TimeEntryResponse, Error> {
+    let q = format!(r#"FOR e IN time_entries
+        FILTER e._key == @key AND e.company_key == @company_key AND e.user_key == @user_key {}"#, ENTRY_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<TimeEntryResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("time entry not found"))
+}
+
+async fn find_timesheet(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<TimesheetResponse, Error> {
+    let q = format!("FOR t IN timesheets FILTER t._key == @key AND t.company_key == @company_key {}", TIMESHEET_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<TimesheetResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("timesheet not found"))
+}
+
+// entries of weeks waiting for or past approval cannot change
+async fn check_week_open(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    user_key: &str,
+    weeks: &[&str],
+) -> Result<(), Error> {
+    let q = r#"FOR t IN timesheets
+        FILTER t.company_key == @company_key AND t.user_key == @user_key AND t.week IN @weeks
+        FILTER t.status IN ['submitted', 'approved']
+        RETURN t.week"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("weeks", to_value(weeks).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    match records.first() {
+        Some(week) => Err(failure(StatusCode::CONFLICT, "timesheet_already_submitted", &[("week", week)])),
+        None => Ok(()),
+    }
+}
+
+async fn running_entry(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    user_key: &str,
+) -> Result<Option<TimeEntryResponse>, Error> {
+    let q = format!(r#"FOR e IN time_entries
+        FILTER e.company_key == @company_key AND e.user_key == @user_key AND e.end == null {}"#, ENTRY_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<TimeEntryResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records.pop())
+}
+
+fn to_entry(
+    company_key: &str,
+    user_key: &str,
+    payload: &TimeEntryRequest,
+    created_at: DateTime<Utc>,
+) -> TimeEntry {
+    let end = payload.end();
+    TimeEntry {
+        company_key: company_key.to_string(),
+        user_key: user_key.to_string(),
+        project: payload.project.trim().to_string(),
+        task: payload.task.clone(),
+        start: payload.start,
+        end: Some(end),
+        starts_at: payload.start.timestamp(),
+        duration_seconds: (end - payload.start).num_seconds(),
+        week: week_of(payload.start),
+        billable: payload.billable.unwrap_or(false),
+        notes: payload.notes.clone(),
+        running: None,
+        created_at,
+        modified_at: Utc::now(),
+    }
+}
+
+async fn insert_entry(
+    db: &Database<ReqwestClient>,
+    data: TimeEntry,
+) -> Result<TimeEntryResponse, Error> {
+    let company_key = data.company_key.clone();
+    let user_key = data.user_key.clone();
+    let collection: Collection<ReqwestClient> = db.collection("time_entries").await.unwrap();
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<TimeEntry>> = match collection.create_document(Document::new(data), options).await {
+        Ok(x) => x,
+        Err(ClientError::Arango(e)) if e.error_num() == UNIQUE_CONSTRAINT_VIOLATED => {
+            return Err(ErrorConflict("a timer is already running"));
+        },
+        Err(e) => return Err(ErrorInternalServerError(e)),
+    };
+    let header = res.header().unwrap();
+    find_entry(db, &company_key, &user_key, &header._key).await
+}
+
+// without a user every entry of the company is returned
+#[instrument(skip_all)]
+pub async fn find_time_entries(
+    company_key: &str,
+    user_key: Option<&str>,
+    params: FindTimeEntriesParams,
+    pool: &DbPool,
+) -> Result<Vec<TimeEntryResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut filters = vec!["FILTER e.company_key == @company_key AND e.starts_at >= @from AND e.starts_at < @to"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("from", to_value(params.from.timestamp()).unwrap());
+    vars.insert("to", to_value(params.to.timestamp()).unwrap());
+    if let Some(user_key) = user_key {
+        filters.push("FILTER e.user_key == @user_key");
+        vars.insert("user_key", to_value(user_key).unwrap());
+    }
+    if let Some(project) = params.project {
+        filters.push("FILTER e.project == @project");
+        vars.insert("project", to_value(project).unwrap());
+    }
+    let q = format!("FOR e IN time_entries {} SORT e.starts_at ASC {}", filters.join(" "), ENTRY_TERMS);
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<TimeEntryResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn create_time_entry(
+    company_key: &str,
+    user_key: &str,
+    payload: &TimeEntryRequest,
+    pool: &DbPool,
+) -> Result<TimeEntryResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let data = to_entry(company_key, user_key, payload, Utc::now());
+    check_week_open(&db, company_key, user_key, &[&data.week]).await?;
+    insert_entry(&db, data).await
+}
+
+// a running timer can be finished this way as well
+#[instrument(skip_all)]
+pub async fn update_time_entry(
+    company_key: &str,
+    user_key: &str,
+    key: &str,
+    payload: &TimeEntryRequest,
+    pool: &DbPool,
+) -> Result<TimeEntryResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_entry(&db, company_key, user_key, key).await?;
+    let data = to_entry(company_key, user_key, payload, current.created_at);
+    check_week_open(&db, company_key, user_key, &[&current.week, &data.week]).await?;
+
+    let q = "FOR e IN time_entries FILTER e._key == @key REPLACE e WITH @data IN time_entries";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_entry(&db, company_key, user_key, key).await
+}
+
+#[instrument(skip_all)]
+pub async fn delete_time_entry(
+    company_key: &str,
+    user_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = find_entry(&db, company_key, user_key, key).await?;
+    check_week_open(&db, company_key, user_key, &[&current.week]).await?;
+
+    let q = "REMOVE @key IN time_entries";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn show_timer(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<Option<TimeEntryResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    running_entry(&db, company_key, user_key).await
+}
+
+// one timer per user and company
+#[instrument(skip_all)]
+pub async fn start_timer(
+    company_key: &str,
+    user_key: &str,
+    payload: &StartTimerRequest,
+    pool: &DbPool,
+) -> Result<TimeEntryResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    if running_entry(&db, company_key, user_key).await?.is_some() {
+        return Err(ErrorConflict("a timer is already running"));
+    }
+    let now = Utc::now();
+    let week = week_of(now);
+    check_week_open(&db, company_key, user_key, &[&week]).await?;
+
+    let data = TimeEntry {
+        company_key: company_key.to_string(),
+        user_key: user_key.to_string(),
+        project: payload.project.trim().to_string(),
+        task: payload.task.clone(),
+        start: now,
+        end: None,
+        starts_at: now.timestamp(),
+        duration_seconds: 0,
+        week,
+        billable: payload.billable.unwrap_or(false),
+        notes: payload.notes.clone(),
+        running: Some(format!("{}/{}", company_key, user_key)),
+        created_at: now,
+        modified_at: now,
+    };
+    insert_entry(&db, data).await
+}
+
+#[instrument(skip_all)]
+pub async fn stop_timer(
+    company_key: &str,
+    user_key: &str,
+    pool: &DbPool,
+) -> Result<TimeEntryResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let current = running_entry(&db, company_key, user_key).await?
+        .ok_or_else(|| ErrorNotFound("no timer is running"))?;
+
+    let now = Utc::now();
+    let q = r#"FOR e IN time_entries
+        FILTER e._key == @key AND e.end == null
+        UPDATE e WITH { end: @now, duration_seconds: @duration_seconds, running: null, modified_at: @now } IN time_entries
+        OPTIONS { keepNull: false }"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(&current._key).unwrap());
+    vars.insert("now", to_value(now).unwrap());
+    vars.insert("duration_seconds", to_value((now - current.start).num_seconds()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_entry(&db, company_key, user_key, &current._key).await
+}
+
+// totals are frozen at submission, rejected weeks can be fixed and submitted again
+#[instrument(skip_all)]
+pub async fn submit_timesheet(
+    company_key: &str,
+    user_key: &str,
+    week: &str,
+    pool: &DbPool,
+) -> Result<TimesheetResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    check_week_open(&db, company_key, user_key, &[week]).await?;
+    if let Some(timer) = running_entry(&db, company_key, user_key).await? {
+        if timer.week == week {
+            return Err(ErrorConflict("stop the running timer first"));
+        }
+    }
+
+    let q = r#"FOR e IN time_entries
+        FILTER e.company_key == @company_key AND e.user_key == @user_key AND e.week == @week
+        COLLECT AGGREGATE total = SUM(e.duration_seconds), billable = SUM(e.billable ? e.duration_seconds : 0)
+        RETURN [total || 0, billable || 0]"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("week", to_value(week).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut totals: Vec<(i64, i64)> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    let (total_seconds, billable_seconds) = totals.pop().unwrap_or((0, 0));
+
+    let data = Timesheet {
+        company_key: company_key.to_string(),
+        user_key: user_key.to_string(),
+        week: week.to_string(),
+        status: "submitted".to_string(),
+        total_seconds,
+        billable_seconds,
+        submitted_at: Utc::now(),
+        decided_by: None,
+        decided_at: None,
+        comment: None,
+    };
+    let q = r#"UPSERT { company_key: @company_key, user_key: @user_key, week: @week }
+        INSERT @data
+        REPLACE @data
+        IN timesheets
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("user_key", to_value(user_key).unwrap());
+    vars.insert("week", to_value(week).unwrap());
+    vars.insert("data", to_value(&data).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    find_timesheet(&db, company_key, &records.pop().unwrap()).await
+}
+
+// without a user every timesheet of the company is returned
+#[instrument(skip_all)]
+pub async fn find_timesheets(
+    company_key: &str,
+    user_key: Option<&str>,
+    params: FindTimesheetsParams,
+    pool: &DbPool,
+) -> Result<Vec<TimesheetResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut filters = vec!["FILTER t.company_key == @company_key"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    if let Some(user_key) = user_key {
+        filters.push("FILTER t.user_key == @user_key");
+        vars.insert("user_key", to_value(user_key).unwrap());
+    }
+    if let Some(status) = params.status {
+        filters.push("FILTER t.status == @status");
+        vars.insert("status", to_value(status).unwrap());
+    }
+    if let Some(week) = params.week {
+        filters.push("FILTER t.week == @week");
+        vars.insert("week", to_value(week).unwrap());
+    }
+    let q = format!("FOR t IN timesheets {} SORT t.week DESC, t.user_key ASC {}", filters.join(" "), TIMESHEET_TERMS);
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<TimesheetResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn decide_timesheet(
+    company_key: &str,
+    key: &str,
+    decided_by: &str,
+    payload: &DecideTimesheetRequest,
+    pool: &DbPool,
+) -> Result<TimesheetResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR t IN timesheets
+        FILTER t._key == @key AND t.company_key == @company_key AND t.status == 'submitted'
+        FILTER t.user_key != @decided_by
+        UPDATE t WITH { status: @status, decided_by: @decided_by, decided_at: @now, comment: @comment } IN timesheets
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("status", to_value(if payload.approved { "approved" } else { "rejected" }).unwrap());
+    vars.insert("decided_by", to_value(decided_by).unwrap());
+    vars.insert("now", to_value(Utc::now()).unwrap());
+    vars.insert("comment", to_value(&payload.comment).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        // managers cannot approve their own time
+        let current = find_timesheet(&db, company_key, key).await?;
+        if current.user_key == decided_by {
+            return Err(ErrorForbidden("cannot decide your own timesheet"));
+        }
+        return Err(ErrorNotFound("submitted timesheet not found"));
+    }
+    find_timesheet(&db, company_key, key).await
+}
+
+// finished entries started within the range, summed per group
+#[instrument(skip_all)]
+pub async fn time_report(
+    company_key: &str,
+    params: &ReportParams,
+    pool: &DbPool,
+) -> Result<Vec<ReportRow>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let (group, label) = match params.group_by.as_str() {
+        "user" => ("e.user_key", "DOCUMENT('users', group).name"),
+        "project" => ("e.project", "null"),
+        _ => ("e.week", "null"),
+    };
+    let mut filters = vec!["FILTER e.company_key == @company_key AND e.end != null AND e.starts_at >= @from AND e.starts_at < @to"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("from", to_value(params.from.timestamp()).unwrap());
+    vars.insert("to", to_value(params.to.timestamp()).unwrap());
+    if let Some(billable) = params.billable {
+        filters.push("FILTER e.billable == @billable");
+        vars.insert("billable", to_value(billable).unwrap());
+    }
+    if let Some(project) = &params.project {
+        filters.push("FILTER e.project == @project");
+        vars.insert("project", to_value(project).unwrap());
+    }
+    let q = format!(r#"FOR e IN time_entries {}
+        COLLECT group = {}
+        AGGREGATE total_seconds = SUM(e.duration_seconds),
+            billable_seconds = SUM(e.billable ? e.duration_seconds : 0),
+            entries = LENGTH(1)
+        SORT group ASC
+        RETURN {{ group, label: {}, total_seconds, billable_seconds, entries }}"#, filters.join(" "), group, label);
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<ReportRow> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// quotes when needed and defuses cells that spreadsheets would read as formulas
+fn csv_field(value: &str) -> String {
+    let value = if value.starts_with(['=', '+', '-', '@']) {
+        format!("'{}", value)
+    } else {
+        value.to_string()
+    };
+    if value.contains([',', '"', '\n', '\r']) {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value
+    }
+}
+
+fn hours(seconds: i64) -> String {
+    format!("{:.2}", seconds as f64 / 3600.0)
+}
+
+pub fn report_csv(group_by: &str, rows: &[ReportRow]) -> String {
+    let mut csv = format!("{},label,hours,billable_hours,entries\r\n", group_by);
+    for row in rows {
+        csv.push_str(&format!(
+            "{},{},{},{},{}\r\n",
+            csv_field(&row.group),
+            csv_field(row.label.as_deref().unwrap_or("")),
+            hours(row.total_seconds),
+            hours(row.billable_seconds),
+            row.entries,
+        ));
+    }
+    csv
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn csv_fields() {
+        assert_eq!(csv_field("plain"), "plain");
+        assert_eq!(csv_field("Doe, Ann"), "\"Doe, Ann\"");
+        assert_eq!(csv_field("say \"hi\"\nthere"), "\"say \"\"hi\"\"\nthere\"");
+        assert_eq!(csv_field("=SUM(A1:A9)"), "'=SUM(A1:A9)");
+        assert_eq!(csv_field("-1,5"), "\"'-1,5\"");
+        assert_eq!(csv_field("@cmd"), "'@cmd");
+    }
+
+    #[test]
+    fn report() {
+        let rows = vec![
+            ReportRow {
+                group: "12".to_string(),
+                label: Some("Doe, Ann".to_string()),
+                total_seconds: 5400,
+                billable_seconds: 3600,
+                entries: 2,
+            },
+            ReportRow {
+                group: "+proj".to_string(),
+                label: None,
+                total_seconds: 60,
+                billable_seconds: 0,
+                entries: 1,
+            },
+        ];
+        assert_eq!(
+            report_csv("user", &rows),
+            "user,label,hours,billable_hours,entries\r\n\
+             12,\"Doe, Ann\",1.50,1.00,2\r\n\
+             '+proj,,0.02,0.00,1\r\n",
+        );
+    }
+}
@@ -0,0 +1,11 @@
+mod models;
+mod controllers;
+mod routes;
+mod reminders;
+mod reports;
+
+pub use models::*;
+pub use controllers::*;
+pub use routes::init;
+pub use reminders::TimesheetReminders;
+pub use reports::WeeklyTimeReports;
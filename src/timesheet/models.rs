@@ -0,0 +1,252 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+pub const MAX_RANGE_DAYS: i64 = 366;
+
+// weeks are ISO weeks of the UTC start, as in 2021-W07
+pub fn week_of(moment: DateTime<Utc>) -> String {
+    let week = moment.iso_week();
+    format!("{:04}-W{:02}", week.year(), week.week())
+}
+
+pub fn parse_week(week: &str) -> Option<NaiveDate> {
+    let (year, number) = week.split_once("-W")?;
+    if number.len() != 2 {
+        return None;
+    }
+    NaiveDate::from_isoywd_opt(year.parse().ok()?, number.parse().ok()?, Weekday::Mon)
+}
+
+fn validate_week(week: &str) -> Result<(), ValidationError> {
+    if parse_week(week).is_none() {
+        return Err(ValidationError::new("wrong_week"));
+    }
+    Ok(())
+}
+
+fn validate_group_by(group_by: &str) -> Result<(), ValidationError> {
+    match group_by {
+        "user" | "project" | "week" => Ok(()),
+        _ => Err(ValidationError::new("wrong_group_by")),
+    }
+}
+
+fn validate_format(format: &str) -> Result<(), ValidationError> {
+    match format {
+        "json" | "csv" => Ok(()),
+        _ => Err(ValidationError::new("wrong_format")),
+    }
+}
+
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    match status {
+        "submitted" | "approved" | "rejected" => Ok(()),
+        _ => Err(ValidationError::new("wrong_status")),
+    }
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_entry"))]
+pub struct TimeEntryRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub project: String,
+    #[validate(length(min = 1, max = 100))]
+    pub task: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>, // either end or duration
+    #[validate(range(min = 1, max = 1440))]
+    pub duration_minutes: Option<u32>,
+    pub billable: Option<bool>,
+    #[validate(length(max = 2000))]
+    pub notes: Option<String>,
+}
+
+impl TimeEntryRequest {
+    pub fn end(&self) -> DateTime<Utc> {
+        match (self.end, self.duration_minutes) {
+            (Some(end), _) => end,
+            (None, Some(minutes)) => self.start + chrono::Duration::minutes(minutes as i64),
+            (None, None) => self.start,
+        }
+    }
+}
+
+fn validate_entry(payload: &TimeEntryRequest) -> Result<(), ValidationError> {
+    if payload.end.is_some() == payload.duration_minutes.is_some() {
+        return Err(ValidationError::new("end_or_duration_required"));
+    }
+    let end = payload.end();
+    if end <= payload.start || end - payload.start > chrono::Duration::days(1) {
+        return Err(ValidationError::new("wrong_end"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct StartTimerRequest {
+    #[validate(length(min = 1, max = 100))]
+    pub project: String,
+    #[validate(length(min = 1, max = 100))]
+    pub task: Option<String>,
+    pub billable: Option<bool>,
+    #[validate(length(max = 2000))]
+    pub notes: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_entries_range"))]
+pub struct FindTimeEntriesParams {
+    pub user_key: Option<String>, // managers only, own entries by default
+    #[validate(length(min = 1, max = 100))]
+    pub project: Option<String>,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+}
+
+fn validate_entries_range(params: &FindTimeEntriesParams) -> Result<(), ValidationError> {
+    if params.to <= params.from || params.to - params.from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(ValidationError::new("wrong_range"));
+    }
+    Ok(())
+}
+
+// a running timer has no end and no duration yet
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct TimeEntry {
+    pub company_key: String,
+    pub user_key: String,
+    pub project: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub task: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub starts_at: i64, // unix seconds, compared numerically in queries
+    pub duration_seconds: i64,
+    pub week: String,
+    pub billable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub notes: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub running: Option<String>, // company and user while the timer runs, uniquely indexed
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimeEntryResponse {
+    pub _key: String,
+    pub user_key: String,
+    pub project: String,
+    pub task: Option<String>,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub duration_seconds: i64,
+    pub week: String,
+    pub billable: bool,
+    pub notes: Option<String>,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct SubmitTimesheetRequest {
+    #[validate(custom = "validate_week")]
+    pub week: String,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindTimesheetsParams {
+    #[validate(custom = "validate_status")]
+    pub status: Option<String>,
+    #[validate(custom = "validate_week")]
+    pub week: Option<String>,
+    pub user_key: Option<String>, // managers only, own timesheets by default
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct DecideTimesheetRequest {
+    pub approved: bool,
+    #[validate(length(max = 2000))]
+    pub comment: Option<String>,
+}
+
+// one per user and week, entries of submitted or approved weeks are locked
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Timesheet {
+    pub company_key: String,
+    pub user_key: String,
+    pub week: String,
+    pub status: String, // submitted, approved or rejected
+    pub total_seconds: i64,
+    pub billable_seconds: i64,
+    pub submitted_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub decided_by: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub decided_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub comment: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct TimesheetResponse {
+    pub _key: String,
+    pub user_key: String,
+    pub user_name: Option<String>,
+    pub week: String,
+    pub status: String,
+    pub total_seconds: i64,
+    pub billable_seconds: i64,
+    pub submitted_at: DateTime<Utc>,
+    pub decided_by: Option<String>,
+    pub decided_at: Option<DateTime<Utc>>,
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+#[validate(schema(function = "validate_report_range"))]
+pub struct ReportParams {
+    #[validate(custom = "validate_group_by")]
+    pub group_by: String,
+    pub from: DateTime<Utc>,
+    pub to: DateTime<Utc>,
+    pub billable: Option<bool>,
+    #[validate(length(min = 1, max = 100))]
+    pub project: Option<String>,
+    #[validate(custom = "validate_format")]
+    pub format: Option<String>, // json by default
+}
+
+fn validate_report_range(params: &ReportParams) -> Result<(), ValidationError> {
+    if params.to <= params.from || params.to - params.from > chrono::Duration::days(MAX_RANGE_DAYS) {
+        return Err(ValidationError::new("wrong_range"));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct ReportRow {
+    pub group: String,
+    pub label: Option<String>, // user name when grouped by user
+    pub total_seconds: i64,
+    pub billable_seconds: i64,
+    pub entries: i64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn weeks() {
+        // the first days of 2021 belong to the last week of 2020
+        let moment = "2021-01-03T23:59:59Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(week_of(moment), "2020-W53");
+        assert_eq!(parse_week("2020-W53"), NaiveDate::from_ymd_opt(2020, 12, 28));
+        assert_eq!(parse_week("2021-W07"), NaiveDate::from_ymd_opt(2021, 2, 15));
+        assert_eq!(parse_week("2021-W7"), None);
+        assert_eq!(parse_week("2021-W54"), None);
+        assert_eq!(parse_week("2021-07"), None);
+    }
+}
@@ -0,0 +1,67 @@
+use async_trait::async_trait;
+use arangors::AqlQuery;
+use chrono::prelude::*;
+use serde::Deserialize;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+
+use crate::database::{current_database, DbPool};
+use crate::job::{enqueue, JobHandler, JobOptions};
+use crate::mailer::{MailPayload, SendMail};
+use crate::timesheet::week_of;
+
+#[derive(Deserialize)]
+struct Pending {
+    email: String,
+    name: String,
+    company: String,
+}
+
+// mails users who tracked time this week but have not submitted the timesheet yet
+pub struct TimesheetReminders;
+
+#[async_trait(?Send)]
+impl JobHandler for TimesheetReminders {
+    const KIND: &'static str = "timesheet_reminders";
+    const MAX_ATTEMPTS: u32 = 1;
+
+    type Payload = ();
+
+    async fn run(_payload: (), pool: &DbPool) -> Result<(), String> {
+        let client = pool.get().await.unwrap();
+        let db = client.db(&current_database()).await.map_err(|e| e.to_string())?;
+
+        let week = week_of(Utc::now());
+        let q = r#"FOR e IN time_entries
+            FILTER e.week == @week
+            COLLECT company_key = e.company_key, user_key = e.user_key
+            LET sheet = FIRST(
+                FOR t IN timesheets
+                    FILTER t.company_key == company_key AND t.user_key == user_key AND t.week == @week
+                    RETURN t.status
+            )
+            FILTER sheet NOT IN ['submitted', 'approved']
+            LET u = DOCUMENT('users', user_key)
+            LET c = DOCUMENT('companies', company_key)
+            FILTER u != null AND u.deleted_at == null AND c != null
+            RETURN { email: u.email, name: u.name, company: c.name }"#;
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("week", to_value(&week).unwrap());
+
+        let aql = AqlQuery::builder()
+            .query(q)
+            .bind_vars(vars)
+            .build();
+        let records: Vec<Pending> = db.aql_query(aql).await.map_err(|e| e.to_string())?;
+        for record in records {
+            let payload = MailPayload {
+                to: record.email,
+                subject: format!("Timesheet {} for {}", week, record.company),
+                body: format!("Hi {},\n\nplease submit your timesheet for {} at {}.", record.name, week, record.company),
+            };
+            enqueue::<SendMail>(&payload, JobOptions::default(), pool).await
+                .map_err(|e| e.to_string())?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,96 @@
+use async_trait::async_trait;
+use arangors::AqlQuery;
+use chrono::{prelude::*, Duration};
+use serde::Deserialize;
+use serde_json::{to_value, Value};
+use std::collections::HashMap;
+
+use crate::database::{current_database, DbPool};
+use crate::job::{enqueue, JobHandler, JobOptions};
+use crate::mailer::{MailPayload, SendMail};
+use crate::timesheet::{parse_week, time_report, week_of, ReportParams, ReportRow};
+
+#[derive(Deserialize)]
+struct Recipients {
+    key: String,
+    name: String,
+    managers: Vec<String>,
+}
+
+fn hours(seconds: i64) -> String {
+    format!("{:.1} h", seconds as f64 / 3600.0)
+}
+
+fn report_body(company: &str, week: &str, rows: &[ReportRow]) -> String {
+    let lines: Vec<String> = rows.iter()
+        .map(|x| format!(
+            "- {}: {} ({} billable, {} entries)",
+            x.label.as_deref().unwrap_or(&x.group), hours(x.total_seconds), hours(x.billable_seconds), x.entries,
+        ))
+        .collect();
+    format!("Time tracked at {} in {}:\n\n{}", company, week, lines.join("\n"))
+}
+
+// mails the managers of every company that tracked time last week the hours per user
+pub struct WeeklyTimeReports;
+
+#[async_trait(?Send)]
+impl JobHandler for WeeklyTimeReports {
+    const KIND: &'static str = "weekly_time_reports";
+    const MAX_ATTEMPTS: u32 = 1;
+
+    type Payload = ();
+
+    async fn run(_payload: (), pool: &DbPool) -> Result<(), String> {
+        let client = pool.get().await.unwrap();
+        let db = client.db(&current_database()).await.map_err(|e| e.to_string())?;
+
+        let week = week_of(Utc::now() - Duration::days(7));
+        let monday = parse_week(&week).and_then(|x| x.and_hms_opt(0, 0, 0)).unwrap();
+        let params = ReportParams {
+            group_by: "user".to_string(),
+            from: Utc.from_utc_datetime(&monday),
+            to: Utc.from_utc_datetime(&monday) + Duration::days(7),
+            billable: None,
+            project: None,
+            format: None,
+        };
+
+        let q = r#"FOR e IN time_entries
+            FILTER e.week == @week
+            COLLECT company_key = e.company_key
+            LET c = DOCUMENT('companies', company_key)
+            FILTER c != null AND c.deleted_at == null
+            LET managers = (
+                FOR m IN memberships
+                    FILTER m._to == c._id AND m.role IN ['admin', 'manager']
+                    LET u = DOCUMENT(m._from)
+                    FILTER u != null AND u.deleted_at == null
+                    RETURN u.email
+            )
+            FILTER LENGTH(managers) > 0
+            RETURN { key: company_key, name: c.name, managers }"#;
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("week", to_value(&week).unwrap());
+
+        let aql = AqlQuery::builder()
+            .query(q)
+            .bind_vars(vars)
+            .build();
+        let companies: Vec<Recipients> = db.aql_query(aql).await.map_err(|e| e.to_string())?;
+        for company in companies {
+            let rows = time_report(&company.key, &params, pool).await.map_err(|e| e.to_string())?;
+            let body = report_body(&company.name, &week, &rows);
+            for email in company.managers {
+                let payload = MailPayload {
+                    to: email,
+                    subject: format!("Time report {} for {}", week, company.name),
+                    body: body.clone(),
+                };
+                enqueue::<SendMail>(&payload, JobOptions::default(), pool).await
+                    .map_err(|e| e.to_string())?;
+            }
+        }
+        Ok(())
+    }
+}
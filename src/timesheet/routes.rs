@@ -0,0 +1,280 @@
+use actix_web::{
+    delete, error::ErrorForbidden, get, http::header, post, put, web, Error, HttpRequest, HttpResponse,
+};
+use validator::Validate;
+
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::timesheet::{
+    self,
+    DecideTimesheetRequest,
+    FindTimeEntriesParams,
+    FindTimesheetsParams,
+    ReportParams,
+    StartTimerRequest,
+    SubmitTimesheetRequest,
+    TimeEntryRequest,
+};
+
+// members track their own time
+async fn require_member(req: &HttpRequest, key: &str, pool: &DbPool) -> Result<String, Error> {
+    let identity = company::require_access(req, key, "time:write")?;
+    let user_key = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("time is tracked by users"))?;
+    if !company::is_company_member(key, user_key, pool).await {
+        return Err(ErrorForbidden("company member required"));
+    }
+    Ok(user_key.to_string())
+}
+
+// managers and company keys see everyone, members only themselves
+async fn visible_user(
+    req: &HttpRequest,
+    key: &str,
+    requested: Option<&String>,
+    pool: &DbPool,
+) -> Result<Option<String>, Error> {
+    let identity = company::require_access(req, key, "time:read")?;
+    let user_key = match identity.user_key() {
+        Some(x) => x,
+        None => return Ok(requested.cloned()),
+    };
+    if company::is_company_manager(key, user_key, pool).await {
+        return Ok(requested.cloned());
+    }
+    match requested {
+        Some(x) if x != user_key => Err(ErrorForbidden("company manager required")),
+        _ if !company::is_company_member(key, user_key, pool).await => Err(ErrorForbidden("company member required")),
+        _ => Ok(Some(user_key.to_string())),
+    }
+}
+
+// returns who acts, as a document id
+async fn require_manager(req: &HttpRequest, key: &str, scope: &str, pool: &DbPool) -> Result<String, Error> {
+    let identity = company::require_access(req, key, scope)?;
+    match identity.user_key() {
+        Some(user_key) if company::is_company_manager(key, user_key, pool).await => Ok(format!("users/{}", user_key)),
+        Some(_) => Err(ErrorForbidden("company manager required")),
+        None => Ok(format!("companies/{}", key)),
+    }
+}
+
+#[get("/companies/{key}/time-entries")]
+async fn find_entries(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindTimeEntriesParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: FindTimeEntriesParams = payload.into_inner();
+    let user_key = visible_user(&req, &key, params.user_key.as_ref(), &pool).await?;
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::find_time_entries(&key, user_key.as_deref(), params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/time-entries")]
+async fn create_entry(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<TimeEntryRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_member(&req, &key, &pool).await?;
+    let params: TimeEntryRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::create_time_entry(&key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/time-entries/{entry_key}")]
+async fn update_entry(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<TimeEntryRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, entry_key) = path.into_inner();
+    let user_key = require_member(&req, &key, &pool).await?;
+    let params: TimeEntryRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::update_time_entry(&key, &user_key, &entry_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/time-entries/{entry_key}")]
+async fn delete_entry(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, entry_key) = path.into_inner();
+    let user_key = require_member(&req, &key, &pool).await?;
+    timesheet::delete_time_entry(&key, &user_key, &entry_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/timer")]
+async fn show_timer(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_member(&req, &key, &pool).await?;
+    match timesheet::show_timer(&key, &user_key, &pool).await? {
+        Some(result) => Ok(HttpResponse::Ok().json(result)),
+        None => Ok(HttpResponse::NoContent().finish()),
+    }
+}
+
+#[post("/companies/{key}/timer/start")]
+async fn start_timer(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<StartTimerRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_member(&req, &key, &pool).await?;
+    let params: StartTimerRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::start_timer(&key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/timer/stop")]
+async fn stop_timer(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_member(&req, &key, &pool).await?;
+    let result = timesheet::stop_timer(&key, &user_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/companies/{key}/timesheets")]
+async fn find_timesheets(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindTimesheetsParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let params: FindTimesheetsParams = payload.into_inner();
+    let user_key = visible_user(&req, &key, params.user_key.as_ref(), &pool).await?;
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::find_timesheets(&key, user_key.as_deref(), params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/timesheets")]
+async fn submit_timesheet(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<SubmitTimesheetRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_member(&req, &key, &pool).await?;
+    let params: SubmitTimesheetRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::submit_timesheet(&key, &user_key, &params.week, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/timesheets/{timesheet_key}/decision")]
+async fn decide_timesheet(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<DecideTimesheetRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, timesheet_key) = path.into_inner();
+    let decided_by = require_manager(&req, &key, "time:write", &pool).await?;
+    let params: DecideTimesheetRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = timesheet::decide_timesheet(&key, &timesheet_key, &decided_by, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/companies/{key}/time-reports")]
+async fn report(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<ReportParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_manager(&req, &key, "time:read", &pool).await?;
+    let params: ReportParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let rows = timesheet::time_report(&key, &params, &pool).await?;
+            match params.format.as_deref() {
+                Some("csv") => Ok(HttpResponse::Ok()
+                    .content_type("text/csv; charset=utf-8")
+                    .insert_header((header::CONTENT_DISPOSITION, format!("attachment; filename=\"time-by-{}.csv\"", params.group_by)))
+                    .body(timesheet::report_csv(&params.group_by, &rows))),
+                _ => Ok(HttpResponse::Ok().json(rows)),
+            }
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find_entries);
+    cfg.service(create_entry);
+    cfg.service(update_entry);
+    cfg.service(delete_entry);
+    cfg.service(show_timer);
+    cfg.service(start_timer);
+    cfg.service(stop_timer);
+    cfg.service(find_timesheets);
+    cfg.service(submit_timesheet);
+    cfg.service(decide_timesheet);
+    cfg.service(report);
+}
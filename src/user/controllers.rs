@@ -1,6 +1,6 @@
 use actix_multipart::Multipart;
 use actix_web::{
-    error::ErrorBadRequest,
+    http::StatusCode,
     web,
     Error,
 };
@@ -18,17 +18,22 @@ use futures::{StreamExt, TryStreamExt}; // for next or try_next of Multipart
 use serde_json::{from_str, json, to_string, to_value, Value};
 use std::{
     collections::HashMap,
-    env,
     ffi::OsStr,
     path::Path,
     str,
     vec::Vec,
 };
-use uuid::Uuid;
+use tracing::instrument;
 use validator::{Validate, ValidationErrors};
 
-use crate::config::db_database;
-use crate::database::DbPool;
+use crate::api_key::revoke_owner_api_keys;
+use crate::auth::revoke_sessions;
+use crate::custom_field::custom_query;
+use crate::database::{current_database, DbPool};
+use crate::i18n::{failure, Invalid};
+use crate::storage::store_file;
+use crate::tag::{parse_tag_names, tag_filter, tags_company};
+use crate::webhook::{dispatch, user_company_keys};
 use crate::user::{
     CreateUserRequest,
     FindUsersParams,
@@ -59,15 +64,12 @@ async fn accept_uploading(
             (mime::IMAGE, _) => {
                 let filename = content_disposition.get_filename().unwrap();
                 let file_extension = Path::new(filename).extension().and_then(OsStr::to_str).unwrap().to_string();
-                let uniqname = format!("{}.{}", Uuid::new_v4().to_string(), file_extension);
-                let mut filepath = env::current_dir()?;
-                filepath.push("storage");
-                filepath.push(&uniqname);
+                let mut body = Vec::with_capacity(512);
                 // field data may be larger than 64KB or it may be on page boundary
                 while let Ok(Some(chunk)) = field.try_next().await {
-                    tokio::fs::write(&filepath, chunk).await.unwrap();
+                    body.extend_from_slice(&chunk);
                 }
-                let pathtext = format!("/storage/{}", uniqname);
+                let pathtext = store_file(&file_extension, &body).await?;
                 vars.insert(String::from(name), pathtext);
             },
             _ => {}
@@ -77,12 +79,13 @@ async fn accept_uploading(
     Ok(vars)
 }
 
+#[instrument(skip_all)]
 pub async fn find_users(
     params: FindUsersParams,
     pool: &DbPool,
 ) -> Result<Vec<UserResponse>, ValidationErrors> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let mut terms = vec!["FOR x IN users"];
     let mut vars: HashMap<&str, Value> = HashMap::new();
@@ -93,18 +96,47 @@ pub async fn find_users(
             vars.insert("@search", to_value(search).unwrap());
         }
     }
+    let tag_names = params.tags.as_deref().map(parse_tag_names).unwrap_or_default();
+    let match_all = params.tags_mode.as_deref() == Some("all");
+    let tags_filter = tag_filter("x", match_all);
+    if !tag_names.is_empty() {
+        let company_key = tags_company(params.tags_company.as_deref())?;
+        terms.push(&tags_filter);
+        vars.insert("tags", to_value(&tag_names).unwrap());
+        vars.insert("tags_company", to_value(company_key).unwrap());
+        if match_all {
+            vars.insert("tag_count", to_value(tag_names.len()).unwrap());
+        }
+    }
+    let custom = custom_query(
+        &db,
+        "users",
+        "x",
+        params.custom_company.as_deref(),
+        params.custom_field.as_deref(),
+        params.custom_value.as_deref(),
+        params.custom_sort.as_deref(),
+    ).await?;
+    if let Some(filter) = &custom.filter {
+        terms.push(filter);
+    }
+    vars.extend(custom.vars);
     if params.sort_by.is_some() {
         let sort_by: String = params.sort_by.unwrap();
         terms.push("SORT x.@@sort_by ASC");
         vars.insert("@sort_by", to_value(sort_by).unwrap());
     }
+    // applied last, so it takes precedence over sort_by
+    if let Some(sort) = &custom.sort {
+        terms.push(sort);
+    }
     if params.limit.is_some() {
         let limit: u32 = params.limit.unwrap();
         terms.push("LIMIT 0, @@limit");
         vars.insert("@limit", to_value(limit).unwrap());
     }
 
-    terms.push("RETURN UNSET(x, 'password')");
+    terms.push("RETURN UNSET(x, 'password', 'two_factor', 'two_factor_pending')");
     let q = terms.join(" ");
 
     let aql = AqlQuery::builder()
@@ -115,12 +147,13 @@ pub async fn find_users(
     Ok(records)
 }
 
+#[instrument(skip_all)]
 pub async fn show_user(
     key: &String,
     pool: &DbPool,
 ) -> Result<UserResponse, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let res: Document<UserResponse> = collection.document(key.as_ref()).await.unwrap();
@@ -128,12 +161,13 @@ pub async fn show_user(
     Ok(record)
 }
 
+#[instrument(skip_all)]
 pub async fn create_user(
     payload: Multipart,
     pool: &DbPool,
 ) -> Result<UserResponse, Error> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let now = Utc::now();
@@ -165,6 +199,7 @@ pub async fn create_user(
         } else {
             None
         },
+        locale: vars.get("locale").map(|x| x.to_string()),
         created_at: now,
         modified_at: now,
     };
@@ -188,32 +223,37 @@ pub async fn create_user(
                 name: record.name.unwrap(),
                 email: record.email.unwrap(),
                 avatar: record.avatar.unwrap(),
+                locale: record.locale,
                 created_at: record.created_at,
                 modified_at: record.modified_at,
                 deleted_at: None,
+                custom: None,
             })
         },
         Err(e) => {
-            let errs = e.field_errors();
-            let text = to_string(&errs).unwrap();
-            Err(ErrorBadRequest(text))
+            Err(Invalid(e).into())
         }
     }
 }
 
+#[instrument(skip_all)]
 pub async fn update_user(
     key: &String,
     payload: Multipart,
     pool: &DbPool,
 ) -> Result<UserResponse, Error> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let now = Utc::now();
 
     let vars: HashMap<String, String> = accept_uploading(payload).await?;
-    let mut req = UpdateUserRequest {
+    // changing the password needs the current one, which only its own endpoint checks
+    if vars.contains_key("password") || vars.contains_key("password_confirmation") {
+        return Err(failure(StatusCode::BAD_REQUEST, "password_has_its_own_endpoint", &[("key", key)]));
+    }
+    let req = UpdateUserRequest {
         name: if vars.contains_key("name") {
             Some(vars.get("name").unwrap().to_string())
         } else {
@@ -224,32 +264,21 @@ pub async fn update_user(
         } else {
             None
         },
-        password: if vars.contains_key("password") {
-            let pswd = vars.get("password").unwrap().to_string();
-            Some(hash(pswd, DEFAULT_COST).unwrap())
-        } else {
-            None
-        },
-        password_confirmation: if vars.contains_key("password_confirmation") {
-            let pswd = vars.get("password_confirmation").unwrap().to_string();
-            Some(hash(pswd, DEFAULT_COST).unwrap())
-        } else {
-            None
-        },
+        password: None,
+        password_confirmation: None,
         avatar: if vars.contains_key("avatar") {
             Some(vars.get("avatar").unwrap().to_string())
         } else {
             None
         },
+        locale: vars.get("locale").map(|x| x.to_string()),
         created_at: None,
         modified_at: Some(now),
         deleted_at: None,
     };
 
-    req.validate().map_err(ErrorBadRequest);
-    if vars.contains_key("password") {
-        req.password = Some(hash(req.password.unwrap(), DEFAULT_COST).unwrap());
-        req.password_confirmation = None;
+    if let Err(e) = req.validate() {
+        return Err(Invalid(e).into());
     }
 
     let options: UpdateOptions = UpdateOptions::builder()
@@ -261,6 +290,9 @@ pub async fn update_user(
     let record: UpdateUserRequest = doc.clone();
     let header = res.header().unwrap();
 
+    let company_keys = user_company_keys(&db, key).await;
+    dispatch(&db, "user.updated", &company_keys, res.old_doc(), res.new_doc()).await;
+
     Ok(UserResponse {
         _id: header._id.clone(),
         _key: header._key.clone(),
@@ -268,18 +300,21 @@ pub async fn update_user(
         name: record.name.unwrap(),
         email: record.email.unwrap(),
         avatar: record.avatar.unwrap(),
+        locale: record.locale,
         created_at: record.created_at.unwrap(),
         modified_at: record.modified_at.unwrap(),
         deleted_at: record.deleted_at,
+        custom: None,
     })
 }
 
+#[instrument(skip_all)]
 pub async fn erase_user(
     key: &String,
     pool: &DbPool,
 ) -> Result<UserResponse, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let options: RemoveOptions = RemoveOptions::builder()
@@ -291,6 +326,12 @@ pub async fn erase_user(
     let record: UpdateUserRequest = doc.clone();
     let header = res.header().unwrap();
 
+    revoke_sessions(&db, key, None).await;
+    revoke_owner_api_keys(&db, &format!("users/{}", key)).await;
+
+    let company_keys = user_company_keys(&db, key).await;
+    dispatch(&db, "user.erased", &company_keys, res.old_doc(), None).await;
+
     Ok(UserResponse {
         _id: header._id.clone(),
         _key: header._key.clone(),
@@ -298,18 +339,21 @@ pub async fn erase_user(
         name: record.name.unwrap(),
         email: record.email.unwrap(),
         avatar: record.avatar.unwrap(),
+        locale: record.locale,
         created_at: record.created_at.unwrap(),
         modified_at: record.modified_at.unwrap(),
         deleted_at: record.deleted_at,
+        custom: None,
     })
 }
 
+#[instrument(skip_all)]
 pub async fn trash_user(
     key: &String,
     pool: &DbPool,
 ) -> Result<UserResponse, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let obj = json!({
@@ -327,6 +371,12 @@ pub async fn trash_user(
     let record: UpdateUserRequest = doc.clone();
     let header = res.header().unwrap();
 
+    revoke_sessions(&db, key, None).await;
+    revoke_owner_api_keys(&db, &format!("users/{}", key)).await;
+
+    let company_keys = user_company_keys(&db, key).await;
+    dispatch(&db, "user.trashed", &company_keys, res.old_doc(), res.new_doc()).await;
+
     Ok(UserResponse {
         _id: header._id.clone(),
         _key: header._key.clone(),
@@ -334,18 +384,21 @@ pub async fn trash_user(
         name: record.name.unwrap(),
         email: record.email.unwrap(),
         avatar: record.avatar.unwrap(),
+        locale: record.locale,
         created_at: record.created_at.unwrap(),
         modified_at: record.modified_at.unwrap(),
         deleted_at: record.deleted_at,
+        custom: None,
     })
 }
 
+#[instrument(skip_all)]
 pub async fn restore_user(
     key: &String,
     pool: &DbPool,
 ) -> Result<UserResponse, &'static str> {
     let client = pool.get().await.unwrap();
-    let db = client.db(&db_database()).await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
 
     let collection: Collection<ReqwestClient> = db.collection("users").await.unwrap();
     let data: UpdateUserRequest = from_str::<UpdateUserRequest>("{\"deleted_at\":null}").unwrap();
@@ -360,6 +413,9 @@ pub async fn restore_user(
     let record: UpdateUserRequest = doc.clone();
     let header = res.header().unwrap();
 
+    let company_keys = user_company_keys(&db, key).await;
+    dispatch(&db, "user.restored", &company_keys, res.old_doc(), res.new_doc()).await;
+
     Ok(UserResponse {
         _id: header._id.clone(),
         _key: header._key.clone(),
@@ -367,8 +423,10 @@ pub async fn restore_user(
         name: record.name.unwrap(),
         email: record.email.unwrap(),
         avatar: record.avatar.unwrap(),
+        locale: record.locale,
         created_at: record.created_at.unwrap(),
         modified_at: record.modified_at.unwrap(),
         deleted_at: record.deleted_at,
+        custom: None,
     })
 }
@@ -1,8 +1,12 @@
 use chrono::prelude::*;
 use serde::{Deserialize, Serialize};
+use serde_json::Value;
 use std::str;
 use validator::{Validate, ValidationError, ValidationErrors};
 
+use crate::i18n::validate_locale;
+use crate::tag::validate_tags_mode;
+
 #[derive(Clone, Debug, Validate, Deserialize)]
 pub struct FindUsersParams {
     pub search: Option<String>,
@@ -10,12 +14,20 @@ pub struct FindUsersParams {
     pub sort_by: Option<String>,
     #[validate(range(min = 1, max = 100))]
     pub limit: Option<u32>,
+    pub tags: Option<String>, // comma separated tag names
+    #[validate(custom = "validate_tags_mode")]
+    pub tags_mode: Option<String>, // all or any, any by default
+    pub tags_company: Option<String>, // company whose tags are matched, required with tags
+    pub custom_company: Option<String>, // company whose custom fields are used below
+    pub custom_field: Option<String>,
+    pub custom_value: Option<String>,
+    pub custom_sort: Option<String>,
 }
 
 fn validate_sort_by(sort_by: &str) -> Result<(), ValidationError> {
     match sort_by {
         "name" | "since" => Ok(()),
-        _ => Err(ValidationError::new("Wrong sort_by")),
+        _ => Err(ValidationError::new("wrong_sort_by")),
     }
 }
 
@@ -28,7 +40,7 @@ pub struct DeleteUserParams {
 fn validate_mode(mode: &str) -> Result<(), ValidationError> {
     match mode {
         "erase" | "trash" | "restore" => Ok(()),
-        _ => Err(ValidationError::new("Wrong mode")),
+        _ => Err(ValidationError::new("wrong_mode")),
     }
 }
 
@@ -44,6 +56,9 @@ pub struct CreateUserRequest {
     pub password_confirmation: Option<String>,
     #[validate(required)]
     pub avatar: Option<String>,
+    #[validate(custom = "validate_locale")]
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub locale: Option<String>, // language of messages, the accept-language header otherwise
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
 }
@@ -63,6 +78,9 @@ pub struct UpdateUserRequest {
     pub password_confirmation: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
     pub avatar: Option<String>,
+    #[validate(custom = "validate_locale")]
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub locale: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
     pub created_at: Option<DateTime<Utc>>,
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
@@ -71,7 +89,7 @@ pub struct UpdateUserRequest {
     pub deleted_at: Option<DateTime<Utc>>,
 }
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct UserResponse {
     pub _id: String,
     pub _key: String,
@@ -79,8 +97,12 @@ pub struct UserResponse {
     pub name: String,
     pub email: String,
     pub avatar: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub locale: Option<String>,
     pub created_at: DateTime<Utc>,
     pub modified_at: DateTime<Utc>,
     #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
     pub deleted_at: Option<DateTime<Utc>>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub custom: Option<Value>, // custom field values by company
 }
@@ -1,8 +1,15 @@
-use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse, Responder};
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse, Responder};
 use actix_multipart::Multipart;
-use serde_json::{from_str, json, Value};
 use validator::Validate;
 
+use crate::auth::{
+    self,
+    Identity,
+    ChangePasswordRequest,
+    DisableTwoFactorRequest,
+    TwoFactorCodeRequest,
+};
+use crate::i18n::Invalid;
 use crate::user::{
     FindUsersParams,
     DeleteUserParams,
@@ -14,30 +21,49 @@ use crate::user::{
     trash_user,
     restore_user,
 };
+use crate::company;
 use crate::database::DbPool;
 
+// users can only change their own account, company keys none at all
+fn require_self(req: &HttpRequest, key: &str) -> Result<Identity, Error> {
+    let identity = auth::authorize(req, "users:write")?;
+    if identity.user_key() != Some(key) {
+        return Err(ErrorForbidden("cannot change another user"));
+    }
+    Ok(identity)
+}
+
 #[get("/users")]
 async fn find(
+    req: HttpRequest,
     payload: web::Query<FindUsersParams>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    auth::authorize(&req, "users:read")?;
     let params: FindUsersParams = payload.into_inner();
     match params.validate() {
         Ok(_) => {
-            let result = find_users(params, &pool).await.unwrap();
-            Ok(HttpResponse::Ok().json(result))
+            if let Some(key) = &params.tags_company {
+                company::require_access(&req, key, "users:read")?;
+            }
+            match find_users(params, &pool).await {
+                Ok(result) => Ok(HttpResponse::Ok().json(result)),
+                Err(e) => Err(Invalid(e).into()),
+            }
         },
         Err(e) => {
-            Ok(HttpResponse::BadRequest().json(e.errors()))
+            Err(Invalid(e).into())
         },
     }
 }
 
 #[get("/users/{key}")]
 async fn show(
+    req: HttpRequest,
     key: web::Path<String>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    auth::authorize(&req, "users:read")?;
     let result = show_user(&key, &pool).await.unwrap();
     Ok(HttpResponse::Ok().json(result))
 }
@@ -47,45 +73,113 @@ async fn create(
     payload: Multipart,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
-    let result = create_user(payload, &pool).await;
-    match result {
-        Ok(r) => {
-            Ok(HttpResponse::Ok().json(r))
+    let result = create_user(payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[put("/users/{key}")]
+async fn update(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: Multipart,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_self(&req, &key)?;
+    let result = update_user(&key, payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/users/{key}/password")]
+async fn change_password(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<ChangePasswordRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authenticate_session(&req)?;
+    let params: ChangePasswordRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            auth::change_password(&key, &identity, &params, &pool).await?;
+            Ok(HttpResponse::NoContent().finish())
         },
         Err(e) => {
-            let reason: Value = from_str(e.to_string().as_str()).unwrap();
-            let obj = json!({
-                "success": false,
-                "messages": reason,
-            });
-            Ok(HttpResponse::BadRequest().json(obj))
+            Err(Invalid(e).into())
         },
     }
 }
 
-#[put("/users/{key}")]
-async fn update(
+#[post("/users/{key}/two-factor")]
+async fn start_two_factor(
+    req: HttpRequest,
     key: web::Path<String>,
-    payload: Multipart,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
-    let result = update_user(&key, payload, &pool).await;
-    match result {
-        Ok(r) => {
-            Ok(HttpResponse::Ok().json(r))
+    let identity = auth::authenticate_for_enrolment(&req)?;
+    let result = auth::start_two_factor(&key, &identity, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/users/{key}/two-factor/confirm")]
+async fn confirm_two_factor(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<TwoFactorCodeRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authenticate_for_enrolment(&req)?;
+    let params: TwoFactorCodeRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = auth::confirm_two_factor(&key, &identity, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
         },
         Err(e) => {
-            Ok(HttpResponse::BadRequest().json("update user failed"))
+            Err(Invalid(e).into())
         },
     }
 }
 
+#[post("/users/{key}/two-factor/recovery-codes")]
+async fn regenerate_recovery_codes(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<TwoFactorCodeRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authenticate_session(&req)?;
+    let params: TwoFactorCodeRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = auth::regenerate_recovery_codes(&key, &identity, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/users/{key}/two-factor")]
+async fn disable_two_factor(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<DisableTwoFactorRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let identity = auth::authenticate_session(&req)?;
+    auth::disable_two_factor(&key, &identity, &payload, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
 #[delete("/users/{key}")]
 async fn delete(
+    req: HttpRequest,
     key: web::Path<String>,
     form: web::Form<DeleteUserParams>,
     pool: web::Data<DbPool>,
 ) -> Result<HttpResponse, Error> {
+    require_self(&req, &key)?;
     match form.mode.as_str() {
         "erase" => {
             let result = erase_user(&key, &pool).await.unwrap();
@@ -111,5 +205,10 @@ pub fn init(cfg: &mut web::ServiceConfig) {
     cfg.service(show);
     cfg.service(create);
     cfg.service(update);
+    cfg.service(change_password);
+    cfg.service(start_two_factor);
+    cfg.service(confirm_two_factor);
+    cfg.service(regenerate_recovery_codes);
+    cfg.service(disable_two_factor);
     cfg.service(delete);
 }
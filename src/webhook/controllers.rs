@@ -0,0 +1,349 @@
+use actix_web::{
+    error::{ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{
+    connection::ReqwestClient,
+    document::{
+        options::{InsertOptions, RemoveOptions},
+        response::DocumentResponse,
+    },
+    AqlQuery, Collection, Database, Document,
+};
+use chrono::prelude::*;
+use rand::{distributions::Alphanumeric, Rng};
+use serde::Serialize;
+use serde_json::{json, to_value, Value};
+use std::collections::HashMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::database::{current_database, DbPool};
+use crate::webhook::{
+    CreateWebhookRequest,
+    CreatedWebhookResponse,
+    Delivery,
+    FindDeliveriesParams,
+    UpdateWebhookRequest,
+    Webhook,
+    WebhookResponse,
+};
+
+// never leave the database, even inside a before/after payload
+const REDACTED_FIELDS: [&str; 4] = ["password", "password_confirmation", "two_factor", "two_factor_pending"];
+
+const WEBHOOK_TERMS: &str = r#"RETURN {
+        _key: w._key,
+        url: w.url,
+        events: w.events,
+        active: w.active,
+        created_at: w.created_at,
+        modified_at: w.modified_at
+    }"#;
+
+fn random_secret() -> String {
+    rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(40)
+        .map(char::from)
+        .collect()
+}
+
+fn redact(value: Value) -> Value {
+    match value {
+        Value::Object(mut map) => {
+            for field in REDACTED_FIELDS.iter() {
+                map.remove(*field);
+            }
+            Value::Object(map)
+        },
+        other => other,
+    }
+}
+
+async fn find_webhook(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<WebhookResponse, Error> {
+    let q = format!("FOR w IN webhooks FILTER w._key == @key AND w.company_key == @company_key {}", WEBHOOK_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<WebhookResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("webhook not found"))
+}
+
+#[instrument(skip_all)]
+pub async fn find_webhooks(
+    company_key: &str,
+    pool: &DbPool,
+) -> Result<Vec<WebhookResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!("FOR w IN webhooks FILTER w.company_key == @company_key SORT w.created_at ASC {}", WEBHOOK_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<WebhookResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn create_webhook(
+    company_key: &str,
+    payload: &CreateWebhookRequest,
+    pool: &DbPool,
+) -> Result<CreatedWebhookResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let collection: Collection<ReqwestClient> = db.collection("webhooks").await.unwrap();
+    let now = Utc::now();
+    let secret = random_secret();
+
+    let data = Webhook {
+        company_key: company_key.to_string(),
+        url: payload.url.clone(),
+        events: payload.events.clone(),
+        secret: secret.clone(),
+        active: true,
+        created_at: now,
+        modified_at: now,
+    };
+    let options: InsertOptions = InsertOptions::builder()
+        .return_new(false)
+        .build();
+
+    let res: DocumentResponse<Document<Webhook>> = collection.create_document(Document::new(data), options).await
+        .map_err(ErrorInternalServerError)?;
+    let header = res.header().unwrap();
+
+    Ok(CreatedWebhookResponse {
+        secret,
+        webhook: find_webhook(&db, company_key, &header._key).await?,
+    })
+}
+
+#[instrument(skip_all)]
+pub async fn update_webhook(
+    company_key: &str,
+    key: &str,
+    payload: &UpdateWebhookRequest,
+    pool: &DbPool,
+) -> Result<WebhookResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut data = json!({
+        "modified_at": Utc::now(),
+    });
+    if let Some(url) = &payload.url {
+        data["url"] = to_value(url).unwrap();
+    }
+    if let Some(events) = &payload.events {
+        data["events"] = to_value(events).unwrap();
+    }
+    if let Some(active) = payload.active {
+        data["active"] = to_value(active).unwrap();
+    }
+
+    let q = r#"FOR w IN webhooks
+        FILTER w._key == @key AND w.company_key == @company_key
+        UPDATE w WITH @data IN webhooks
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("data", data);
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let updated: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if updated.is_empty() {
+        return Err(ErrorNotFound("webhook not found"));
+    }
+    find_webhook(&db, company_key, key).await
+}
+
+// deliveries are removed together with their webhook
+#[instrument(skip_all)]
+pub async fn delete_webhook(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_webhook(&db, company_key, key).await?;
+
+    let q = "FOR d IN webhook_deliveries FILTER d.webhook_key == @key REMOVE d IN webhook_deliveries";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+
+    let collection: Collection<ReqwestClient> = db.collection("webhooks").await.unwrap();
+    let options: RemoveOptions = RemoveOptions::builder()
+        .return_old(false)
+        .build();
+    let _res: DocumentResponse<Document<Webhook>> = collection.remove_document(key, options, None).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+#[instrument(skip_all)]
+pub async fn find_deliveries(
+    company_key: &str,
+    key: &str,
+    params: FindDeliveriesParams,
+    pool: &DbPool,
+) -> Result<Vec<Document<Delivery>>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_webhook(&db, company_key, key).await?;
+
+    let mut terms = vec!["FOR d IN webhook_deliveries FILTER d.webhook_key == @key"];
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    if let Some(status) = params.status {
+        terms.push("FILTER d.status == @status");
+        vars.insert("status", to_value(status).unwrap());
+    }
+    terms.push("SORT d.created_at DESC LIMIT 0, @limit RETURN d");
+    vars.insert("limit", to_value(params.limit.unwrap_or(50)).unwrap());
+    let q = terms.join(" ");
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<Document<Delivery>> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+// puts a delivery back in the queue with a fresh set of attempts
+#[instrument(skip_all)]
+pub async fn retry_delivery(
+    company_key: &str,
+    key: &str,
+    delivery_key: &str,
+    pool: &DbPool,
+) -> Result<Document<Delivery>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = r#"FOR d IN webhook_deliveries
+        FILTER d._key == @delivery_key AND d.webhook_key == @key AND d.company_key == @company_key
+        UPDATE d WITH { status: 'pending', attempts: 0, next_attempt_at: @now } IN webhook_deliveries
+        RETURN NEW"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("delivery_key", to_value(delivery_key).unwrap());
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("now", to_value(Utc::now().timestamp()).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<Document<Delivery>> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("delivery not found"))
+}
+
+#[instrument(skip_all)]
+pub async fn user_company_keys(
+    db: &Database<ReqwestClient>,
+    user_key: &str,
+) -> Vec<String> {
+    let q = "FOR m IN memberships FILTER m._from == @user RETURN PARSE_IDENTIFIER(m._to).key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("user", to_value(format!("users/{}", user_key)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await.unwrap()
+}
+
+// queues the event for every subscribed webhook of the given companies, the dispatcher sends it
+#[instrument(skip_all)]
+pub async fn dispatch<T: Serialize>(
+    db: &Database<ReqwestClient>,
+    event: &str,
+    company_keys: &[String],
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    let q = "FOR w IN webhooks FILTER w.active == true AND @event IN w.events AND w.company_key IN @company_keys RETURN w";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("event", to_value(event).unwrap());
+    vars.insert("company_keys", to_value(company_keys).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let webhooks: Vec<Document<Webhook>> = db.aql_query(aql).await.unwrap();
+    if webhooks.is_empty() {
+        return;
+    }
+
+    let now = Utc::now();
+    let data = json!({
+        "before": before.map(|x| redact(to_value(x).unwrap())),
+        "after": after.map(|x| redact(to_value(x).unwrap())),
+    });
+    let collection: Collection<ReqwestClient> = db.collection("webhook_deliveries").await.unwrap();
+    for webhook in webhooks {
+        let key = Uuid::new_v4().to_simple().to_string();
+        let mut doc = Document::new(Delivery {
+            webhook_key: webhook.header._key.clone(),
+            company_key: webhook.company_key.clone(),
+            event: event.to_string(),
+            payload: json!({
+                "id": key,
+                "event": event,
+                "created_at": now,
+                "data": data,
+            }),
+            status: "pending".to_string(),
+            attempts: 0,
+            next_attempt_at: now,
+            log: vec![],
+            created_at: now,
+            delivered_at: None,
+        });
+        doc.header._key = key;
+        let options: InsertOptions = InsertOptions::builder()
+            .return_new(false)
+            .build();
+        let _res: DocumentResponse<Document<Delivery>> = collection.create_document(doc, options).await.unwrap();
+    }
+}
@@ -0,0 +1,212 @@
+use arangors::{connection::ReqwestClient, AqlQuery, ClientError, Database, Document};
+use chrono::{prelude::*, Duration};
+use hmac::{Hmac, Mac, NewMac};
+use reqwest::header::CONTENT_TYPE;
+use serde::Deserialize;
+use serde_json::{to_string, to_value, Value};
+use sha2::Sha256;
+use std::{collections::HashMap, time::Instant};
+
+use crate::database::{current_database, with_database, DbPool};
+use crate::tenant::tenant_databases;
+use crate::webhook::{Delivery, DeliveryAttempt, MAX_ATTEMPTS, RETRY_BASE_SECONDS};
+
+const POLL_INTERVAL_SECONDS: u64 = 5;
+const TIMEOUT_SECONDS: u64 = 10;
+const BATCH_SIZE: u32 = 20;
+// a batch is sent one delivery after the other, so the lease outlasts every request of it timing
+// out and other instances skip deliveries that are still being sent
+const LEASE_SECONDS: i64 = BATCH_SIZE as i64 * TIMEOUT_SECONDS as i64 + 60;
+
+#[derive(Debug, Deserialize)]
+struct DueDelivery {
+    delivery: Document<Delivery>,
+    url: Option<String>,
+    secret: Option<String>,
+    active: Option<bool>,
+}
+
+// receivers verify the signature over "{timestamp}.{body}" with the webhook secret
+fn sign(secret: &str, timestamp: i64, body: &str) -> String {
+    let mut mac = Hmac::<Sha256>::new_from_slice(secret.as_bytes()).unwrap();
+    mac.update(format!("{}.{}", timestamp, body).as_bytes());
+    format!("{:x}", mac.finalize().into_bytes())
+}
+
+fn retry_delay(attempts: u32) -> Duration {
+    let exponent = attempts.saturating_sub(1).min(16);
+    Duration::seconds(RETRY_BASE_SECONDS * 2i64.pow(exponent))
+}
+
+// deliveries that keep failing are dead lettered instead of retried forever
+fn delivery_status(succeeded: bool, attempts: u32) -> &'static str {
+    if succeeded {
+        "succeeded"
+    } else if attempts >= MAX_ATTEMPTS {
+        "dead"
+    } else {
+        "pending"
+    }
+}
+
+async fn claim_due(db: &Database<ReqwestClient>) -> Result<Vec<DueDelivery>, ClientError> {
+    let q = r#"FOR d IN webhook_deliveries
+        FILTER d.status == 'pending' AND d.next_attempt_at <= @now
+        SORT d.next_attempt_at ASC
+        LIMIT @batch
+        UPDATE d WITH { next_attempt_at: @lease } IN webhook_deliveries
+        LET w = DOCUMENT('webhooks', NEW.webhook_key)
+        RETURN { delivery: NEW, url: w.url, secret: w.secret, active: w.active }"#;
+    let now = Utc::now();
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("now", to_value(now.timestamp()).unwrap());
+    vars.insert("lease", to_value((now + Duration::seconds(LEASE_SECONDS)).timestamp()).unwrap());
+    vars.insert("batch", to_value(BATCH_SIZE).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    db.aql_query(aql).await
+}
+
+async fn record_attempt(
+    db: &Database<ReqwestClient>,
+    due: &DueDelivery,
+    attempt: DeliveryAttempt,
+) -> Result<(), ClientError> {
+    let succeeded = attempt.status_code.map_or(false, |x| (200..300).contains(&x));
+    let attempts = due.delivery.attempts + 1;
+    let status = delivery_status(succeeded, attempts);
+
+    let q = r#"LET d = DOCUMENT('webhook_deliveries', @key)
+        UPDATE d WITH {
+            status: @status,
+            attempts: @attempts,
+            next_attempt_at: @next_attempt_at,
+            delivered_at: @delivered_at,
+            log: PUSH(d.log, @attempt)
+        } IN webhook_deliveries"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(&due.delivery.header._key).unwrap());
+    vars.insert("status", to_value(status).unwrap());
+    vars.insert("attempts", to_value(attempts).unwrap());
+    vars.insert("next_attempt_at", to_value((Utc::now() + retry_delay(attempts)).timestamp()).unwrap());
+    vars.insert("delivered_at", to_value(if succeeded { Some(attempt.at) } else { None }).unwrap());
+    vars.insert("attempt", to_value(&attempt).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await?;
+    Ok(())
+}
+
+async fn send(http: &reqwest::Client, due: &DueDelivery) -> DeliveryAttempt {
+    let at = Utc::now();
+    let started = Instant::now();
+
+    let (url, secret) = match (&due.url, &due.secret, due.active) {
+        (Some(url), Some(secret), Some(true)) => (url, secret),
+        _ => {
+            return DeliveryAttempt {
+                at,
+                status_code: None,
+                error: Some("webhook is disabled".to_string()),
+                duration_ms: 0,
+            };
+        },
+    };
+
+    let body = to_string(&due.delivery.payload).unwrap();
+    let timestamp = at.timestamp();
+    let result = http.post(url)
+        .header(CONTENT_TYPE, "application/json")
+        .header("X-Webhook-Id", &due.delivery.header._key)
+        .header("X-Webhook-Event", &due.delivery.event)
+        .header("X-Webhook-Timestamp", timestamp.to_string())
+        .header("X-Webhook-Signature", format!("sha256={}", sign(secret, timestamp, &body)))
+        .body(body)
+        .send()
+        .await;
+
+    let (status_code, error) = match result {
+        Ok(res) if res.status().is_success() => (Some(res.status().as_u16()), None),
+        Ok(res) => (Some(res.status().as_u16()), Some(format!("unexpected status {}", res.status()))),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    DeliveryAttempt {
+        at,
+        status_code,
+        error,
+        duration_ms: started.elapsed().as_millis() as u64,
+    }
+}
+
+async fn deliver_due(pool: &DbPool, http: &reqwest::Client) -> Result<(), ClientError> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await?;
+
+    for due in claim_due(&db).await? {
+        let attempt = send(http, &due).await;
+        record_attempt(&db, &due, attempt).await?;
+    }
+    Ok(())
+}
+
+// polls every database for deliveries that are due, for the lifetime of the server
+pub fn start_dispatcher(pool: DbPool) {
+    actix_web::rt::spawn(async move {
+        let http = reqwest::Client::builder()
+            .timeout(std::time::Duration::from_secs(TIMEOUT_SECONDS))
+            .build()
+            .unwrap();
+
+        loop {
+            match tenant_databases(&pool).await {
+                Ok(databases) => {
+                    for database in databases {
+                        if let Err(e) = with_database(database.clone(), deliver_due(&pool, &http)).await {
+                            tracing::error!(database = %database, error = %e, "webhook delivery failed");
+                        }
+                    }
+                },
+                Err(e) => tracing::error!(error = %e, "webhook dispatcher cannot list databases"),
+            }
+            actix_web::rt::time::sleep(std::time::Duration::from_secs(POLL_INTERVAL_SECONDS)).await;
+        }
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signature() {
+        let body = r#"{"event":"user.created"}"#;
+        assert_eq!(
+            sign("secret", 1600000000, body),
+            "592d3b6567c5b86fe3d0a4c44d49c3c1acd1248410806bc5ad36290728535d8b",
+        );
+        assert_ne!(sign("secret", 1600000001, body), sign("secret", 1600000000, body));
+        assert_ne!(sign("other", 1600000000, body), sign("secret", 1600000000, body));
+    }
+
+    #[test]
+    fn backoff() {
+        assert_eq!(retry_delay(1), Duration::seconds(RETRY_BASE_SECONDS));
+        assert_eq!(retry_delay(2), Duration::seconds(RETRY_BASE_SECONDS * 2));
+        assert_eq!(retry_delay(4), Duration::seconds(RETRY_BASE_SECONDS * 8));
+        assert_eq!(retry_delay(40), retry_delay(17));
+    }
+
+    #[test]
+    fn dead_letter() {
+        assert_eq!(delivery_status(true, MAX_ATTEMPTS), "succeeded");
+        assert_eq!(delivery_status(false, 1), "pending");
+        assert_eq!(delivery_status(false, MAX_ATTEMPTS - 1), "pending");
+        assert_eq!(delivery_status(false, MAX_ATTEMPTS), "dead");
+    }
+}
@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod dispatcher;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use dispatcher::start_dispatcher;
+pub use routes::init;
@@ -0,0 +1,115 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use validator::{Validate, ValidationError};
+
+// `user.created` is sent to the first company the user joins, sign up itself has no company
+pub const EVENTS: [&str; 13] = [
+    "company.created",
+    "company.updated",
+    "company.trashed",
+    "company.restored",
+    "company.erased",
+    "user.created",
+    "user.updated",
+    "user.trashed",
+    "user.restored",
+    "user.erased",
+    "member.added",
+    "member.removed",
+    "announcement.published",
+];
+pub const MAX_ATTEMPTS: u32 = 8;
+pub const RETRY_BASE_SECONDS: i64 = 30; // doubled after every failed attempt
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct CreateWebhookRequest {
+    #[validate(url)]
+    pub url: String,
+    #[validate(custom = "validate_events")]
+    pub events: Vec<String>,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct UpdateWebhookRequest {
+    #[validate(url)]
+    pub url: Option<String>,
+    #[validate(custom = "validate_events")]
+    pub events: Option<Vec<String>>,
+    pub active: Option<bool>,
+}
+
+fn validate_events(events: &[String]) -> Result<(), ValidationError> {
+    if events.is_empty() || !events.iter().all(|x| EVENTS.contains(&x.as_str())) {
+        return Err(ValidationError::new("wrong_events"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindDeliveriesParams {
+    #[validate(custom = "validate_status")]
+    pub status: Option<String>,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+}
+
+fn validate_status(status: &str) -> Result<(), ValidationError> {
+    if status != "pending" && status != "succeeded" && status != "dead" {
+        return Err(ValidationError::new("wrong_status"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Webhook {
+    pub company_key: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub secret: String, // signing key, shown once when the webhook is created
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct WebhookResponse {
+    pub _key: String,
+    pub url: String,
+    pub events: Vec<String>,
+    pub active: bool,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct CreatedWebhookResponse {
+    pub secret: String,
+    #[serde(flatten)]
+    pub webhook: WebhookResponse,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct DeliveryAttempt {
+    pub at: DateTime<Utc>,
+    pub status_code: Option<u16>,
+    pub error: Option<String>,
+    pub duration_ms: u64,
+}
+
+// one event sent to one webhook, kept as the delivery log
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Delivery {
+    pub webhook_key: String,
+    pub company_key: String,
+    pub event: String,
+    pub payload: Value,
+    pub status: String, // pending until delivered, dead after MAX_ATTEMPTS failures
+    pub attempts: u32,
+    #[serde(with = "chrono::serde::ts_seconds")] // compared numerically when polling
+    pub next_attempt_at: DateTime<Utc>,
+    pub log: Vec<DeliveryAttempt>,
+    pub created_at: DateTime<Utc>,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub delivered_at: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,119 @@
+use actix_web::{delete, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::webhook::{
+    self,
+    CreateWebhookRequest,
+    FindDeliveriesParams,
+    UpdateWebhookRequest,
+};
+
+#[get("/companies/{key}/webhooks")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_admin(&req, &key, &pool).await?;
+    let result = webhook::find_webhooks(&key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/webhooks")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreateWebhookRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    company::require_admin(&req, &key, &pool).await?;
+    let params: CreateWebhookRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = webhook::create_webhook(&key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/webhooks/{webhook_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<UpdateWebhookRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, webhook_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: UpdateWebhookRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = webhook::update_webhook(&key, &webhook_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/webhooks/{webhook_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, webhook_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    webhook::delete_webhook(&key, &webhook_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/webhooks/{webhook_key}/deliveries")]
+async fn find_deliveries(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Query<FindDeliveriesParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, webhook_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let params: FindDeliveriesParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = webhook::find_deliveries(&key, &webhook_key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[post("/companies/{key}/webhooks/{webhook_key}/deliveries/{delivery_key}/retry")]
+async fn retry_delivery(
+    req: HttpRequest,
+    path: web::Path<(String, String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, webhook_key, delivery_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    let result = webhook::retry_delivery(&key, &webhook_key, &delivery_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(find_deliveries);
+    cfg.service(retry_delivery);
+}
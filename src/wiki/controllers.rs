@@ -0,0 +1,582 @@
+use actix_web::{
+    error::{ErrorBadRequest, ErrorConflict, ErrorInternalServerError, ErrorNotFound},
+    Error,
+};
+use arangors::{connection::ReqwestClient, AqlQuery, Database};
+use chrono::prelude::*;
+use serde_json::{to_value, Value};
+use similar::{ChangeTag, TextDiff};
+use std::collections::HashMap;
+use tracing::instrument;
+
+use crate::database::{current_database, DbPool};
+use crate::wiki::{
+    link_slugs,
+    render_html,
+    CreatePageRequest,
+    DiffLine,
+    DiffParams,
+    DiffResponse,
+    FindPagesParams,
+    Page,
+    PageResponse,
+    PageSummary,
+    Revision,
+    RevisionResponse,
+    RollbackRequest,
+    SearchPagesParams,
+    UpdatePageRequest,
+    SEARCH_VIEW,
+};
+
+const PAGE_TERMS: &str = "RETURN UNSET(p, '_id', '_rev', 'company_key', 'links')";
+
+const SUMMARY_TERMS: &str = r#"RETURN {
+        _key: p._key,
+        slug: p.slug,
+        title: p.title,
+        parent_key: p.parent_key,
+        children: LENGTH(FOR c IN wiki_pages FILTER c.company_key == p.company_key AND c.parent_key == p._key RETURN 1),
+        modified_at: p.modified_at
+    }"#;
+
+const REVISION_TERMS: &str = r#"RETURN {
+        number: r.number,
+        title: r.title,
+        body: @with_body ? r.body : null,
+        author_key: r.author_key,
+        author_name: DOCUMENT('users', r.author_key).name,
+        comment: r.comment,
+        created_at: r.created_at
+    }"#;
+
+// what a save changes besides the revision number
+struct Save<'a> {
+    title: &'a str,
+    body: &'a str,
+    parent_key: Option<String>,
+    ancestors: Vec<String>,
+    author_key: &'a str,
+    comment: Option<String>,
+}
+
+async fn find_page(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+) -> Result<PageResponse, Error> {
+    let q = format!("FOR p IN wiki_pages FILTER p._key == @key AND p.company_key == @company_key {}", PAGE_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<PageResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("page not found"))
+}
+
+async fn find_revision(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    number: u32,
+) -> Result<RevisionResponse, Error> {
+    let q = format!(r#"FOR r IN wiki_revisions
+        FILTER r.page_key == @key AND r.number == @number AND r.company_key == @company_key {}"#, REVISION_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("number", to_value(number).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("with_body", to_value(true).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<RevisionResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    records.pop().ok_or_else(|| ErrorNotFound("revision not found"))
+}
+
+// ancestors of a page placed under parent_key, which must not be the page itself or below it
+async fn ancestors_under(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    parent_key: Option<&str>,
+    moving_key: Option<&str>,
+) -> Result<Vec<String>, Error> {
+    let parent_key = match parent_key {
+        Some(x) => x,
+        None => return Ok(vec![]),
+    };
+    let parent = find_page(db, company_key, parent_key).await
+        .map_err(|_| ErrorBadRequest("parent page not found"))?;
+    let mut ancestors = parent.ancestors;
+    ancestors.push(parent._key);
+    if let Some(moving_key) = moving_key {
+        if ancestors.iter().any(|x| x == moving_key) {
+            return Err(ErrorBadRequest("a page cannot move below itself"));
+        }
+    }
+    Ok(ancestors)
+}
+
+// edges follow the [[slug]] links of the body, links to missing pages wait in the page document
+async fn sync_links(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    body: &str,
+) -> Result<(), Error> {
+    let from = format!("wiki_pages/{}", key);
+    let q = "FOR l IN wiki_links FILTER l._from == @from REMOVE l IN wiki_links";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(&from).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+
+    let q = r#"FOR p IN wiki_pages
+        FILTER p.company_key == @company_key AND p.slug IN @slugs AND p._id != @from
+        INSERT { _from: @from, _to: p._id, company_key: @company_key } INTO wiki_links"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("from", to_value(&from).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("slugs", to_value(link_slugs(body)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// pages written before this one may already link to its slug
+async fn link_new_page(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    slug: &str,
+) -> Result<(), Error> {
+    let q = r#"FOR p IN wiki_pages
+        FILTER p.company_key == @company_key AND @slug IN p.links AND p._id != @to
+        INSERT { _from: p._id, _to: @to, company_key: @company_key } INTO wiki_links"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("to", to_value(format!("wiki_pages/{}", key)).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("slug", to_value(slug).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(())
+}
+
+// the page and its new revision are written by one query, a stale base revision writes nothing
+async fn save_page(
+    db: &Database<ReqwestClient>,
+    company_key: &str,
+    key: &str,
+    base_revision: u32,
+    save: Save<'_>,
+) -> Result<(), Error> {
+    let now = Utc::now();
+    let revision = Revision {
+        page_key: key.to_string(),
+        company_key: company_key.to_string(),
+        number: base_revision + 1,
+        title: save.title.to_string(),
+        body: save.body.to_string(),
+        author_key: save.author_key.to_string(),
+        comment: save.comment,
+        created_at: now,
+    };
+    let q = r#"FOR p IN wiki_pages
+        FILTER p._key == @key AND p.company_key == @company_key AND p.revision == @base_revision
+        UPDATE p WITH {
+            title: @revision.title,
+            body: @revision.body,
+            parent_key: @parent_key,
+            ancestors: @ancestors,
+            links: @links,
+            revision: @revision.number,
+            author_key: @revision.author_key,
+            modified_at: @revision.created_at
+        } IN wiki_pages
+        OPTIONS { keepNull: false }
+        INSERT @revision INTO wiki_revisions
+        RETURN NEW._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("base_revision", to_value(base_revision).unwrap());
+    vars.insert("parent_key", to_value(&save.parent_key).unwrap());
+    vars.insert("ancestors", to_value(&save.ancestors).unwrap());
+    vars.insert("links", to_value(link_slugs(save.body)).unwrap());
+    vars.insert("revision", to_value(&revision).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if records.is_empty() {
+        return Err(ErrorConflict("page was changed since the base revision"));
+    }
+
+    // pages below a moved page follow it
+    let mut chain = save.ancestors;
+    chain.push(key.to_string());
+    let q = r#"FOR p IN wiki_pages
+        FILTER @key IN p.ancestors
+        UPDATE p WITH { ancestors: APPEND(@chain, SLICE(p.ancestors, POSITION(p.ancestors, @key, true) + 1)) } IN wiki_pages"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("chain", to_value(chain).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let _records: Vec<Value> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+
+    sync_links(db, company_key, key, save.body).await
+}
+
+#[instrument(skip_all)]
+pub async fn find_pages(
+    company_key: &str,
+    params: FindPagesParams,
+    pool: &DbPool,
+) -> Result<Vec<PageSummary>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(r#"FOR p IN wiki_pages
+        FILTER p.company_key == @company_key AND p.parent_key == @parent_key
+        SORT p.title ASC {}"#, SUMMARY_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("parent_key", to_value(params.parent_key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<PageSummary> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn search_pages(
+    company_key: &str,
+    params: SearchPagesParams,
+    pool: &DbPool,
+) -> Result<Vec<PageSummary>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let q = format!(r#"FOR p IN {}
+        SEARCH p.company_key == @company_key AND ANALYZER(
+            p.title IN TOKENS(@q, 'text_en') OR p.body IN TOKENS(@q, 'text_en'),
+            'text_en'
+        )
+        SORT BM25(p) DESC
+        LIMIT @limit {}"#, SEARCH_VIEW, SUMMARY_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("q", to_value(params.q).unwrap());
+    vars.insert("limit", to_value(params.limit.unwrap_or(20)).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<PageSummary> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_page(
+    company_key: &str,
+    key: &str,
+    html: bool,
+    pool: &DbPool,
+) -> Result<PageResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let mut page = find_page(&db, company_key, key).await?;
+    if html {
+        page.html = Some(render_html(&page.body));
+    }
+    Ok(page)
+}
+
+#[instrument(skip_all)]
+pub async fn create_page(
+    company_key: &str,
+    author_key: &str,
+    payload: &CreatePageRequest,
+    pool: &DbPool,
+) -> Result<PageResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let now = Utc::now();
+    let data = Page {
+        company_key: company_key.to_string(),
+        slug: payload.slug.clone(),
+        title: payload.title.trim().to_string(),
+        body: payload.body.clone(),
+        parent_key: payload.parent_key.clone(),
+        ancestors: ancestors_under(&db, company_key, payload.parent_key.as_deref(), None).await?,
+        links: link_slugs(&payload.body),
+        revision: 1,
+        author_key: author_key.to_string(),
+        created_at: now,
+        modified_at: now,
+    };
+    let revision = Revision {
+        page_key: String::new(), // known once the page is in
+        company_key: company_key.to_string(),
+        number: 1,
+        title: data.title.clone(),
+        body: data.body.clone(),
+        author_key: author_key.to_string(),
+        comment: None,
+        created_at: now,
+    };
+    let q = r#"INSERT @page INTO wiki_pages
+        LET page = NEW
+        INSERT MERGE(@revision, { page_key: page._key }) INTO wiki_revisions
+        RETURN page._key"#;
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("page", to_value(&data).unwrap());
+    vars.insert("revision", to_value(&revision).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let mut records: Vec<String> = db.aql_query(aql).await
+        .map_err(|_| ErrorConflict("slug already used"))?;
+    let key = records.pop().unwrap();
+
+    sync_links(&db, company_key, &key, &data.body).await?;
+    link_new_page(&db, company_key, &key, &data.slug).await?;
+    find_page(&db, company_key, &key).await
+}
+
+#[instrument(skip_all)]
+pub async fn update_page(
+    company_key: &str,
+    key: &str,
+    author_key: &str,
+    payload: &UpdatePageRequest,
+    pool: &DbPool,
+) -> Result<PageResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_page(&db, company_key, key).await?;
+    let ancestors = ancestors_under(&db, company_key, payload.parent_key.as_deref(), Some(key)).await?;
+    save_page(&db, company_key, key, payload.base_revision, Save {
+        title: payload.title.trim(),
+        body: &payload.body,
+        parent_key: payload.parent_key.clone(),
+        ancestors,
+        author_key,
+        comment: payload.comment.clone(),
+    }).await?;
+    find_page(&db, company_key, key).await
+}
+
+// pages with children have to be emptied first
+#[instrument(skip_all)]
+pub async fn delete_page(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<(), Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_page(&db, company_key, key).await?;
+    let q = "FOR p IN wiki_pages FILTER p.company_key == @company_key AND p.parent_key == @key LIMIT 1 RETURN p._key";
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("company_key", to_value(company_key).unwrap());
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(q)
+        .bind_vars(vars)
+        .build();
+    let children: Vec<String> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    if !children.is_empty() {
+        return Err(ErrorConflict("page has children"));
+    }
+
+    for q in [
+        "FOR l IN wiki_links FILTER l._from == CONCAT('wiki_pages/', @key) OR l._to == CONCAT('wiki_pages/', @key) REMOVE l IN wiki_links",
+        "FOR r IN wiki_revisions FILTER r.page_key == @key REMOVE r IN wiki_revisions",
+        "REMOVE @key IN wiki_pages",
+    ] {
+        let mut vars: HashMap<&str, Value> = HashMap::new();
+        vars.insert("key", to_value(key).unwrap());
+
+        let aql = AqlQuery::builder()
+            .query(q)
+            .bind_vars(vars)
+            .build();
+        let _records: Vec<Value> = db.aql_query(aql).await
+            .map_err(ErrorInternalServerError)?;
+    }
+    Ok(())
+}
+
+// newest first, without bodies
+#[instrument(skip_all)]
+pub async fn find_revisions(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<Vec<RevisionResponse>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_page(&db, company_key, key).await?;
+    let q = format!("FOR r IN wiki_revisions FILTER r.page_key == @key SORT r.number DESC {}", REVISION_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+    vars.insert("with_body", to_value(false).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<RevisionResponse> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
+
+#[instrument(skip_all)]
+pub async fn show_revision(
+    company_key: &str,
+    key: &str,
+    number: u32,
+    pool: &DbPool,
+) -> Result<RevisionResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_revision(&db, company_key, key, number).await
+}
+
+// line based, from may be newer than to
+#[instrument(skip_all)]
+pub async fn diff_revisions(
+    company_key: &str,
+    key: &str,
+    params: &DiffParams,
+    pool: &DbPool,
+) -> Result<DiffResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let old = find_revision(&db, company_key, key, params.from).await?;
+    let new = find_revision(&db, company_key, key, params.to).await?;
+    let old_body = old.body.unwrap_or_default();
+    let new_body = new.body.unwrap_or_default();
+
+    let diff = TextDiff::from_lines(&old_body, &new_body);
+    let lines = diff.iter_all_changes()
+        .map(|change| DiffLine {
+            tag: match change.tag() {
+                ChangeTag::Equal => "equal",
+                ChangeTag::Insert => "insert",
+                ChangeTag::Delete => "delete",
+            }.to_string(),
+            text: change.value().to_string(),
+        })
+        .collect();
+    let unified = diff.unified_diff()
+        .context_radius(3)
+        .header(&format!("revision {}", params.from), &format!("revision {}", params.to))
+        .to_string();
+    Ok(DiffResponse {
+        from: params.from,
+        to: params.to,
+        title_changed: old.title != new.title,
+        lines,
+        unified,
+    })
+}
+
+// the old content comes back as a new revision, history is never rewritten
+#[instrument(skip_all)]
+pub async fn rollback_page(
+    company_key: &str,
+    key: &str,
+    author_key: &str,
+    payload: &RollbackRequest,
+    pool: &DbPool,
+) -> Result<PageResponse, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    let page = find_page(&db, company_key, key).await?;
+    let revision = find_revision(&db, company_key, key, payload.revision).await?;
+    let body = revision.body.unwrap_or_default();
+    save_page(&db, company_key, key, page.revision, Save {
+        title: &revision.title,
+        body: &body,
+        parent_key: page.parent_key,
+        ancestors: page.ancestors,
+        author_key,
+        comment: Some(payload.comment.clone().unwrap_or_else(|| format!("Rollback to revision {}", payload.revision))),
+    }).await?;
+    find_page(&db, company_key, key).await
+}
+
+// what links here
+#[instrument(skip_all)]
+pub async fn find_backlinks(
+    company_key: &str,
+    key: &str,
+    pool: &DbPool,
+) -> Result<Vec<PageSummary>, Error> {
+    let client = pool.get().await.unwrap();
+    let db = client.db(&current_database()).await.unwrap();
+
+    find_page(&db, company_key, key).await?;
+    let q = format!("FOR p IN 1..1 INBOUND CONCAT('wiki_pages/', @key) wiki_links SORT p.title ASC {}", SUMMARY_TERMS);
+    let mut vars: HashMap<&str, Value> = HashMap::new();
+    vars.insert("key", to_value(key).unwrap());
+
+    let aql = AqlQuery::builder()
+        .query(&q)
+        .bind_vars(vars)
+        .build();
+    let records: Vec<PageSummary> = db.aql_query(aql).await
+        .map_err(ErrorInternalServerError)?;
+    Ok(records)
+}
@@ -0,0 +1,49 @@
+use pulldown_cmark::{html, Options, Parser};
+
+use crate::wiki::validate_slug;
+
+// [[slug]] in the order they first appear
+pub fn link_slugs(body: &str) -> Vec<String> {
+    let mut slugs: Vec<String> = vec![];
+    let mut rest = body;
+    while let Some(start) = rest.find("[[") {
+        rest = &rest[start + 2..];
+        let end = match rest.find("]]") {
+            Some(x) => x,
+            None => break,
+        };
+        let slug = rest[..end].trim();
+        if validate_slug(slug).is_ok() && !slugs.iter().any(|x| x == slug) {
+            slugs.push(slug.to_string());
+        }
+        rest = &rest[end + 2..];
+    }
+    slugs
+}
+
+// [[slug]] becomes a relative link, raw HTML in the body is stripped by the sanitiser
+pub fn render_html(body: &str) -> String {
+    let mut markdown = String::with_capacity(body.len());
+    let mut rest = body;
+    while let Some(start) = rest.find("[[") {
+        markdown.push_str(&rest[..start]);
+        rest = &rest[start + 2..];
+        match rest.find("]]") {
+            Some(end) if validate_slug(rest[..end].trim()).is_ok() => {
+                let slug = rest[..end].trim();
+                markdown.push_str(&format!("[{0}]({0})", slug));
+                rest = &rest[end + 2..];
+            },
+            _ => markdown.push_str("[["),
+        }
+    }
+    markdown.push_str(rest);
+
+    let mut options = Options::empty();
+    options.insert(Options::ENABLE_TABLES);
+    options.insert(Options::ENABLE_STRIKETHROUGH);
+    options.insert(Options::ENABLE_TASKLISTS);
+    let mut html = String::new();
+    html::push_html(&mut html, Parser::new_ext(&markdown, options));
+    ammonia::clean(&html)
+}
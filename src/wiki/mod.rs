@@ -0,0 +1,9 @@
+mod models;
+mod controllers;
+mod markdown;
+mod routes;
+
+pub use models::*;
+pub use controllers::*;
+pub use markdown::{link_slugs, render_html};
+pub use routes::init;
@@ -0,0 +1,151 @@
+use chrono::prelude::*;
+use serde::{Deserialize, Serialize};
+use validator::{Validate, ValidationError};
+
+pub const SEARCH_VIEW: &str = "wiki_search";
+
+// slugs name pages in [[slug]] links
+pub fn validate_slug(slug: &str) -> Result<(), ValidationError> {
+    if slug.is_empty() || !slug.chars().all(|c| c.is_ascii_lowercase() || c.is_ascii_digit() || c == '-') {
+        return Err(ValidationError::new("wrong_slug"));
+    }
+    Ok(())
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct FindPagesParams {
+    pub parent_key: Option<String>, // top level pages when none
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct SearchPagesParams {
+    #[validate(length(min = 1, max = 200))]
+    pub q: String,
+    #[validate(range(min = 1, max = 100))]
+    pub limit: Option<u32>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct ShowPageParams {
+    pub html: Option<bool>, // adds the rendered body
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct CreatePageRequest {
+    #[validate(length(min = 1, max = 100), custom = "validate_slug")]
+    pub slug: String,
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(max = 200000))]
+    pub body: String,
+    pub parent_key: Option<String>,
+}
+
+// base_revision is the revision the editor started from, saving over a newer one is refused
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct UpdatePageRequest {
+    #[validate(length(min = 1, max = 200))]
+    pub title: String,
+    #[validate(length(max = 200000))]
+    pub body: String,
+    pub parent_key: Option<String>,
+    pub base_revision: u32,
+    #[validate(length(max = 500))]
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, Deserialize)]
+pub struct DiffParams {
+    pub from: u32,
+    pub to: u32,
+}
+
+#[derive(Clone, Debug, Validate, Deserialize)]
+pub struct RollbackRequest {
+    pub revision: u32,
+    #[validate(length(max = 500))]
+    pub comment: Option<String>,
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Page {
+    pub company_key: String,
+    pub slug: String,
+    pub title: String,
+    pub body: String, // Markdown
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub parent_key: Option<String>,
+    pub ancestors: Vec<String>, // keys from the top level page down to the parent
+    pub links: Vec<String>, // slugs linked from the body, also those without a page yet
+    pub revision: u32,
+    pub author_key: String, // of the latest revision
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+// every save is kept whole
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Revision {
+    pub page_key: String,
+    pub company_key: String,
+    pub number: u32,
+    pub title: String,
+    pub body: String,
+    pub author_key: String,
+    #[serde(skip_serializing_if = "Option::is_none")] // if none, excluded from query
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PageResponse {
+    pub _key: String,
+    pub slug: String,
+    pub title: String,
+    pub body: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub html: Option<String>,
+    pub parent_key: Option<String>,
+    pub ancestors: Vec<String>,
+    pub revision: u32,
+    pub author_key: String,
+    pub created_at: DateTime<Utc>,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct PageSummary {
+    pub _key: String,
+    pub slug: String,
+    pub title: String,
+    pub parent_key: Option<String>,
+    pub children: i64,
+    pub modified_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+pub struct RevisionResponse {
+    pub number: u32,
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub body: Option<String>, // left out of listings
+    pub author_key: String,
+    pub author_name: Option<String>,
+    pub comment: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffLine {
+    pub tag: String, // equal, insert or delete
+    pub text: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct DiffResponse {
+    pub from: u32,
+    pub to: u32,
+    pub title_changed: bool,
+    pub lines: Vec<DiffLine>,
+    pub unified: String,
+}
@@ -0,0 +1,222 @@
+use actix_web::{delete, error::ErrorForbidden, get, post, put, web, Error, HttpRequest, HttpResponse};
+use validator::Validate;
+
+use crate::company;
+use crate::database::DbPool;
+use crate::i18n::Invalid;
+use crate::wiki::{
+    self,
+    CreatePageRequest,
+    DiffParams,
+    FindPagesParams,
+    RollbackRequest,
+    SearchPagesParams,
+    ShowPageParams,
+    UpdatePageRequest,
+};
+
+// the wiki is open to members and to keys of the company
+async fn require_reader(req: &HttpRequest, key: &str, pool: &DbPool) -> Result<(), Error> {
+    let identity = company::require_access(req, key, "wiki:read")?;
+    if let Some(user_key) = identity.user_key() {
+        if !company::is_company_member(key, user_key, pool).await {
+            return Err(ErrorForbidden("company member required"));
+        }
+    }
+    Ok(())
+}
+
+// revisions are signed by people
+async fn require_editor(req: &HttpRequest, key: &str, pool: &DbPool) -> Result<String, Error> {
+    let identity = company::require_access(req, key, "wiki:write")?;
+    let user_key = identity.user_key()
+        .ok_or_else(|| ErrorForbidden("pages are edited by users"))?;
+    if !company::is_company_member(key, user_key, pool).await {
+        return Err(ErrorForbidden("company member required"));
+    }
+    Ok(user_key.to_string())
+}
+
+#[get("/companies/{key}/wiki")]
+async fn find(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<FindPagesParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::find_pages(&key, payload.into_inner(), &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// registered before show so that it is not taken for a key
+#[get("/companies/{key}/wiki/search")]
+async fn search(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Query<SearchPagesParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    require_reader(&req, &key, &pool).await?;
+    let params: SearchPagesParams = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = wiki::search_pages(&key, params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/companies/{key}/wiki/{page_key}")]
+async fn show(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Query<ShowPageParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::show_page(&key, &page_key, payload.html.unwrap_or(false), &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/wiki")]
+async fn create(
+    req: HttpRequest,
+    key: web::Path<String>,
+    payload: web::Json<CreatePageRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let user_key = require_editor(&req, &key, &pool).await?;
+    let params: CreatePageRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = wiki::create_page(&key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Created().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[put("/companies/{key}/wiki/{page_key}")]
+async fn update(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<UpdatePageRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    let user_key = require_editor(&req, &key, &pool).await?;
+    let params: UpdatePageRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = wiki::update_page(&key, &page_key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[delete("/companies/{key}/wiki/{page_key}")]
+async fn delete(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    company::require_admin(&req, &key, &pool).await?;
+    wiki::delete_page(&key, &page_key, &pool).await?;
+    Ok(HttpResponse::NoContent().finish())
+}
+
+#[get("/companies/{key}/wiki/{page_key}/revisions")]
+async fn find_revisions(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::find_revisions(&key, &page_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/companies/{key}/wiki/{page_key}/revisions/{number}")]
+async fn show_revision(
+    req: HttpRequest,
+    path: web::Path<(String, String, u32)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key, number) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::show_revision(&key, &page_key, number, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[get("/companies/{key}/wiki/{page_key}/diff")]
+async fn diff(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Query<DiffParams>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::diff_revisions(&key, &page_key, &payload, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+#[post("/companies/{key}/wiki/{page_key}/rollback")]
+async fn rollback(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    payload: web::Json<RollbackRequest>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    let user_key = require_editor(&req, &key, &pool).await?;
+    let params: RollbackRequest = payload.into_inner();
+    match params.validate() {
+        Ok(_) => {
+            let result = wiki::rollback_page(&key, &page_key, &user_key, &params, &pool).await?;
+            Ok(HttpResponse::Ok().json(result))
+        },
+        Err(e) => {
+            Err(Invalid(e).into())
+        },
+    }
+}
+
+#[get("/companies/{key}/wiki/{page_key}/backlinks")]
+async fn find_backlinks(
+    req: HttpRequest,
+    path: web::Path<(String, String)>,
+    pool: web::Data<DbPool>,
+) -> Result<HttpResponse, Error> {
+    let (key, page_key) = path.into_inner();
+    require_reader(&req, &key, &pool).await?;
+    let result = wiki::find_backlinks(&key, &page_key, &pool).await?;
+    Ok(HttpResponse::Ok().json(result))
+}
+
+// function that will be called on new Application to configure routes for this module
+pub fn init(cfg: &mut web::ServiceConfig) {
+    cfg.service(find);
+    cfg.service(search);
+    cfg.service(show);
+    cfg.service(create);
+    cfg.service(update);
+    cfg.service(delete);
+    cfg.service(find_revisions);
+    cfg.service(show_revision);
+    cfg.service(diff);
+    cfg.service(rollback);
+    cfg.service(find_backlinks);
+}